@@ -0,0 +1,11 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rustyscript::{bench, RuntimeOptions};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("default");
+    bench::all(&mut group, RuntimeOptions::default);
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+use crate::{Error, FunctionArguments};
+use deno_core::serde_json;
+
+/// A reusable list of arguments for calling javascript functions
+/// Clearing the buffer keeps its allocation, so a single buffer can be refilled for each
+/// call in a hot loop, instead of building a new `Vec` of arguments every time
+///
+/// Derefs to `FunctionArguments`, so it can be passed anywhere arguments are expected
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ ArgBuffer, Runtime, Module, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("/path/to/module.js", "export function add(a, b) { return a + b; };");
+/// let module = runtime.load_modules(&module, vec![])?;
+///
+/// let mut args = ArgBuffer::with_capacity(2);
+/// for i in 0..10 {
+///     args.clear();
+///     args.push(i).push(1);
+///     let value: usize = runtime.call_function(Some(&module), "add", &args)?;
+///     assert_eq!(i + 1, value);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArgBuffer(Vec<serde_json::Value>);
+
+impl ArgBuffer {
+    /// Create a new, empty buffer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new, empty buffer with room for `capacity` arguments
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
+
+    /// Add an argument to the end of the buffer
+    pub fn push<A>(&mut self, value: A) -> &mut Self
+    where
+        serde_json::Value: From<A>,
+    {
+        self.0.push(serde_json::Value::from(value));
+        self
+    }
+
+    /// Serialize a value, and add it to the end of the buffer
+    pub fn push_serialized<A>(&mut self, value: &A) -> Result<&mut Self, Error>
+    where
+        A: serde::Serialize,
+    {
+        self.0.push(serde_json::to_value(value)?);
+        Ok(self)
+    }
+
+    /// Remove all arguments from the buffer, keeping its allocated capacity
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// The number of arguments the buffer can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+}
+
+impl std::ops::Deref for ArgBuffer {
+    type Target = FunctionArguments;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<serde_json::Value>> for ArgBuffer {
+    fn from(value: Vec<serde_json::Value>) -> Self {
+        Self(value)
+    }
+}
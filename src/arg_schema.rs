@@ -0,0 +1,380 @@
+use crate::Error;
+use deno_core::serde_json::Value;
+use serde::{Deserialize, Serialize};
+
+/// The type of a value accepted by an [ArgSchema]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgType {
+    /// Any value, including `null`
+    Any,
+
+    /// `null`, which is also what `undefined` arguments become
+    Null,
+
+    /// `true` or `false`
+    Boolean,
+
+    /// Any number
+    Number,
+
+    /// A number with no fractional part
+    Integer,
+
+    /// A string
+    String,
+
+    /// An array
+    Array,
+
+    /// An object that is not an array or `null`
+    Object,
+}
+
+impl ArgType {
+    /// Returns true if `value` is of this type
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Null => value.is_null(),
+            Self::Boolean => value.is_boolean(),
+            Self::Number => value.is_number(),
+            Self::Integer => value
+                .as_f64()
+                .is_some_and(|n| n.fract() == 0.0 && n.is_finite()),
+            Self::String => value.is_string(),
+            Self::Array => value.is_array(),
+            Self::Object => value.is_object(),
+        }
+    }
+
+    /// The name of the type, as used in error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Null => "null",
+            Self::Boolean => "boolean",
+            Self::Number => "number",
+            Self::Integer => "integer",
+            Self::String => "string",
+            Self::Array => "array",
+            Self::Object => "object",
+        }
+    }
+
+    /// The name of the type of a JSON value, as used in error messages
+    fn name_of(value: &Value) -> &'static str {
+        match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+/// A single argument described by an [ArgSchema]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgSpec {
+    /// The name of the argument, used in error messages
+    pub name: String,
+
+    /// The type the argument must have
+    #[serde(rename = "type")]
+    pub kind: ArgType,
+
+    /// If true, the argument can be left out, or be `null`
+    pub optional: bool,
+}
+
+/// Describes the arguments a registered function accepts
+/// Attach one with `Runtime::set_function_schema`, and calls from JS with invalid
+/// arguments throw a `TypeError` in the script, before the function is called
+///
+/// Build one with the [crate::schema] macro, or by chaining `arg`, `optional` and `rest`
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ schema, ArgSchema, ArgType, Runtime, Error, serde_json::Value };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// runtime.register_function("repeat", |args| {
+///     let text = args[0].as_str().unwrap_or_default();
+///     let count = args.get(1).and_then(Value::as_u64).unwrap_or(1);
+///     Ok(Value::from(text.repeat(count as usize)))
+/// })?;
+///
+/// // Same as ArgSchema::new().arg("text", ArgType::String).optional("count", ArgType::Integer)
+/// runtime.set_function_schema("repeat", schema!(text: string, count?: integer))?;
+///
+/// let message: String = runtime.eval("
+///     try { rustyscript.functions.repeat(5) } catch (e) { `${e.name}: ${e.message}` }
+/// ")?;
+/// assert_eq!("TypeError: repeat: argument 1 (text) must be a string, got number", message);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArgSchema {
+    args: Vec<ArgSpec>,
+    rest: Option<ArgType>,
+}
+
+impl ArgSchema {
+    /// Create a schema for a function that takes no arguments
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a required argument
+    /// Required arguments must come before optional ones
+    pub fn arg(mut self, name: &str, kind: ArgType) -> Self {
+        self.args.push(ArgSpec {
+            name: name.to_string(),
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Add an optional argument, which may be left out or be `null`
+    pub fn optional(mut self, name: &str, kind: ArgType) -> Self {
+        self.args.push(ArgSpec {
+            name: name.to_string(),
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    /// Accept any number of further arguments of the given type
+    /// Without this, extra arguments are rejected
+    pub fn rest(mut self, kind: ArgType) -> Self {
+        self.rest = Some(kind);
+        self
+    }
+
+    /// The arguments described by this schema, in order
+    pub fn args(&self) -> &[ArgSpec] {
+        &self.args
+    }
+
+    /// The type of any further arguments, if they are accepted
+    pub fn rest_type(&self) -> Option<ArgType> {
+        self.rest
+    }
+
+    /// Check the arguments of a call to `function` against this schema
+    /// Returns `Error::InvalidArguments` describing the first problem found
+    pub fn validate(&self, function: &str, args: &[Value]) -> Result<(), Error> {
+        let invalid = |msg: String| Err(Error::InvalidArguments(format!("{function}: {msg}")));
+
+        for (i, spec) in self.args.iter().enumerate() {
+            match args.get(i) {
+                None | Some(Value::Null) if spec.optional => {}
+                None => return invalid(format!("missing argument {} ({})", i + 1, spec.name)),
+                Some(value) if !spec.kind.matches(value) => {
+                    return invalid(format!(
+                        "argument {} ({}) must be {} {}, got {}",
+                        i + 1,
+                        spec.name,
+                        article(spec.kind),
+                        spec.kind.name(),
+                        ArgType::name_of(value)
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let extra = args.iter().enumerate().skip(self.args.len());
+        match self.rest {
+            None if args.len() > self.args.len() => invalid(format!(
+                "expected at most {} arguments, got {}",
+                self.args.len(),
+                args.len()
+            )),
+            Some(kind) => {
+                for (i, value) in extra {
+                    if !kind.matches(value) {
+                        return invalid(format!(
+                            "argument {} must be {} {}, got {}",
+                            i + 1,
+                            article(kind),
+                            kind.name(),
+                            ArgType::name_of(value)
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// "a" or "an", to go before the name of a type
+fn article(kind: ArgType) -> &'static str {
+    match kind {
+        ArgType::Any | ArgType::Integer | ArgType::Array | ArgType::Object => "an",
+        _ => "a",
+    }
+}
+
+/// Builds an [ArgSchema] from a list of arguments and their types
+///
+/// Each argument is written as `name: type`, or `name?: type` if it is optional
+/// A final `...name: type` accepts any number of further arguments of that type
+/// Types are `any`, `null`, `boolean`, `number`, `integer`, `string`, `array` and `object`
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ schema, ArgSchema, ArgType };
+///
+/// let schema = schema!(format: string, values?: array, ...rest: any);
+/// assert_eq!(
+///     ArgSchema::new()
+///         .arg("format", ArgType::String)
+///         .optional("values", ArgType::Array)
+///         .rest(ArgType::Any),
+///     schema
+/// );
+/// ```
+#[macro_export]
+macro_rules! schema {
+    (@args $schema:expr;) => {
+        $schema
+    };
+    (@args $schema:expr; ...$name:ident: $kind:ident $(,)?) => {
+        $schema.rest($crate::schema!(@type $kind))
+    };
+    (@args $schema:expr; $name:ident?: $kind:ident $(, $($rest:tt)*)?) => {
+        $crate::schema!(@args $schema.optional(stringify!($name), $crate::schema!(@type $kind)); $($($rest)*)?)
+    };
+    (@args $schema:expr; $name:ident: $kind:ident $(, $($rest:tt)*)?) => {
+        $crate::schema!(@args $schema.arg(stringify!($name), $crate::schema!(@type $kind)); $($($rest)*)?)
+    };
+
+    (@type any) => { $crate::ArgType::Any };
+    (@type null) => { $crate::ArgType::Null };
+    (@type boolean) => { $crate::ArgType::Boolean };
+    (@type number) => { $crate::ArgType::Number };
+    (@type integer) => { $crate::ArgType::Integer };
+    (@type string) => { $crate::ArgType::String };
+    (@type array) => { $crate::ArgType::Array };
+    (@type object) => { $crate::ArgType::Object };
+    (@type $other:ident) => {
+        compile_error!(concat!("unknown argument type `", stringify!($other), "`"))
+    };
+
+    ($($args:tt)*) => {
+        $crate::schema!(@args $crate::ArgSchema::new(); $($args)*)
+    };
+}
+
+#[cfg(test)]
+mod test_arg_schema {
+    use super::*;
+    use crate::{json_args, Module, Runtime};
+
+    #[test]
+    fn test_validate() {
+        let schema = schema!(a: number, b?: string, ...rest: integer);
+        assert!(schema.validate("f", &[1.5.into()]).is_ok());
+        assert!(schema
+            .validate("f", &[1.into(), Value::Null, 2.into(), 3.into()])
+            .is_ok());
+
+        let e = schema.validate("f", &[]).unwrap_err();
+        assert_eq!("f: missing argument 1 (a)", e.to_string());
+
+        let e = schema.validate("f", &["x".into()]).unwrap_err();
+        assert_eq!(
+            "f: argument 1 (a) must be a number, got string",
+            e.to_string()
+        );
+
+        let e = schema
+            .validate("f", &[1.into(), "x".into(), 2.5.into()])
+            .unwrap_err();
+        assert_eq!(
+            "f: argument 3 must be an integer, got number",
+            e.to_string()
+        );
+
+        let e = schema!(a: any)
+            .validate("f", &[1.into(), 2.into()])
+            .unwrap_err();
+        assert_eq!("f: expected at most 1 arguments, got 2", e.to_string());
+    }
+
+    #[test]
+    fn test_schema_runtime() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("add", |args| {
+                Ok(Value::from(
+                    args[0].as_i64().unwrap() + args[1].as_i64().unwrap(),
+                ))
+            })
+            .expect("Could not register function");
+        runtime
+            .register_async_function("echo", |args| {
+                let value = args[0].clone();
+                Box::pin(async move { Ok(value) })
+            })
+            .expect("Could not register function");
+
+        runtime
+            .set_function_schema("add", schema!(a: integer, b: integer))
+            .expect("Could not set schema");
+        runtime
+            .set_function_schema("echo", schema!(value: string))
+            .expect("Could not set schema");
+        runtime
+            .set_function_schema("missing", schema!())
+            .expect_err("Set a schema on an unregistered function");
+
+        let module = Module::new(
+            "test_schema.js",
+            "
+            const describe = (e) => `${e instanceof TypeError} ${e.message}`;
+            export const add = (...args) => {
+                try { return rustyscript.functions.add(...args); } catch (e) { return describe(e); }
+            };
+            export const echo = async (...args) => {
+                try { return await rustyscript.async_functions.echo(...args); } catch (e) { return describe(e); }
+            };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let value: i64 = runtime
+            .call_function(Some(&module), "add", json_args!(1, 2))
+            .unwrap();
+        assert_eq!(3, value);
+        let value: String = runtime
+            .call_function(Some(&module), "add", json_args!(1, "2"))
+            .unwrap();
+        assert_eq!(
+            "true add: argument 2 (b) must be an integer, got string",
+            value
+        );
+
+        let value: String = runtime
+            .call_function(Some(&module), "echo", json_args!("hi"))
+            .unwrap();
+        assert_eq!("hi", value);
+        let value: String = runtime
+            .call_function(Some(&module), "echo", json_args!())
+            .unwrap();
+        assert_eq!("true echo: missing argument 1 (value)", value);
+
+        let functions = runtime.registered_functions().unwrap();
+        assert_eq!(Some(schema!(a: integer, b: integer)), functions[0].schema);
+    }
+}
@@ -0,0 +1,107 @@
+//! Provides reusable benchmark scenarios, built on [criterion]
+//! These are the same scenarios used by the crate's own benchmarks, so downstream users can measure
+//! their own runtime configurations and extensions in a consistent way
+//!
+//! Each scenario is added to a criterion benchmark group, so results for different configurations
+//! can be told apart by group name, and compared against saved criterion baselines:
+//! ```rust,no_run
+//! use criterion::Criterion;
+//! use rustyscript::{bench, RuntimeOptions};
+//!
+//! fn benchmark(c: &mut Criterion) {
+//!     let mut group = c.benchmark_group("default_options");
+//!     bench::cold_start(&mut group, RuntimeOptions::default);
+//!     bench::eval_throughput(&mut group, RuntimeOptions::default, "1 + 1");
+//!     group.finish();
+//! }
+//! ```
+use crate::{FunctionArguments, Module, Runtime, RuntimeOptions};
+use criterion::{measurement::WallTime, BenchmarkGroup};
+
+/// The group type used by all scenarios
+pub type Group<'a> = BenchmarkGroup<'a, WallTime>;
+
+/// Run all of the standard scenarios with the given runtime configuration
+/// The worker round-trip scenario is included if the `worker` feature is enabled
+pub fn all<F>(group: &mut Group<'_>, options: F)
+where
+    F: Fn() -> RuntimeOptions,
+{
+    cold_start(group, &options);
+    eval_throughput(group, &options, "1 + 1");
+
+    let module = Module::new(
+        "bench.js",
+        "export function test(...args) { return args.length; }",
+    );
+    call_latency(
+        group,
+        &options,
+        &module,
+        "test",
+        crate::json_args!("test", 1, false),
+    );
+
+    #[cfg(feature = "worker")]
+    worker_round_trip(group, Default::default());
+}
+
+/// Measures the time taken to create a new runtime
+pub fn cold_start<F>(group: &mut Group<'_>, options: F)
+where
+    F: Fn() -> RuntimeOptions,
+{
+    group.bench_function("cold_start", |b| {
+        b.iter(|| Runtime::new(options()).expect("Could not create runtime"))
+    });
+}
+
+/// Measures the time taken to evaluate an expression in an existing runtime
+pub fn eval_throughput<F>(group: &mut Group<'_>, options: F, expr: &str)
+where
+    F: Fn() -> RuntimeOptions,
+{
+    let mut runtime = Runtime::new(options()).expect("Could not create runtime");
+    group.bench_function("eval_throughput", |b| {
+        b.iter(|| {
+            runtime
+                .eval::<crate::serde_json::Value>(expr)
+                .expect("Could not evaluate expression")
+        })
+    });
+}
+
+/// Measures the time taken to call a function exported by a module
+pub fn call_latency<F>(
+    group: &mut Group<'_>,
+    options: F,
+    module: &Module,
+    name: &str,
+    args: &FunctionArguments,
+) where
+    F: Fn() -> RuntimeOptions,
+{
+    let mut runtime = Runtime::new(options()).expect("Could not create runtime");
+    let module = runtime.load_module(module).expect("Could not load module");
+    group.bench_function("call_latency", |b| {
+        b.iter(|| {
+            runtime
+                .call_function::<crate::serde_json::Value>(Some(&module), name, args)
+                .expect("Could not call function")
+        })
+    });
+}
+
+/// Measures the time taken to send a query to a worker and wait for its response
+#[cfg(feature = "worker")]
+pub fn worker_round_trip(group: &mut Group<'_>, options: crate::worker::DefaultWorkerOptions) {
+    let worker = crate::worker::DefaultWorker::new(options).expect("Could not create worker");
+    group.bench_function("worker_round_trip", |b| {
+        b.iter(|| {
+            worker
+                .eval::<crate::serde_json::Value>("1 + 1".to_string())
+                .expect("Could not evaluate expression")
+        })
+    });
+    worker.stop().expect("Could not stop worker");
+}
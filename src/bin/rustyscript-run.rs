@@ -0,0 +1,181 @@
+//! A small host for running scripts, built only on rustyscript's public API
+//! Useful for trying out the crate's features, and for reproducing bug reports
+//!
+//! ```text
+//! rustyscript-run [OPTIONS] <FILE>     Run a module, then its entrypoint if it registers one
+//! rustyscript-run [OPTIONS] -e <EXPR>  Evaluate an expression, and print the result
+//! rustyscript-run [OPTIONS]            Start an interactive prompt
+//! ```
+use rustyscript::{
+    format_value, json_args, serde_json, DeterministicOptions, Error, ImportPolicy, Module,
+    Runtime, RuntimeBuilder, UrlPattern,
+};
+use std::{io::Write, time::Duration};
+
+const USAGE: &str = "\
+Usage:
+  rustyscript-run [OPTIONS] <FILE>     Run a module, then its entrypoint if it registers one
+  rustyscript-run [OPTIONS] -e <EXPR>  Evaluate an expression, and print the result
+  rustyscript-run [OPTIONS]            Start an interactive prompt
+
+Options:
+  --timeout <MS>          Time limit for each call, in milliseconds
+  --offline               Deny all remote imports
+  --allow-import <URL>    Only allow remote imports matching the pattern; can be repeated
+  --seed <N>              Run deterministically, with a seeded Math.random and a virtual clock
+  --max-timers <N>        Limit the number of pending timers
+  --stats                 Print runtime statistics before exiting
+  --json                  Print errors as JSON reports
+  -h, --help              Print this message";
+
+/// What to run
+enum Command {
+    Run(String),
+    Eval(String),
+    Prompt,
+}
+
+struct Args {
+    command: Command,
+    builder: RuntimeBuilder,
+    stats: bool,
+    json: bool,
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let json = args.json;
+    if let Err(e) = run(args) {
+        if json {
+            let report = serde_json::to_string(&e.report()).unwrap_or_default();
+            eprintln!("{report}");
+        } else {
+            eprintln!("{}", e.as_highlighted());
+        }
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut command = Command::Prompt;
+    let mut builder = Runtime::builder();
+    let mut allow_list = Vec::new();
+    let (mut stats, mut json) = (false, false);
+
+    fn value<T: std::str::FromStr>(
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<T, String> {
+        let value = args.next().ok_or(format!("{flag} requires a value"))?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid value for {flag}: {value}"))
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            "-e" | "--eval" => command = Command::Eval(value(&arg, &mut args)?),
+            "--timeout" => {
+                builder = builder.timeout(Duration::from_millis(value(&arg, &mut args)?));
+            }
+            "--offline" => builder = builder.import_policy(ImportPolicy::Offline),
+            "--allow-import" => {
+                allow_list.push(UrlPattern::new(&value::<String>(&arg, &mut args)?));
+            }
+            "--seed" => {
+                builder = builder.deterministic(DeterministicOptions {
+                    seed: value(&arg, &mut args)?,
+                    ..Default::default()
+                });
+            }
+            "--max-timers" => builder = builder.max_timers(value(&arg, &mut args)?),
+            "--stats" => stats = true,
+            "--json" => json = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option: {flag}")),
+            file => command = Command::Run(file.to_string()),
+        }
+    }
+
+    if !allow_list.is_empty() {
+        builder = builder.import_policy(ImportPolicy::AllowList(allow_list));
+    }
+
+    Ok(Args {
+        command,
+        builder,
+        stats,
+        json,
+    })
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let mut runtime = args.builder.build()?;
+
+    match args.command {
+        Command::Run(path) => {
+            let module = Module::load(&path)?;
+            let handle = runtime.load_module(&module)?;
+            if handle.entrypoint().is_some() {
+                let value: serde_json::Value = runtime.call_entrypoint(&handle, json_args!())?;
+                if !value.is_null() {
+                    println!("{}", format_value(&value));
+                }
+            }
+        }
+
+        Command::Eval(expr) => {
+            let value: serde_json::Value = runtime.eval(&expr)?;
+            println!("{}", format_value(&value));
+        }
+
+        Command::Prompt => prompt(&mut runtime, args.json),
+    }
+
+    if args.stats {
+        let stats = serde_json::to_string_pretty(&runtime.stats())?;
+        eprintln!("{stats}");
+    }
+
+    Ok(())
+}
+
+/// Evaluate expressions from stdin until it closes, or `exit` is entered
+fn prompt(runtime: &mut Runtime, json: bool) {
+    let stdin = std::io::stdin();
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+
+        let mut input = String::new();
+        match stdin.read_line(&mut input) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        let input = input.trim();
+        if input.is_empty() {
+            continue;
+        } else if ["exit", "quit"].contains(&input) {
+            break;
+        }
+
+        match runtime.eval::<serde_json::Value>(input) {
+            Ok(value) => println!("{}", format_value(&value)),
+            Err(e) if json => {
+                eprintln!("{}", serde_json::to_string(&e.report()).unwrap_or_default());
+            }
+            Err(e) => eprintln!("{}", e.as_highlighted()),
+        }
+    }
+}
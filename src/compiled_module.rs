@@ -0,0 +1,110 @@
+use crate::Module;
+use serde::{Deserialize, Serialize};
+
+/// A module that has been transpiled and compiled ahead of time
+/// Created with [Module::compile] or `Runtime::compile_module`, and loaded with `Runtime::load_compiled`
+///
+/// Loading a compiled module skips transpiling it, and V8 reuses the code cache instead of
+/// parsing and compiling the source again - worthwhile for large scripts loaded by many runtimes
+///
+/// Compiled modules can be serialized, to be stored and reused by later processes
+/// The code cache is only valid for the V8 version and flags it was created with - if they
+/// differ, V8 rejects the cache, and the module is compiled from source as usual
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Module, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let compiled = Module::new("test.ts", "export const value: number = 42;").compile()?;
+///
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = runtime.load_compiled(&compiled)?;
+/// let value: i64 = runtime.get_value(Some(&module), "value")?;
+/// assert_eq!(42, value);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CompiledModule {
+    module: Module,
+    code: String,
+    source_map: Option<Vec<u8>>,
+    code_cache: Vec<u8>,
+}
+
+impl CompiledModule {
+    pub(crate) fn new(
+        module: Module,
+        code: String,
+        source_map: Option<Vec<u8>>,
+        code_cache: Vec<u8>,
+    ) -> Self {
+        Self {
+            module,
+            code,
+            source_map,
+            code_cache,
+        }
+    }
+
+    /// The module this was compiled from
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// The V8 code cache for the module
+    pub fn code_cache(&self) -> &[u8] {
+        &self.code_cache
+    }
+
+    /// The transpiled javascript the code cache was created from
+    pub(crate) fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub(crate) fn source_map(&self) -> Option<&[u8]> {
+        self.source_map.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test_compiled_module {
+    use crate::{Module, Runtime};
+
+    #[test]
+    fn test_load_compiled() {
+        let module = Module::new(
+            "test_compiled.ts",
+            "
+            const double = (x: number): number => x * 2;
+            export const run = (x: number) => double(x);
+        ",
+        );
+        let compiled = module.compile().expect("Could not compile module");
+        assert!(!compiled.code_cache().is_empty());
+        assert_eq!(&module, compiled.module());
+
+        // Compiled modules survive a round trip through storage
+        let stored = deno_core::serde_json::to_string(&compiled).unwrap();
+        let compiled: super::CompiledModule = deno_core::serde_json::from_str(&stored).unwrap();
+
+        for _ in 0..2 {
+            let mut runtime =
+                Runtime::new(Default::default()).expect("Could not create the runtime");
+            let handle = runtime
+                .load_compiled(&compiled)
+                .expect("Could not load module");
+            let value: i64 = runtime
+                .call_function(Some(&handle), "run", &[21.into()])
+                .expect("Could not call function");
+            assert_eq!(42, value);
+        }
+
+        let e = Module::new("test_compiled_invalid.js", "export const = ;")
+            .compile()
+            .unwrap_err();
+        assert!(matches!(e, crate::Error::JsError(_)), "{e}");
+    }
+}
@@ -0,0 +1,244 @@
+//! Provides round-trip checks for values passed between rust and javascript
+//! These are the same value corpus and generator used by the crate's own tests, so downstream users
+//! writing custom extensions or wire formats can verify their type mappings in a consistent way
+//!
+//! Values are sent into javascript as function arguments, returned unchanged, and deserialized back:
+//! ```rust
+//! use rustyscript::{conformance, Runtime};
+//!
+//! #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! let mut runtime = Runtime::new(Default::default()).unwrap();
+//! conformance::assert_round_trip(&mut runtime, &Point { x: 1, y: -2 });
+//!
+//! // Check a corpus of edge cases, and a few hundred generated values
+//! conformance::check_corpus(&mut runtime);
+//! conformance::check_generated(&mut runtime, 42, 200);
+//! ```
+//!
+//! Note that javascript has a single number type - whole floats come back as integers,
+//! and integers beyond 2^53 lose precision. [equivalent] compares values with that in mind
+use crate::{serde_json::Value, Error, Runtime, Undefined};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Name of the global function used to return values unchanged
+const ECHO: &str = "__rustyscript_conformance_echo";
+
+/// The largest integer javascript can represent exactly
+pub const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Send a value into javascript and back, returning the deserialized result
+pub fn round_trip<T>(runtime: &mut Runtime, value: &T) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let defined: bool = runtime.eval(&format!("typeof globalThis.{ECHO} === 'function'"))?;
+    if !defined {
+        runtime.eval::<Undefined>(&format!("globalThis.{ECHO} = (value) => value"))?;
+    }
+
+    let value = crate::serde_json::to_value(value)?;
+    runtime.call_function(None, ECHO, &[value])
+}
+
+/// Assert that a value survives a round trip into javascript unchanged
+///
+/// # Panics
+/// If the round trip fails, or the value returned does not match
+pub fn assert_round_trip<T>(runtime: &mut Runtime, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    match round_trip(runtime, value) {
+        Ok(result) => assert_eq!(value, &result, "value changed in a round trip"),
+        Err(e) => panic!("round trip of {value:?} failed: {e}"),
+    }
+}
+
+/// Assert that a JSON value survives a round trip, as judged by [equivalent]
+///
+/// # Panics
+/// If the round trip fails, or the value returned is not equivalent
+pub fn assert_json_round_trip(runtime: &mut Runtime, value: &Value) {
+    match round_trip(runtime, value) {
+        Ok(result) => assert!(
+            equivalent(value, &result),
+            "value changed in a round trip: {value} became {result}"
+        ),
+        Err(e) => panic!("round trip of {value} failed: {e}"),
+    }
+}
+
+/// Returns true if two JSON values are the same once passed through javascript
+/// Numbers are compared by value, so `1` and `1.0` are equivalent, and key order is ignored
+pub fn equivalent(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equivalent(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, a)| b.get(k).is_some_and(|b| equivalent(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// A corpus of edge cases for value conversion
+pub fn corpus() -> Vec<Value> {
+    use crate::serde_json::json;
+    vec![
+        json!(null),
+        json!(true),
+        json!(false),
+        json!(0),
+        json!(-1),
+        json!(MAX_SAFE_INTEGER),
+        json!(-MAX_SAFE_INTEGER),
+        json!(0.5),
+        json!(-1.25e-10),
+        json!(1.7976931348623157e308),
+        json!(f64::MIN_POSITIVE),
+        json!(""),
+        json!("ascii"),
+        json!("quotes \" and \\ backslashes"),
+        json!("line\nbreaks\r\nand\ttabs"),
+        json!("unicode é ß 漢字 🦀"),
+        json!("\u{0000} nul"),
+        json!([]),
+        json!({}),
+        json!([1, "two", null, [3.5], {"four": 4}]),
+        json!({"nested": {"deeply": {"very": {"much": [1, [2, [3]]]}}}}),
+        json!({"": "empty key", "1": "numeric key", "b": 1, "a": 2}),
+        json!({"__proto__": "not a prototype"}),
+    ]
+}
+
+/// Assert that every value in [corpus] survives a round trip
+///
+/// # Panics
+/// If any value fails to round trip
+pub fn check_corpus(runtime: &mut Runtime) {
+    for value in corpus() {
+        assert_json_round_trip(runtime, &value);
+    }
+}
+
+/// Assert that `count` generated values survive a round trip
+/// The same seed always generates the same values, so failures can be reproduced
+///
+/// # Panics
+/// If any value fails to round trip
+pub fn check_generated(runtime: &mut Runtime, seed: u64, count: usize) {
+    let mut generator = ValueGenerator::new(seed);
+    for _ in 0..count {
+        assert_json_round_trip(runtime, &generator.value());
+    }
+}
+
+/// Generates random JSON values from a seed, for round-trip testing
+/// Numbers stay within the range javascript can represent exactly
+pub struct ValueGenerator {
+    state: u64,
+    max_depth: usize,
+}
+
+impl ValueGenerator {
+    /// Create a generator, with nesting limited to 4 levels
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            max_depth: 4,
+        }
+    }
+
+    /// Set the maximum nesting depth of generated arrays and objects
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Generate the next value
+    pub fn value(&mut self) -> Value {
+        self.value_at(0)
+    }
+
+    fn value_at(&mut self, depth: usize) -> Value {
+        let kinds = if depth < self.max_depth { 7 } else { 5 };
+        match self.below(kinds) {
+            0 => Value::Null,
+            1 => Value::Bool(self.below(2) == 1),
+            2 => {
+                let n = (self.next() >> 11) as i64 % MAX_SAFE_INTEGER;
+                (if self.below(2) == 1 { -n } else { n }).into()
+            }
+            3 => {
+                let n = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+                let exponent = self.below(40) as i32 - 20;
+                (n * 10f64.powi(exponent)).into()
+            }
+            4 => Value::String(self.string()),
+            5 => {
+                let len = self.below(5);
+                Value::Array((0..len).map(|_| self.value_at(depth + 1)).collect())
+            }
+            _ => {
+                let len = self.below(5);
+                Value::Object(
+                    (0..len)
+                        .map(|_| (self.string(), self.value_at(depth + 1)))
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    fn string(&mut self) -> String {
+        const CHARS: &[char] = &['a', 'Z', '0', ' ', '"', '\\', '\n', 'é', '漢', '🦀'];
+        let len = self.below(8);
+        (0..len).map(|_| CHARS[self.below(CHARS.len())]).collect()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// splitmix64
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test_conformance {
+    use super::*;
+
+    #[test]
+    fn test_round_trips() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        check_corpus(&mut runtime);
+        check_generated(&mut runtime, 0x5eed, 500);
+
+        assert_round_trip(&mut runtime, &vec![Some(1u8), None, Some(255)]);
+        assert_round_trip(&mut runtime, &("tuple".to_string(), 2i64, 0.25f32));
+    }
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let mut a = ValueGenerator::new(7);
+        let mut b = ValueGenerator::new(7);
+        for _ in 0..50 {
+            assert_eq!(a.value(), b.value());
+        }
+    }
+}
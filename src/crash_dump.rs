@@ -0,0 +1,164 @@
+use crate::RuntimeStats;
+use deno_core::v8;
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr},
+    io::Write,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+};
+
+/// What a runtime was doing when it last ran javascript
+/// Kept in a slot of the runtime's isolate, so that it can be written out if the isolate aborts
+struct CrashContext {
+    path: PathBuf,
+    activity: Option<(String, RuntimeStats)>,
+    heap_limit: Option<(usize, usize)>,
+}
+
+/// Stored in the isolate's slot - the near-heap-limit callback is given a pointer to it
+type SharedContext = Rc<RefCell<CrashContext>>;
+
+thread_local! {
+    // The isolate that last ran javascript on this thread
+    // V8 does not say which isolate failed when it reports an error, but it is the one running
+    static CURRENT: RefCell<Weak<RefCell<CrashContext>>> = const { RefCell::new(Weak::new()) };
+}
+
+// The bindings do not expose fatal error handlers, so V8's own method is called directly
+#[cfg(not(windows))]
+extern "C" {
+    #[link_name = "_ZN2v87Isolate20SetFatalErrorHandlerEPFvPKcS2_E"]
+    fn set_fatal_error_handler(
+        isolate: *mut v8::Isolate,
+        callback: extern "C" fn(*const c_char, *const c_char),
+    );
+}
+
+/// Install the handlers that write crash reports to `path` when this isolate aborts
+/// Reports are written if the isolate runs out of memory, or, outside of windows, hits a fatal error
+pub(crate) fn install(isolate: &mut v8::Isolate, path: &Path) {
+    let context: SharedContext = Rc::new(RefCell::new(CrashContext {
+        path: path.to_path_buf(),
+        activity: None,
+        heap_limit: None,
+    }));
+
+    // The slot keeps the context alive for as long as the isolate can call back with it
+    let data = Rc::as_ptr(&context) as *mut c_void;
+    isolate.set_slot(context);
+    isolate.set_oom_error_handler(on_oom);
+    isolate.add_near_heap_limit_callback(on_near_heap_limit, data);
+
+    // SAFETY: the isolate is valid, and the callback never returns to V8
+    #[cfg(not(windows))]
+    unsafe {
+        set_fatal_error_handler(isolate, on_fatal_error);
+    }
+}
+
+/// Record the activity of the runtime about to run javascript in this isolate
+/// Does nothing if crash reports were not installed for it
+pub(crate) fn set_context(isolate: &v8::Isolate, activity: String, stats: RuntimeStats) {
+    let Some(context) = isolate.get_slot::<SharedContext>() else {
+        return;
+    };
+    if let Ok(mut context) = context.try_borrow_mut() {
+        context.activity = Some((activity, stats));
+    }
+
+    CURRENT.with(|current| *current.borrow_mut() = Rc::downgrade(context));
+}
+
+extern "C" fn on_near_heap_limit(data: *mut c_void, current: usize, initial: usize) -> usize {
+    // SAFETY: `data` points to the context in the isolate's slot, which outlives its callbacks
+    let context = unsafe { &*(data as *const RefCell<CrashContext>) };
+    if let Ok(mut context) = context.try_borrow_mut() {
+        context.heap_limit = Some((current, initial));
+    }
+
+    // Leave the limit unchanged - this callback only records it
+    current
+}
+
+extern "C" fn on_oom(location: *const c_char, details: &v8::OomDetails) {
+    let reason = format!("out of memory at {}", c_str_lossy(location));
+    let heap_oom = format!("heap oom: {}", details.is_heap_oom);
+    let detail = c_str_lossy(details.detail);
+    report(&reason, &[&heap_oom, &format!("detail: {detail}")]);
+}
+
+extern "C" fn on_fatal_error(location: *const c_char, message: *const c_char) {
+    let location = c_str_lossy(location);
+    let message = c_str_lossy(message);
+    report(
+        &format!("fatal error in {location}"),
+        &[&format!("detail: {message}")],
+    );
+
+    // V8 only aborts by itself if no handler is set, and its state cannot be trusted after this
+    eprintln!("\n#\n# Fatal error in {location}\n# {message}\n#\n");
+    std::process::abort();
+}
+
+/// Write a report for the isolate that last ran javascript on this thread
+fn report(reason: &str, details: &[&str]) {
+    CURRENT.with(|current| {
+        let Some(context) = current.try_borrow().ok().and_then(|c| c.upgrade()) else {
+            return;
+        };
+        let Ok(context) = context.try_borrow() else {
+            return;
+        };
+
+        // The process is about to abort, so there is nowhere to report a failure to write
+        let _ = write_report(&context, reason, details);
+    });
+}
+
+fn write_report(context: &CrashContext, reason: &str, details: &[&str]) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(&context.path)?;
+    writeln!(file, "rustyscript crash report")?;
+    writeln!(file, "reason: {reason}")?;
+    for detail in details {
+        writeln!(file, "{detail}")?;
+    }
+
+    if let Some((current, initial)) = context.heap_limit {
+        writeln!(
+            file,
+            "heap limit: {current} bytes (initially {initial} bytes)"
+        )?;
+    }
+
+    let Some((activity, stats)) = &context.activity else {
+        writeln!(file, "last activity: none")?;
+        return file.sync_all();
+    };
+    writeln!(file, "last activity: {activity}")?;
+
+    writeln!(file)?;
+    writeln!(file, "stats before last activity:")?;
+    writeln!(file, "used heap size: {}", stats.used_heap_size)?;
+    writeln!(file, "total heap size: {}", stats.total_heap_size)?;
+    writeln!(file, "pending ops: {}", stats.pending_ops)?;
+    writeln!(file, "module count: {}", stats.module_count)?;
+    writeln!(file, "eval time: {:?}", stats.eval_time)?;
+
+    let mut op_calls: Vec<_> = stats.op_calls.iter().collect();
+    op_calls.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    writeln!(file)?;
+    writeln!(file, "op calls:")?;
+    for (name, calls) in op_calls {
+        writeln!(file, "{name}: {calls}")?;
+    }
+
+    file.sync_all()
+}
+
+fn c_str_lossy(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(s) }.to_string_lossy().to_string()
+}
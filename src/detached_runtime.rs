@@ -0,0 +1,76 @@
+use crate::Runtime;
+use std::mem::ManuallyDrop;
+
+/// Gives access to the runtime held by a value
+pub(crate) trait HostsRuntime {
+    fn runtime(&mut self) -> &mut Runtime;
+}
+
+impl HostsRuntime for Runtime {
+    fn runtime(&mut self) -> &mut Runtime {
+        self
+    }
+}
+
+impl<A> HostsRuntime for (Runtime, A) {
+    fn runtime(&mut self) -> &mut Runtime {
+        &mut self.0
+    }
+}
+
+impl<A, B> HostsRuntime for (Runtime, A, B) {
+    fn runtime(&mut self) -> &mut Runtime {
+        &mut self.0
+    }
+}
+
+/// A runtime whose isolate is only entered while it is in use
+///
+/// V8 enters each isolate on creation, and requires isolates on a thread to be dropped
+/// in the reverse order of creation. Holding several runtimes on one thread, and dropping
+/// them in any other order, would panic - so the isolate is exited as soon as the runtime
+/// is detached, and entered again around each use, and just before it is dropped
+pub(crate) struct DetachedRuntime<T: HostsRuntime>(ManuallyDrop<T>);
+
+impl<T: HostsRuntime> DetachedRuntime<T> {
+    /// Detach a runtime that was just created, before any other runtime is created on this thread
+    pub fn new(mut value: T) -> Self {
+        // Safety: a new runtime's isolate is the one entered last on this thread
+        unsafe { value.runtime().deno_runtime().v8_isolate().exit() };
+        Self(ManuallyDrop::new(value))
+    }
+
+    /// Run `f` with the runtime's isolate entered
+    pub fn with<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        let _entered = Entered::new(self.0.runtime());
+        f(&mut self.0)
+    }
+}
+
+impl<T: HostsRuntime> Drop for DetachedRuntime<T> {
+    fn drop(&mut self) {
+        // The isolate is exited again as it is dropped
+        unsafe {
+            self.0.runtime().deno_runtime().v8_isolate().enter();
+            ManuallyDrop::drop(&mut self.0);
+        }
+    }
+}
+
+/// Keeps an isolate entered until dropped, even if the code using it panics
+struct Entered(*mut deno_core::v8::Isolate);
+
+impl Entered {
+    fn new(runtime: &mut Runtime) -> Self {
+        let isolate: &mut deno_core::v8::Isolate = runtime.deno_runtime().v8_isolate();
+        unsafe { isolate.enter() };
+        Self(isolate)
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        // Safety: the runtime outlives this guard, which is dropped at the end of `with`
+        unsafe { (*self.0).exit() };
+    }
+}
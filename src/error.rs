@@ -12,6 +12,10 @@ pub enum Error {
     #[error("{0} could not be found in global, or module exports")]
     ValueNotFound(String),
 
+    /// Triggers when a segment of a property path could not be found
+    #[error("{0} could not be found while resolving {1}")]
+    PathNotFound(String, String),
+
     /// Triggers when attempting to call a value as a function
     #[error("{0} is not a function")]
     ValueNotCallable(String),
@@ -24,21 +28,81 @@ pub enum Error {
     #[error("value could not be deserialized: {0}")]
     JsonDecode(String),
 
+    /// Triggers when a value cannot be copied with the structured clone algorithm,
+    /// such as a function, or converted from a `StructuredValue`
+    #[error("value could not be cloned: {0}")]
+    DataClone(String),
+
     /// Triggers when a module could not be loaded from the filesystem
     #[error("{0}")]
     ModuleNotFound(String),
 
+    /// Triggers when a remote import is denied by the runtime's `ImportPolicy`
+    #[error("import of {0} was denied by the import policy")]
+    ImportDenied(String),
+
+    /// Triggers when a script accesses a resource the host has not granted it
+    #[error("{0} was denied")]
+    PermissionDenied(String),
+
+    /// Triggers when a tenant's quota is exhausted, before running any more javascript
+    #[error("{0}")]
+    QuotaExceeded(crate::quota::QuotaExceeded),
+
     /// Triggers on runtime issues during execution of a module
     #[error("{0}")]
     Runtime(String),
 
-    /// Runtime error we successfully downcast
+    /// Triggers when javascript throws an exception
+    /// Carries the exception's name, message and stack frames, along with the value thrown
     #[error("{0}")]
-    JsError(#[from] deno_core::error::JsError),
+    JsError(Box<JsException>),
 
     /// Triggers when a module times out before finishing
     #[error("Module timed out: {0}")]
     Timeout(String),
+
+    /// Returned by registered functions to throw a JS error with a name, code or data of its own
+    /// See [CallbackError]
+    #[error("{0}")]
+    Callback(Box<CallbackError>),
+
+    /// Triggers when JS calls a registered function with arguments that do not match its `ArgSchema`
+    /// Thrown in JS as a `TypeError`
+    #[error("{0}")]
+    InvalidArguments(String),
+
+    /// Triggers when a `RuntimeBuilder` is given an invalid configuration
+    #[error("invalid runtime configuration: {0}")]
+    Configuration(String),
+
+    /// Triggers when a worker's bounded query queue is full
+    #[error("worker queue is full ({0} queries pending)")]
+    WorkerBusy(usize),
+
+    /// Triggers when a worker thread panics
+    /// Carries the panic message and backtrace, and the query the worker was handling
+    #[error("{0}")]
+    WorkerCrashed(Box<WorkerCrashReport>),
+
+    /// Returned by a `SupervisedWorker` call that found its worker stopped
+    /// The worker has been restarted, but the call was not retried
+    /// Carries the crash report, or the error the call returned
+    #[error("worker stopped unexpectedly, and was restarted: {0}")]
+    WorkerRestarted(Box<Error>),
+
+    /// Triggers when an operation is stopped through a `CancelHandle` or an `InterruptHandle`
+    #[error("{0} was cancelled")]
+    Cancelled(String),
+
+    /// Triggers in strict mode when a function or expression results in `undefined`
+    #[error("{0} was undefined")]
+    UnexpectedUndefined(String),
+
+    /// Triggers when `strict_top_level_await` is set, and a module's top-level await
+    /// does not settle before the module times out, or before the event loop runs dry
+    #[error("top-level await in {0} never resolved")]
+    UnresolvedTopLevelAwait(String),
 }
 
 impl Error {
@@ -103,6 +167,237 @@ impl Error {
             _ => format!("{}", self),
         }
     }
+
+    /// A stable name for the kind of error, such as `"JsError"` or `"Timeout"`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::MissingEntrypoint(_) => "MissingEntrypoint",
+            Error::ValueNotFound(_) => "ValueNotFound",
+            Error::PathNotFound(_, _) => "PathNotFound",
+            Error::ValueNotCallable(_) => "ValueNotCallable",
+            Error::V8Encoding(_) => "V8Encoding",
+            Error::JsonDecode(_) => "JsonDecode",
+            Error::DataClone(_) => "DataClone",
+            Error::ModuleNotFound(_) => "ModuleNotFound",
+            Error::ImportDenied(_) => "ImportDenied",
+            Error::PermissionDenied(_) => "PermissionDenied",
+            Error::QuotaExceeded(_) => "QuotaExceeded",
+            Error::Runtime(_) => "Runtime",
+            Error::JsError(_) => "JsError",
+            Error::Timeout(_) => "Timeout",
+            Error::WorkerBusy(_) => "WorkerBusy",
+            Error::WorkerCrashed(_) => "WorkerCrashed",
+            Error::WorkerRestarted(_) => "WorkerRestarted",
+            Error::Cancelled(_) => "Cancelled",
+            Error::Configuration(_) => "Configuration",
+            Error::InvalidArguments(_) => "InvalidArguments",
+            Error::Callback(_) => "Callback",
+            Error::UnexpectedUndefined(_) => "UnexpectedUndefined",
+            Error::UnresolvedTopLevelAwait(_) => "UnresolvedTopLevelAwait",
+        }
+    }
+
+    /// Summarize the error in a flat, serializable form
+    /// Suitable for sending on to another process or a frontend
+    pub fn report(&self) -> ErrorReport {
+        let mut report = ErrorReport {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            stack: None,
+            source_line: None,
+            file_name: None,
+            line_number: None,
+            column_number: None,
+        };
+
+        if let Error::JsError(e) = self {
+            report.message = e.exception_message.clone();
+            report.stack.clone_from(&e.stack);
+            report.source_line.clone_from(&e.source_line);
+            if let Some(frame) = e.frames.first() {
+                report.file_name.clone_from(&frame.file_name);
+                report.line_number = frame.line_number;
+                report.column_number = frame.column_number;
+            }
+        }
+
+        report
+    }
+}
+
+/// A flat, serializable summary of an [Error], created with `Error::report`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ErrorReport {
+    /// The kind of error - see `Error::code`
+    pub code: String,
+
+    /// The error message
+    pub message: String,
+
+    /// The javascript stack trace, for exceptions
+    pub stack: Option<String>,
+
+    /// The line of source code the exception was thrown from, if known
+    pub source_line: Option<String>,
+
+    /// The file the exception was thrown from, if known
+    pub file_name: Option<String>,
+
+    /// The line the exception was thrown from, if known
+    pub line_number: Option<i64>,
+
+    /// The column the exception was thrown from, if known
+    pub column_number: Option<i64>,
+}
+
+/// Details of a worker thread panic, as returned in `Error::WorkerCrashed`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerCrashReport {
+    /// The panic message
+    pub message: String,
+
+    /// Where the panic happened, as `file:line:column`, if known
+    pub location: Option<String>,
+
+    /// Backtrace of the worker thread at the time of the panic
+    /// Empty if it could not be captured, such as when another panic hook replaced the worker's own
+    pub backtrace: String,
+
+    /// Description of the query the worker was handling, if any
+    pub query: Option<String>,
+}
+
+impl std::fmt::Display for WorkerCrashReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "worker thread panicked: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        if let Some(query) = &self.query {
+            write!(f, " while handling {query}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned by a registered rust function, thrown in JS as a `rustyscript.CallbackError`
+/// The JS error has the same `name` and `message`, and `code` and `data` properties if they are set
+///
+/// Other errors returned by registered functions are also thrown as a `rustyscript.CallbackError`,
+/// named `Error`, with their kind as the `code` - see `Error::code`
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ CallbackError, Runtime, Error, serde_json::json };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// runtime.register_function("find", |args| {
+///     Err(CallbackError::new(format!("no user named {}", args[0]))
+///         .with_name("NotFoundError")
+///         .with_code("E_NOT_FOUND")
+///         .with_data(json!({ "user": args[0] }))
+///         .into())
+/// })?;
+///
+/// let caught: String = runtime.eval("
+///     try { rustyscript.functions.find('bob') } catch (e) {
+///         `${e instanceof rustyscript.CallbackError} ${e.name} ${e.code} ${e.data.user}`
+///     }
+/// ")?;
+/// assert_eq!("true NotFoundError E_NOT_FOUND bob", caught);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CallbackError {
+    /// The name of the JS error, such as `NotFoundError`
+    pub name: String,
+
+    /// The error message
+    pub message: String,
+
+    /// A machine-readable code for the error, set as the JS error's `code` property
+    pub code: Option<String>,
+
+    /// Extra details, set as the JS error's `data` property
+    pub data: Option<deno_core::serde_json::Value>,
+}
+
+impl CallbackError {
+    /// Create an error named `CallbackError`, with no code or data
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            name: "CallbackError".to_string(),
+            message: message.into(),
+            code: None,
+            data: None,
+        }
+    }
+
+    /// Set the name of the JS error
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the JS error's `code` property
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Set the JS error's `data` property
+    pub fn with_data(mut self, data: deno_core::serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+}
+
+impl std::fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
+}
+
+impl From<CallbackError> for Error {
+    fn from(error: CallbackError) -> Self {
+        Error::Callback(Box::new(error))
+    }
+}
+
+/// A javascript exception, as returned in `Error::JsError`
+///
+/// Dereferences to deno's [deno_core::error::JsError], for the exception's `name`, `message`,
+/// `stack`, and the `frames` (file, line and column) it was thrown from
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsException {
+    /// Details of the exception, including its stack trace
+    pub error: deno_core::error::JsError,
+
+    /// The value that was thrown, if it could be represented as JSON
+    /// For `Error` objects, this contains only their own enumerable properties, such as a `code`
+    pub value: Option<deno_core::serde_json::Value>,
+}
+
+impl std::ops::Deref for JsException {
+    type Target = deno_core::error::JsError;
+    fn deref(&self) -> &Self::Target {
+        &self.error
+    }
+}
+
+impl std::fmt::Display for JsException {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl From<deno_core::error::JsError> for JsException {
+    fn from(error: deno_core::error::JsError) -> Self {
+        Self { error, value: None }
+    }
 }
 
 #[macro_use]
@@ -121,6 +416,7 @@ mod error_macro {
 }
 
 map_error!(std::cell::BorrowMutError, |e| Error::Runtime(e.to_string()));
+map_error!(std::cell::BorrowError, |e| Error::Runtime(e.to_string()));
 map_error!(std::io::Error, |e| Error::ModuleNotFound(e.to_string()));
 map_error!(deno_core::v8::DataError, |e| Error::Runtime(e.to_string()));
 map_error!(deno_core::ModuleResolutionError, |e| Error::Runtime(
@@ -136,12 +432,19 @@ map_error!(deno_core::serde_v8::Error, |e| Error::JsonDecode(
     e.to_string()
 ));
 
+map_error!(deno_core::error::JsError, |e| Error::JsError(Box::new(
+    e.into()
+)));
+
 map_error!(deno_core::anyhow::Error, |e| {
-    // trydowncast to deno_core::error::JsError
+    // trydowncast to deno_core::error::JsError, or one of our own errors raised by the module loader
     let s = e.to_string();
     match e.downcast::<deno_core::error::JsError>() {
-        Ok(js_error) => Error::JsError(js_error),
-        Err(_) => Error::Runtime(s),
+        Ok(js_error) => Error::JsError(Box::new(js_error.into())),
+        Err(e) => match e.downcast::<Error>() {
+            Ok(e) => e,
+            Err(_) => Error::Runtime(s),
+        },
     }
 });
 
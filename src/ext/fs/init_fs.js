@@ -0,0 +1,31 @@
+// File access for scripts, limited to the directories granted by the host's FsPermissions
+const toPath = (path) => {
+    if (path instanceof URL) {
+        if (path.protocol !== 'file:') throw new TypeError(`Must be a file URL, got ${path.href}`);
+        return decodeURIComponent(path.pathname);
+    }
+    return String(path);
+};
+
+const readTextFileSync = (path) => Deno.core.ops.op_fs_read_text_file(toPath(path));
+const readFileSync = (path) => Deno.core.ops.op_fs_read_file(toPath(path));
+
+const writeFileSync = (path, data, options = {}) => {
+    if (!(data instanceof Uint8Array)) {
+        throw new TypeError('The data to write must be a Uint8Array');
+    }
+    Deno.core.ops.op_fs_write_file(toPath(path), data, Boolean(options.append));
+};
+const writeTextFileSync = (path, data, options = {}) =>
+    writeFileSync(path, Deno.core.encode(String(data)), options);
+
+Object.assign(globalThis.Deno, {
+    readTextFileSync,
+    readFileSync,
+    writeTextFileSync,
+    writeFileSync,
+    readTextFile: async (path) => readTextFileSync(path),
+    readFile: async (path) => readFileSync(path),
+    writeTextFile: async (path, data, options) => writeTextFileSync(path, data, options),
+    writeFile: async (path, data, options) => writeFileSync(path, data, options),
+});
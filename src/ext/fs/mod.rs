@@ -0,0 +1,205 @@
+use crate::Error;
+use deno_core::{extension, op2, Extension, OpState};
+use std::path::{Path, PathBuf};
+
+/// Source of the `node:fs/promises` module, served by the module loader
+pub const NODE_FS_PROMISES: &str = include_str!("node_fs_promises.js");
+
+/// Directories a script may access through the `fs` extension
+/// Nothing is accessible by default
+///
+/// Paths are checked after resolving `..` and symlinks, so scripts cannot escape a granted directory
+#[derive(Debug, Clone, Default)]
+pub struct FsPermissions {
+    /// Directories whose files, and subdirectories, can be read
+    pub read: Vec<PathBuf>,
+
+    /// Directories whose files, and subdirectories, can be created, written and appended to
+    pub write: Vec<PathBuf>,
+}
+
+impl FsPermissions {
+    /// Returns the resolved path if `path` is inside one of the directories granted for reading
+    pub fn check_read(&self, path: &str) -> Result<PathBuf, Error> {
+        let resolved = std::fs::canonicalize(path).map_err(|e| io_error(path, e))?;
+        Self::check(&self.read, path, resolved, "read")
+    }
+
+    /// Returns the resolved path if `path` is inside one of the directories granted for writing
+    /// The file itself does not need to exist yet, but its parent directory does
+    pub fn check_write(&self, path: &str) -> Result<PathBuf, Error> {
+        let target = Path::new(path);
+        let resolved = match std::fs::canonicalize(target) {
+            Ok(resolved) => resolved,
+
+            // A symlink to a missing file would be followed when the file is created
+            Err(_)
+                if std::fs::symlink_metadata(target)
+                    .is_ok_and(|metadata| metadata.file_type().is_symlink()) =>
+            {
+                return Err(Error::PermissionDenied(format!("write access to {path}")));
+            }
+
+            // A new file - resolve its parent instead
+            Err(_) => match (target.parent(), target.file_name()) {
+                (Some(parent), Some(name)) => {
+                    let parent = if parent.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        parent
+                    };
+                    std::fs::canonicalize(parent)
+                        .map_err(|e| io_error(path, e))?
+                        .join(name)
+                }
+                _ => return Err(Error::PermissionDenied(format!("write access to {path}"))),
+            },
+        };
+        Self::check(&self.write, path, resolved, "write")
+    }
+
+    /// Opens a file for writing, if `check_write` allows it
+    /// Symlinks put in place of the file after the check are not followed, and the path of the
+    /// opened file is checked again before anything is written
+    ///
+    /// A file created here is removed again if that second check fails
+    fn open_write(&self, path: &str, append: bool) -> Result<std::fs::File, Error> {
+        let resolved = self.check_write(path)?;
+
+        // Truncated only once the file is known to be the one that was checked
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).append(append);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NOFOLLOW);
+
+        // Created exclusively, so that a file made here can be told apart from one that was already there
+        let (file, created) = match options.clone().create_new(true).open(&resolved) {
+            Ok(file) => (file, true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => (
+                options.open(&resolved).map_err(|e| io_error(path, e))?,
+                false,
+            ),
+            Err(e) => return Err(io_error(path, e)),
+        };
+
+        if let Err(e) = self.check_opened(path, &resolved, &file) {
+            if created {
+                remove_created(&resolved, &file);
+            }
+            return Err(e);
+        }
+
+        if !append {
+            file.set_len(0).map_err(|e| io_error(path, e))?;
+        }
+        Ok(file)
+    }
+
+    /// Check that the file opened at `resolved` is still within the granted directories
+    fn check_opened(&self, path: &str, resolved: &Path, file: &std::fs::File) -> Result<(), Error> {
+        let real = std::fs::canonicalize(resolved).map_err(|e| io_error(path, e))?;
+        let real = Self::check(&self.write, path, real, "write")?;
+
+        #[cfg(unix)]
+        if !same_file(file, &real) {
+            return Err(Error::PermissionDenied(format!("write access to {path}")));
+        }
+
+        #[cfg(not(unix))]
+        let _ = (file, real);
+        Ok(())
+    }
+
+    fn check(
+        granted: &[PathBuf],
+        path: &str,
+        resolved: PathBuf,
+        access: &str,
+    ) -> Result<PathBuf, Error> {
+        let allowed = granted
+            .iter()
+            .filter_map(|dir| std::fs::canonicalize(dir).ok())
+            .any(|dir| resolved.starts_with(dir));
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "{access} access to {path}"
+            )))
+        }
+    }
+}
+
+/// Returns true if `path` is the file that was opened as `file`
+#[cfg(unix)]
+fn same_file(file: &std::fs::File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), std::fs::symlink_metadata(path)) {
+        (Ok(opened), Ok(found)) => (opened.dev(), opened.ino()) == (found.dev(), found.ino()),
+        _ => false,
+    }
+}
+
+/// Remove a file created by `open_write` that failed its second check
+/// Nothing is removed if something else has been put at its path since
+fn remove_created(path: &Path, file: &std::fs::File) {
+    #[cfg(unix)]
+    if !same_file(file, path) {
+        return;
+    }
+
+    #[cfg(not(unix))]
+    let _ = file;
+
+    // The write is refused either way - the empty file is only left behind if this fails
+    let _ = std::fs::remove_file(path);
+}
+
+fn io_error(path: &str, e: std::io::Error) -> Error {
+    Error::Runtime(format!("{path}: {e}"))
+}
+
+#[op2]
+#[string]
+fn op_fs_read_text_file(state: &mut OpState, #[string] path: String) -> Result<String, Error> {
+    let resolved = state.borrow::<FsPermissions>().check_read(&path)?;
+    std::fs::read_to_string(resolved).map_err(|e| io_error(&path, e))
+}
+
+#[op2]
+#[buffer]
+fn op_fs_read_file(state: &mut OpState, #[string] path: String) -> Result<Vec<u8>, Error> {
+    let resolved = state.borrow::<FsPermissions>().check_read(&path)?;
+    std::fs::read(resolved).map_err(|e| io_error(&path, e))
+}
+
+#[op2(fast)]
+fn op_fs_write_file(
+    state: &mut OpState,
+    #[string] path: String,
+    #[buffer] data: &[u8],
+    append: bool,
+) -> Result<(), Error> {
+    let mut file = state.borrow::<FsPermissions>().open_write(&path, append)?;
+    std::io::Write::write_all(&mut file, data).map_err(|e| io_error(&path, e))
+}
+
+extension!(
+    init_fs,
+    deps = [rustyscript],
+    ops = [op_fs_read_text_file, op_fs_read_file, op_fs_write_file],
+    esm_entry_point = "ext:init_fs/init_fs.js",
+    esm = [ dir "src/ext/fs", "init_fs.js" ],
+    options = {
+        permissions: FsPermissions,
+    },
+    state = |state, config| state.put(config.permissions),
+);
+
+pub fn extensions(permissions: FsPermissions) -> Vec<Extension> {
+    vec![init_fs::init_ops_and_esm(permissions)]
+}
+
+pub fn snapshot_extensions(permissions: FsPermissions) -> Vec<Extension> {
+    vec![init_fs::init_ops(permissions)]
+}
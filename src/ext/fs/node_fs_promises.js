@@ -0,0 +1,41 @@
+// A minimal `node:fs/promises`, on top of the Deno file APIs from the fs feature
+// Only UTF-8 text, and binary data as Uint8Array, are supported
+const encodingOf = (options) => {
+    const encoding = typeof options === 'string' ? options : options?.encoding;
+    if (encoding && !['utf8', 'utf-8'].includes(encoding.toLowerCase())) {
+        throw new TypeError(`Unsupported encoding: ${encoding}`);
+    }
+    return encoding;
+};
+
+const toBytes = (data) => {
+    if (ArrayBuffer.isView(data)) {
+        return new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+    }
+    if (data instanceof ArrayBuffer) {
+        return new Uint8Array(data);
+    }
+
+    // `new Uint8Array(n)` would quietly write n zero bytes for a number
+    throw new TypeError(
+        `The "data" argument must be a string, TypedArray, DataView or ArrayBuffer. Received ${typeof data}`
+    );
+};
+
+export async function readFile(path, options) {
+    return encodingOf(options) ? Deno.readTextFile(path) : Deno.readFile(path);
+}
+
+export async function writeFile(path, data, options) {
+    encodingOf(options);
+    const append = options?.flag === 'a';
+    return typeof data === 'string'
+        ? Deno.writeTextFile(path, data, { append })
+        : Deno.writeFile(path, toBytes(data), { append });
+}
+
+export async function appendFile(path, data, options) {
+    return writeFile(path, data, { ...(typeof options === 'string' ? { encoding: options } : options), flag: 'a' });
+}
+
+export default { readFile, writeFile, appendFile };
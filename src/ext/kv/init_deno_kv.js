@@ -0,0 +1,181 @@
+/**
+ * A subset of the Deno KV API, backed by the host's key-value store
+ * Keys are stored JSON-encoded, and entries as `{ value, versionstamp }`
+ * Values must be JSON-serializable
+ * Versionstamps come from the store, so runtimes sharing it agree on them
+ */
+
+function nextVersionstamp() {
+    return Deno.core.ops.op_kv_versionstamp();
+}
+
+// Byte key parts are stored as hex, which keeps them compact inside the JSON-encoded key
+function toHex(bytes) {
+    let hex = '';
+    for (const byte of bytes) hex += byte.toString(16).padStart(2, '0');
+    return hex;
+}
+
+function fromHex(hex) {
+    const bytes = new Uint8Array(hex.length / 2);
+    for (let i = 0; i < bytes.length; i++) {
+        bytes[i] = parseInt(hex.substr(i * 2, 2), 16);
+    }
+    return bytes;
+}
+
+function encodeKeyPart(part) {
+    switch (typeof part) {
+        case 'string':
+        case 'number':
+        case 'boolean':
+            return part;
+        case 'bigint':
+            return { bigint: part.toString() };
+        default:
+            if (part instanceof Uint8Array) return { bytes: toHex(part) };
+            throw new TypeError(`Unsupported key part: ${part}`);
+    }
+}
+
+function decodeKeyPart(part) {
+    if (part !== null && typeof part === 'object') {
+        return 'bigint' in part ? BigInt(part.bigint) : fromHex(part.bytes);
+    }
+    return part;
+}
+
+function encodeKey(key) {
+    if (!Array.isArray(key)) throw new TypeError('Key must be an array');
+    return JSON.stringify(key.map(encodeKeyPart));
+}
+
+function decodeKey(encoded) {
+    return JSON.parse(encoded).map(decodeKeyPart);
+}
+
+function toEntry(key, stored) {
+    if (stored === null || stored === undefined) {
+        return { key, value: null, versionstamp: null };
+    }
+
+    // Values written by the host or `rustyscript.kv` have no versionstamp
+    if (typeof stored !== 'object' || !('versionstamp' in stored)) {
+        return { key, value: stored, versionstamp: '0'.repeat(20) };
+    }
+
+    return { key, value: stored.value, versionstamp: stored.versionstamp };
+}
+
+class AtomicOperation {
+    #kv;
+    #checks = [];
+    #mutations = [];
+
+    constructor(kv) {
+        this.#kv = kv;
+    }
+
+    check(...checks) {
+        this.#checks.push(...checks);
+        return this;
+    }
+
+    set(key, value) {
+        this.#mutations.push({ type: 'set', key, value });
+        return this;
+    }
+
+    delete(key) {
+        this.#mutations.push({ type: 'delete', key });
+        return this;
+    }
+
+    async commit() {
+        for (const { key, versionstamp } of this.#checks) {
+            const entry = await this.#kv.get(key);
+            if (entry.versionstamp !== versionstamp) return { ok: false };
+        }
+
+        const versionstamp = nextVersionstamp();
+        for (const mutation of this.#mutations) {
+            const key = encodeKey(mutation.key);
+            if (mutation.type === 'set') {
+                Deno.core.ops.op_kv_set(key, { value: mutation.value, versionstamp });
+            } else {
+                Deno.core.ops.op_kv_delete(key);
+            }
+        }
+
+        return { ok: true, versionstamp };
+    }
+}
+
+class Kv {
+    #closed = false;
+
+    #assertOpen() {
+        if (this.#closed) throw new TypeError('The KV store is closed');
+    }
+
+    async get(key) {
+        this.#assertOpen();
+        return toEntry(key, Deno.core.ops.op_kv_get(encodeKey(key)));
+    }
+
+    async getMany(keys) {
+        return Promise.all(keys.map((key) => this.get(key)));
+    }
+
+    async set(key, value) {
+        this.#assertOpen();
+        const versionstamp = nextVersionstamp();
+        Deno.core.ops.op_kv_set(encodeKey(key), { value, versionstamp });
+        return { ok: true, versionstamp };
+    }
+
+    async delete(key) {
+        this.#assertOpen();
+        Deno.core.ops.op_kv_delete(encodeKey(key));
+    }
+
+    async *list(selector, options = {}) {
+        this.#assertOpen();
+        if (!selector || !Array.isArray(selector.prefix)) {
+            throw new TypeError('Only prefix selectors are supported');
+        }
+
+        // Match keys strictly longer than the prefix, as Deno does
+        let prefix = encodeKey(selector.prefix).slice(0, -1);
+        if (selector.prefix.length) prefix += ',';
+
+        const entries = [];
+        for (const [encoded, stored] of Deno.core.ops.op_kv_list(prefix)) {
+            try {
+                entries.push([encoded, toEntry(decodeKey(encoded), stored)]);
+            } catch {
+                // Not a key written through this API
+            }
+        }
+
+        entries.sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+        if (options.reverse) entries.reverse();
+
+        const limit = options.limit ?? entries.length;
+        for (const [, entry] of entries.slice(0, limit)) {
+            yield entry;
+        }
+    }
+
+    atomic() {
+        this.#assertOpen();
+        return new AtomicOperation(this);
+    }
+
+    close() {
+        this.#closed = true;
+    }
+}
+
+// The path is ignored - storage is always provided by the host
+globalThis.Deno.openKv = async (_path) => new Kv();
@@ -0,0 +1,7 @@
+import { registerNamespace } from 'ext:rustyscript/rustyscript.js';
+registerNamespace('kv', {
+    'get': (key) => Deno.core.ops.op_kv_get(key),
+    'set': (key, value) => Deno.core.ops.op_kv_set(key, value),
+    'delete': (key) => Deno.core.ops.op_kv_delete(key),
+    'list': (prefix = '') => Deno.core.ops.op_kv_list(prefix),
+});
@@ -0,0 +1,167 @@
+use crate::Error;
+use deno_core::{extension, op2, serde_json, Extension, OpState};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+/// Key used by the default `KvStore::next_versionstamp` to keep its counter in the store
+const VERSIONSTAMP_KEY: &str = "\0versionstamp";
+
+/// Key-value store trait
+/// Implement this trait to back the `rustyscript.kv` API with your own storage
+/// You will need to use interior mutability, since the store is shared with JS
+pub trait KvStore {
+    /// Get a value from the store
+    fn get(&self, key: &str) -> Result<Option<serde_json::Value>, Error>;
+
+    /// Insert or replace a value in the store
+    fn set(&self, key: &str, value: serde_json::Value) -> Result<(), Error>;
+
+    /// Remove a value from the store
+    /// Returns true if the key was present
+    fn delete(&self, key: &str) -> Result<bool, Error>;
+
+    /// List all entries whose key starts with `prefix`, in any order
+    /// Stores that cannot be listed can leave this unimplemented, which returns an error
+    fn list(&self, prefix: &str) -> Result<Vec<(String, serde_json::Value)>, Error> {
+        let _ = prefix;
+        Err(Error::Runtime(
+            "This key-value store does not support listing".to_string(),
+        ))
+    }
+
+    /// Returns a new versionstamp, greater than any returned before by this store
+    /// Used by `Deno.openKv`, so that every runtime sharing the store agrees on versions
+    ///
+    /// By default the counter is kept in the store itself, under a reserved key
+    fn next_versionstamp(&self) -> Result<u64, Error> {
+        let last = self
+            .get(VERSIONSTAMP_KEY)?
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default();
+        self.set(VERSIONSTAMP_KEY, (last + 1).into())?;
+        Ok(last + 1)
+    }
+}
+
+/// Default in-memory key-value store
+/// Clones share the same underlying data, so a copy can be kept by the host
+/// to read and write the values seen by JS
+#[derive(Default, Clone)]
+pub struct MemoryKvStore {
+    values: Rc<RefCell<HashMap<String, serde_json::Value>>>,
+    version: Rc<Cell<u64>>,
+}
+impl KvStore for MemoryKvStore {
+    fn get(&self, key: &str) -> Result<Option<serde_json::Value>, Error> {
+        Ok(self.values.borrow().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: serde_json::Value) -> Result<(), Error> {
+        self.values.borrow_mut().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.values.borrow_mut().remove(key).is_some())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<(String, serde_json::Value)>, Error> {
+        Ok(self
+            .values
+            .borrow()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn next_versionstamp(&self) -> Result<u64, Error> {
+        self.version.set(self.version.get() + 1);
+        Ok(self.version.get())
+    }
+}
+
+type KvStoreBox = Box<dyn KvStore>;
+
+#[op2]
+#[serde]
+fn op_kv_get(
+    #[string] key: String,
+    state: &mut OpState,
+) -> Result<Option<serde_json::Value>, Error> {
+    state.borrow::<KvStoreBox>().get(&key)
+}
+
+#[op2]
+fn op_kv_set(
+    #[string] key: String,
+    #[serde] value: serde_json::Value,
+    state: &mut OpState,
+) -> Result<(), Error> {
+    state.borrow::<KvStoreBox>().set(&key, value)
+}
+
+#[op2(fast)]
+fn op_kv_delete(#[string] key: String, state: &mut OpState) -> Result<bool, Error> {
+    state.borrow::<KvStoreBox>().delete(&key)
+}
+
+#[op2]
+#[serde]
+fn op_kv_list(
+    #[string] prefix: String,
+    state: &mut OpState,
+) -> Result<Vec<(String, serde_json::Value)>, Error> {
+    state.borrow::<KvStoreBox>().list(&prefix)
+}
+
+#[cfg(feature = "deno_kv")]
+#[op2]
+#[string]
+fn op_kv_versionstamp(state: &mut OpState) -> Result<String, Error> {
+    let version = state.borrow::<KvStoreBox>().next_versionstamp()?;
+    Ok(format!("{version:020x}"))
+}
+
+extension!(
+    init_kv,
+    deps = [rustyscript],
+    ops = [op_kv_get, op_kv_set, op_kv_delete, op_kv_list],
+    esm_entry_point = "ext:init_kv/init_kv.js",
+    esm = [ dir "src/ext/kv", "init_kv.js" ],
+    options = {
+        store: Option<KvStoreBox>,
+    },
+    state = |state, config| {
+        let store = config.store.unwrap_or_else(|| Box::new(MemoryKvStore::default()));
+        state.put::<KvStoreBox>(store);
+    },
+);
+
+#[cfg(feature = "deno_kv")]
+extension!(
+    init_deno_kv,
+    deps = [init_kv],
+    ops = [op_kv_versionstamp],
+    esm_entry_point = "ext:init_deno_kv/init_deno_kv.js",
+    esm = [ dir "src/ext/kv", "init_deno_kv.js" ],
+);
+
+pub fn extensions(store: Option<KvStoreBox>) -> Vec<Extension> {
+    vec![
+        init_kv::init_ops_and_esm(store),
+        #[cfg(feature = "deno_kv")]
+        init_deno_kv::init_ops_and_esm(),
+    ]
+}
+
+pub fn snapshot_extensions(store: Option<KvStoreBox>) -> Vec<Extension> {
+    vec![
+        init_kv::init_ops(store),
+        #[cfg(feature = "deno_kv")]
+        init_deno_kv::init_ops(),
+    ]
+}
@@ -1,6 +1,7 @@
 use deno_core::Extension;
 
 pub mod rustyscript;
+pub mod timers;
 
 #[cfg(feature = "console")]
 pub mod console;
@@ -23,6 +24,24 @@ pub mod webidl;
 #[cfg(feature = "io")]
 pub mod io;
 
+#[cfg(feature = "kv")]
+pub mod kv;
+
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "fs")]
+pub mod fs;
+
+#[cfg(feature = "process")]
+pub mod process;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "serve")]
+pub mod serve;
+
 /// Options for configuring extensions
 pub struct ExtensionOptions {
     /// Options specific to the deno_web, deno_fetch and deno_net extensions
@@ -40,6 +59,31 @@ pub struct ExtensionOptions {
     /// Optional path to the directory where the webstorage extension will store its data
     #[cfg(feature = "webstorage")]
     pub webstorage_origin_storage_dir: Option<PathBuf>,
+
+    /// Optional store backing the `rustyscript.kv` API
+    /// If not provided, an empty in-memory store is used
+    #[cfg(feature = "kv")]
+    pub kv_store: Option<Box<dyn kv::KvStore>>,
+
+    /// Optional policy approving connections and messages for the `WebSocket` API
+    /// If not provided, all connections are allowed
+    #[cfg(feature = "websocket")]
+    pub websocket_policy: Option<std::rc::Rc<dyn websocket::WebSocketPolicy>>,
+
+    /// Directories scripts may read and write through the `fs` extension
+    /// By default, no files are accessible
+    #[cfg(feature = "fs")]
+    pub fs_permissions: fs::FsPermissions,
+
+    /// Executables scripts may run through `Deno.Command`
+    /// By default, nothing can be run
+    #[cfg(feature = "process")]
+    pub process_permissions: process::ProcessPermissions,
+
+    /// Database files scripts may open through `rustyscript.sqlite`
+    /// By default, only the runtime's in-memory database is available
+    #[cfg(feature = "sqlite")]
+    pub sqlite_permissions: sqlite::SqlitePermissions,
 }
 
 impl Default for ExtensionOptions {
@@ -53,6 +97,21 @@ impl Default for ExtensionOptions {
 
             #[cfg(feature = "io")]
             io_pipes: Some(Default::default()),
+
+            #[cfg(feature = "kv")]
+            kv_store: None,
+
+            #[cfg(feature = "websocket")]
+            websocket_policy: None,
+
+            #[cfg(feature = "fs")]
+            fs_permissions: fs::FsPermissions::default(),
+
+            #[cfg(feature = "process")]
+            process_permissions: process::ProcessPermissions::default(),
+
+            #[cfg(feature = "sqlite")]
+            sqlite_permissions: sqlite::SqlitePermissions::default(),
         }
     }
 }
@@ -80,12 +139,34 @@ pub fn all_extensions(
     #[cfg(feature = "web")]
     extensions.extend(web::extensions(options.web));
 
+    // deno_web provides its own timers
+    #[cfg(not(feature = "web"))]
+    extensions.extend(timers::extensions());
+
     #[cfg(feature = "crypto")]
     extensions.extend(crypto::extensions(options.crypto_seed));
 
     #[cfg(feature = "io")]
     extensions.extend(io::extensions(options.io_pipes));
 
+    #[cfg(feature = "kv")]
+    extensions.extend(kv::extensions(options.kv_store));
+
+    #[cfg(feature = "websocket")]
+    extensions.extend(websocket::extensions(options.websocket_policy));
+
+    #[cfg(feature = "fs")]
+    extensions.extend(fs::extensions(options.fs_permissions));
+
+    #[cfg(feature = "process")]
+    extensions.extend(process::extensions(options.process_permissions));
+
+    #[cfg(feature = "sqlite")]
+    extensions.extend(sqlite::extensions(options.sqlite_permissions));
+
+    #[cfg(feature = "serve")]
+    extensions.extend(serve::extensions());
+
     extensions.extend(user_extensions);
     extensions
 }
@@ -113,12 +194,33 @@ pub fn all_snapshot_extensions(
     #[cfg(feature = "web")]
     extensions.extend(web::snapshot_extensions(options.web));
 
+    #[cfg(not(feature = "web"))]
+    extensions.extend(timers::snapshot_extensions());
+
     #[cfg(feature = "crypto")]
     extensions.extend(crypto::snapshot_extensions(options.crypto_seed));
 
     #[cfg(feature = "io")]
     extensions.extend(io::snapshot_extensions(options.io_pipes));
 
+    #[cfg(feature = "kv")]
+    extensions.extend(kv::snapshot_extensions(options.kv_store));
+
+    #[cfg(feature = "websocket")]
+    extensions.extend(websocket::snapshot_extensions(options.websocket_policy));
+
+    #[cfg(feature = "fs")]
+    extensions.extend(fs::snapshot_extensions(options.fs_permissions));
+
+    #[cfg(feature = "process")]
+    extensions.extend(process::snapshot_extensions(options.process_permissions));
+
+    #[cfg(feature = "sqlite")]
+    extensions.extend(sqlite::snapshot_extensions(options.sqlite_permissions));
+
+    #[cfg(feature = "serve")]
+    extensions.extend(serve::snapshot_extensions());
+
     extensions.extend(user_extensions);
     extensions
 }
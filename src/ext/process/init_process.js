@@ -0,0 +1,39 @@
+// A `Deno.Command` for running the executables allowed by the host's ProcessPermissions
+// Only `output` and `outputSync` are supported - the command runs to completion before its output is returned
+const toOutput = (output) => ({
+    ...output,
+    stdout: new Uint8Array(output.stdout),
+    stderr: new Uint8Array(output.stderr),
+});
+
+class Command {
+    #args;
+
+    constructor(command, options = {}) {
+        if (command instanceof URL) command = decodeURIComponent(command.pathname);
+        this.#args = {
+            command: String(command),
+            args: (options.args ?? []).map(String),
+            cwd: options.cwd === undefined ? null : String(options.cwd),
+            env: Object.fromEntries(Object.entries(options.env ?? {}).map(([k, v]) => [k, String(v)])),
+            clearEnv: Boolean(options.clearEnv),
+            stdin: options.stdin ?? null,
+            stdout: options.stdout ?? 'piped',
+            stderr: options.stderr ?? 'piped',
+        };
+    }
+
+    outputSync() {
+        return toOutput(Deno.core.ops.op_command_output_sync(this.#args));
+    }
+
+    async output() {
+        return toOutput(await Deno.core.ops.op_command_output(this.#args));
+    }
+
+    spawn() {
+        throw new Error('Deno.Command.spawn is not supported - use output or outputSync');
+    }
+}
+
+globalThis.Deno.Command = Command;
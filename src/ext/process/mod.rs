@@ -0,0 +1,324 @@
+use crate::{interrupt_handle::StopSignal, Error};
+use deno_core::{extension, futures::channel::oneshot, op2, Extension, OpState, ToJsBuffer};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Executables a script may run through `Deno.Command`
+/// Nothing can be run by default
+#[derive(Debug, Clone, Default)]
+pub struct ProcessPermissions {
+    /// Commands that may be run, matched exactly against the name or path given to `Deno.Command`
+    /// Allowing `git` does not allow `/usr/bin/git`, and the reverse
+    ///
+    /// Names are looked up in the host's `PATH`, never in a `PATH` set by the script,
+    /// and relative paths such as `./tool` are refused, since the script controls the working directory
+    pub allow: Vec<String>,
+
+    /// Environment variables a script may set for the commands it runs
+    /// Setting any other variable is refused, so scripts cannot change what a command does
+    /// through variables such as `LD_PRELOAD`, `PATH` or `NODE_OPTIONS`
+    ///
+    /// Commands inherit the host's environment, unless the script asks for it to be cleared
+    pub env: Vec<String>,
+
+    /// Directories a script may run commands in, including their subdirectories
+    /// Commands run in the host's working directory unless the script sets `cwd`,
+    /// which is checked after resolving `..` and symlinks
+    pub cwd: Vec<PathBuf>,
+}
+
+impl ProcessPermissions {
+    /// Returns the path of the executable to run for `command`,
+    /// or an error if `command` is not in the allow-list
+    pub fn check(&self, command: &str) -> Result<PathBuf, Error> {
+        let denied = || Error::PermissionDenied(format!("running {command}"));
+        if !self.allow.iter().any(|allowed| allowed == command) {
+            return Err(denied());
+        }
+
+        let path = Path::new(command);
+        if path.is_absolute() {
+            Ok(path.to_path_buf())
+        } else if path.components().count() > 1 {
+            Err(denied())
+        } else {
+            find_in_path(command)
+                .ok_or_else(|| Error::Runtime(format!("could not find {command} in PATH")))
+        }
+    }
+
+    /// Returns an error unless a script may set the environment variable `name`
+    pub fn check_env(&self, name: &str) -> Result<(), Error> {
+        // Variable names are case-insensitive on windows
+        let allowed = self.env.iter().any(|allowed| {
+            if cfg!(windows) {
+                allowed.eq_ignore_ascii_case(name)
+            } else {
+                allowed == name
+            }
+        });
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "setting the environment variable {name}"
+            )))
+        }
+    }
+
+    /// Returns the resolved directory, if commands may be run in `cwd`
+    pub fn check_cwd(&self, cwd: &Path) -> Result<PathBuf, Error> {
+        let resolved = std::fs::canonicalize(cwd)
+            .map_err(|e| Error::Runtime(format!("{}: {e}", cwd.display())))?;
+        let allowed = self
+            .cwd
+            .iter()
+            .filter_map(|dir| std::fs::canonicalize(dir).ok())
+            .any(|dir| resolved.starts_with(dir));
+
+        if allowed {
+            Ok(resolved)
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "running commands in {}",
+                cwd.display()
+            )))
+        }
+    }
+}
+
+/// Look up an executable in the host's `PATH`
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    #[cfg(windows)]
+    let extensions: Vec<String> = std::env::var("PATHEXT")
+        .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+        .split(';')
+        .map(str::to_string)
+        .collect();
+    #[cfg(not(windows))]
+    let extensions: Vec<String> = vec![];
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        std::iter::once(dir.join(name))
+            .chain(
+                extensions
+                    .iter()
+                    .map(|ext| dir.join(format!("{name}{ext}"))),
+            )
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[derive(serde::Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Stdio {
+    #[default]
+    Piped,
+    Inherit,
+    Null,
+}
+
+impl From<Stdio> for std::process::Stdio {
+    fn from(stdio: Stdio) -> Self {
+        match stdio {
+            Stdio::Piped => Self::piped(),
+            Stdio::Inherit => Self::inherit(),
+            Stdio::Null => Self::null(),
+        }
+    }
+}
+
+/// Options given to `Deno.Command`
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommandArgs {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    #[serde(default)]
+    clear_env: bool,
+    stdin: Option<Stdio>,
+    #[serde(default)]
+    stdout: Stdio,
+    #[serde(default)]
+    stderr: Stdio,
+}
+
+#[derive(serde::Serialize)]
+struct CommandOutput {
+    code: i32,
+    success: bool,
+    signal: Option<i32>,
+    stdout: ToJsBuffer,
+    stderr: ToJsBuffer,
+}
+
+impl CommandArgs {
+    /// Check the command against `permissions`, returning the executable to run
+    /// The working directory is replaced by its resolved path
+    fn check(&mut self, permissions: &ProcessPermissions) -> Result<PathBuf, Error> {
+        let program = permissions.check(&self.command)?;
+        for name in self.env.keys() {
+            permissions.check_env(name)?;
+        }
+        if let Some(cwd) = &self.cwd {
+            self.cwd = Some(permissions.check_cwd(cwd)?);
+        }
+        Ok(program)
+    }
+
+    /// Run `program`, the executable `check` resolved for the command
+    /// The process is killed if `stopped` returns true before it exits
+    fn run(self, program: PathBuf, stopped: impl Fn() -> bool) -> Result<CommandOutput, Error> {
+        // How often the process is checked on while it runs
+        const POLL: std::time::Duration = std::time::Duration::from_millis(5);
+
+        let mut command = std::process::Command::new(program);
+        command.args(&self.args);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        if self.clear_env {
+            command.env_clear();
+        }
+        command
+            .envs(&self.env)
+            .stdin(self.stdin.unwrap_or(Stdio::Null))
+            .stdout(self.stdout)
+            .stderr(self.stderr);
+
+        let error =
+            |e: std::io::Error| Error::Runtime(format!("could not run {}: {e}", self.command));
+        let mut child = command.spawn().map_err(error)?;
+
+        // Pipes are read while the process runs, so that it cannot block on a full pipe
+        drop(child.stdin.take());
+        let stdout = child.stdout.take().map(read_all);
+        let stderr = child.stderr.take().map(read_all);
+
+        let status = loop {
+            match child.try_wait().map_err(error)? {
+                Some(status) => break status,
+                None if stopped() => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Error::Runtime(format!(
+                        "{} was killed, since the call that ran it was stopped",
+                        self.command
+                    )));
+                }
+                None => std::thread::sleep(POLL),
+            }
+        };
+
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        let collect = |pipe: Option<std::thread::JoinHandle<Vec<u8>>>| {
+            pipe.and_then(|pipe| pipe.join().ok()).unwrap_or_default()
+        };
+        Ok(CommandOutput {
+            code: status.code().unwrap_or(1),
+            success: status.success(),
+            signal,
+            stdout: collect(stdout).into(),
+            stderr: collect(stderr).into(),
+        })
+    }
+}
+
+/// Read a pipe to its end on a thread of its own
+fn read_all(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+/// Sets a flag when dropped - the command thread watches it, and kills the process
+/// if the op is dropped before it completes, such as when the call times out
+struct KillOnDrop(Arc<AtomicBool>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[op2]
+#[serde]
+fn op_command_output_sync(
+    state: &mut OpState,
+    #[serde] mut args: CommandArgs,
+) -> Result<CommandOutput, Error> {
+    let program = args.check(state.borrow::<ProcessPermissions>())?;
+
+    // The isolate is blocked meanwhile, so a timeout or interrupt cannot stop the call
+    // until the process is killed
+    let stop = state.try_borrow::<StopSignal>().cloned();
+    args.run(program, || stop.as_ref().is_some_and(StopSignal::is_set))
+}
+
+#[op2(async)]
+#[serde]
+async fn op_command_output(
+    state: Rc<RefCell<OpState>>,
+    #[serde] mut args: CommandArgs,
+) -> Result<CommandOutput, Error> {
+    let (program, stop) = {
+        let state = state.borrow();
+        let program = args.check(state.borrow::<ProcessPermissions>())?;
+        (program, state.try_borrow::<StopSignal>().cloned())
+    };
+
+    // The process runs on its own thread, so the event loop can carry on meanwhile
+    let dropped = KillOnDrop(Arc::new(AtomicBool::new(false)));
+    let cancelled = dropped.0.clone();
+    let stopped =
+        move || cancelled.load(Ordering::SeqCst) || stop.as_ref().is_some_and(StopSignal::is_set);
+
+    let (tx, rx) = oneshot::channel();
+    std::thread::spawn(move || tx.send(args.run(program, stopped)));
+    let output = rx
+        .await
+        .map_err(|_| Error::Runtime("The command thread stopped".to_string()))?;
+    drop(dropped);
+    output
+}
+
+extension!(
+    init_process,
+    deps = [rustyscript],
+    ops = [op_command_output_sync, op_command_output],
+    esm_entry_point = "ext:init_process/init_process.js",
+    esm = [ dir "src/ext/process", "init_process.js" ],
+    options = {
+        permissions: ProcessPermissions,
+    },
+    state = |state, config| state.put(config.permissions),
+);
+
+pub fn extensions(permissions: ProcessPermissions) -> Vec<Extension> {
+    vec![init_process::init_ops_and_esm(permissions)]
+}
+
+pub fn snapshot_extensions(permissions: ProcessPermissions) -> Vec<Extension> {
+    vec![init_process::init_ops(permissions)]
+}
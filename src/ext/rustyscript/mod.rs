@@ -1,10 +1,173 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
 
-use crate::{error::Error, RsAsyncFunction, RsFunction};
-use deno_core::{extension, op2, serde_json, v8, Extension, OpState};
+use crate::{
+    error::Error, spans::enter_span, ArgSchema, CallContext, CallbackError, CallbackLayer,
+    FunctionInfo, JsException, RegisteredFunction, RsAsyncFunction, RsFunction,
+};
+use deno_core::{error::AnyError, extension, op2, serde_json, v8, Extension, OpState};
 
 type FnCache = HashMap<String, Box<dyn RsFunction>>;
 type AsyncFnCache = HashMap<String, Box<dyn RsAsyncFunction>>;
+type FnInfoCache = HashMap<String, FunctionInfo>;
+type FnSchemaCache = HashMap<String, ArgSchema>;
+
+/// Middleware applied to every call to a registered function
+pub type CallbackMiddleware = Rc<Vec<Box<dyn CallbackLayer>>>;
+
+/// Applies the `on_call` hook of each layer, in order
+fn before_call(
+    middleware: Option<&CallbackMiddleware>,
+    name: &str,
+    args: &mut Vec<serde_json::Value>,
+) -> Result<(), Error> {
+    for layer in middleware.into_iter().flat_map(|m| m.iter()) {
+        layer.on_call(name, args)?;
+    }
+    Ok(())
+}
+
+/// Applies the `on_result` hook of each layer, in reverse order
+fn after_call(
+    middleware: Option<&CallbackMiddleware>,
+    name: &str,
+    mut result: Result<serde_json::Value, Error>,
+) -> Result<serde_json::Value, Error> {
+    for layer in middleware.into_iter().flat_map(|m| m.iter()).rev() {
+        result = layer.on_result(name, result);
+    }
+    result
+}
+
+/// Names the JS class of errors returned from ops
+/// Arguments rejected by a function's `ArgSchema` are thrown as `TypeError`s, anything else as an `Error`
+/// Errors from registered functions use a builder registered in `rustyscript.js`
+pub fn error_class(error: &AnyError) -> &'static str {
+    if error.is::<EncodedCallbackError>() {
+        return "RustyscriptCallbackError";
+    }
+
+    match error.downcast_ref::<Error>() {
+        Some(Error::InvalidArguments(_)) => "TypeError",
+        _ => "Error",
+    }
+}
+
+/// A `CallbackError` encoded as JSON, which is the message JS receives for it
+#[derive(Debug)]
+struct EncodedCallbackError(String);
+
+impl std::fmt::Display for EncodedCallbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EncodedCallbackError {}
+
+/// Convert an error from a call to a registered function into the error thrown in JS
+/// Errors other than `CallbackError`s are named `Error`, with their kind as the code
+fn callback_error(error: Error) -> AnyError {
+    let error = match error {
+        Error::Callback(error) => *error,
+        Error::InvalidArguments(_) => return error.into(),
+        error => CallbackError::new(error.to_string())
+            .with_name("Error")
+            .with_code(error.code()),
+    };
+
+    match serde_json::to_string(&error) {
+        Ok(json) => EncodedCallbackError(json).into(),
+        Err(e) => Error::Runtime(e.to_string()).into(),
+    }
+}
+
+/// Check the arguments of a call against the function's `ArgSchema`, if it has one
+fn check_schema(state: &OpState, name: &str, args: &[serde_json::Value]) -> Result<(), Error> {
+    match state
+        .try_borrow::<FnSchemaCache>()
+        .and_then(|schemas| schemas.get(name))
+    {
+        Some(schema) => schema.validate(name, args),
+        None => Ok(()),
+    }
+}
+
+/// Namespaces whose functions have been disabled with `Runtime::set_namespace_enabled`
+#[derive(Default)]
+pub struct DisabledNamespaces(pub HashSet<String>);
+
+/// Returns an error if the function `name` belongs to a disabled namespace
+fn check_namespace(state: &OpState, name: &str) -> Result<(), Error> {
+    let disabled = state.try_borrow::<DisabledNamespaces>();
+    match (name.split_once('.'), disabled) {
+        (Some((namespace, _)), Some(disabled)) if disabled.0.contains(namespace) => Err(
+            Error::Runtime(format!("the `{namespace}` namespace is disabled")),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Tracks the context of calls to functions registered with a call context
+#[derive(Default)]
+pub struct CallContextState {
+    /// Functions registered to receive a context
+    pub functions: HashSet<String>,
+
+    /// The request ID set by the host
+    pub request_id: Option<String>,
+
+    /// The context of the call in progress
+    pub current: Rc<RefCell<CallContext>>,
+}
+
+/// Records the context of a call, if the function was registered to receive one
+fn record_call_context(scope: &mut v8::HandleScope, state: &OpState, name: &str) {
+    let Some(contexts) = state.try_borrow::<CallContextState>() else {
+        return;
+    };
+    if !contexts.functions.contains(name) {
+        return;
+    }
+
+    *contexts.current.borrow_mut() = CallContext {
+        function: name.to_string(),
+        module: caller_module(scope),
+        request_id: contexts.request_id.clone(),
+
+        #[cfg(feature = "tracing")]
+        span: Some(tracing::Span::current()),
+    };
+}
+
+/// Finds the script or module the current call was made from, skipping extension code
+fn caller_module(scope: &mut v8::HandleScope) -> Option<String> {
+    let trace = v8::StackTrace::current_stack_trace(scope, 16)?;
+    for i in 0..trace.get_frame_count() {
+        let Some(name) = trace
+            .get_frame(scope, i)
+            .and_then(|frame| frame.get_script_name(scope))
+        else {
+            continue;
+        };
+
+        let name = name.to_rust_string_lossy(scope);
+        if !name.is_empty() && !name.starts_with("ext:") {
+            return Some(name);
+        }
+    }
+
+    None
+}
+
+/// Queue of messages sent from JS using `rustyscript.postMessage`
+pub type MessageQueue = VecDeque<serde_json::Value>;
+
+/// The value of the last exception reported to the host, as JSON
+pub struct LastException(pub serde_json::Value);
 
 #[op2]
 /// Registers a JS function with the runtime as being the entrypoint for the module
@@ -20,43 +183,234 @@ fn op_register_entrypoint(
     Ok(())
 }
 
+#[op2]
+/// Queues a message from JS, to be picked up by the host
+///
+/// # Arguments
+/// * `state` - The runtime's state, into which the message will be put
+/// * `message` - The value to send
+fn op_post_message(state: &mut OpState, #[serde] message: serde_json::Value) -> Result<(), Error> {
+    if !state.has::<MessageQueue>() {
+        state.put(MessageQueue::new());
+    }
+
+    state.borrow_mut::<MessageQueue>().push_back(message);
+    Ok(())
+}
+
+#[op2(fast)]
+/// Records the value of an exception as it is converted into an error for the host
+/// Only the first is kept until it is taken, so the `cause` of an error does not replace it
+///
+/// # Arguments
+/// * `state` - The runtime's state, into which the value will be put
+/// * `json` - The thrown value, serialized as JSON
+fn op_record_exception(state: &mut OpState, #[string] json: &str) {
+    if !state.has::<LastException>() {
+        if let Ok(value) = serde_json::from_str(json) {
+            state.put(LastException(value));
+        }
+    }
+}
+
+type ExceptionHook = Rc<dyn Fn(&JsException)>;
+
+/// The host's handlers for errors raised outside of a call
+#[derive(Clone, Default)]
+pub struct ExceptionHooks {
+    /// Called with promise rejections that nothing handled
+    pub on_unhandled_rejection: Option<ExceptionHook>,
+
+    /// Called with exceptions thrown from timer callbacks
+    pub on_uncaught_exception: Option<ExceptionHook>,
+}
+
+#[op2(reentrant)]
+/// Passes an unhandled rejection or uncaught exception to the host's handler for it
+/// Returns false if the host has no such handler, so the error can be reported as usual
+///
+/// # Arguments
+/// * `state` - The runtime's state, holding the handlers
+/// * `exception` - The value that was thrown, or that the promise was rejected with
+/// * `rejection` - True for a promise rejection, false for an exception
+fn op_report_error(
+    scope: &mut v8::HandleScope,
+    state: Rc<RefCell<OpState>>,
+    exception: v8::Local<v8::Value>,
+    rejection: bool,
+) -> bool {
+    let handler = state
+        .borrow()
+        .try_borrow::<ExceptionHooks>()
+        .and_then(|hooks| {
+            if rejection {
+                hooks.on_unhandled_rejection.clone()
+            } else {
+                hooks.on_uncaught_exception.clone()
+            }
+        });
+    let Some(handler) = handler else {
+        return false;
+    };
+
+    // Formatting the exception records its value - keep any value recorded before it,
+    // which belongs to an error the host has not seen yet
+    let previous = state.borrow_mut().try_take::<LastException>();
+    let error = deno_core::error::JsError::from_v8_exception(scope, exception);
+    let value = state.borrow_mut().try_take::<LastException>();
+    if let Some(previous) = previous {
+        state.borrow_mut().put(previous);
+    }
+
+    handler(&JsException {
+        error,
+        value: value.map(|value| value.0),
+    });
+    true
+}
+
+#[op2]
+#[serde]
+/// Lists the rust functions registered with the runtime, sorted by name
+/// Includes any metadata supplied at registration
+fn op_list_functions(state: &mut OpState) -> Vec<RegisteredFunction> {
+    registered_functions(state)
+}
+
+/// Lists the rust functions registered with the runtime, sorted by name
+/// Functions in disabled namespaces are left out
+pub fn registered_functions(state: &OpState) -> Vec<RegisteredFunction> {
+    let mut names: Vec<(&String, bool)> = Vec::new();
+    if let Some(table) = state.try_borrow::<FnCache>() {
+        names.extend(table.keys().map(|name| (name, false)));
+    }
+    if let Some(table) = state.try_borrow::<AsyncFnCache>() {
+        names.extend(table.keys().map(|name| (name, true)));
+    }
+    names.retain(|(name, _)| check_namespace(state, name).is_ok());
+    names.sort();
+
+    let infos = state.try_borrow::<FnInfoCache>();
+    let schemas = state.try_borrow::<FnSchemaCache>();
+    names
+        .into_iter()
+        .map(|(name, is_async)| RegisteredFunction {
+            name: name.clone(),
+            is_async,
+            info: infos
+                .and_then(|infos| infos.get(name))
+                .cloned()
+                .unwrap_or_default(),
+            schema: schemas.and_then(|schemas| schemas.get(name)).cloned(),
+        })
+        .collect()
+}
+
+#[op2(fast)]
+/// Checks if any rust functions have been registered under the given namespace
+fn op_has_namespace(#[string] namespace: String, state: &mut OpState) -> bool {
+    if state
+        .try_borrow::<DisabledNamespaces>()
+        .is_some_and(|disabled| disabled.0.contains(&namespace))
+    {
+        return false;
+    }
+
+    let prefix = format!("{namespace}.");
+    let sync_match = state
+        .try_borrow::<FnCache>()
+        .is_some_and(|table| table.keys().any(|name| name.starts_with(&prefix)));
+    let async_match = state
+        .try_borrow::<AsyncFnCache>()
+        .is_some_and(|table| table.keys().any(|name| name.starts_with(&prefix)));
+
+    sync_match || async_match
+}
+
+#[op2(fast)]
+/// Checks if the rust function registered under the given name is async
+fn op_is_async_function(#[string] name: String, state: &mut OpState) -> bool {
+    state
+        .try_borrow::<AsyncFnCache>()
+        .is_some_and(|table| table.contains_key(&name))
+}
+
 #[op2]
 #[serde]
 fn call_registered_function(
+    scope: &mut v8::HandleScope,
     #[string] name: String,
-    #[serde] args: Vec<serde_json::Value>,
+    #[serde] mut args: Vec<serde_json::Value>,
     state: &mut OpState,
-) -> Result<serde_json::Value, Error> {
-    if state.has::<FnCache>() {
-        let table = state.borrow_mut::<FnCache>();
-        if let Some(callback) = table.get(&name) {
-            return callback(&args);
-        }
-    }
+) -> Result<serde_json::Value, AnyError> {
+    record_call_context(scope, state, &name);
+    enter_span!("rustyscript.callback", function = %name);
+    let result = check_namespace(state, &name)
+        .and_then(|()| check_schema(state, &name, &args))
+        .and_then(|()| {
+            let middleware = state.try_borrow::<CallbackMiddleware>();
+            let callback = state
+                .try_borrow::<FnCache>()
+                .and_then(|table| table.get(&name));
 
-    Err(Error::ValueNotCallable(name.to_string()))
+            match callback {
+                Some(callback) => {
+                    before_call(middleware, &name, &mut args)?;
+                    after_call(middleware, &name, callback(&args))
+                }
+                None => Err(Error::ValueNotCallable(name.to_string())),
+            }
+        });
+    result.map_err(callback_error)
 }
 
 #[op2(async)]
 #[serde]
 fn call_registered_function_async(
+    scope: &mut v8::HandleScope,
     #[string] name: String,
-    #[serde] args: Vec<serde_json::Value>,
+    #[serde] mut args: Vec<serde_json::Value>,
     state: &mut OpState,
-) -> impl std::future::Future<Output = Result<serde_json::Value, Error>> {
-    if state.has::<AsyncFnCache>() {
-        let table = state.borrow_mut::<AsyncFnCache>();
-        if let Some(callback) = table.get(&name) {
-            return callback(args);
+) -> impl std::future::Future<Output = Result<serde_json::Value, AnyError>> {
+    record_call_context(scope, state, &name);
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!("rustyscript.async_callback", function = %name);
+
+    let middleware = state.try_borrow::<CallbackMiddleware>().cloned();
+    let callback = state
+        .try_borrow::<AsyncFnCache>()
+        .and_then(|table| table.get(&name));
+    let checked = check_namespace(state, &name).and_then(|()| check_schema(state, &name, &args));
+    let future = match (checked, callback) {
+        (Err(e), _) => Err(e),
+        (Ok(()), Some(callback)) => {
+            before_call(middleware.as_ref(), &name, &mut args).map(|()| callback(args))
         }
-    }
+        (Ok(()), None) => Err(Error::ValueNotCallable(name.clone())),
+    };
+
+    let future = async move {
+        match future {
+            Ok(future) => after_call(middleware.as_ref(), &name, future.await),
+            Err(e) => Err(e),
+        }
+        .map_err(callback_error)
+    };
+
+    #[cfg(feature = "tracing")]
+    return tracing::Instrument::instrument(future, span);
 
-    Box::pin(std::future::ready(Err(Error::ValueNotCallable(name))))
+    #[cfg(not(feature = "tracing"))]
+    future
 }
 
 extension!(
     rustyscript,
-    ops = [op_register_entrypoint, call_registered_function, call_registered_function_async],
+    ops = [
+        op_register_entrypoint, op_post_message, op_record_exception, op_report_error, op_list_functions,
+        op_has_namespace, op_is_async_function,
+        call_registered_function, call_registered_function_async
+    ],
     esm_entry_point = "ext:rustyscript/rustyscript.js",
     esm = [ dir "src/ext/rustyscript", "rustyscript.js" ],
 );
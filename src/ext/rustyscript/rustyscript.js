@@ -24,12 +24,82 @@ const getterOnly = (getter) => {
 }
 const applyToGlobal = (properties) => Object.defineProperties(globalThis, properties);
 
+// Functions registered under a namespace, such as rustyscript.db.query
+const namespace = (ns) => new Proxy({}, {
+    get: function(_target, name) {
+        const fullName = `${ns}.${String(name)}`;
+        if (Deno.core.ops.op_is_async_function(fullName)) {
+            return (...args) => Deno.core.ops.call_registered_function_async(fullName, args);
+        }
+        return (...args) => Deno.core.ops.call_registered_function(fullName, args);
+    }
+});
+
+// Errors returned by registered functions, with the name, code and data given by the host
+class CallbackError extends Error {
+    constructor(message, { name, code, data } = {}) {
+        super(message);
+        this.name = name ?? 'CallbackError';
+        if (code !== undefined && code !== null) this.code = code;
+        if (data !== undefined && data !== null) this.data = data;
+    }
+}
+Deno.core.registerErrorBuilder('RustyscriptCallbackError', (json) => {
+    const { name, message, code, data } = JSON.parse(json);
+    return new CallbackError(message, { name, code, data });
+});
+
+// Namespaces provided by other extensions, such as rustyscript.kv
+const extensionNamespaces = {};
+const registerNamespace = (name, value) => extensionNamespaces[name] = Object.freeze(value);
+
+// Compile `WebAssembly.compileStreaming` and `instantiateStreaming` sources read in one piece
+// Sources can be a Response, or a promise of one, or the module's bytes
+// The web feature replaces this with an implementation that streams fetch responses
+Deno.core.setWasmStreamingCallback(async (source, rid) => {
+    try {
+        source = await source;
+        if (typeof source?.arrayBuffer === 'function') {
+            if (source.url) Deno.core.ops.op_wasm_streaming_set_url(rid, source.url);
+            source = await source.arrayBuffer();
+        }
+
+        const bytes = ArrayBuffer.isView(source) ? source : new Uint8Array(source);
+        Deno.core.ops.op_wasm_streaming_feed(rid, bytes);
+        Deno.core.close(rid);
+    } catch (err) {
+        Deno.core.abortWasmStreaming(rid, err);
+    }
+});
+
+// Record thrown values as they are reported to the host, so errors can include them
+Deno.core.ops.op_set_format_exception_callback((exception) => {
+    try {
+        Deno.core.ops.op_record_exception(JSON.stringify(exception) ?? 'null');
+    } catch {
+        // Values that cannot be represented as JSON, such as cyclic objects, are left out
+    }
+});
+
+// Pass errors raised outside of a call to the host's handlers, if it has any
+// Otherwise they are reported as usual, failing whatever is running the event loop
+Deno.core.setUnhandledPromiseRejectionHandler(
+    (_promise, reason) => Deno.core.ops.op_report_error(reason, true)
+);
+Deno.core.setReportExceptionCallback((error) => {
+    if (!Deno.core.ops.op_report_error(error, false)) {
+        Deno.core.reportUnhandledException(error);
+    }
+});
+
 // Populate the global object
-globalThis.rustyscript = {
+const rustyscript = {
     'register_entrypoint': (f) => Deno.core.ops.op_register_entrypoint(f),
     'bail': (msg) => { throw new Error(msg) },
+    'postMessage': (value) => Deno.core.ops.op_post_message(value),
+    'CallbackError': CallbackError,
     
-    'functions': new Proxy({}, {
+    'functions': new Proxy(() => Deno.core.ops.op_list_functions(), {
         get: function(_target, name) {
             return (...args) => Deno.core.ops.call_registered_function(name, args);
         }
@@ -41,8 +111,18 @@ globalThis.rustyscript = {
         }
     })
 };
-Object.freeze(globalThis.rustyscript);
+Object.freeze(rustyscript);
+globalThis.rustyscript = new Proxy(rustyscript, {
+    get: function(target, name) {
+        if (typeof name !== 'string' || name in target) {
+            return target[name];
+        } else if (name in extensionNamespaces) {
+            return extensionNamespaces[name];
+        }
+        return Deno.core.ops.op_has_namespace(name) ? namespace(name) : undefined;
+    }
+});
 
 export {
-    nonEnumerable, readOnly, writeable, getterOnly, applyToGlobal
+    nonEnumerable, readOnly, writeable, getterOnly, applyToGlobal, registerNamespace
 };
\ No newline at end of file
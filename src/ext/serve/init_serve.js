@@ -0,0 +1,213 @@
+// Minimal Headers, Request and Response, for builds without the web feature
+// The full fetch implementation is used instead when it is available
+import { applyToGlobal, nonEnumerable, registerNamespace } from 'ext:rustyscript/rustyscript.js';
+
+const toBytes = (body) => {
+    if (body === undefined || body === null) return new Uint8Array(0);
+    if (typeof body === 'string') return Deno.core.encode(body);
+    if (body instanceof ArrayBuffer) return new Uint8Array(body.slice(0));
+    if (ArrayBuffer.isView(body)) {
+        return new Uint8Array(body.buffer.slice(body.byteOffset, body.byteOffset + body.byteLength));
+    }
+    return Deno.core.encode(String(body));
+};
+
+class Headers {
+    #map = new Map();
+
+    constructor(init = undefined) {
+        if (init === undefined || init === null) return;
+        const entries = init instanceof Headers || Array.isArray(init) ? init : Object.entries(init);
+        for (const [name, value] of entries) {
+            this.append(name, value);
+        }
+    }
+
+    append(name, value) {
+        const key = String(name).toLowerCase();
+        const existing = this.#map.get(key);
+        this.#map.set(key, existing === undefined ? String(value) : `${existing}, ${value}`);
+    }
+
+    set(name, value) {
+        this.#map.set(String(name).toLowerCase(), String(value));
+    }
+
+    get(name) {
+        return this.#map.get(String(name).toLowerCase()) ?? null;
+    }
+
+    has(name) {
+        return this.#map.has(String(name).toLowerCase());
+    }
+
+    delete(name) {
+        this.#map.delete(String(name).toLowerCase());
+    }
+
+    forEach(callback, thisArg = undefined) {
+        for (const [name, value] of this) {
+            callback.call(thisArg, value, name, this);
+        }
+    }
+
+    *entries() {
+        yield* [...this.#map.entries()].sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+    }
+
+    *keys() {
+        for (const [name] of this.entries()) yield name;
+    }
+
+    *values() {
+        for (const [, value] of this.entries()) yield value;
+    }
+
+    [Symbol.iterator]() {
+        return this.entries();
+    }
+}
+
+// Shared body handling for Request and Response
+class Body {
+    #body;
+    #used = false;
+
+    constructor(body) {
+        this.#body = toBytes(body);
+    }
+
+    get bodyUsed() {
+        return this.#used;
+    }
+
+    async arrayBuffer() {
+        if (this.#used) throw new TypeError('Body already consumed');
+        this.#used = true;
+        return this.#body.buffer;
+    }
+
+    async bytes() {
+        return new Uint8Array(await this.arrayBuffer());
+    }
+
+    async text() {
+        return Deno.core.decode(await this.bytes());
+    }
+
+    async json() {
+        return JSON.parse(await this.text());
+    }
+}
+
+class Request extends Body {
+    #url;
+    #method;
+    #headers;
+
+    constructor(input, init = {}) {
+        const method = String(init.method ?? input?.method ?? 'GET').toUpperCase();
+        if ((method === 'GET' || method === 'HEAD') && init.body !== undefined && init.body !== null) {
+            throw new TypeError('Request with GET/HEAD method cannot have body');
+        }
+
+        super(init.body);
+        this.#url = input instanceof Request ? input.url : String(input);
+        this.#method = method;
+        this.#headers = new Headers(init.headers ?? input?.headers);
+    }
+
+    get url() {
+        return this.#url;
+    }
+
+    get method() {
+        return this.#method;
+    }
+
+    get headers() {
+        return this.#headers;
+    }
+}
+
+class Response extends Body {
+    #status;
+    #statusText;
+    #headers;
+
+    constructor(body = null, init = {}) {
+        const status = init.status ?? 200;
+        if (!Number.isInteger(status) || status < 200 || status > 599) {
+            throw new RangeError(`The status provided (${status}) is outside the range [200, 599]`);
+        }
+
+        super(body);
+        this.#status = status;
+        this.#statusText = String(init.statusText ?? '');
+        this.#headers = new Headers(init.headers);
+        if (typeof body === 'string' && !this.#headers.has('content-type')) {
+            this.#headers.set('content-type', 'text/plain;charset=UTF-8');
+        }
+    }
+
+    static json(data, init = {}) {
+        const response = new Response(JSON.stringify(data), init);
+        response.headers.set('content-type', 'application/json');
+        return response;
+    }
+
+    get status() {
+        return this.#status;
+    }
+
+    get statusText() {
+        return this.#statusText;
+    }
+
+    get ok() {
+        return this.#status >= 200 && this.#status < 300;
+    }
+
+    get headers() {
+        return this.#headers;
+    }
+}
+
+if (globalThis.Request === undefined) {
+    applyToGlobal({
+        Headers: nonEnumerable(Headers),
+        Request: nonEnumerable(Request),
+        Response: nonEnumerable(Response),
+    });
+}
+
+// Pass a request from the host to a module's `fetch` handler
+// The response is returned as the length of its metadata, the metadata as JSON, then the body
+async function dispatch(handler, method, url, headers, body) {
+    if (typeof handler?.fetch !== 'function') {
+        throw new TypeError('The default export must be an object with a fetch(request) method');
+    }
+
+    const hasBody = method !== 'GET' && method !== 'HEAD';
+    const request = new globalThis.Request(url, { method, headers, body: hasBody ? body : null });
+    const response = await handler.fetch(request);
+    if (!(response instanceof globalThis.Response)) {
+        throw new TypeError('fetch(request) must return a Response');
+    }
+
+    const content = new Uint8Array(await response.arrayBuffer());
+    const meta = Deno.core.encode(JSON.stringify({
+        status: response.status,
+        headers: [...response.headers],
+    }));
+
+    const output = new Uint8Array(4 + meta.length + content.length);
+    new DataView(output.buffer).setUint32(0, meta.length);
+    output.set(meta, 4);
+    output.set(content, 4 + meta.length);
+    return output;
+}
+
+registerNamespace('serve', {
+    'dispatch': dispatch,
+});
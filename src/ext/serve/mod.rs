@@ -0,0 +1,15 @@
+use deno_core::{extension, Extension};
+extension!(
+    init_serve,
+    deps = [rustyscript],
+    esm_entry_point = "ext:init_serve/init_serve.js",
+    esm = [ dir "src/ext/serve", "init_serve.js" ],
+);
+
+pub fn extensions() -> Vec<Extension> {
+    vec![init_serve::init_ops_and_esm()]
+}
+
+pub fn snapshot_extensions() -> Vec<Extension> {
+    vec![init_serve::init_ops()]
+}
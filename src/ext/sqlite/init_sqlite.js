@@ -0,0 +1,27 @@
+import { registerNamespace } from 'ext:rustyscript/rustyscript.js';
+
+class Database {
+    #rid;
+
+    constructor(rid) {
+        this.#rid = rid;
+    }
+
+    // Run a statement, returning the number of rows changed
+    execute(sql, params = []) {
+        return Deno.core.ops.op_sqlite_execute(this.#rid, String(sql), params);
+    }
+
+    // Run a query, returning its rows as objects keyed by column name
+    query(sql, params = []) {
+        return Deno.core.ops.op_sqlite_query(this.#rid, String(sql), params);
+    }
+
+    close() {
+        Deno.core.tryClose(this.#rid);
+    }
+}
+
+registerNamespace('sqlite', {
+    'open': (path = ':memory:') => new Database(Deno.core.ops.op_sqlite_open(String(path))),
+});
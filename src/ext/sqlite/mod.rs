@@ -0,0 +1,197 @@
+use crate::Error;
+use deno_core::{extension, op2, serde_json, Extension, OpState, Resource, ResourceId};
+use rusqlite::{limits::Limit, types::Value as SqlValue, Connection, OpenFlags};
+use std::{borrow::Cow, path::PathBuf, rc::Rc};
+
+/// Database files a script may open through `rustyscript.sqlite.open`
+/// Without a path, or with `:memory:`, scripts get the runtime's own in-memory database,
+/// which is always allowed and shared by every connection in the runtime
+///
+/// Statements that open other files, such as `ATTACH` and `VACUUM`, are refused
+#[derive(Debug, Clone, Default)]
+pub struct SqlitePermissions {
+    /// Database files, or directories of database files, that may be opened
+    /// Files that do not exist yet are created on open
+    pub allow: Vec<PathBuf>,
+}
+
+impl SqlitePermissions {
+    /// Returns the resolved path if `path` is an allowed database file
+    pub fn check(&self, path: &str) -> Result<PathBuf, Error> {
+        let target = std::path::Path::new(path);
+        let resolved = match std::fs::canonicalize(target) {
+            Ok(resolved) => Some(resolved),
+
+            // A new database - resolve its directory instead
+            Err(_) => target.file_name().and_then(|name| {
+                let parent = target.parent().filter(|p| !p.as_os_str().is_empty());
+                std::fs::canonicalize(parent.unwrap_or(".".as_ref()))
+                    .ok()
+                    .map(|parent| parent.join(name))
+            }),
+        };
+
+        let allowed = resolved.as_ref().is_some_and(|resolved| {
+            self.allow
+                .iter()
+                .filter_map(|allowed| std::fs::canonicalize(allowed).ok())
+                .any(|allowed| resolved.starts_with(allowed))
+        });
+        match resolved {
+            Some(resolved) if allowed => Ok(resolved),
+            _ => Err(Error::PermissionDenied(format!("opening database {path}"))),
+        }
+    }
+}
+
+/// Stop a connection from reaching files other than the one it was opened on
+/// `ATTACH` and `VACUUM INTO` both open a new file, so both fail once no databases may be attached
+fn restrict(connection: Connection) -> Connection {
+    connection.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0);
+    connection
+}
+
+/// The runtime's in-memory database, created on first use
+#[derive(Default)]
+struct MemoryDatabase(Option<Rc<Connection>>);
+
+struct DatabaseResource(Rc<Connection>);
+impl Resource for DatabaseResource {
+    fn name(&self) -> Cow<'_, str> {
+        "sqliteDatabase".into()
+    }
+}
+
+fn sql_error(e: rusqlite::Error) -> Error {
+    Error::Runtime(e.to_string())
+}
+
+fn database(state: &OpState, rid: ResourceId) -> Result<Rc<DatabaseResource>, Error> {
+    state
+        .resource_table
+        .get::<DatabaseResource>(rid)
+        .map_err(|_| Error::Runtime("The database is closed".to_string()))
+}
+
+/// Convert query parameters from JS to SQLite values
+fn to_sql(params: Vec<serde_json::Value>) -> Result<Vec<SqlValue>, Error> {
+    params
+        .into_iter()
+        .map(|value| match value {
+            serde_json::Value::Null => Ok(SqlValue::Null),
+            serde_json::Value::Bool(b) => Ok(SqlValue::Integer(b.into())),
+            serde_json::Value::Number(n) => Ok(match n.as_i64() {
+                Some(i) => SqlValue::Integer(i),
+                None => SqlValue::Real(n.as_f64().unwrap_or(f64::NAN)),
+            }),
+            serde_json::Value::String(s) => Ok(SqlValue::Text(s)),
+            value => Err(Error::Runtime(format!(
+                "{value} cannot be used as a query parameter"
+            ))),
+        })
+        .collect()
+}
+
+/// Convert a SQLite value to JS - blobs become arrays of bytes
+fn from_sql(value: SqlValue) -> serde_json::Value {
+    match value {
+        SqlValue::Null => serde_json::Value::Null,
+        SqlValue::Integer(i) => i.into(),
+        SqlValue::Real(f) => f.into(),
+        SqlValue::Text(s) => s.into(),
+        SqlValue::Blob(b) => b.into(),
+    }
+}
+
+#[op2(fast)]
+#[smi]
+fn op_sqlite_open(state: &mut OpState, #[string] path: String) -> Result<ResourceId, Error> {
+    let connection = if path.is_empty() || path == ":memory:" {
+        let memory = state.borrow_mut::<MemoryDatabase>();
+        match &memory.0 {
+            Some(connection) => connection.clone(),
+            None => {
+                let connection = Connection::open_in_memory().map_err(sql_error)?;
+                let connection = Rc::new(restrict(connection));
+                memory.0 = Some(connection.clone());
+                connection
+            }
+        }
+    } else {
+        // The path was checked once resolved, so a symlink swapped in since must not be followed
+        let resolved = state.borrow::<SqlitePermissions>().check(&path)?;
+        let flags = OpenFlags::default() | OpenFlags::SQLITE_OPEN_NOFOLLOW;
+        let connection = Connection::open_with_flags(resolved, flags).map_err(sql_error)?;
+        Rc::new(restrict(connection))
+    };
+
+    Ok(state.resource_table.add(DatabaseResource(connection)))
+}
+
+#[op2]
+#[number]
+fn op_sqlite_execute(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[string] sql: String,
+    #[serde] params: Vec<serde_json::Value>,
+) -> Result<usize, Error> {
+    let database = database(state, rid)?;
+    let params = to_sql(params)?;
+    database
+        .0
+        .execute(&sql, rusqlite::params_from_iter(params))
+        .map_err(sql_error)
+}
+
+#[op2]
+#[serde]
+fn op_sqlite_query(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[string] sql: String,
+    #[serde] params: Vec<serde_json::Value>,
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, Error> {
+    let database = database(state, rid)?;
+    let params = to_sql(params)?;
+    let mut statement = database.0.prepare(&sql).map_err(sql_error)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    let rows = statement
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            let mut object = serde_json::Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                object.insert(column.clone(), from_sql(row.get(i)?));
+            }
+            Ok(object)
+        })
+        .map_err(sql_error)?;
+    rows.collect::<Result<_, _>>().map_err(sql_error)
+}
+
+extension!(
+    init_sqlite,
+    deps = [rustyscript],
+    ops = [op_sqlite_open, op_sqlite_execute, op_sqlite_query],
+    esm_entry_point = "ext:init_sqlite/init_sqlite.js",
+    esm = [ dir "src/ext/sqlite", "init_sqlite.js" ],
+    options = {
+        permissions: SqlitePermissions,
+    },
+    state = |state, config| {
+        state.put(config.permissions);
+        state.put(MemoryDatabase::default());
+    },
+);
+
+pub fn extensions(permissions: SqlitePermissions) -> Vec<Extension> {
+    vec![init_sqlite::init_ops_and_esm(permissions)]
+}
+
+pub fn snapshot_extensions(permissions: SqlitePermissions) -> Vec<Extension> {
+    vec![init_sqlite::init_ops(permissions)]
+}
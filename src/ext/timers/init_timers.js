@@ -0,0 +1,199 @@
+// Timers used when the web feature is disabled
+// They are tracked here so the host can list them, and fire them early
+import { applyToGlobal, writeable } from 'ext:rustyscript/rustyscript.js';
+
+const timers = new Map();
+let nextId = 1;
+
+// In deterministic mode, time only moves when the host advances it,
+// and timers are fired by the host instead of being queued with the event loop
+let virtualClock = null;
+
+// Maximum number of outstanding timers, if limited by the host
+let maxTimers = Infinity;
+const now = () => (virtualClock ? virtualClock.now : Date.now());
+
+function run(id) {
+    const timer = timers.get(id);
+    if (!timer) return;
+
+    if (timer.repeat) {
+        timer.due = now() + timer.delay;
+    } else {
+        timers.delete(id);
+    }
+    timer.callback(...timer.args);
+}
+
+function schedule(timer) {
+    timer.due = now() + timer.delay;
+    if (virtualClock) return;
+
+    timer.handle = Deno.core.queueUserTimer(
+        Deno.core.getTimerDepth() + 1,
+        timer.repeat,
+        timer.delay,
+        () => run(timer.id)
+    );
+}
+
+function createTimer(callback, delay, args, repeat) {
+    if (typeof callback !== 'function') {
+        throw new TypeError('Timer callback must be a function');
+    }
+    if (timers.size >= maxTimers) {
+        throw new RangeError(`Too many pending timers (limit is ${maxTimers})`);
+    }
+
+    // Intervals of 0ms would never let the host run timers up to a later time
+    const minDelay = repeat ? 1 : 0;
+    const timer = {
+        id: nextId++,
+        delay: Math.max(minDelay, Number(delay) || 0),
+        repeat,
+        callback,
+        args,
+    };
+    schedule(timer);
+    timers.set(timer.id, timer);
+    return timer.id;
+}
+
+function clearTimer(id) {
+    const timer = timers.get(id);
+    if (timer) {
+        cancel(timer);
+        timers.delete(id);
+    }
+}
+
+function cancel(timer) {
+    if (timer.handle !== undefined) {
+        Deno.core.cancelTimer(timer.handle);
+    }
+}
+
+// Lists pending timers, soonest first
+function pendingTimers() {
+    const time = now();
+    return [...timers.values()]
+        .map(({ id, due, repeat }) => ({ id, dueIn: Math.max(0, due - time), repeat }))
+        .sort((a, b) => a.dueIn - b.dueIn);
+}
+
+// Runs a timer's callback now, instead of when it is due
+// Intervals are rescheduled from now, other timers are removed
+function fireTimer(id) {
+    const timer = timers.get(id);
+    if (!timer) return false;
+
+    fire(timer);
+    return true;
+}
+
+function fire(timer) {
+    cancel(timer);
+    if (timer.repeat) {
+        schedule(timer);
+    } else {
+        timers.delete(timer.id);
+    }
+
+    timer.callback(...timer.args);
+}
+
+// Switches to deterministic mode - seeds Math.random, and replaces the clock with a virtual one
+function enableDeterministic(seed, startTime) {
+    virtualClock = { now: startTime, start: startTime };
+
+    // mulberry32
+    let state = seed >>> 0;
+    Math.random = () => {
+        state = (state + 0x6d2b79f5) >>> 0;
+        let t = state;
+        t = Math.imul(t ^ (t >>> 15), t | 1);
+        t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+        return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    };
+
+    // A plain function rather than a class, since `Date()` called without `new`
+    // must return the current time as a string instead of throwing
+    const RealDate = globalThis.Date;
+    function VirtualDate(...args) {
+        if (!new.target) return new RealDate(virtualClock.now).toString();
+        return Reflect.construct(RealDate, args.length ? args : [virtualClock.now], new.target);
+    }
+
+    // Dates keep the real prototype, so they still pass `instanceof Date`
+    Object.setPrototypeOf(VirtualDate, RealDate);
+    Object.defineProperties(VirtualDate, {
+        name: { value: "Date" },
+        length: { value: RealDate.length },
+        prototype: { value: RealDate.prototype },
+        now: { value: () => virtualClock.now, writable: true, configurable: true },
+    });
+    Object.defineProperty(RealDate.prototype, "constructor", {
+        value: VirtualDate,
+        writable: true,
+        configurable: true,
+    });
+    applyToGlobal({ Date: writeable(VirtualDate) });
+
+    if (globalThis.performance) {
+        globalThis.performance.now = () => virtualClock.now - virtualClock.start;
+    }
+}
+
+// Limits the number of outstanding timers
+function setTimerLimit(max) {
+    maxTimers = max;
+}
+
+// Gets the time `ms` from now, to run timers up to
+function advanceTime(ms) {
+    return now() + Math.max(0, ms);
+}
+
+// Sets the virtual clock to `ms` since the epoch, returning the time to run timers up to
+// Moving the clock back leaves timers due at the same times, so they fire later
+function setTime(ms) {
+    if (virtualClock) virtualClock.now = Math.min(virtualClock.now, ms);
+    return ms;
+}
+
+// Fires the next timer due at or before `until`, without waiting for it
+// In deterministic mode, the virtual clock moves to when the timer was due, and
+// to `until` once no timers are left to fire
+// Returns false once no timers are due
+function runNextTimer(until) {
+    let next = null;
+    for (const timer of timers.values()) {
+        if (timer.due <= until && (!next || timer.due < next.due)) next = timer;
+    }
+
+    if (!next) {
+        if (virtualClock) virtualClock.now = Math.max(virtualClock.now, until);
+        return false;
+    }
+
+    if (virtualClock) virtualClock.now = Math.max(virtualClock.now, next.due);
+    fire(next);
+    return true;
+}
+
+Deno.core.ops.op_register_timer_hooks({
+    pendingTimers,
+    fireTimer,
+    enableDeterministic,
+    setTimerLimit,
+    advanceTime,
+    setTime,
+    runNextTimer,
+});
+
+applyToGlobal({
+    setTimeout: writeable((callback, delay, ...args) => createTimer(callback, delay, args, false)),
+    setInterval: writeable((callback, delay, ...args) => createTimer(callback, delay, args, true)),
+    clearTimeout: writeable(clearTimer),
+    clearInterval: writeable(clearTimer),
+});
@@ -0,0 +1,144 @@
+use crate::error::Error;
+use deno_core::{extension, op2, v8, Extension, OpState};
+use std::time::{Duration, SystemTime};
+
+/// A timer created with `setTimeout` or `setInterval` that has not yet fired
+/// Retrieved with `Runtime::pending_timers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTimer {
+    /// The id returned to JS by `setTimeout` or `setInterval`
+    pub id: u32,
+
+    /// How long until the timer is due to fire
+    pub due_in: Duration,
+
+    /// True if the timer was created with `setInterval`
+    pub repeat: bool,
+}
+
+/// A pending timer, as reported by JS
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RawPendingTimer {
+    id: u32,
+    due_in: f64,
+    repeat: bool,
+}
+
+impl From<RawPendingTimer> for PendingTimer {
+    fn from(value: RawPendingTimer) -> Self {
+        Self {
+            id: value.id,
+            due_in: Duration::from_secs_f64(value.due_in.max(0.0) / 1000.0),
+            repeat: value.repeat,
+        }
+    }
+}
+
+/// Options for running a runtime deterministically
+/// `Math.random` is seeded, and `Date`, `performance.now` and timers follow a virtual clock
+/// that only moves when `Runtime::advance_time` is called
+#[derive(Debug, Clone)]
+pub struct DeterministicOptions {
+    /// Seed for `Math.random`
+    pub seed: u64,
+
+    /// The time the virtual clock starts at
+    pub start_time: SystemTime,
+}
+
+impl Default for DeterministicOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            start_time: SystemTime::UNIX_EPOCH,
+        }
+    }
+}
+
+impl DeterministicOptions {
+    /// The seed, folded into the 32 bits used by the JS generator
+    pub(crate) fn seed32(&self) -> u32 {
+        (self.seed ^ (self.seed >> 32)) as u32
+    }
+
+    /// The start time, as JS milliseconds since the epoch
+    pub(crate) fn start_ms(&self) -> f64 {
+        epoch_ms(self.start_time)
+    }
+}
+
+/// A time, as JS milliseconds since the epoch
+pub(crate) fn epoch_ms(time: SystemTime) -> f64 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(d) => d.as_secs_f64() * 1000.0,
+        Err(e) => -e.duration().as_secs_f64() * 1000.0,
+    }
+}
+
+/// JS functions used by the host to list and fire timers
+#[derive(Clone)]
+pub(crate) struct TimerHooks {
+    pub pending: v8::Global<v8::Function>,
+    pub fire: v8::Global<v8::Function>,
+    pub enable_deterministic: v8::Global<v8::Function>,
+    pub set_timer_limit: v8::Global<v8::Function>,
+    pub advance_time: v8::Global<v8::Function>,
+    pub set_time: v8::Global<v8::Function>,
+    pub run_next: v8::Global<v8::Function>,
+}
+
+/// Get a function property of a hooks object
+fn hook(
+    scope: &mut v8::HandleScope,
+    hooks: v8::Local<v8::Object>,
+    name: &str,
+) -> Result<v8::Global<v8::Function>, Error> {
+    let key = v8::String::new(scope, name).ok_or_else(|| Error::V8Encoding(name.to_string()))?;
+    let value = hooks
+        .get(scope, key.into())
+        .ok_or_else(|| Error::ValueNotFound(name.to_string()))?;
+    let function: v8::Local<v8::Function> = value
+        .try_into()
+        .or::<Error>(Err(Error::ValueNotCallable(name.to_string())))?;
+    Ok(v8::Global::new(scope, function))
+}
+
+#[op2]
+/// Registers the functions used by the host to list and fire timers
+///
+/// # Arguments
+/// * `state` - The runtime's state, into which the functions will be put
+/// * `hooks` - An object containing the timer functions
+fn op_register_timer_hooks(
+    scope: &mut v8::HandleScope,
+    state: &mut OpState,
+    hooks: v8::Local<v8::Object>,
+) -> Result<(), Error> {
+    state.put(TimerHooks {
+        pending: hook(scope, hooks, "pendingTimers")?,
+        fire: hook(scope, hooks, "fireTimer")?,
+        enable_deterministic: hook(scope, hooks, "enableDeterministic")?,
+        set_timer_limit: hook(scope, hooks, "setTimerLimit")?,
+        advance_time: hook(scope, hooks, "advanceTime")?,
+        set_time: hook(scope, hooks, "setTime")?,
+        run_next: hook(scope, hooks, "runNextTimer")?,
+    });
+    Ok(())
+}
+
+extension!(
+    init_timers,
+    deps = [rustyscript],
+    ops = [op_register_timer_hooks],
+    esm_entry_point = "ext:init_timers/init_timers.js",
+    esm = [ dir "src/ext/timers", "init_timers.js" ],
+);
+
+pub fn extensions() -> Vec<Extension> {
+    vec![init_timers::init_ops_and_esm()]
+}
+
+pub fn snapshot_extensions() -> Vec<Extension> {
+    vec![init_timers::init_ops()]
+}
@@ -7,9 +7,35 @@ import * as eventSource from "ext:deno_fetch/27_eventsource.js";
 
 Deno.core.setWasmStreamingCallback(fetch.handleWasmStreaming);
 
+// Route requests through the host's fetch handler, if one was provided
+const interceptedFetch = async (input, init) => {
+    if (!Deno.core.ops.op_has_fetch_handler()) {
+        return fetch.fetch(input, init);
+    }
+
+    const req = new request.Request(input, init);
+    const forward = req.clone();
+    const body = req.body === null ? null : new Uint8Array(await req.arrayBuffer());
+    const result = await Deno.core.ops.op_fetch_handler({
+        method: req.method,
+        url: req.url,
+        headers: [...req.headers],
+        body,
+    });
+
+    if (result === null) {
+        return fetch.fetch(forward);
+    }
+
+    return new response.Response(result.body, {
+        status: result.status,
+        headers: result.headers,
+    });
+};
+
 import { applyToGlobal, writeable, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
 applyToGlobal({
-    fetch: writeable(fetch.fetch),
+    fetch: writeable(interceptedFetch),
     Request: nonEnumerable(request.Request),
     Response: nonEnumerable(response.Response),
     Headers: nonEnumerable(headers.Headers),
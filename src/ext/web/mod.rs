@@ -1,5 +1,6 @@
-use deno_core::{extension, Extension, ModuleSpecifier};
-use std::{rc::Rc, sync::Arc};
+use crate::Error;
+use deno_core::{extension, op2, Extension, JsBuffer, ModuleSpecifier, OpState, ToJsBuffer};
+use std::{future::Future, pin::Pin, rc::Rc, sync::Arc};
 
 #[derive(Clone)]
 pub struct Permissions;
@@ -54,6 +55,99 @@ impl deno_net::NetPermissions for Permissions {
     }
 }
 
+/// A request made from JS using `fetch`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FetchRequest {
+    /// HTTP method, such as `GET`
+    pub method: String,
+
+    /// The URL being requested
+    pub url: String,
+
+    /// Request headers, as name-value pairs
+    pub headers: Vec<(String, String)>,
+
+    /// The request body, if there is one
+    pub body: Option<Vec<u8>>,
+}
+
+/// A response to return to JS from a `FetchHandler`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FetchResponse {
+    /// HTTP status code
+    pub status: u16,
+
+    /// Response headers, as name-value pairs
+    pub headers: Vec<(String, String)>,
+
+    /// The response body
+    pub body: Vec<u8>,
+}
+
+/// Intercepts all calls to `fetch` made from JS
+/// Implement this trait to observe, restrict, or reroute outbound HTTP requests
+pub trait FetchHandler {
+    /// Handle a request made from JS
+    ///
+    /// Return `Ok(Some(response))` to answer the request directly,
+    /// `Ok(None)` to let it continue through the default HTTP client,
+    /// or an error to reject it
+    fn fetch(
+        &self,
+        request: FetchRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<Option<FetchResponse>, Error>>>>;
+}
+
+/// A `FetchRequest` as it arrives from JS, with the body passed as a buffer instead of an array of numbers
+#[derive(serde::Deserialize)]
+struct OpFetchRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<JsBuffer>,
+}
+
+/// A `FetchResponse` as it is returned to JS, with the body passed as a buffer
+#[derive(serde::Serialize)]
+struct OpFetchResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: ToJsBuffer,
+}
+
+#[op2(fast)]
+fn op_has_fetch_handler(state: &mut OpState) -> bool {
+    state.has::<Rc<dyn FetchHandler>>()
+}
+
+#[op2(async)]
+#[serde]
+fn op_fetch_handler(
+    #[serde] request: OpFetchRequest,
+    state: &mut OpState,
+) -> impl Future<Output = Result<Option<OpFetchResponse>, Error>> {
+    let handler = state.try_borrow::<Rc<dyn FetchHandler>>().cloned();
+    let request = FetchRequest {
+        method: request.method,
+        url: request.url,
+        headers: request.headers,
+        body: request.body.map(|body| body.to_vec()),
+    };
+
+    async move {
+        let Some(handler) = handler else {
+            return Ok(None);
+        };
+
+        let response = handler.fetch(request).await?;
+        Ok(response.map(|response| OpFetchResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body.into(),
+        }))
+    }
+}
+
 extension!(
     init_web,
     deps = [rustyscript],
@@ -65,9 +159,18 @@ extension!(
 extension!(
     init_fetch,
     deps = [rustyscript],
+    ops = [op_has_fetch_handler, op_fetch_handler],
     esm_entry_point = "ext:init_fetch/init_fetch.js",
     esm = [ dir "src/ext/web", "init_fetch.js" ],
-    state = |state| state.put(Permissions{})
+    options = {
+        handler: Option<Rc<dyn FetchHandler>>,
+    },
+    state = |state, config| {
+        state.put(Permissions{});
+        if let Some(handler) = config.handler {
+            state.put(handler);
+        }
+    },
 );
 
 extension!(
@@ -106,6 +209,9 @@ pub struct WebOptions {
 
     /// File fetch handler for fetch
     pub file_fetch_handler: Rc<dyn deno_fetch::FetchHandler>,
+
+    /// Optional handler intercepting all calls to `fetch` made from JS
+    pub fetch_handler: Option<Rc<dyn FetchHandler>>,
 }
 
 impl Default for WebOptions {
@@ -119,6 +225,7 @@ impl Default for WebOptions {
             unsafely_ignore_certificate_errors: None,
             client_cert_chain_and_key: deno_tls::TlsKeys::Null,
             file_fetch_handler: Rc::new(deno_fetch::DefaultFileFetchHandler),
+            fetch_handler: None,
         }
     }
 }
@@ -143,7 +250,7 @@ pub fn extensions(options: WebOptions) -> Vec<Extension> {
             file_fetch_handler: options.file_fetch_handler,
         }),
         init_web::init_ops_and_esm(),
-        init_fetch::init_ops_and_esm(),
+        init_fetch::init_ops_and_esm(options.fetch_handler),
         init_net::init_ops_and_esm(),
     ]
 }
@@ -165,7 +272,7 @@ pub fn snapshot_extensions(options: WebOptions) -> Vec<Extension> {
             file_fetch_handler: options.file_fetch_handler,
         }),
         init_web::init_ops(),
-        init_fetch::init_ops(),
+        init_fetch::init_ops(options.fetch_handler),
         init_net::init_ops(),
     ]
 }
@@ -0,0 +1,182 @@
+// Minimal TextEncoder, TextDecoder, atob and btoa, for builds without the web feature
+// Only UTF-8 is supported - other encodings need the full deno_web implementation
+import { DOMException } from 'ext:deno_web/01_dom_exception.js';
+
+const UTF8_LABELS = ['utf-8', 'utf8', 'unicode-1-1-utf-8'];
+
+const toBytes = (input) => {
+    if (input === undefined) return new Uint8Array(0);
+    if (input instanceof ArrayBuffer) return new Uint8Array(input);
+    if (ArrayBuffer.isView(input)) {
+        return new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
+    }
+    throw new TypeError('The provided value is not of type \'(ArrayBuffer or ArrayBufferView)\'');
+};
+
+class TextEncoder {
+    get encoding() {
+        return 'utf-8';
+    }
+
+    encode(input = '') {
+        return Deno.core.encode(String(input));
+    }
+
+    // Writes as many whole characters as fit into `dest`
+    encodeInto(source, dest) {
+        source = String(source);
+        let read = 0;
+        let written = 0;
+        while (read < source.length) {
+            let code = source.codePointAt(read);
+            const units = code > 0xffff ? 2 : 1;
+            if (code >= 0xd800 && code <= 0xdfff) code = 0xfffd;
+
+            const size = code < 0x80 ? 1 : code < 0x800 ? 2 : code < 0x10000 ? 3 : 4;
+            if (written + size > dest.length) break;
+
+            if (size === 1) {
+                dest[written] = code;
+            } else {
+                const lead = [0, 0, 0xc0, 0xe0, 0xf0][size];
+                dest[written] = lead | (code >> (6 * (size - 1)));
+                for (let i = 1; i < size; i++) {
+                    dest[written + i] = 0x80 | ((code >> (6 * (size - 1 - i))) & 0x3f);
+                }
+            }
+
+            read += units;
+            written += size;
+        }
+        return { read, written };
+    }
+}
+
+class TextDecoder {
+    #fatal;
+    #ignoreBOM;
+    #pending = [];
+    #bomSeen = false;
+
+    constructor(label = 'utf-8', options = {}) {
+        if (!UTF8_LABELS.includes(String(label).trim().toLowerCase())) {
+            throw new RangeError(`The encoding label provided ('${label}') is not supported`);
+        }
+        this.#fatal = Boolean(options.fatal);
+        this.#ignoreBOM = Boolean(options.ignoreBOM);
+    }
+
+    get encoding() {
+        return 'utf-8';
+    }
+
+    get fatal() {
+        return this.#fatal;
+    }
+
+    get ignoreBOM() {
+        return this.#ignoreBOM;
+    }
+
+    decode(input, options = {}) {
+        const stream = Boolean(options.stream);
+        const bytes = [...this.#pending, ...toBytes(input)];
+        this.#pending = [];
+
+        let output = '';
+        let i = 0;
+        while (i < bytes.length) {
+            const byte = bytes[i];
+            const [size, min] = byte < 0x80 ? [1, 0] :
+                byte >= 0xc2 && byte <= 0xdf ? [2, 0x80] :
+                byte >= 0xe0 && byte <= 0xef ? [3, 0x800] :
+                byte >= 0xf0 && byte <= 0xf4 ? [4, 0x10000] : [0, 0];
+
+            let code = size === 1 ? byte : byte & (0xff >> (size + 1));
+            let length = 1;
+            while (size > 1 && length < size && i + length < bytes.length) {
+                const next = bytes[i + length];
+                if ((next & 0xc0) !== 0x80) break;
+                code = (code << 6) | (next & 0x3f);
+                length++;
+            }
+
+            // Keep an incomplete sequence at the end of a stream chunk for the next call
+            if (size > 1 && length < size && i + length === bytes.length && stream) {
+                this.#pending = bytes.slice(i);
+                break;
+            }
+
+            if (size === 0 || length < size || code < min || code > 0x10ffff ||
+                (code >= 0xd800 && code <= 0xdfff)) {
+                if (this.#fatal) {
+                    throw new TypeError('The encoded data was not valid for encoding utf-8');
+                }
+                output += '\ufffd';
+                this.#bomSeen = true;
+                i += length;
+                continue;
+            }
+
+            // A byte order mark is only skipped at the start of a stream
+            const bom = code === 0xfeff && !this.#bomSeen && !this.#ignoreBOM;
+            this.#bomSeen = true;
+            if (!bom) output += String.fromCodePoint(code);
+            i += size;
+        }
+
+        if (!stream) {
+            this.#bomSeen = false;
+        }
+        return output;
+    }
+}
+
+const BASE64 = 'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/';
+
+function btoa(data) {
+    data = String(data);
+    let output = '';
+    for (let i = 0; i < data.length; i += 3) {
+        const chunk = [0, 1, 2].map((j) => data.charCodeAt(i + j));
+        if (chunk.some((c) => c > 0xff)) {
+            throw new DOMException(
+                'The string to be encoded contains characters outside of the Latin1 range.',
+                'InvalidCharacterError'
+            );
+        }
+
+        const [a, b, c] = chunk;
+        output += BASE64[a >> 2];
+        output += BASE64[((a & 3) << 4) | (isNaN(b) ? 0 : b >> 4)];
+        output += isNaN(b) ? '=' : BASE64[((b & 15) << 2) | (isNaN(c) ? 0 : c >> 6)];
+        output += isNaN(c) ? '=' : BASE64[c & 63];
+    }
+    return output;
+}
+
+function atob(data) {
+    data = String(data).replace(/[\t\n\f\r ]/g, '');
+    if (data.length % 4 === 0) data = data.replace(/==?$/, '');
+    if (data.length % 4 === 1 || /[^A-Za-z0-9+/]/.test(data)) {
+        throw new DOMException(
+            'The string to be decoded is not correctly encoded.',
+            'InvalidCharacterError'
+        );
+    }
+
+    let output = '';
+    let buffer = 0;
+    let bits = 0;
+    for (const char of data) {
+        buffer = (buffer << 6) | BASE64.indexOf(char);
+        bits += 6;
+        if (bits >= 8) {
+            bits -= 8;
+            output += String.fromCharCode((buffer >> bits) & 0xff);
+        }
+    }
+    return output;
+}
+
+export { atob, btoa, TextDecoder, TextEncoder };
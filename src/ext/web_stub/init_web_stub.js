@@ -0,0 +1,11 @@
+import { DOMException } from 'ext:deno_web/01_dom_exception.js';
+import * as encoding from 'ext:deno_web/02_encoding.js';
+
+import { applyToGlobal, nonEnumerable, writeable } from 'ext:rustyscript/rustyscript.js';
+applyToGlobal({
+    DOMException: nonEnumerable(DOMException),
+    TextEncoder: nonEnumerable(encoding.TextEncoder),
+    TextDecoder: nonEnumerable(encoding.TextDecoder),
+    atob: writeable(encoding.atob),
+    btoa: writeable(encoding.btoa),
+});
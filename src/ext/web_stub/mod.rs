@@ -2,8 +2,8 @@ use deno_core::{extension, Extension};
 extension!(
     deno_web,
     deps = [rustyscript],
-    esm_entry_point = "ext:deno_web/01_dom_exception.js",
-    esm = [ dir "src/ext/web_stub", "01_dom_exception.js" ],
+    esm_entry_point = "ext:deno_web/init_web_stub.js",
+    esm = [ dir "src/ext/web_stub", "01_dom_exception.js", "02_encoding.js", "init_web_stub.js" ],
 );
 
 pub fn extensions() -> Vec<Extension> {
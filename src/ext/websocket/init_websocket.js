@@ -0,0 +1,215 @@
+// A WebSocket client, backed by a connection on the host's event loop
+// Connections and messages pass through the host's WebSocketPolicy, if one is set
+import { applyToGlobal, nonEnumerable } from 'ext:rustyscript/rustyscript.js';
+
+const CONNECTING = 0;
+const OPEN = 1;
+const CLOSING = 2;
+const CLOSED = 3;
+
+class WebSocket {
+    static CONNECTING = CONNECTING;
+    static OPEN = OPEN;
+    static CLOSING = CLOSING;
+    static CLOSED = CLOSED;
+
+    #rid = null;
+    #url;
+    #protocol = '';
+    #readyState = CONNECTING;
+    #binaryType = 'arraybuffer';
+    #listeners = {};
+
+    onopen = null;
+    onmessage = null;
+    onerror = null;
+    onclose = null;
+
+    constructor(url, protocols = []) {
+        url = new URL(url);
+        if (url.protocol === 'http:') url.protocol = 'ws:';
+        if (url.protocol === 'https:') url.protocol = 'wss:';
+        if (url.protocol !== 'ws:' && url.protocol !== 'wss:') {
+            throw new SyntaxError(`The URL's scheme must be either 'ws' or 'wss', got '${url.protocol}'`);
+        }
+        if (url.hash) {
+            throw new SyntaxError('The URL must not contain a fragment');
+        }
+
+        this.#url = url.href;
+        protocols = typeof protocols === 'string' ? [protocols] : [...protocols];
+        this.#connect(protocols);
+    }
+
+    get url() {
+        return this.#url;
+    }
+
+    get protocol() {
+        return this.#protocol;
+    }
+
+    get extensions() {
+        return '';
+    }
+
+    get readyState() {
+        return this.#readyState;
+    }
+
+    get bufferedAmount() {
+        return 0;
+    }
+
+    get binaryType() {
+        return this.#binaryType;
+    }
+
+    set binaryType(value) {
+        if (value === 'arraybuffer' || value === 'blob') {
+            this.#binaryType = value;
+        }
+    }
+
+    send(data) {
+        if (this.#readyState === CONNECTING) {
+            throw domException('The socket is still connecting', 'InvalidStateError');
+        }
+        if (this.#readyState !== OPEN) return;
+
+        // Messages blocked by the host's policy throw here
+        if (typeof data === 'string') {
+            Deno.core.ops.op_ws_send_text(this.#rid, data);
+        } else if (data instanceof ArrayBuffer) {
+            Deno.core.ops.op_ws_send_binary(this.#rid, new Uint8Array(data));
+        } else if (ArrayBuffer.isView(data)) {
+            Deno.core.ops.op_ws_send_binary(
+                this.#rid,
+                new Uint8Array(data.buffer, data.byteOffset, data.byteLength)
+            );
+        } else {
+            Deno.core.ops.op_ws_send_text(this.#rid, String(data));
+        }
+    }
+
+    close(code = undefined, reason = '') {
+        if (code !== undefined && code !== 1000 && (code < 3000 || code > 4999)) {
+            throw domException(`The close code must be either 1000, or between 3000 and 4999. ${code} is neither.`, 'InvalidAccessError');
+        }
+        reason = String(reason);
+        if (Deno.core.encode(reason).length > 123) {
+            throw new SyntaxError('The close reason may not be longer than 123 bytes');
+        }
+
+        if (this.#readyState === CLOSING || this.#readyState === CLOSED) return;
+        const connecting = this.#readyState === CONNECTING;
+        this.#readyState = CLOSING;
+        if (!connecting) {
+            Deno.core.ops.op_ws_close(this.#rid, code ?? 0, reason);
+        }
+    }
+
+    addEventListener(type, listener) {
+        if (typeof listener !== 'function' && typeof listener?.handleEvent !== 'function') return;
+        const listeners = this.#listeners[type] ??= [];
+        if (!listeners.includes(listener)) listeners.push(listener);
+    }
+
+    removeEventListener(type, listener) {
+        const listeners = this.#listeners[type] ?? [];
+        const index = listeners.indexOf(listener);
+        if (index !== -1) listeners.splice(index, 1);
+    }
+
+    dispatchEvent(event) {
+        const handler = this[`on${event.type}`];
+        const listeners = [
+            ...(typeof handler === 'function' ? [handler] : []),
+            ...(this.#listeners[event.type] ?? []),
+        ];
+        for (const listener of listeners) {
+            try {
+                if (typeof listener === 'function') {
+                    listener.call(this, event);
+                } else {
+                    listener.handleEvent(event);
+                }
+            } catch (e) {
+                reportError(e);
+            }
+        }
+        return true;
+    }
+
+    #dispatch(init) {
+        this.dispatchEvent({ target: this, currentTarget: this, ...init });
+    }
+
+    async #connect(protocols) {
+        try {
+            const [rid, protocol] = await Deno.core.ops.op_ws_connect(this.#url, protocols);
+            this.#rid = rid;
+            this.#protocol = protocol;
+        } catch (e) {
+            this.#readyState = CLOSED;
+            this.#dispatch({ type: 'error', message: e.message });
+            this.#dispatch({ type: 'close', code: 1006, reason: '', wasClean: false });
+            return;
+        }
+
+        // Closed before the connection was established
+        if (this.#readyState === CLOSING) {
+            Deno.core.ops.op_ws_close(this.#rid, 0, '');
+        } else {
+            this.#readyState = OPEN;
+            this.#dispatch({ type: 'open' });
+        }
+
+        while (true) {
+            const event = await Deno.core.ops.op_ws_next(this.#rid);
+            switch (event.kind) {
+                case 'text':
+                    this.#dispatch({ type: 'message', data: event.data, origin: this.#url });
+                    break;
+
+                case 'binary':
+                    this.#dispatch({ type: 'message', data: this.#binary(event.data), origin: this.#url });
+                    break;
+
+                case 'error':
+                    this.#dispatch({ type: 'error', message: event.message });
+                    break;
+
+                case 'close':
+                    this.#readyState = CLOSED;
+                    Deno.core.tryClose(this.#rid);
+                    this.#dispatch({
+                        type: 'close',
+                        code: event.code,
+                        reason: event.reason,
+                        wasClean: event.wasClean,
+                    });
+                    return;
+            }
+        }
+    }
+
+    #binary(data) {
+        const bytes = new Uint8Array(data);
+        if (this.#binaryType === 'blob' && typeof Blob === 'function') {
+            return new Blob([bytes]);
+        }
+        return bytes.buffer;
+    }
+}
+
+// DOMException is only available with the web or web_stub features
+const domException = (message, name) => typeof DOMException === 'function' ?
+    new DOMException(message, name) : Object.assign(new Error(message), { name });
+
+// Errors thrown by listeners must not stop the connection
+const reportError = (e) => globalThis.reportError ? globalThis.reportError(e) : globalThis.console?.error(e);
+
+applyToGlobal({
+    WebSocket: nonEnumerable(WebSocket),
+});
@@ -0,0 +1,373 @@
+use crate::Error;
+use deno_core::{
+    extension,
+    futures::{
+        channel::mpsc,
+        stream::{SplitStream, StreamExt},
+    },
+    op2,
+    url::Url,
+    AsyncRefCell, Extension, OpState, RcRef, Resource, ResourceId,
+};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        client::IntoClientRequest,
+        protocol::{frame::coding::CloseCode, CloseFrame, WebSocketConfig},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+/// Direction of a message passing through a [WebSocketPolicy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageDirection {
+    /// Sent by the script
+    Sent,
+
+    /// Received from the server
+    Received,
+}
+
+/// A message passing through a [WebSocketPolicy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSocketMessage<'a> {
+    /// A text message
+    Text(&'a str),
+
+    /// A binary message
+    Binary(&'a [u8]),
+}
+
+/// Host-side control over the `WebSocket` API
+/// Implement this trait to approve connections, and inspect or drop messages
+/// Without a policy, scripts may connect to any URL
+pub trait WebSocketPolicy {
+    /// Return false to refuse a connection to `url`
+    /// The script sees the refusal as a failed connection
+    ///
+    /// The URL is parsed, so its host and port are the ones the connection is made to
+    fn allow_connect(&self, url: &Url) -> bool;
+
+    /// Return false to drop a message to or from `url`
+    /// Dropped messages sent by the script make `WebSocket.send` throw,
+    /// and dropped messages from the server are never delivered
+    fn allow_message(
+        &self,
+        url: &Url,
+        direction: MessageDirection,
+        message: WebSocketMessage,
+    ) -> bool {
+        let _ = (url, direction, message);
+        true
+    }
+}
+
+type PolicyRc = Rc<dyn WebSocketPolicy>;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Largest message accepted from a server
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How long to wait for a connection and its handshake, or for the server's reply to a close
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sent from a connection to JS
+#[derive(serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Event {
+    Text {
+        data: String,
+    },
+    Binary {
+        data: Vec<u8>,
+    },
+    Error {
+        message: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Close {
+        code: u16,
+        reason: String,
+        was_clean: bool,
+    },
+}
+
+impl Event {
+    /// The close event for a connection that ended without a closing handshake
+    fn abnormal_close() -> Self {
+        Self::Close {
+            code: 1006,
+            reason: String::new(),
+            was_clean: false,
+        }
+    }
+}
+
+/// An open connection, as seen from JS
+/// Messages are written by a task on the event loop, in the order they were sent
+struct WebSocketResource {
+    url: Url,
+    outgoing: mpsc::UnboundedSender<Message>,
+    incoming: AsyncRefCell<SplitStream<Socket>>,
+
+    // When the script asked to close the connection, if it has
+    closing: Cell<Option<Instant>>,
+
+    // Set once the connection has failed, so the next event closes it
+    failed: Cell<bool>,
+}
+
+impl Resource for WebSocketResource {
+    fn name(&self) -> Cow<str> {
+        "webSocket".into()
+    }
+}
+
+fn policy(state: &OpState) -> Option<PolicyRc> {
+    state.try_borrow::<PolicyRc>().cloned()
+}
+
+fn resource(state: &OpState, rid: ResourceId) -> Result<Rc<WebSocketResource>, Error> {
+    state
+        .resource_table
+        .get::<WebSocketResource>(rid)
+        .map_err(|e| Error::Runtime(e.to_string()))
+}
+
+#[op2(async)]
+#[serde]
+async fn op_ws_connect(
+    state: Rc<RefCell<OpState>>,
+    #[string] url: String,
+    #[serde] protocols: Vec<String>,
+) -> Result<(ResourceId, String), Error> {
+    let url = Url::parse(&url).map_err(|e| Error::Runtime(format!("invalid url {url}: {e}")))?;
+    if !matches!(url.scheme(), "ws" | "wss") {
+        return Err(Error::Runtime(format!(
+            "unsupported scheme {}",
+            url.scheme()
+        )));
+    }
+
+    if let Some(policy) = policy(&state.borrow()) {
+        if !policy.allow_connect(&url) {
+            return Err(Error::Runtime(format!(
+                "connection to {url} was denied by the websocket policy"
+            )));
+        }
+    }
+
+    let mut request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| Error::Runtime(format!("could not connect to {url}: {e}")))?;
+    if !protocols.is_empty() {
+        let protocols = protocols
+            .join(", ")
+            .parse()
+            .map_err(|_| Error::Runtime("invalid websocket protocol".to_string()))?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", protocols);
+    }
+
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_MESSAGE_SIZE),
+        ..Default::default()
+    };
+    let connect = tokio_tungstenite::connect_async_with_config(request, Some(config), true);
+    let (socket, response) = tokio::time::timeout(CONNECT_TIMEOUT, connect)
+        .await
+        .map_err(|_| Error::Timeout(format!("could not connect to {url} in time")))?
+        .map_err(|e| Error::Runtime(format!("could not connect to {url}: {e}")))?;
+
+    let protocol = response
+        .headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|protocol| protocol.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    // Queued messages are written as the event loop runs, and the writer stops once
+    // the resource, and the sender with it, is dropped
+    let (sink, incoming) = socket.split();
+    let (outgoing, queued) = mpsc::unbounded();
+    deno_core::unsync::spawn(queued.map(Ok).forward(sink));
+
+    let rid = state.borrow_mut().resource_table.add(WebSocketResource {
+        url,
+        outgoing,
+        incoming: AsyncRefCell::new(incoming),
+        closing: Cell::new(None),
+        failed: Cell::new(false),
+    });
+    Ok((rid, protocol))
+}
+
+/// Queue a message, after checking it against the policy
+fn send(state: &OpState, rid: ResourceId, message: WebSocketMessage) -> Result<(), Error> {
+    let resource = resource(state, rid)?;
+    if let Some(policy) = policy(state) {
+        if !policy.allow_message(&resource.url, MessageDirection::Sent, message) {
+            return Err(Error::Runtime(
+                "message was blocked by the websocket policy".to_string(),
+            ));
+        }
+    }
+
+    let message = match message {
+        WebSocketMessage::Text(text) => Message::Text(text.to_string()),
+        WebSocketMessage::Binary(data) => Message::Binary(data.to_vec()),
+    };
+    resource
+        .outgoing
+        .unbounded_send(message)
+        .map_err(|_| Error::Runtime("The websocket is closed".to_string()))
+}
+
+#[op2(fast)]
+fn op_ws_send_text(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[string] data: &str,
+) -> Result<(), Error> {
+    send(state, rid, WebSocketMessage::Text(data))
+}
+
+#[op2(fast)]
+fn op_ws_send_binary(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[buffer] data: &[u8],
+) -> Result<(), Error> {
+    send(state, rid, WebSocketMessage::Binary(data))
+}
+
+/// A `code` of 0 closes the connection without a status code
+#[op2(fast)]
+fn op_ws_close(
+    state: &mut OpState,
+    #[smi] rid: ResourceId,
+    #[smi] code: u16,
+    #[string] reason: String,
+) -> Result<(), Error> {
+    let resource = resource(state, rid)?;
+    if resource.closing.get().is_some() {
+        return Ok(());
+    }
+    resource.closing.set(Some(Instant::now()));
+
+    let frame = (code != 0).then(|| CloseFrame {
+        code: CloseCode::from(code),
+        reason: reason.into(),
+    });
+
+    // A connection that has already failed has nothing left to close
+    let _ = resource.outgoing.unbounded_send(Message::Close(frame));
+    Ok(())
+}
+
+#[op2(async)]
+#[serde]
+async fn op_ws_next(state: Rc<RefCell<OpState>>, #[smi] rid: ResourceId) -> Result<Event, Error> {
+    let (resource, policy) = {
+        let state = state.borrow();
+        (resource(&state, rid)?, policy(&state))
+    };
+
+    let mut incoming = RcRef::map(&resource, |r| &r.incoming).borrow_mut().await;
+    loop {
+        if resource.failed.get() {
+            return Ok(Event::abnormal_close());
+        }
+
+        // Pings are answered, and closes echoed, as the connection is read
+        let next = match resource.closing.get() {
+            None => incoming.next().await,
+            Some(start) => {
+                let remaining = CONNECT_TIMEOUT.saturating_sub(start.elapsed());
+                match tokio::time::timeout(remaining, incoming.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        resource.failed.set(true);
+                        return Ok(Event::Error {
+                            message: "the server did not acknowledge the close".to_string(),
+                        });
+                    }
+                }
+            }
+        };
+
+        let event = match next {
+            Some(Ok(Message::Text(data))) => Event::Text { data },
+            Some(Ok(Message::Binary(data))) => Event::Binary { data },
+            Some(Ok(Message::Close(frame))) => {
+                return Ok(match frame {
+                    Some(frame) => Event::Close {
+                        code: frame.code.into(),
+                        reason: frame.reason.into_owned(),
+                        was_clean: true,
+                    },
+                    None => Event::Close {
+                        code: 1005,
+                        reason: String::new(),
+                        was_clean: true,
+                    },
+                })
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => {
+                resource.failed.set(true);
+                return Ok(Event::Error {
+                    message: e.to_string(),
+                });
+            }
+            None => return Ok(Event::abnormal_close()),
+        };
+
+        let message = match &event {
+            Event::Text { data } => WebSocketMessage::Text(data),
+            Event::Binary { data } => WebSocketMessage::Binary(data),
+            _ => return Ok(event),
+        };
+
+        let allowed = policy.as_ref().map_or(true, |policy| {
+            policy.allow_message(&resource.url, MessageDirection::Received, message)
+        });
+        if allowed {
+            return Ok(event);
+        }
+    }
+}
+
+extension!(
+    init_websocket,
+    deps = [rustyscript],
+    ops = [op_ws_connect, op_ws_send_text, op_ws_send_binary, op_ws_close, op_ws_next],
+    esm_entry_point = "ext:init_websocket/init_websocket.js",
+    esm = [ dir "src/ext/websocket", "init_websocket.js" ],
+    options = {
+        policy: Option<PolicyRc>,
+    },
+    state = |state, config| {
+        if let Some(policy) = config.policy {
+            state.put::<PolicyRc>(policy);
+        }
+    },
+);
+
+pub fn extensions(policy: Option<PolicyRc>) -> Vec<Extension> {
+    vec![init_websocket::init_ops_and_esm(policy)]
+}
+
+pub fn snapshot_extensions(policy: Option<PolicyRc>) -> Vec<Extension> {
+    vec![init_websocket::init_ops(policy)]
+}
@@ -0,0 +1,213 @@
+use crate::Error;
+use deno_core::{url::Url, ModuleSpecifier};
+
+/// Controls which remote modules may be imported
+/// Only applies to `http`, `https` and `npm:` imports - and only if the `url_import` feature
+/// is enabled, since remote imports are always denied without it
+///
+/// `npm:` imports are checked against the registry URL they resolve to
+#[derive(Debug, Clone, Default)]
+pub enum ImportPolicy {
+    /// Allow all remote imports
+    #[default]
+    AllowAll,
+
+    /// Deny all remote imports
+    Offline,
+
+    /// Only allow remote imports matching one of the patterns
+    AllowList(Vec<UrlPattern>),
+}
+
+impl ImportPolicy {
+    /// Check a remote import against the policy
+    /// Returns `Error::ImportDenied` if it is not allowed
+    pub fn check(&self, url: &ModuleSpecifier) -> Result<(), Error> {
+        let allowed = match self {
+            Self::AllowAll => true,
+            Self::Offline => false,
+            Self::AllowList(patterns) => patterns.iter().any(|p| p.matches(url.as_str())),
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::ImportDenied(url.to_string()))
+        }
+    }
+}
+
+/// A URL pattern for use in `ImportPolicy::AllowList`
+/// URLs are parsed, and their scheme, host, port, path and query are matched separately
+///
+/// In the host, `*` matches within a single DNS label, so `*.example.com` matches
+/// `cdn.example.com` but not `a.b.example.com` or `example.com`
+/// In the scheme, path and query, `*` matches any sequence of characters
+/// All other characters match themselves
+///
+/// Without a port, only URLs on the scheme's default port match
+/// Without a query, URLs match whatever their query is. The fragment and userinfo are ignored
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::UrlPattern;
+///
+/// let pattern = UrlPattern::new("https://cdn.example.com/libs/*");
+/// assert!(pattern.matches("https://cdn.example.com/libs/util.js"));
+/// assert!(!pattern.matches("https://evil.example.com/libs/util.js"));
+/// assert!(!pattern.matches("https://cdn.example.com@evil.com/libs/util.js"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlPattern(String);
+
+impl UrlPattern {
+    /// Create a new pattern
+    pub fn new(pattern: &str) -> Self {
+        Self(pattern.to_string())
+    }
+
+    /// Returns true if the URL matches the pattern
+    /// URLs that cannot be parsed never match
+    pub fn matches(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+
+        let Some((scheme, rest)) = self.0.split_once("://") else {
+            return false;
+        };
+        let (authority, rest) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        // An IPv6 host keeps its brackets, so only look for a port after them
+        let port_start = authority.rfind(']').unwrap_or(0);
+        let (host, port) = match authority[port_start..].rfind(':') {
+            Some(i) => (
+                &authority[..port_start + i],
+                Some(&authority[port_start + i + 1..]),
+            ),
+            None => (authority, None),
+        };
+
+        let host = host.to_ascii_lowercase();
+        let host_matches = url.host_str().is_some_and(|url_host| {
+            let url_labels: Vec<&str> = url_host.split('.').collect();
+            let labels: Vec<&str> = host.split('.').collect();
+            labels.len() == url_labels.len()
+                && labels
+                    .iter()
+                    .zip(url_labels)
+                    .all(|(label, url_label)| glob(label, url_label))
+        });
+        let port_matches = match port {
+            Some(port) => url
+                .port_or_known_default()
+                .is_some_and(|url_port| glob(port, &url_port.to_string())),
+            None => url.port().is_none(),
+        };
+        let path = if path.is_empty() { "/" } else { path };
+
+        glob(&scheme.to_ascii_lowercase(), url.scheme())
+            && host_matches
+            && port_matches
+            && glob(path, url.path())
+            && query.is_none_or(|query| glob(query, url.query().unwrap_or_default()))
+    }
+}
+
+/// Returns true if `text` matches `pattern`, where `*` matches any sequence of characters
+pub(crate) fn glob(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut u) = (0, 0);
+
+    // Position of the last `*`, and of the text when it was reached, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while u < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, u));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[u] {
+            p += 1;
+            u += 1;
+        } else if let Some((star_p, star_u)) = star {
+            // Let the last `*` consume one more character
+            p = star_p + 1;
+            u = star_u + 1;
+            star = Some((star_p, star_u + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+impl From<&str> for UrlPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test_import_policy {
+    use super::*;
+
+    #[test]
+    fn test_url_pattern() {
+        let pattern = UrlPattern::new("https://*.example.com/*.js");
+        assert!(pattern.matches("https://cdn.example.com/lib/util.js"));
+        assert!(!pattern.matches("https://cdn.example.com/lib/util.ts"));
+        assert!(!pattern.matches("http://cdn.example.com/util.js"));
+
+        let pattern = UrlPattern::new("https://example.com/util.js");
+        assert!(pattern.matches("https://example.com/util.js"));
+        assert!(!pattern.matches("https://example.com/util.jsx"));
+    }
+
+    #[test]
+    fn test_url_pattern_smuggling() {
+        let pattern = UrlPattern::new("https://*.example.com/*.js");
+
+        // Query, userinfo and fragment cannot stand in for the host or path
+        assert!(!pattern.matches("https://attacker.com/?.example.com/a.js"));
+        assert!(!pattern.matches("https://cdn.example.com@attacker.com/a.js"));
+        assert!(!pattern.matches("https://attacker.com/#.example.com/a.js"));
+        assert!(!pattern.matches("https://attacker.com/a.example.com/a.js"));
+
+        // `*` matches exactly one label of the host
+        assert!(!pattern.matches("https://a.b.example.com/a.js"));
+        assert!(!pattern.matches("https://example.com/a.js"));
+        assert!(!pattern.matches("https://cdn.example.com.attacker.com/a.js"));
+
+        // Other ports need to be allowed explicitly
+        assert!(!pattern.matches("https://cdn.example.com:8443/a.js"));
+        assert!(UrlPattern::new("https://cdn.example.com:*/*")
+            .matches("https://cdn.example.com:8443/a.js"));
+
+        // The query is only checked if the pattern has one
+        assert!(pattern.matches("https://cdn.example.com/a.js?v=1"));
+        assert!(!UrlPattern::new("https://cdn.example.com/a.js?v=1")
+            .matches("https://cdn.example.com/a.js?v=2"));
+    }
+
+    #[test]
+    fn test_import_policy() {
+        let url = ModuleSpecifier::parse("https://example.com/util.js").unwrap();
+        assert!(ImportPolicy::AllowAll.check(&url).is_ok());
+        assert!(matches!(
+            ImportPolicy::Offline.check(&url),
+            Err(Error::ImportDenied(_))
+        ));
+
+        let policy = ImportPolicy::AllowList(vec!["https://example.com/*".into()]);
+        assert!(policy.check(&url).is_ok());
+
+        let url = ModuleSpecifier::parse("https://example.org/util.js").unwrap();
+        assert!(matches!(policy.check(&url), Err(Error::ImportDenied(_))));
+    }
+}
@@ -1,14 +1,41 @@
 use crate::{
     cache_provider::ModuleCacheProvider,
-    ext,
+    crash_dump,
+    ext::{
+        self,
+        rustyscript::{
+            CallContextState, CallbackMiddleware, DisabledNamespaces, ExceptionHooks,
+            LastException, MessageQueue,
+        },
+        timers::{DeterministicOptions, PendingTimer, RawPendingTimer, TimerHooks},
+    },
+    interrupt_handle::{InterruptHandle, Watchdog},
     js_function::JsFunction,
-    module_loader::RustyLoader,
+    js_value::JsValue,
+    module_loader::{CancelHandle, LoadObserver, LoadProgress, LoaderOptions, RustyLoader},
+    promise_tracker,
+    quota::{Quota, QuotaUsage},
+    realm::RealmHandle,
+    resource_handle::HandleTable,
+    runtime_stats::{RuntimeStats, StatsTracker},
+    spans::enter_span,
+    state_cell::StateCell,
     traits::{ToDefinedValue, ToModuleSpecifier, ToV8String},
     transpiler::{self, transpile_extension},
-    Error, Module, ModuleHandle,
+    CompiledModule, Error, ExportInfo, ExportKind, LoadResult, Module, ModuleHandle, V8Value,
+};
+use deno_core::{serde_json, v8, JsRuntime, ModuleId, PollEventLoopOptions, RuntimeOptions};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
 };
-use deno_core::{serde_json, v8, JsRuntime, PollEventLoopOptions, RuntimeOptions};
-use std::{collections::HashMap, pin::Pin, rc::Rc, time::Duration};
 
 /// Represents a function that can be registered with the runtime
 pub trait RsFunction: Fn(&FunctionArguments) -> Result<serde_json::Value, Error> + 'static {}
@@ -33,9 +60,112 @@ impl<F> RsAsyncFunction for F where
 {
 }
 
+/// Middleware wrapping every call from JS to a registered rust function
+/// Layers are applied in order before the call, and in reverse order after it
+pub trait CallbackLayer {
+    /// Called before the function runs
+    /// Arguments can be modified, or the call rejected by returning an error
+    fn on_call(&self, name: &str, args: &mut Vec<serde_json::Value>) -> Result<(), Error> {
+        let _ = (name, args);
+        Ok(())
+    }
+
+    /// Called after the function runs, with its result
+    /// The result can be inspected, or replaced
+    fn on_result(
+        &self,
+        name: &str,
+        result: Result<serde_json::Value, Error>,
+    ) -> Result<serde_json::Value, Error> {
+        let _ = name;
+        result
+    }
+}
+
 /// Type required to pass arguments to JsFunctions
 pub type FunctionArguments = [serde_json::Value];
 
+/// Optional metadata describing a registered rust function
+/// Returned to JS by `rustyscript.functions()`, to allow scripts to discover the functions on offer
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FunctionInfo {
+    /// The number of arguments the function expects
+    pub arity: Option<usize>,
+
+    /// A human-readable signature for the function, such as `add(a: number, b: number): number`
+    pub signature: Option<String>,
+}
+
+/// A rust function registered with the runtime, as listed by `Runtime::registered_functions`
+/// Serialized the same way for JS, by `rustyscript.functions()`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RegisteredFunction {
+    /// The name the function is registered under, including its namespace if it has one
+    pub name: String,
+
+    /// True if the function was registered as async, and returns a promise to JS
+    #[serde(rename = "async")]
+    pub is_async: bool,
+
+    /// Metadata supplied when the function was registered
+    #[serde(flatten)]
+    pub info: FunctionInfo,
+
+    /// The schema its arguments are checked against, set with `Runtime::set_function_schema`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<crate::ArgSchema>,
+}
+
+/// Describes a call from JS to a function registered with `Runtime::register_function_with_context`
+/// Use it to correlate a callback with the module and request that triggered it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallContext {
+    /// The name the function was called by
+    pub function: String,
+
+    /// Specifier of the script or module the call was made from, if it could be found
+    pub module: Option<String>,
+
+    /// The request ID set with `Runtime::set_request_id`, if any
+    pub request_id: Option<String>,
+
+    /// The span that was active when the call was made
+    #[cfg(feature = "tracing")]
+    pub span: Option<tracing::Span>,
+}
+
+/// Options for driving the event loop with `Runtime::run_event_loop`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollOptions {
+    /// Keep the event loop alive while an inspector session is connected
+    pub wait_for_inspector: bool,
+
+    /// Let V8 run its own pending tasks, such as WebAssembly compilation, on each poll
+    pub pump_v8_message_loop: bool,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            wait_for_inspector: false,
+            pump_v8_message_loop: true,
+        }
+    }
+}
+
+impl From<PollOptions> for PollEventLoopOptions {
+    fn from(options: PollOptions) -> Self {
+        Self {
+            wait_for_inspector: options.wait_for_inspector,
+            pump_v8_message_loop: options.pump_v8_message_loop,
+        }
+    }
+}
+
+/// Handler for promise rejections and exceptions raised outside of a call
+/// See `RuntimeOptions::on_unhandled_rejection` and `RuntimeOptions::on_uncaught_exception`
+pub type ExceptionHandler = Box<dyn Fn(&crate::JsException)>;
+
 /// Represents the set of options accepted by the runtime constructor
 pub struct InnerRuntimeOptions {
     /// A set of deno_core extensions to add to the runtime
@@ -47,9 +177,16 @@ pub struct InnerRuntimeOptions {
     /// Function to use as entrypoint if the module does not provide one
     pub default_entrypoint: Option<String>,
 
-    /// Amount of time to run for before killing the thread
+    /// Amount of time each call may run for before timing out
+    /// A call that runs past it is stopped, even in the middle of synchronous code
     pub timeout: Duration,
 
+    /// Optional total amount of wall-clock time the runtime may spend in calls, across all calls
+    /// This includes time spent waiting on timers and I/O, not just time spent running JS
+    /// A call that runs past what remains is stopped, even in the middle of synchronous code,
+    /// and once exhausted, calls will time out until `Runtime::reset_budget` is called
+    pub time_budget: Option<Duration>,
+
     /// Optional cache provider for the module loader
     pub module_cache: Option<Box<dyn ModuleCacheProvider>>,
 
@@ -58,6 +195,84 @@ pub struct InnerRuntimeOptions {
     /// as when the snapshot was created
     /// If provided, user-supplied extensions must be instantiated with `init_ops` instead of `init_ops_and_esm`
     pub startup_snapshot: Option<&'static [u8]>,
+
+    /// Optional maximum size of the V8 heap, in bytes
+    /// V8 aborts the process if the limit is reached - set `crash_dump_path` to find out why
+    pub max_heap_size: Option<usize>,
+
+    /// If true, function calls and `eval` will return an error
+    /// instead of deserializing an `undefined` result
+    pub strict_undefined: bool,
+
+    /// If true, loading a module whose top-level await never settles fails with
+    /// `Error::UnresolvedTopLevelAwait`, rather than a timeout or a generic error
+    /// The module counts as loaded once its top-level await settles - it no longer
+    /// has to wait for the timeout if the await is stuck
+    pub strict_top_level_await: bool,
+
+    /// If true, the runtime counts promises that have not yet settled,
+    /// so that leaked promises can be found with `Runtime::pending_promise_count`
+    /// This slows down code that creates a lot of promises
+    pub track_promises: bool,
+
+    /// Middleware applied to every call from JS to a registered rust function
+    pub callback_middleware: Vec<Box<dyn CallbackLayer>>,
+
+    /// Called with promise rejections that nothing handled
+    /// If set, these no longer fail the call or event loop run they happened during
+    pub on_unhandled_rejection: Option<ExceptionHandler>,
+
+    /// Called with exceptions thrown outside of a call, such as from timer callbacks
+    /// If set, these no longer fail the call or event loop run they happened during
+    pub on_uncaught_exception: Option<ExceptionHandler>,
+
+    /// If set, a crash report is written to this path if the isolate runs out of memory,
+    /// or hits a fatal error, just before the process aborts
+    /// Fatal errors are not reported on windows
+    /// The report contains the last function called, module loaded or expression evaluated,
+    /// heap limits, and the runtime's statistics from just before that call
+    pub crash_dump_path: Option<std::path::PathBuf>,
+
+    /// Controls which remote modules may be imported
+    /// Defaults to `ImportPolicy::AllowAll`
+    pub import_policy: crate::ImportPolicy,
+
+    /// Rewrite import specifiers before they are resolved, in the order given
+    /// See `ResolverHook` for details
+    pub resolver_hooks: Vec<Box<dyn crate::ResolverHook>>,
+
+    /// If set, the security profile overrides the options for each capability it does not grant
+    /// See `Profile` for the presets, and `Profile::builder` for custom profiles
+    pub profile: Option<crate::Profile>,
+
+    /// If true, the `WebAssembly` global is removed, and `.wasm` modules cannot be imported
+    /// For embeddings that must not run WebAssembly at all
+    pub disable_wasm: bool,
+
+    /// Values set on `globalThis` as the runtime is created, before any module is evaluated
+    /// Use these to pass configuration to scripts without templating it into their source
+    pub globals: HashMap<String, serde_json::Value>,
+
+    /// If set, a DevTools-compatible inspector server is started for the runtime
+    #[cfg(feature = "inspector")]
+    pub inspector: Option<crate::InspectorOptions>,
+
+    /// If set, the runtime is deterministic - `Math.random` is seeded, and `Date`,
+    /// `performance.now` and timers follow a virtual clock moved by `advance_time`
+    /// Not available with the `web` feature, whose timers cannot be controlled - creating the runtime fails
+    pub deterministic: Option<DeterministicOptions>,
+
+    /// If set, `setTimeout` and `setInterval` throw a `RangeError` once this many timers are pending
+    /// Not available with the `web` feature, whose timers cannot be controlled - creating the runtime fails
+    pub max_timers: Option<usize>,
+
+    /// If set, usage is recorded against a tenant's quota, and the runtime refuses
+    /// to run javascript once any of the quota's limits is reached
+    pub quota: Option<Quota>,
+
+    /// Options for resolving `npm:` specifiers
+    #[cfg(feature = "npm")]
+    pub npm_options: crate::NpmOptions,
 }
 
 impl Default for InnerRuntimeOptions {
@@ -66,8 +281,31 @@ impl Default for InnerRuntimeOptions {
             extensions: Default::default(),
             default_entrypoint: Default::default(),
             timeout: Duration::MAX,
+            time_budget: None,
             module_cache: None,
             startup_snapshot: None,
+            max_heap_size: None,
+            strict_undefined: false,
+            strict_top_level_await: false,
+            track_promises: false,
+            callback_middleware: Vec::new(),
+            on_unhandled_rejection: None,
+            on_uncaught_exception: None,
+            crash_dump_path: None,
+            import_policy: Default::default(),
+            resolver_hooks: Vec::new(),
+            profile: None,
+            disable_wasm: false,
+            globals: HashMap::new(),
+            deterministic: None,
+            max_timers: None,
+            quota: None,
+
+            #[cfg(feature = "inspector")]
+            inspector: None,
+
+            #[cfg(feature = "npm")]
+            npm_options: Default::default(),
 
             extension_options: Default::default(),
         }
@@ -79,10 +317,51 @@ impl Default for InnerRuntimeOptions {
 pub struct InnerRuntime {
     pub deno_runtime: JsRuntime,
     pub options: InnerRuntimeOptions,
+    reload_count: usize,
+    stats: StatsTracker,
+    loader: Rc<RustyLoader>,
+    quota_error: Option<Error>,
+    interrupted: Arc<AtomicBool>,
+
+    // Stops a call once its time runs out, and records whether it did
+    // Created on the first call with a time limit, and then armed for each call
+    watchdog: Option<Watchdog>,
+    watchdog_fired: Arc<AtomicBool>,
+
+    // The error to report if the watchdog stops the current call - None while disarmed
+    watchdog_limit: Option<String>,
+
+    #[cfg(feature = "inspector")]
+    inspector_address: Option<std::net::SocketAddr>,
 }
 impl InnerRuntime {
-    pub fn new(options: InnerRuntimeOptions) -> Result<Self, Error> {
-        let loader = Rc::new(RustyLoader::new(options.module_cache));
+    pub fn new(mut options: InnerRuntimeOptions) -> Result<Self, Error> {
+        if let Some(profile) = options.profile.take() {
+            profile.apply(&mut options);
+        }
+
+        // deno_web provides its own timers, which have no hooks to limit or control them
+        #[cfg(feature = "web")]
+        for (set, option) in [
+            (options.deterministic.is_some(), "deterministic"),
+            (options.max_timers.is_some(), "max_timers"),
+        ] {
+            if set {
+                return Err(Error::Runtime(format!(
+                    "RuntimeOptions::{option} is not available with the `web` feature, since deno_web's timers cannot be controlled"
+                )));
+            }
+        }
+
+        let loader = Rc::new(RustyLoader::with_options(LoaderOptions {
+            cache_provider: options.module_cache,
+            import_policy: options.import_policy,
+            resolver_hooks: options.resolver_hooks,
+            disable_wasm: options.disable_wasm,
+
+            #[cfg(feature = "npm")]
+            npm_options: options.npm_options,
+        }));
 
         // If a snapshot is provided, do not reload ops
         let extensions = if options.startup_snapshot.is_some() {
@@ -91,28 +370,136 @@ impl InnerRuntime {
             ext::all_extensions(options.extensions, options.extension_options)
         };
 
-        Ok(Self {
-            deno_runtime: JsRuntime::try_new(RuntimeOptions {
-                module_loader: Some(loader.clone()),
+        let stats = StatsTracker::default();
+        let mut deno_runtime = JsRuntime::try_new(RuntimeOptions {
+            module_loader: Some(loader.clone()),
 
-                extension_transpiler: Some(Rc::new(|specifier, code| {
-                    transpile_extension(specifier, code)
-                })),
+            extension_transpiler: Some(Rc::new(|specifier, code| {
+                transpile_extension(specifier, code)
+            })),
 
-                source_map_getter: Some(loader),
+            source_map_getter: Some(loader.clone()),
 
-                startup_snapshot: options.startup_snapshot,
-                extensions,
+            startup_snapshot: options.startup_snapshot,
+            extensions,
 
-                ..Default::default()
-            })?,
+            create_params: options
+                .max_heap_size
+                .map(|max| v8::CreateParams::default().heap_limits(0, max)),
+
+            op_metrics_factory_fn: Some(stats.op_metrics_factory_fn()),
+            get_error_class_fn: Some(&ext::rustyscript::error_class),
+            custom_module_evaluation_cb: Some(Box::new(crate::module_loader::evaluate_data_module)),
+
+            #[cfg(feature = "inspector")]
+            inspector: options.inspector.is_some(),
+
+            ..Default::default()
+        })?;
 
+        #[cfg(feature = "inspector")]
+        let inspector_address = options
+            .inspector
+            .as_ref()
+            .map(|inspector| crate::inspector::start(&mut deno_runtime, inspector))
+            .transpose()?;
+
+        if let Some(path) = &options.crash_dump_path {
+            crash_dump::install(deno_runtime.v8_isolate(), path);
+        }
+
+        if options.track_promises {
+            promise_tracker::install(deno_runtime.v8_isolate());
+        }
+
+        if options.disable_wasm {
+            deno_runtime.execute_script(
+                "ext:rustyscript/disable_wasm.js",
+                "delete globalThis.WebAssembly;",
+            )?;
+        }
+
+        if !options.callback_middleware.is_empty() {
+            let middleware: CallbackMiddleware = Rc::new(options.callback_middleware);
+            deno_runtime.op_state().borrow_mut().put(middleware);
+        }
+
+        if options.on_unhandled_rejection.is_some() || options.on_uncaught_exception.is_some() {
+            deno_runtime.op_state().borrow_mut().put(ExceptionHooks {
+                on_unhandled_rejection: options.on_unhandled_rejection.map(Rc::from),
+                on_uncaught_exception: options.on_uncaught_exception.map(Rc::from),
+            });
+        }
+
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let watchdog_fired = Arc::new(AtomicBool::new(false));
+
+        #[cfg(feature = "process")]
+        deno_runtime
+            .op_state()
+            .borrow_mut()
+            .put(crate::interrupt_handle::StopSignal {
+                interrupted: interrupted.clone(),
+                watchdog_fired: watchdog_fired.clone(),
+            });
+
+        let mut runtime = Self {
+            deno_runtime,
             options: InnerRuntimeOptions {
                 timeout: options.timeout,
+                time_budget: options.time_budget,
                 default_entrypoint: options.default_entrypoint,
+                strict_undefined: options.strict_undefined,
+                strict_top_level_await: options.strict_top_level_await,
+                track_promises: options.track_promises,
+                crash_dump_path: options.crash_dump_path,
+                max_heap_size: options.max_heap_size,
+                disable_wasm: options.disable_wasm,
+                deterministic: options.deterministic,
+                max_timers: options.max_timers,
+                quota: options.quota,
                 ..Default::default()
             },
-        })
+            reload_count: 0,
+            stats,
+            loader,
+            quota_error: None,
+            interrupted,
+            watchdog: None,
+            watchdog_fired,
+            watchdog_limit: None,
+
+            #[cfg(feature = "inspector")]
+            inspector_address,
+        };
+
+        for (name, value) in &options.globals {
+            runtime.set_global(name, value)?;
+        }
+
+        if let Some(max_timers) = runtime.options.max_timers {
+            let hooks = runtime.timer_hooks()?;
+            runtime.call_function_by_ref_async::<serde_json::Value>(
+                None,
+                hooks.set_timer_limit,
+                &[max_timers.into()],
+            )?;
+        }
+
+        if let Some(deterministic) = &runtime.options.deterministic {
+            let args = [
+                deterministic.seed32().into(),
+                deterministic.start_ms().into(),
+            ];
+            let hooks = runtime.timer_hooks()?;
+            runtime.call_function_by_ref_async::<serde_json::Value>(
+                None,
+                hooks.enable_deterministic,
+                &args,
+            )?;
+        }
+
+        Ok(runtime)
     }
 
     /// Access the underlying deno runtime instance directly
@@ -120,6 +507,12 @@ impl InnerRuntime {
         &mut self.deno_runtime
     }
 
+    /// The address the inspector server is listening on, if it was started
+    #[cfg(feature = "inspector")]
+    pub fn inspector_address(&self) -> Option<std::net::SocketAddr> {
+        self.inspector_address
+    }
+
     /// Remove and return a value from the state
     pub fn take<T>(&mut self) -> Option<T>
     where
@@ -148,6 +541,369 @@ impl InnerRuntime {
         Ok(())
     }
 
+    /// The table of rust objects handed to JS as resource handles
+    /// Created on first use, and shared by every clone returned
+    pub fn handles(&mut self) -> HandleTable {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.borrow_mut();
+        if !state.has::<HandleTable>() {
+            state.put(HandleTable::new());
+        }
+        state.borrow::<HandleTable>().clone()
+    }
+
+    /// The application state shared with stateful functions
+    /// Created on first use, and shared by every clone returned
+    pub fn state(&mut self) -> StateCell {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.borrow_mut();
+        if !state.has::<StateCell>() {
+            state.put(StateCell::new());
+        }
+        state.borrow::<StateCell>().clone()
+    }
+
+    /// Remove and return all messages sent from JS using `rustyscript.postMessage`
+    pub fn poll_messages(&mut self) -> Vec<serde_json::Value> {
+        self.take::<MessageQueue>()
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// Remove and return the oldest message sent from JS using `rustyscript.postMessage`
+    pub fn next_message(&mut self) -> Option<serde_json::Value> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut().ok()?;
+        state.try_borrow_mut::<MessageQueue>()?.pop_front()
+    }
+
+    /// Start counting the runtime's time budget from zero again
+    pub fn reset_budget(&mut self) {
+        self.stats.reset_budget();
+    }
+
+    /// Register the runtime to receive low-memory notifications from `memory_pressure::notify`
+    #[cfg(feature = "memory_pressure")]
+    pub fn listen_for_memory_pressure(&mut self) {
+        let handle = self.deno_runtime.v8_isolate().thread_safe_handle();
+        crate::memory_pressure::register(handle);
+    }
+
+    /// List the timers created with `setTimeout` or `setInterval` that have not yet fired
+    pub fn pending_timers(&mut self) -> Result<Vec<PendingTimer>, Error> {
+        let hooks = self.timer_hooks()?;
+        let timers: Vec<RawPendingTimer> =
+            self.call_function_by_ref_async(None, hooks.pending, &[])?;
+        Ok(timers.into_iter().map(PendingTimer::from).collect())
+    }
+
+    /// Run a pending timer's callback now, instead of when it is due
+    /// Returns false if there is no pending timer with that id
+    pub fn fire_timer(&mut self, id: u32) -> Result<bool, Error> {
+        let hooks = self.timer_hooks()?;
+        self.call_function_by_ref_async(None, hooks.fire, &[id.into()])
+    }
+
+    /// Move the virtual clock of a deterministic runtime forward, firing timers that become due
+    /// Returns the number of timer callbacks run
+    pub fn advance_time(&mut self, duration: Duration) -> Result<usize, Error> {
+        if self.options.deterministic.is_none() {
+            return Err(Error::Runtime(
+                "advance_time requires the runtime to be deterministic".to_string(),
+            ));
+        }
+
+        self.run_timers_ahead(duration)
+    }
+
+    /// Set the virtual clock of a deterministic runtime, firing timers due up to that time
+    /// Returns the number of timer callbacks run
+    pub fn set_time(&mut self, time: SystemTime) -> Result<usize, Error> {
+        if self.options.deterministic.is_none() {
+            return Err(Error::Runtime(
+                "set_time requires the runtime to be deterministic".to_string(),
+            ));
+        }
+
+        let hooks = self.timer_hooks()?;
+        let ms = crate::ext::timers::epoch_ms(time);
+        let until: f64 = self.call_function_by_ref_async(None, hooks.set_time, &[ms.into()])?;
+        self.run_timers_to(until)
+    }
+
+    /// Fire all timers that are due, without waiting for the event loop
+    /// Returns the number of timer callbacks run
+    pub fn tick_timers(&mut self) -> Result<usize, Error> {
+        self.run_timers_ahead(Duration::ZERO)
+    }
+
+    /// Fire all timers due before `until`, in the order they are due, without waiting for them
+    /// In a deterministic runtime, the virtual clock is moved forward to `until`
+    /// Returns the number of timer callbacks run
+    pub fn run_timers_until(&mut self, until: Instant) -> Result<usize, Error> {
+        self.run_timers_ahead(until.saturating_duration_since(Instant::now()))
+    }
+
+    /// Fire all timers due within `duration` from now
+    fn run_timers_ahead(&mut self, duration: Duration) -> Result<usize, Error> {
+        let hooks = self.timer_hooks()?;
+        let ms = duration.as_secs_f64() * 1000.0;
+        let until: f64 = self.call_function_by_ref_async(None, hooks.advance_time, &[ms.into()])?;
+        self.run_timers_to(until)
+    }
+
+    /// Fire all timers due at or before `until`, in JS milliseconds since the epoch
+    fn run_timers_to(&mut self, until: f64) -> Result<usize, Error> {
+        let hooks = self.timer_hooks()?;
+
+        // One timer per call, so that promises resolved by each callback settle before the next
+        let mut fired = 0;
+        while self.call_function_by_ref_async(None, hooks.run_next.clone(), &[until.into()])? {
+            fired += 1;
+        }
+        Ok(fired)
+    }
+
+    /// Get the JS functions used to list and fire timers
+    fn timer_hooks(&mut self) -> Result<TimerHooks, Error> {
+        let state = self.deno_runtime.op_state();
+        let state = state.borrow();
+        match state.try_borrow::<TimerHooks>() {
+            Some(hooks) => Ok(hooks.clone()),
+            None => Err(Error::Runtime(
+                "Timers are provided by deno_web with the `web` feature, and cannot be inspected or controlled".to_string(),
+            )),
+        }
+    }
+
+    /// Run the event loop until there are no more pending ops or promises, or until `timeout`
+    pub fn await_event_loop(&mut self, timeout: Duration) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = Self::run_async_task(
+            async {
+                self.deno_runtime
+                    .run_event_loop(PollEventLoopOptions::default())
+                    .await?;
+                Ok::<(), Error>(())
+            },
+            timeout,
+        );
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
+    /// Run the event loop until there are no more pending ops or promises,
+    /// on the async runtime of the caller, and without a timeout
+    pub async fn run_event_loop(&mut self, options: PollOptions) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = self
+            .deno_runtime
+            .run_event_loop(options.into())
+            .await
+            .map_err(Error::from);
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
+    /// Poll the event loop once, running whatever timers, ops and promises are ready
+    /// Returns true if there is no more pending work
+    pub async fn poll_once(&mut self) -> Result<bool, Error> {
+        let start = Instant::now();
+        let poll = std::future::poll_fn(|cx| {
+            std::task::Poll::Ready(
+                self.deno_runtime
+                    .poll_event_loop(cx, PollEventLoopOptions::default()),
+            )
+        })
+        .await;
+        let result = match poll {
+            std::task::Poll::Ready(result) => result.map(|()| true).map_err(Error::from),
+            std::task::Poll::Pending => Ok(false),
+        };
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
+    /// Record what the runtime is about to do, for crash reports
+    /// Does nothing unless `crash_dump_path` was set
+    fn record_activity<F>(&mut self, activity: F)
+    where
+        F: FnOnce(&mut Self) -> String,
+    {
+        if self.options.crash_dump_path.is_some() {
+            let activity = activity(self);
+            let stats = self.stats();
+            crash_dump::set_context(self.deno_runtime.v8_isolate(), activity, stats);
+        }
+    }
+
+    /// Record time spent running JS, and count it against the quota if there is one
+    fn record_usage(&mut self, elapsed: Duration) {
+        // The call has finished, so its time limit no longer needs enforcing
+        self.disarm_watchdog();
+        self.stats.add_eval_time(elapsed);
+
+        if let Some(quota) = &self.options.quota {
+            let usage = QuotaUsage {
+                run_time: elapsed,
+                invocations: 1,
+                ..Default::default()
+            };
+
+            // Reported by the next call, since this one has already run
+            if let Err(e) = quota.record(&usage) {
+                self.quota_error = Some(e);
+            }
+        }
+    }
+
+    /// Attach the value thrown by javascript to an error, if one was recorded
+    /// The recorded value is cleared either way, so it cannot be attached to a later error
+    fn attach_exception<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        let value = self
+            .deno_runtime
+            .op_state()
+            .borrow_mut()
+            .try_take::<LastException>();
+
+        // A call that finished before its time ran out keeps its result
+        let limit = self.watchdog_limit.clone();
+        if self.take_watchdog_fired() && result.is_err() {
+            let limit = limit.unwrap_or_else(|| "time limit exceeded".to_string());
+            return Err(Error::Timeout(limit));
+        }
+
+        // A call that finished before the interrupt arrived keeps its result
+        if self.take_interrupt() && result.is_err() {
+            return Err(Error::Cancelled("script execution".to_string()));
+        }
+
+        match (result, value) {
+            (Err(Error::JsError(mut e)), Some(LastException(value))) if e.value.is_none() => {
+                e.value = Some(value);
+                Err(Error::JsError(e))
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Record bytes sent over the network against the quota, if there is one
+    pub fn record_egress(&mut self, bytes: u64) -> Result<(), Error> {
+        match &self.options.quota {
+            Some(quota) => quota.record(&QuotaUsage {
+                egress_bytes: bytes,
+                ..Default::default()
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the timeout to use for the next call
+    /// This is the smallest of the per-call timeout, what remains of the time budget,
+    /// and what remains of the quota's run time
+    ///
+    /// The watchdog is armed to stop the call once that time runs out
+    pub(crate) fn call_timeout(&mut self) -> Result<Duration, Error> {
+        // An interrupt that arrived while nothing was running must not stop this call
+        self.disarm_watchdog();
+        self.take_watchdog_fired();
+        self.take_interrupt();
+
+        if let Some(e) = self.quota_error.take() {
+            return Err(e);
+        }
+
+        let timeout = match &self.options.quota {
+            Some(quota) => match quota.check()? {
+                Some(remaining) => remaining.min(self.options.timeout),
+                None => self.options.timeout,
+            },
+            None => self.options.timeout,
+        };
+
+        let mut limit = format!("call took longer than {}ms", timeout.as_millis());
+        let timeout = match self.options.time_budget {
+            Some(budget) => {
+                let remaining = budget.saturating_sub(self.stats.budget_used());
+                let exhausted = format!("time budget of {}ms exhausted", budget.as_millis());
+                if remaining.is_zero() {
+                    return Err(Error::Timeout(exhausted));
+                }
+                if remaining < timeout {
+                    limit = exhausted;
+                }
+                remaining.min(timeout)
+            }
+            None => timeout,
+        };
+
+        // Without a limit, such as with the default timeout, there is nothing to enforce
+        if let Some(deadline) = Instant::now().checked_add(timeout) {
+            let fired = self.watchdog_fired.clone();
+            let isolate = self.deno_runtime.v8_isolate().thread_safe_handle();
+            self.watchdog
+                .get_or_insert_with(|| Watchdog::new(isolate, fired))
+                .arm(deadline);
+            self.watchdog_limit = Some(limit);
+        }
+        Ok(timeout)
+    }
+
+    /// Stop enforcing the current call's time limit
+    fn disarm_watchdog(&mut self) {
+        if self.watchdog_limit.take().is_some() {
+            if let Some(watchdog) = &self.watchdog {
+                watchdog.disarm();
+            }
+        }
+    }
+
+    /// A handle that stops the javascript this runtime is running, from any thread
+    pub fn interrupt_handle(&mut self) -> InterruptHandle {
+        let isolate = self.deno_runtime.v8_isolate().thread_safe_handle();
+        InterruptHandle::new(isolate, self.interrupted.clone())
+    }
+
+    /// Returns true if the watchdog stopped the runtime, and lets it run javascript again
+    fn take_watchdog_fired(&mut self) -> bool {
+        if !self.watchdog_fired.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+
+        self.deno_runtime.v8_isolate().cancel_terminate_execution();
+        true
+    }
+
+    /// Returns true if the runtime was interrupted, and lets it run javascript again
+    fn take_interrupt(&mut self) -> bool {
+        if !self.interrupted.swap(false, Ordering::SeqCst) {
+            return false;
+        }
+
+        self.deno_runtime.v8_isolate().cancel_terminate_execution();
+        true
+    }
+
+    /// The number of promises that have not yet settled
+    /// Returns an error unless the runtime was created with `track_promises`
+    pub fn pending_promise_count(&mut self) -> Result<usize, Error> {
+        promise_tracker::pending(self.deno_runtime.v8_isolate()).ok_or_else(|| {
+            Error::Runtime(
+                "Promises are not being counted - enable `track_promises` in the runtime options"
+                    .to_string(),
+            )
+        })
+    }
+
+    /// Get resource usage statistics for this runtime
+    pub fn stats(&mut self) -> RuntimeStats {
+        let mut heap = v8::HeapStatistics::default();
+        self.deno_runtime
+            .v8_isolate()
+            .get_heap_statistics(&mut heap);
+        self.stats.to_stats(&heap)
+    }
+
     /// Register an async rust function
     /// The function must return a Future that resolves to a serde_json::Value
     /// and accept a vec of serde_json::Value as arguments
@@ -170,6 +926,123 @@ impl InnerRuntime {
         Ok(())
     }
 
+    /// Register an async rust function that is given the runtime's application state
+    pub fn register_async_stateful_function<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+                Vec<serde_json::Value>,
+                StateCell,
+            )
+                -> Pin<Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>>
+            + 'static,
+    {
+        let state = self.state();
+        self.register_async_function(name, move |args| callback(args, state.clone()))
+    }
+
+    /// Register an async rust function that is given the context of each call
+    pub fn register_async_function_with_context<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+                Vec<serde_json::Value>,
+                CallContext,
+            )
+                -> Pin<Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>>
+            + 'static,
+    {
+        let current = self.enable_call_context(name)?;
+        self.register_async_function(name, move |args| {
+            let context = current.borrow().clone();
+            callback(args, context)
+        })
+    }
+
+    /// Register an async rust function under a namespace
+    /// It will be callable from JS as `rustyscript.namespace.name`
+    pub fn register_async_function_ns<F>(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        self.register_async_function(&format!("{namespace}.{name}"), callback)
+    }
+
+    /// Register an async rust function, along with metadata describing it
+    pub fn register_async_function_with_info<F>(
+        &mut self,
+        name: &str,
+        info: FunctionInfo,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        self.register_async_function(name, callback)?;
+        self.set_function_info(name, info)
+    }
+
+    /// Register a rust function that is given the runtime's application state
+    pub fn register_stateful_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: Fn(&FunctionArguments, &StateCell) -> Result<serde_json::Value, Error> + 'static,
+    {
+        let state = self.state();
+        self.register_function(name, move |args| callback(args, &state))
+    }
+
+    /// Register a rust function that is given the context of each call
+    pub fn register_function_with_context<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&FunctionArguments, &CallContext) -> Result<serde_json::Value, Error> + 'static,
+    {
+        let current = self.enable_call_context(name)?;
+        self.register_function(name, move |args| {
+            let context = current.borrow().clone();
+            callback(args, &context)
+        })
+    }
+
+    /// Record calls to a function in the call context, returning the context of the current call
+    fn enable_call_context(&mut self, name: &str) -> Result<Rc<RefCell<CallContext>>, Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        if !state.has::<CallContextState>() {
+            state.put(CallContextState::default());
+        }
+
+        let contexts = state.borrow_mut::<CallContextState>();
+        contexts.functions.insert(name.to_string());
+        Ok(contexts.current.clone())
+    }
+
+    /// Set the request ID passed to functions registered with a call context
+    pub fn set_request_id(&mut self, request_id: Option<String>) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+        if !state.has::<CallContextState>() {
+            state.put(CallContextState::default());
+        }
+
+        state.borrow_mut::<CallContextState>().request_id = request_id;
+        Ok(())
+    }
+
     /// Register a rust function
     /// The function must return a serde_json::Value
     /// and accept a slice of serde_json::Value as arguments
@@ -192,13 +1065,176 @@ impl InnerRuntime {
         Ok(())
     }
 
-    /// Get a value from a runtime instance
-    ///
-    /// # Arguments
-    /// * `module_context` - A module handle to use for context, to find exports
-    /// * `name` - A string representing the name of the value to find
-    ///
-    /// # Returns
+    /// Register a rust function under a namespace
+    /// It will be callable from JS as `rustyscript.namespace.name`
+    pub fn register_function_ns<F>(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        self.register_function(&format!("{namespace}.{name}"), callback)
+    }
+
+    /// Register a rust function, along with metadata describing it
+    pub fn register_function_with_info<F>(
+        &mut self,
+        name: &str,
+        info: FunctionInfo,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        self.register_function(name, callback)?;
+        self.set_function_info(name, info)
+    }
+
+    /// Replace a registered rust function
+    /// Returns an error if no function is registered under that name
+    pub fn replace_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        match state
+            .try_borrow_mut::<HashMap<String, Box<dyn RsFunction>>>()
+            .and_then(|table| table.get_mut(name))
+        {
+            Some(entry) => {
+                *entry = Box::new(callback);
+                Ok(())
+            }
+            None => Err(Error::ValueNotFound(name.to_string())),
+        }
+    }
+
+    /// Replace a registered async rust function
+    /// Returns an error if no async function is registered under that name
+    pub fn replace_async_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        match state
+            .try_borrow_mut::<HashMap<String, Box<dyn RsAsyncFunction>>>()
+            .and_then(|table| table.get_mut(name))
+        {
+            Some(entry) => {
+                *entry = Box::new(callback);
+                Ok(())
+            }
+            None => Err(Error::ValueNotFound(name.to_string())),
+        }
+    }
+
+    /// Remove a registered rust function, sync or async, along with its metadata
+    /// Returns true if a function was removed
+    pub fn unregister_function(&mut self, name: &str) -> Result<bool, Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        let sync_removed = state
+            .try_borrow_mut::<HashMap<String, Box<dyn RsFunction>>>()
+            .and_then(|table| table.remove(name))
+            .is_some();
+        let async_removed = state
+            .try_borrow_mut::<HashMap<String, Box<dyn RsAsyncFunction>>>()
+            .and_then(|table| table.remove(name))
+            .is_some();
+        if let Some(infos) = state.try_borrow_mut::<HashMap<String, FunctionInfo>>() {
+            infos.remove(name);
+        }
+        if let Some(schemas) = state.try_borrow_mut::<HashMap<String, crate::ArgSchema>>() {
+            schemas.remove(name);
+        }
+
+        Ok(sync_removed || async_removed)
+    }
+
+    /// Check the arguments of calls from JS to a registered function against a schema
+    /// Returns an error if no function is registered under that name
+    pub fn set_function_schema(
+        &mut self,
+        name: &str,
+        schema: crate::ArgSchema,
+    ) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        let sync_match = state
+            .try_borrow::<HashMap<String, Box<dyn RsFunction>>>()
+            .is_some_and(|table| table.contains_key(name));
+        let async_match = state
+            .try_borrow::<HashMap<String, Box<dyn RsAsyncFunction>>>()
+            .is_some_and(|table| table.contains_key(name));
+        if !sync_match && !async_match {
+            return Err(Error::ValueNotFound(name.to_string()));
+        }
+
+        if !state.has::<HashMap<String, crate::ArgSchema>>() {
+            state.put(HashMap::<String, crate::ArgSchema>::new());
+        }
+        state
+            .borrow_mut::<HashMap<String, crate::ArgSchema>>()
+            .insert(name.to_string(), schema);
+        Ok(())
+    }
+
+    /// Enable or disable the functions registered under a namespace
+    pub fn set_namespace_enabled(&mut self, namespace: &str, enabled: bool) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<DisabledNamespaces>() {
+            state.put(DisabledNamespaces::default());
+        }
+        let disabled = &mut state.borrow_mut::<DisabledNamespaces>().0;
+        if enabled {
+            disabled.remove(namespace);
+        } else {
+            disabled.insert(namespace.to_string());
+        }
+        Ok(())
+    }
+
+    /// List the rust functions registered with the runtime, sorted by name
+    pub fn registered_functions(&mut self) -> Result<Vec<RegisteredFunction>, Error> {
+        let state = self.deno_runtime().op_state();
+        let state = state.try_borrow()?;
+        Ok(ext::rustyscript::registered_functions(&state))
+    }
+
+    /// Store metadata for a registered function
+    fn set_function_info(&mut self, name: &str, info: FunctionInfo) -> Result<(), Error> {
+        let state = self.deno_runtime().op_state();
+        let mut state = state.try_borrow_mut()?;
+
+        if !state.has::<HashMap<String, FunctionInfo>>() {
+            state.put(HashMap::<String, FunctionInfo>::new());
+        }
+
+        state
+            .borrow_mut::<HashMap<String, FunctionInfo>>()
+            .insert(name.to_string(), info);
+
+        Ok(())
+    }
+
+    /// Get a value from a runtime instance
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
     /// A `Result` containing the deserialized result or an error (`Error`) if the
     /// value cannot be found, if there are issues with, or if the result cannot be
     /// deserialized.
@@ -216,6 +1252,150 @@ impl InnerRuntime {
         Ok(deno_core::serde_v8::from_v8(&mut scope, value)?)
     }
 
+    /// Get a value from a runtime instance, keeping track of `undefined`, `null`,
+    /// and values that do not exist
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the `JsValue` envelope, or an error (`Error`) if the
+    /// result cannot be deserialized.
+    pub fn get_js_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<JsValue<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = match self.find_value_ref(module_context, name)? {
+            Some(value) => self.resolve_value_async(value)?,
+            None => return Ok(JsValue::Missing),
+        };
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let value = v8::Local::<v8::Value>::new(&mut scope, value);
+        JsValue::from_v8(&mut scope, value)
+    }
+
+    /// Get a value from a runtime instance by following a dot-separated property path
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `path` - A path to the value, such as `config.limits.maxUsers`
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if a
+    /// segment of the path cannot be found, or if the result cannot be deserialized.
+    pub fn get_path<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        path: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let value = self.get_path_ref(module_context, path)?;
+        let value = self.resolve_value_async(value)?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let value = v8::Local::<v8::Value>::new(&mut scope, value);
+        Ok(deno_core::serde_v8::from_v8(&mut scope, value)?)
+    }
+
+    /// Set a value in a runtime instance by following a dot-separated property path
+    /// A path with a single segment will set a value in the global context
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `path` - A path to the value, such as `config.limits.maxUsers`
+    /// * `value` - The value to set
+    ///
+    /// # Returns
+    /// A `Result` containing an error (`Error`) if a segment of the path cannot
+    /// be found, or if the value cannot be set.
+    pub fn set_path<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        path: &str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        let (parent, key) = match path.rsplit_once('.') {
+            Some((parent, key)) => (Some(self.get_path_ref(module_context, parent)?), key),
+            None => (None, path),
+        };
+
+        let context = self.deno_runtime.main_context();
+        let mut scope = self.deno_runtime.handle_scope();
+        let object = match parent {
+            Some(parent) => {
+                let parent = v8::Local::new(&mut scope, parent);
+                v8::Local::<v8::Object>::try_from(parent)
+                    .map_err(|_| Error::PathNotFound(key.to_string(), path.to_string()))?
+            }
+            None => context.open(&mut scope).global(&mut scope),
+        };
+
+        let key = key.to_v8_string(&mut scope)?;
+        let value = deno_core::serde_v8::to_v8(&mut scope, value)?;
+        match object.set(&mut scope, key.into(), value) {
+            Some(true) => Ok(()),
+            _ => Err(Error::Runtime(format!("{path} could not be set"))),
+        }
+    }
+
+    /// Set a property of `globalThis`
+    /// Unlike `set_path`, the name is used as-is, even if it contains dots
+    pub fn set_global<T>(&mut self, name: &str, value: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        let context = self.deno_runtime.main_context();
+        let mut scope = self.deno_runtime.handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+
+        let key = name.to_v8_string(&mut scope)?;
+        let value = deno_core::serde_v8::to_v8(&mut scope, value)?;
+        match global.set(&mut scope, key.into(), value) {
+            Some(true) => Ok(()),
+            _ => Err(Error::Runtime(format!("global {name} could not be set"))),
+        }
+    }
+
+    /// Follow a dot-separated property path, starting from module exports or the global context
+    fn get_path_ref(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        path: &str,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let mut segments = path.split('.');
+        let root = segments.next().unwrap_or_default();
+        let root = self
+            .get_value_ref_sync(module_context, root)
+            .map_err(|_| Error::PathNotFound(root.to_string(), path.to_string()))?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let mut value = v8::Local::new(&mut scope, root);
+        for segment in segments {
+            let object = v8::Local::<v8::Object>::try_from(value)
+                .map_err(|_| Error::PathNotFound(segment.to_string(), path.to_string()))?;
+
+            let key = segment.to_v8_string(&mut scope)?;
+            value = match object.has(&mut scope, key.into()) {
+                Some(true) => object.get(&mut scope, key.into()),
+                _ => None,
+            }
+            .ok_or_else(|| Error::PathNotFound(segment.to_string(), path.to_string()))?;
+        }
+
+        Ok(v8::Global::new(&mut scope, value))
+    }
+
     /// Evaluate a piece of non-ECMAScript-module JavaScript code
     /// The expression is evaluated in the global context, so changes persist
     ///
@@ -230,13 +1410,143 @@ impl InnerRuntime {
     where
         T: serde::de::DeserializeOwned,
     {
-        let result = self.deno_runtime().execute_script("", expr.to_string())?;
+        self.call_timeout()?;
+        enter_span!("rustyscript.eval", length = expr.len());
+        self.record_activity(|_| "evaluating an expression".to_string());
+        let start = Instant::now();
+        let result = self.deno_runtime().execute_script("", expr.to_string());
+        self.record_usage(start.elapsed());
+        let result = self.attach_exception(result.map_err(Error::from))?;
 
         let mut scope = self.deno_runtime.handle_scope();
         let result = v8::Local::new(&mut scope, result);
+        if self.options.strict_undefined && result.is_undefined() {
+            return Err(Error::UnexpectedUndefined("expression".to_string()));
+        }
         Ok(deno_core::serde_v8::from_v8(&mut scope, result)?)
     }
 
+    /// Create a new realm - a context in the runtime's isolate, with its own globals
+    pub fn create_realm(&mut self) -> Result<RealmHandle, Error> {
+        let mut scope = self.deno_runtime.handle_scope();
+        let context = v8::Context::new(&mut scope);
+        Ok(RealmHandle::new(v8::Global::new(&mut scope, context)))
+    }
+
+    /// Evaluate a piece of non-ECMAScript-module JavaScript code in a realm
+    pub fn eval_in_realm<T>(&mut self, realm: &RealmHandle, expr: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        enter_span!("rustyscript.eval_in_realm", length = expr.len());
+        self.run_in_realm(realm, "expression", |scope| {
+            let source = expr.to_v8_string(scope)?;
+            let script = v8::Script::compile(scope, source, None);
+            Ok(script.and_then(|script| script.run(scope)))
+        })
+    }
+
+    /// Call a function on a realm's global object
+    pub fn call_function_in_realm<T>(
+        &mut self,
+        realm: &RealmHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        enter_span!("rustyscript.call_function_in_realm", function = name);
+        self.run_in_realm(realm, name, |scope| {
+            let global = scope.get_current_context().global(scope);
+            let key = name.to_v8_string(scope)?;
+            let function = global
+                .get(scope, key.into())
+                .and_then(|value| v8::Local::<v8::Function>::try_from(value).ok())
+                .ok_or_else(|| Error::ValueNotCallable(name.to_string()))?;
+
+            let args = args
+                .iter()
+                .map(|arg| deno_core::serde_v8::to_v8(scope, arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            let receiver = v8::undefined(scope).into();
+            Ok(function.call(scope, receiver, &args))
+        })
+    }
+
+    /// Run `f` inside a realm, and deserialize the value it returns
+    /// Promises are resolved by running the microtask queue, since realms have no event loop
+    fn run_in_realm<T>(
+        &mut self,
+        realm: &RealmHandle,
+        name: &str,
+        f: impl for<'s> FnOnce(
+            &mut v8::HandleScope<'s>,
+        ) -> Result<Option<v8::Local<'s, v8::Value>>, Error>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.call_timeout()?;
+        self.record_activity(|_| format!("running {name} in a realm"));
+        let start = Instant::now();
+
+        let strict_undefined = self.options.strict_undefined;
+        let result = Self::realm_value(&mut self.deno_runtime, realm, name, strict_undefined, f);
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
+    fn realm_value<T>(
+        deno_runtime: &mut JsRuntime,
+        realm: &RealmHandle,
+        name: &str,
+        strict_undefined: bool,
+        f: impl for<'s> FnOnce(
+            &mut v8::HandleScope<'s>,
+        ) -> Result<Option<v8::Local<'s, v8::Value>>, Error>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut scope = deno_runtime.handle_scope();
+        let context = v8::Local::new(&mut scope, realm.context());
+        let mut scope = v8::ContextScope::new(&mut scope, context);
+        let mut scope = v8::TryCatch::new(&mut scope);
+
+        let mut value = f(&mut scope)?;
+        if let Some(promise) = value.and_then(|v| v8::Local::<v8::Promise>::try_from(v).ok()) {
+            scope.perform_microtask_checkpoint();
+            match promise.state() {
+                v8::PromiseState::Fulfilled => value = Some(promise.result(&mut scope)),
+                v8::PromiseState::Rejected => {
+                    let exception = promise.result(&mut scope);
+                    let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+                    return Err(e.into());
+                }
+                v8::PromiseState::Pending => {
+                    return Err(Error::Runtime(format!(
+                        "{name} returned a promise that did not settle - realms have no event loop"
+                    )));
+                }
+            }
+        }
+
+        match value {
+            Some(value) if strict_undefined && value.is_undefined() => {
+                Err(Error::UnexpectedUndefined(name.to_string()))
+            }
+            Some(value) => Ok(deno_core::serde_v8::from_v8(&mut scope, value)?),
+            None => {
+                let exception = scope
+                    .exception()
+                    .unwrap_or_else(|| v8::undefined(&mut scope).into());
+                let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+                Err(e.into())
+            }
+        }
+    }
+
     /// Calls a stored javascript function and deserializes its return value.
     ///
     /// # Arguments
@@ -260,27 +1570,368 @@ impl InnerRuntime {
         self.call_function_by_ref_async(module_context, function, args)
     }
 
-    /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
+    /// Calls a javascript function within the Deno runtime by its name and deserializes its return value.
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the javascript function to call.
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub fn call_function<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let function = self.get_function_by_name(module_context, name)?;
+        self.call_function_by_ref_async(module_context, function, args)
+    }
+
+    /// Like `call_function`, but returns a future instead of blocking on the result
+    /// The future drives the event loop until the function's result is resolved,
+    /// and must be run inside a tokio runtime - see `Runtime::run_async`
+    pub async fn call_function_async<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let function = self.get_function_by_name(module_context, name)?;
+        let start = Instant::now();
+        let result = async {
+            let result = self.call_function_by_ref_sync(module_context, function, args)?;
+            let future = self.deno_runtime.resolve(result);
+            let result = self
+                .deno_runtime
+                .with_event_loop_future(future, Default::default())
+                .await?;
+
+            let mut scope = self.deno_runtime.handle_scope();
+            let result = v8::Local::new(&mut scope, result);
+            if self.options.strict_undefined && result.is_undefined() {
+                return Err(Error::UnexpectedUndefined("function result".to_string()));
+            }
+            Ok::<T, Error>(deno_core::serde_v8::from_v8(&mut scope, result)?)
+        }
+        .await;
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
+    /// Calls a javascript function by its name, without waiting for its result
+    /// Returns the value it returned, which may be a promise - see `await_promise`
+    pub fn call_function_promise(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        self.call_timeout()?;
+        let function = self.get_function_by_name(module_context, name)?;
+        let start = Instant::now();
+        let result = self.call_function_by_ref_sync(module_context, function, args);
+        self.record_usage(start.elapsed());
+        result
+    }
+
+    /// Run the event loop until a value returned by `call_function_promise` is resolved,
+    /// and deserialize it
+    /// `timeout`, if given, applies instead of the runtime's timeout when it is shorter
+    pub fn await_promise<T>(
+        &mut self,
+        value: v8::Global<v8::Value>,
+        timeout: Option<Duration>,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let call_timeout = self.call_timeout()?;
+        let timeout = timeout.map_or(call_timeout, |timeout| timeout.min(call_timeout));
+        let result = self.resolve_value_with_timeout(value, timeout)?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let result = v8::Local::new(&mut scope, result);
+        if self.options.strict_undefined && result.is_undefined() {
+            return Err(Error::UnexpectedUndefined("function result".to_string()));
+        }
+        Ok(deno_core::serde_v8::from_v8(&mut scope, result)?)
+    }
+
+    /// Returns true if a value is not a promise, or is a promise that has settled
+    pub fn is_promise_settled(&mut self, value: &v8::Global<v8::Value>) -> bool {
+        let mut scope = self.deno_runtime.handle_scope();
+        let value = v8::Local::new(&mut scope, value);
+        match v8::Local::<v8::Promise>::try_from(value) {
+            Ok(promise) => promise.state() != v8::PromiseState::Pending,
+            Err(_) => true,
+        }
+    }
+
+    /// Calls the default export of a module, which must be a function
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle to a loaded module
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the module has no default export, if it is not a function,
+    /// if there are issues with calling the function, or if the result cannot be deserialized.
+    pub fn call_default_export<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let value = self.get_module_export_value(module_context, "default")?;
+
+        let function = {
+            let mut scope = self.deno_runtime.handle_scope();
+            let local_value = v8::Local::<v8::Value>::new(&mut scope, value);
+            let f: v8::Local<v8::Function> = local_value
+                .try_into()
+                .or::<Error>(Err(Error::ValueNotCallable("default".to_string())))?;
+            v8::Global::<v8::Function>::new(&mut scope, f)
+        };
+
+        self.call_function_by_ref_async(Some(module_context), function, args)
+    }
+
+    /// Calls a javascript function by its name, passing binary data as `Uint8Array`s
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The JSON arguments to pass to the function
+    /// * `bytes` - Binary arguments, passed after `args`
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub fn call_function_with_bytes<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+        bytes: &[&[u8]],
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let function = self.get_function_by_name(module_context, name)?;
+        self.call_function_by_ref_async_with_bytes(module_context, function, args, bytes)
+    }
+
+    /// Calls a javascript function by its name, keeping track of `undefined`, `null`,
+    /// and functions that do not exist
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the javascript function to call.
+    ///
+    /// # Returns
+    /// A `Result` containing the `JsValue` envelope of the function's result, or an
+    /// error (`Error`) if the value is not a function, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub fn call_function_js_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<JsValue<T>, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        if self.find_value_ref(module_context, name)?.is_none() {
+            return Ok(JsValue::Missing);
+        }
+
+        let function = self.get_function_by_name(module_context, name)?;
+        let result = self.call_function_by_ref_sync(module_context, function, args)?;
+        let result = self.resolve_value_async(result)?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let result = v8::Local::<v8::Value>::new(&mut scope, result);
+        JsValue::from_v8(&mut scope, result)
+    }
+
+    /// Calls a javascript function by its name, and returns its result without deserializing it
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the javascript function to call.
+    ///
+    /// # Returns
+    /// A `Result` containing a `V8Value` that borrows the runtime, or an
+    /// error (`Error`) if the function cannot be found, or if there are issues with
+    /// calling the function.
+    pub fn call_function_raw(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<V8Value<'_>, Error> {
+        let function = self.get_function_by_name(module_context, name)?;
+        let result = self.call_function_by_ref_sync(module_context, function, args)?;
+        let result = self.resolve_value_async(result)?;
+        Ok(V8Value::new(&mut self.deno_runtime, result))
+    }
+
+    /// Calls a javascript function by its name, passing arguments and returning its result
+    /// with the structured clone algorithm instead of JSON
+    ///
+    /// # Arguments
+    /// * `module_context` - A module handle to use for context, to find exports
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing a clone of the function's result, or an error (`Error`) if the
+    /// function cannot be found, if there are issues with calling the function, or if the
+    /// result cannot be cloned.
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_structured(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        let function = self.get_function_by_name(module_context, name)?;
+        self.call_function_by_ref_structured(module_context, function, args)
+    }
+
+    /// Like `call_function_structured`, but the function returns `[value, transfer]`,
+    /// as with `postMessage` - the buffers in `transfer` are moved out instead of copied
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_structured_transfer(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        let function = self.get_function_by_name(module_context, name)?;
+        self.call_function_by_ref_structured_with(module_context, function, args, true)
+    }
+
+    /// Calls a javascript function, passing arguments and returning its result
+    /// with the structured clone algorithm instead of JSON
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_by_ref_structured(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: v8::Global<v8::Function>,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        self.call_function_by_ref_structured_with(module_context, function, args, false)
+    }
+
+    /// Calls a javascript function with the structured clone algorithm
+    /// If `transfer` is set, the function returns `[value, transfer]`
+    #[cfg(feature = "structured_clone")]
+    fn call_function_by_ref_structured_with(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: v8::Global<v8::Function>,
+        args: Vec<crate::StructuredValue>,
+        transfer: bool,
+    ) -> Result<crate::StructuredValue, Error> {
+        let extra = {
+            let mut scope = self.deno_runtime.handle_scope();
+            args.into_iter()
+                .map(|arg| {
+                    let value = arg.into_v8(&mut scope)?;
+                    Ok(v8::Global::new(&mut scope, value))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+
+        enter_span!("rustyscript.call_function", function = %self.function_name(&function));
+        let result =
+            self.call_function_by_ref_sync_with_extra(module_context, function, &[], &extra);
+        let result = self.attach_exception(result)?;
+        let result = self.resolve_value_async(result)?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let result = v8::Local::new(&mut scope, result);
+        let (result, transfer) = if transfer {
+            Self::split_transfer(&mut scope, result)?
+        } else {
+            (result, Vec::new())
+        };
+
+        if self.options.strict_undefined && result.is_undefined() {
+            return Err(Error::UnexpectedUndefined("function result".to_string()));
+        }
+        crate::StructuredValue::from_v8(&mut scope, result, transfer)
+    }
+
+    /// Split a `[value, transfer]` pair, where `transfer` is an array of `ArrayBuffer`s
+    #[cfg(feature = "structured_clone")]
+    fn split_transfer<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        pair: v8::Local<'s, v8::Value>,
+    ) -> Result<
+        (
+            v8::Local<'s, v8::Value>,
+            Vec<v8::Local<'s, v8::ArrayBuffer>>,
+        ),
+        Error,
+    > {
+        let invalid = || {
+            Error::DataClone("the function must return a [value, transfer list] pair".to_string())
+        };
+
+        let pair = v8::Local::<v8::Array>::try_from(pair).map_err(|_| invalid())?;
+        let value = pair.get_index(scope, 0).ok_or_else(invalid)?;
+        let list = pair.get_index(scope, 1).ok_or_else(invalid)?;
+        let list = v8::Local::<v8::Array>::try_from(list).map_err(|_| invalid())?;
+
+        let mut transfer = Vec::with_capacity(list.length() as usize);
+        for i in 0..list.length() {
+            let buffer = list.get_index(scope, i).ok_or_else(invalid)?;
+            let buffer = v8::Local::<v8::ArrayBuffer>::try_from(buffer).map_err(|_| {
+                Error::DataClone("only ArrayBuffers can be transferred".to_string())
+            })?;
+            transfer.push(buffer);
+        }
+        Ok((value, transfer))
+    }
+
+    /// Calls a javascript function by its name, and feeds its result to a serde visitor
+    /// without building an intermediate value
     ///
     /// # Arguments
     /// * `module_context` - A module handle to use for context, to find exports
     /// * `name` - A string representing the name of the javascript function to call.
+    /// * `visitor` - The visitor to drive with the function's result
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the function call (`T`)
-    /// or an error (`Error`) if the function cannot be found, if there are issues with
-    /// calling the function, or if the result cannot be deserialized.
-    pub fn call_function<T>(
+    /// A `Result` containing the visitor's output, or an error (`Error`) if the function
+    /// cannot be found, if there are issues with calling the function, or if the visitor fails.
+    pub fn call_function_visit<'de, V>(
         &mut self,
         module_context: Option<&ModuleHandle>,
         name: &str,
         args: &FunctionArguments,
-    ) -> Result<T, Error>
+        visitor: V,
+    ) -> Result<V::Value, Error>
     where
-        T: deno_core::serde::de::DeserializeOwned,
+        V: serde::de::Visitor<'de>,
     {
-        let function = self.get_function_by_name(module_context, name)?;
-        self.call_function_by_ref_async(module_context, function, args)
+        self.call_function_raw(module_context, name, args)?
+            .visit(visitor)
     }
 
     /// Attempt to get a value out of the global context (globalThis.name)
@@ -356,14 +2007,129 @@ impl InnerRuntime {
             .map_err(|_| Error::ValueNotFound(name.to_string()))
     }
 
+    /// Attempt to find a value in module exports, or the global context
+    /// Unlike `get_value_ref_sync`, `undefined` and `null` values are returned as-is
+    ///
+    /// # Returns
+    /// None if no value by that name exists
+    fn find_value_ref(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<Option<v8::Global<v8::Value>>, Error> {
+        let module_namespace = match module_context {
+            Some(module_context) => Some(
+                self.deno_runtime
+                    .get_module_namespace(module_context.id())?,
+            ),
+            None => None,
+        };
+
+        let context = self.deno_runtime.main_context();
+        let mut scope = self.deno_runtime.handle_scope();
+        let key: v8::Local<v8::Value> = name.to_v8_string(&mut scope)?.into();
+
+        if let Some(module_namespace) = module_namespace {
+            let module_namespace = module_namespace.open(&mut scope);
+            if module_namespace.has(&mut scope, key) == Some(true) {
+                if let Some(v) = module_namespace.get(&mut scope, key) {
+                    return Ok(Some(v8::Global::new(&mut scope, v)));
+                }
+            }
+        }
+
+        let global = context.open(&mut scope).global(&mut scope);
+        if global.has(&mut scope, key) == Some(true) {
+            if let Some(v) = global.get(&mut scope, key) {
+                return Ok(Some(v8::Global::new(&mut scope, v)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// List the values exported by a module, sorted by name
+    pub fn module_exports(&mut self, module: &ModuleHandle) -> Result<Vec<ExportInfo>, Error> {
+        let namespace = self.deno_runtime.get_module_namespace(module.id())?;
+        let mut scope = self.deno_runtime.handle_scope();
+        let namespace = v8::Local::new(&mut scope, namespace);
+
+        let names = namespace
+            .get_own_property_names(&mut scope, Default::default())
+            .ok_or_else(|| Error::Runtime("Could not list module exports".to_string()))?;
+
+        let length_key = "length".to_v8_string(&mut scope)?;
+        let mut exports = Vec::with_capacity(names.length() as usize);
+        for i in 0..names.length() {
+            let Some(key) = names.get_index(&mut scope, i) else {
+                continue;
+            };
+            let name = key.to_rust_string_lossy(&mut scope);
+
+            // Exports that were never initialized cannot be read, and are reported as values
+            let value = namespace.get(&mut scope, key);
+            let (kind, arity) = match value.map(v8::Local::<v8::Function>::try_from) {
+                Some(Ok(function)) => {
+                    let arity = function
+                        .get(&mut scope, length_key.into())
+                        .and_then(|length| length.uint32_value(&mut scope));
+                    let source = function.to_rust_string_lossy(&mut scope);
+                    let kind = if source.starts_with("class") {
+                        ExportKind::Class
+                    } else {
+                        ExportKind::Function
+                    };
+                    (kind, arity)
+                }
+                _ => (ExportKind::Value, None),
+            };
+
+            exports.push(ExportInfo { name, kind, arity });
+        }
+
+        Ok(exports)
+    }
+
+    /// Resolve a value, waiting on the event loop if it is a promise
+    fn resolve_value_async(
+        &mut self,
+        value: v8::Global<v8::Value>,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let timeout = self.call_timeout()?;
+        self.resolve_value_with_timeout(value, timeout)
+    }
+
+    /// Like `resolve_value_async`, but with a specific timeout
+    fn resolve_value_with_timeout(
+        &mut self,
+        value: v8::Global<v8::Value>,
+        timeout: Duration,
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        let start = Instant::now();
+        let result = Self::run_async_task(
+            async {
+                let future = self.deno_runtime.resolve(value);
+                let result = self
+                    .deno_runtime
+                    .with_event_loop_future(future, Default::default())
+                    .await?;
+                Ok::<v8::Global<v8::Value>, Error>(result)
+            },
+            timeout,
+        );
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
+    }
+
     pub fn get_value_ref_async(
         &mut self,
         module_context: Option<&ModuleHandle>,
         name: &str,
     ) -> Result<v8::Global<v8::Value>, Error> {
-        let timeout = self.options.timeout;
-        Self::run_async_task(
-            async move {
+        let timeout = self.call_timeout()?;
+        let start = Instant::now();
+        let result = Self::run_async_task(
+            async {
                 let result = self.get_value_ref_sync(module_context, name)?;
                 let future = self.deno_runtime.resolve(result);
                 let result = self
@@ -379,7 +2145,9 @@ impl InnerRuntime {
                 Ok::<v8::Global<v8::Value>, Error>(value)
             },
             timeout,
-        )
+        );
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
     }
 
     /// This method takes a javascript function and invokes it within the Deno runtime.
@@ -400,6 +2168,51 @@ impl InnerRuntime {
         function: v8::Global<v8::Function>,
         args: &FunctionArguments,
     ) -> Result<v8::Global<v8::Value>, Error> {
+        enter_span!("rustyscript.call_function", function = %self.function_name(&function));
+        let result = self.call_function_by_ref_sync_with_extra(module_context, function, args, &[]);
+        self.attach_exception(result)
+    }
+
+    /// Copy each of `bytes` into a new `Uint8Array`, rather than going through JSON
+    fn bytes_arguments(&mut self, bytes: &[&[u8]]) -> Result<Vec<v8::Global<v8::Value>>, Error> {
+        let mut scope = self.deno_runtime.handle_scope();
+        bytes
+            .iter()
+            .map(|bytes| {
+                let store =
+                    v8::ArrayBuffer::new_backing_store_from_vec(bytes.to_vec()).make_shared();
+                let buffer = v8::ArrayBuffer::with_backing_store(&mut scope, &store);
+                let array = v8::Uint8Array::new(&mut scope, buffer, 0, bytes.len())
+                    .ok_or(Error::V8Encoding("binary argument".to_string()))?;
+                Ok(v8::Global::new(
+                    &mut scope,
+                    v8::Local::<v8::Value>::from(array),
+                ))
+            })
+            .collect()
+    }
+
+    /// Like `call_function_by_ref_sync`, but passes each of `extra` after the other arguments
+    fn call_function_by_ref_sync_with_extra(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: v8::Global<v8::Function>,
+        args: &FunctionArguments,
+        extra: &[v8::Global<v8::Value>],
+    ) -> Result<v8::Global<v8::Value>, Error> {
+        // Only the watchdog can stop synchronous code, so make sure it is armed
+        if self.watchdog_limit.is_none() {
+            self.call_timeout()?;
+        }
+
+        self.record_activity(|runtime| {
+            let name = runtime.function_name(&function);
+            match module_context {
+                Some(module) => format!("calling {name} in {}", module.module().filename()),
+                None => format!("calling {name}"),
+            }
+        });
+
         let module_namespace = if let Some(module_context) = module_context {
             Some(
                 self.deno_runtime
@@ -431,7 +2244,8 @@ impl InnerRuntime {
             .iter()
             .map(|f| deno_core::serde_v8::to_v8(&mut scope, f))
             .collect();
-        let final_args = f_args?;
+        let mut final_args = f_args?;
+        final_args.extend(extra.iter().map(|value| v8::Local::new(&mut scope, value)));
 
         let result = function_instance.call(&mut scope, namespace, &final_args);
         match result {
@@ -440,24 +2254,11 @@ impl InnerRuntime {
                 Ok(value)
             }
             None if scope.has_caught() => {
-                let e = scope.message().unwrap();
-
-                let filename = e.get_script_resource_name(&mut scope);
-                let linenumber = e.get_line_number(&mut scope).unwrap_or_default();
-                let filename = if let Some(v) = filename {
-                    let filename = v.to_rust_string_lossy(&mut scope);
-                    format!("{filename}:{linenumber}: ")
-                } else if let Some(module_context) = module_context {
-                    let filename = module_context.module().filename().to_string();
-                    format!("{filename}:{linenumber}: ")
-                } else {
-                    "".to_string()
-                };
-
-                let msg = e.get(&mut scope).to_rust_string_lossy(&mut scope);
-
-                let s = format!("{filename}{msg}");
-                Err(Error::Runtime(s))
+                let exception = scope
+                    .exception()
+                    .unwrap_or_else(|| v8::undefined(&mut scope).into());
+                let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+                Err(e.into())
             }
             None => Err(Error::Runtime(
                 "Unknown error during function execution".to_string(),
@@ -465,6 +2266,13 @@ impl InnerRuntime {
         }
     }
 
+    /// The name of a javascript function, for crash reports and traces
+    fn function_name(&mut self, function: &v8::Global<v8::Function>) -> String {
+        let mut scope = self.deno_runtime.handle_scope();
+        let name = function.open(&mut scope).get_name(&mut scope);
+        name.to_rust_string_lossy(&mut scope)
+    }
+
     /// Retrieves a javascript function by its name from the Deno runtime's global context.
     ///
     /// # Arguments
@@ -503,10 +2311,34 @@ impl InnerRuntime {
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        let timeout = self.options.timeout;
-        Self::run_async_task(
-            async move {
-                let result = self.call_function_by_ref_sync(module_context, function, args)?;
+        self.call_function_by_ref_async_with_bytes(module_context, function, args, &[])
+    }
+
+    /// Like `call_function_by_ref_async`, but passes each of `bytes` after the other
+    /// arguments, as a `Uint8Array`
+    fn call_function_by_ref_async_with_bytes<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        function: v8::Global<v8::Function>,
+        args: &FunctionArguments,
+        bytes: &[&[u8]],
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let timeout = self.call_timeout()?;
+        enter_span!("rustyscript.call_function", function = %self.function_name(&function));
+        let strict_undefined = self.options.strict_undefined;
+        let extra = self.bytes_arguments(bytes)?;
+        let start = Instant::now();
+        let result = Self::run_async_task(
+            async {
+                let result = self.call_function_by_ref_sync_with_extra(
+                    module_context,
+                    function,
+                    args,
+                    &extra,
+                )?;
                 let future = self.deno_runtime.resolve(result);
                 let result = self
                     .deno_runtime
@@ -517,13 +2349,18 @@ impl InnerRuntime {
 
                 let mut scope = self.deno_runtime.handle_scope();
                 let result = v8::Local::new(&mut scope, result);
+                if strict_undefined && result.is_undefined() {
+                    return Err(Error::UnexpectedUndefined("function result".to_string()));
+                }
 
                 // Decode value
                 let value: T = deno_core::serde_v8::from_v8(&mut scope, result)?;
                 Ok::<T, Error>(value)
             },
             timeout,
-        )
+        );
+        self.record_usage(start.elapsed());
+        self.attach_exception(result)
     }
 
     pub fn run_async_task<T, F>(f: F, timeout: Duration) -> Result<T, Error>
@@ -541,6 +2378,86 @@ impl InnerRuntime {
         })?
     }
 
+    /// Make a module available to imports from other modules, without loading it
+    /// Imports resolving to the module's filename are served from memory instead of the filesystem
+    pub fn mount_module(&mut self, module: &Module) -> Result<(), Error> {
+        let specifier = module.filename().to_module_specifier()?;
+        self.loader.mount(&specifier, module.contents());
+        Ok(())
+    }
+
+    /// Load and evaluate a set of modules that may import each other, such as the
+    /// contents of a directory
+    ///
+    /// Every module is mounted first, so imports between them are served from memory,
+    /// and each is evaluated once, after the modules it imports
+    ///
+    /// Will return a handle to each module, in the order given
+    pub fn load_module_set(&mut self, modules: &[Module]) -> Result<Vec<ModuleHandle>, Error> {
+        let timeout = self.call_timeout()?;
+        if modules.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut specifiers = Vec::with_capacity(modules.len());
+        for module in modules {
+            let specifier = module.filename().to_module_specifier()?;
+            self.loader.mount(&specifier, module.contents());
+            specifiers.push(specifier);
+        }
+
+        // A generated module importing the whole set evaluates each module exactly once
+        self.reload_count += 1;
+        let mut entry_specifier = specifiers[0].clone();
+        entry_specifier.set_query(Some(&format!("module_set={}", self.reload_count)));
+        let entry_code = specifiers
+            .iter()
+            .map(|specifier| format!("import {};", serde_json::Value::from(specifier.as_str())))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        enter_span!(
+            "rustyscript.load_module_set",
+            modules = ?modules.iter().map(Module::filename).collect::<Vec<_>>(),
+        );
+        self.record_activity(|_| {
+            let names: Vec<_> = modules.iter().map(Module::filename).collect();
+            format!("loading {}", names.join(", "))
+        });
+        let strict_tla = self.options.strict_top_level_await;
+        let start = Instant::now();
+        let deadline = start.checked_add(timeout);
+        let deno_runtime = &mut self.deno_runtime();
+        let result = Self::run_async_task(
+            async move {
+                let entry_id = deno_runtime
+                    .load_side_es_module_from_code(&entry_specifier, entry_code)
+                    .await?;
+                Self::evaluate_module(
+                    deno_runtime,
+                    entry_id,
+                    entry_specifier.as_str(),
+                    strict_tla,
+                    deadline,
+                )
+                .await?;
+
+                // Each module is already registered and evaluated, so this only looks up its id
+                let mut handles = Vec::with_capacity(modules.len());
+                for (module, specifier) in modules.iter().zip(&specifiers) {
+                    let id = deno_runtime.load_side_es_module(specifier).await?;
+                    handles.push(ModuleHandle::new(module, id, None));
+                }
+                Ok::<_, Error>(handles)
+            },
+            timeout,
+        );
+        self.record_usage(start.elapsed());
+        let handles = self.attach_exception(result)?;
+        self.stats.add_modules(modules.len());
+        Ok(handles)
+    }
+
     /// Load one or more modules
     ///
     /// Will return a handle to the main module, or the last
@@ -550,8 +2467,20 @@ impl InnerRuntime {
         main_module: Option<&Module>,
         side_modules: Vec<&Module>,
     ) -> Result<ModuleHandle, Error> {
-        let timeout = self.options.timeout;
-        let default_entrypoint = self.options.default_entrypoint.clone();
+        self.load_modules_with_handles(main_module, side_modules)
+            .map(|result| result.main().clone())
+    }
+
+    /// Load one or more modules
+    ///
+    /// Will return handles to every module loaded, with the main module - or the last
+    /// side-module - as the main handle
+    pub fn load_modules_with_handles(
+        &mut self,
+        main_module: Option<&Module>,
+        side_modules: Vec<&Module>,
+    ) -> Result<LoadResult, Error> {
+        let timeout = self.call_timeout()?;
 
         if main_module.is_none() && side_modules.is_empty() {
             return Err(Error::Runtime(
@@ -559,10 +2488,28 @@ impl InnerRuntime {
             ));
         }
 
+        let module_count = side_modules.len() + usize::from(main_module.is_some());
+        enter_span!(
+            "rustyscript.load_modules",
+            main = ?main_module.map(Module::filename),
+            side = ?side_modules.iter().map(|m| m.filename()).collect::<Vec<_>>(),
+        );
+        self.record_activity(|_| {
+            let names: Vec<_> = main_module
+                .iter()
+                .chain(side_modules.iter())
+                .map(|m| m.filename())
+                .collect();
+            format!("loading {}", names.join(", "))
+        });
+        let strict_tla = self.options.strict_top_level_await;
+        let start = Instant::now();
+        let deadline = start.checked_add(timeout);
         let deno_runtime = &mut self.deno_runtime();
-        let module_handle_stub = Self::run_async_task(
+        let result = Self::run_async_task(
             async move {
                 let mut module_handle_stub = Default::default();
+                let mut handles = HashMap::new();
 
                 // Get additional modules first
                 for side_module in side_modules {
@@ -574,12 +2521,16 @@ impl InnerRuntime {
                     let s_modid = deno_runtime
                         .load_side_es_module_from_code(&module_specifier, code)
                         .await?;
-                    let result = deno_runtime.mod_evaluate(s_modid);
-                    deno_runtime
-                        .run_event_loop(PollEventLoopOptions::default())
-                        .await?;
-                    result.await?;
+                    Self::evaluate_module(
+                        deno_runtime,
+                        s_modid,
+                        side_module.filename(),
+                        strict_tla,
+                        deadline,
+                    )
+                    .await?;
                     module_handle_stub = ModuleHandle::new(side_module, s_modid, None);
+                    handles.insert(module_specifier.to_string(), module_handle_stub.clone());
                 }
 
                 // Load main module
@@ -593,21 +2544,220 @@ impl InnerRuntime {
                         .await?;
 
                     // Finish execution
-                    let result = deno_runtime.mod_evaluate(module_id);
-                    deno_runtime
-                        .run_event_loop(PollEventLoopOptions {
-                            wait_for_inspector: false,
-                            ..Default::default()
-                        })
-                        .await?;
-                    result.await?;
+                    Self::evaluate_module(
+                        deno_runtime,
+                        module_id,
+                        module.filename(),
+                        strict_tla,
+                        deadline,
+                    )
+                    .await?;
                     module_handle_stub = ModuleHandle::new(module, module_id, None);
+                    handles.insert(module_specifier.to_string(), module_handle_stub.clone());
                 }
 
-                Ok::<ModuleHandle, Error>(module_handle_stub)
+                Ok::<_, Error>((module_handle_stub, handles))
+            },
+            timeout,
+        );
+        self.record_usage(start.elapsed());
+        let (module_handle_stub, mut handles) = self.attach_exception(result)?;
+        self.stats.add_modules(module_count);
+
+        let main = self.with_entrypoint(module_handle_stub)?;
+        let specifier = main.module().filename().to_module_specifier()?;
+        handles.insert(specifier.to_string(), main.clone());
+        Ok(LoadResult::new(main, handles))
+    }
+
+    /// Like `load_modules`, but reports each imported module as it is loaded
+    /// Returns `Error::Cancelled` if `cancel` is triggered before all imports are loaded
+    pub fn load_modules_with_progress(
+        &mut self,
+        main_module: Option<&Module>,
+        side_modules: Vec<&Module>,
+        cancel: &CancelHandle,
+        progress: Rc<dyn Fn(&LoadProgress)>,
+    ) -> Result<ModuleHandle, Error> {
+        self.loader.observe(Some(LoadObserver {
+            progress,
+            cancel: cancel.clone(),
+        }));
+        let result = self.load_modules(main_module, side_modules);
+        self.loader.observe(None);
+        result
+    }
+
+    /// Re-evaluate an updated version of a module
+    ///
+    /// The module is loaded under a fresh specifier, so the new code runs
+    /// even if a module with the same filename was already loaded.
+    /// Handles to the previous version remain valid, and keep returning the old exports
+    ///
+    /// Only the returned handle sees the new code - imports of the module's filename, by modules
+    /// loaded before or after the reload, keep resolving to the first version.
+    /// V8 cannot unload modules, so every version stays in memory for the life of the runtime
+    ///
+    /// Will return a handle to the updated module
+    pub fn reload_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        let timeout = self.call_timeout()?;
+        self.reload_count += 1;
+
+        let mut module_specifier = module.filename().to_module_specifier()?;
+        module_specifier.set_query(Some(&format!("reload={}", self.reload_count)));
+
+        let strict_tla = self.options.strict_top_level_await;
+        let start = Instant::now();
+        let deadline = start.checked_add(timeout);
+        let deno_runtime = &mut self.deno_runtime();
+        let module_handle_stub = Self::run_async_task(
+            async move {
+                let (code, _) = transpiler::transpile(&module_specifier, module.contents())?;
+                let code = deno_core::FastString::from(code);
+
+                let modid = deno_runtime
+                    .load_side_es_module_from_code(&module_specifier, code)
+                    .await?;
+                Self::evaluate_module(deno_runtime, modid, module.filename(), strict_tla, deadline)
+                    .await?;
+                Ok::<ModuleHandle, Error>(ModuleHandle::new(module, modid, None))
+            },
+            timeout,
+        );
+        self.record_usage(start.elapsed());
+        let module_handle_stub = self.attach_exception(module_handle_stub)?;
+        self.stats.add_modules(1);
+
+        self.with_entrypoint(module_handle_stub)
+    }
+
+    /// Transpile a module, and compile it to a V8 code cache, without evaluating it
+    pub fn compile_module(&mut self, module: &Module) -> Result<CompiledModule, Error> {
+        let specifier = module.filename().to_module_specifier()?;
+        let (code, source_map) = transpiler::transpile(&specifier, module.contents())?;
+
+        let mut scope = self.deno_runtime.handle_scope();
+        let name = specifier.as_str().to_v8_string(&mut scope)?;
+        let source = code.to_v8_string(&mut scope)?;
+        let source_map_url = v8::String::empty(&mut scope);
+        let origin = v8::ScriptOrigin::new(
+            &mut scope,
+            name.into(),
+            0,
+            0,
+            false,
+            0,
+            source_map_url.into(),
+            false,
+            false,
+            true,
+        );
+
+        let mut scope = v8::TryCatch::new(&mut scope);
+        let source = v8::script_compiler::Source::new(source, Some(&origin));
+        let Some(compiled) = v8::script_compiler::compile_module(&mut scope, source) else {
+            let exception = scope
+                .exception()
+                .unwrap_or_else(|| v8::undefined(&mut scope).into());
+            let e = deno_core::error::JsError::from_v8_exception(&mut scope, exception);
+            return Err(e.into());
+        };
+
+        let code_cache = compiled
+            .get_unbound_module_script(&mut scope)
+            .create_code_cache()
+            .ok_or_else(|| Error::Runtime(format!("Could not create a code cache for {module}")))?;
+
+        Ok(CompiledModule::new(
+            module.clone(),
+            code,
+            source_map.map(|map| map.to_vec()),
+            code_cache.to_vec(),
+        ))
+    }
+
+    /// Load and evaluate a precompiled module
+    ///
+    /// Will return a handle to the module
+    pub fn load_compiled(&mut self, compiled: &CompiledModule) -> Result<ModuleHandle, Error> {
+        let timeout = self.call_timeout()?;
+        let module = compiled.module();
+        let module_specifier = module.filename().to_module_specifier()?;
+        self.loader.mount_compiled(&module_specifier, compiled);
+
+        enter_span!("rustyscript.load_compiled", module = module.filename());
+        self.record_activity(|_| format!("loading {}", module.filename()));
+        let strict_tla = self.options.strict_top_level_await;
+        let start = Instant::now();
+        let deadline = start.checked_add(timeout);
+        let deno_runtime = &mut self.deno_runtime();
+        let module_handle_stub = Self::run_async_task(
+            async move {
+                let modid = deno_runtime.load_side_es_module(&module_specifier).await?;
+                Self::evaluate_module(deno_runtime, modid, module.filename(), strict_tla, deadline)
+                    .await?;
+                Ok::<ModuleHandle, Error>(ModuleHandle::new(module, modid, None))
             },
             timeout,
-        )?;
+        );
+        self.record_usage(start.elapsed());
+        let module_handle_stub = self.attach_exception(module_handle_stub)?;
+        self.stats.add_modules(1);
+
+        self.with_entrypoint(module_handle_stub)
+    }
+
+    /// Evaluate a loaded module, and run the event loop until it is idle
+    ///
+    /// With `strict`, a top-level await that has not settled by `deadline`, or that can
+    /// never settle because the event loop has nothing left to run, is reported as
+    /// `Error::UnresolvedTopLevelAwait`
+    async fn evaluate_module(
+        deno_runtime: &mut JsRuntime,
+        module_id: ModuleId,
+        filename: &str,
+        strict: bool,
+        deadline: Option<Instant>,
+    ) -> Result<(), Error> {
+        let result = deno_runtime.mod_evaluate(module_id);
+        if !strict {
+            deno_runtime
+                .run_event_loop(PollEventLoopOptions::default())
+                .await?;
+            return Ok(result.await?);
+        }
+
+        let unresolved = || Error::UnresolvedTopLevelAwait(filename.to_string());
+        let evaluation =
+            deno_runtime.with_event_loop_promise(Box::pin(result), PollEventLoopOptions::default());
+        let evaluated = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), evaluation)
+                .await
+                .map_err(|_| unresolved())?,
+            None => evaluation.await,
+        };
+
+        // Raised by the event loop when nothing is left that could settle the await
+        if let Err(e) = evaluated {
+            let message = e.to_string();
+            if message.contains("Top-level await promise never resolved")
+                || message.contains("event loop has already resolved")
+            {
+                return Err(unresolved());
+            }
+            return Err(e.into());
+        }
+
+        deno_runtime
+            .run_event_loop(PollEventLoopOptions::default())
+            .await?;
+        Ok(())
+    }
+
+    /// Attach an entrypoint to a freshly loaded module handle
+    /// Uses the function registered by the module if there is one, or the default entrypoint
+    fn with_entrypoint(&mut self, module_handle_stub: ModuleHandle) -> Result<ModuleHandle, Error> {
+        let default_entrypoint = self.options.default_entrypoint.clone();
 
         // Try to get an entrypoint
         let state = self.deno_runtime().op_state();
@@ -758,7 +2908,51 @@ mod test_inner_runtime {
         let e = runtime
             .call_function::<usize>(Some(&module), "fn", json_args!(1))
             .unwrap_err();
-        assert!(e.to_string().ends_with("test.js:2: Uncaught Error: msg"));
+        match e {
+            Error::JsError(e) => {
+                assert_eq!(Some("Error"), e.name.as_deref());
+                assert_eq!(Some("msg"), e.message.as_deref());
+                let frame = e.frames.first().expect("Missing stack frame");
+                assert!(frame
+                    .file_name
+                    .as_deref()
+                    .unwrap_or_default()
+                    .ends_with("test.js"));
+                assert_eq!(Some(2), frame.line_number);
+            }
+            e => panic!("Expected a JsError, got {e}"),
+        }
+    }
+
+    #[test]
+    fn test_exception_value() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const fn = () => { throw { code: 42 } };
+            export const reject = async () => { throw new RangeError('too big') };
+        ",
+        );
+
+        let mut runtime = InnerRuntime::new(Default::default()).expect("Could not load runtime");
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .expect("Could not load module");
+
+        match runtime.call_function::<usize>(Some(&module), "fn", json_args!()) {
+            Err(Error::JsError(e)) => {
+                assert_eq!(Some(serde_json::json!({ "code": 42 })), e.value);
+            }
+            _ => panic!("Expected a JsError"),
+        }
+
+        match runtime.call_function::<usize>(Some(&module), "reject", json_args!()) {
+            Err(Error::JsError(e)) => {
+                assert_eq!(Some("RangeError"), e.name.as_deref());
+                assert_eq!(Some(serde_json::json!({})), e.value);
+            }
+            _ => panic!("Expected a JsError"),
+        }
     }
 
     #[test]
@@ -890,6 +3084,79 @@ mod test_inner_runtime {
         assert_eq!(value, 2);
     }
 
+    #[cfg(feature = "web")]
+    #[test]
+    fn test_fetch_handler() {
+        struct Handler;
+        impl crate::FetchHandler for Handler {
+            fn fetch(
+                &self,
+                request: crate::FetchRequest,
+            ) -> Pin<
+                Box<dyn std::future::Future<Output = Result<Option<crate::FetchResponse>, Error>>>,
+            > {
+                let result = if request.url.starts_with("https://denied.example") {
+                    Err(Error::Runtime("Request denied".to_string()))
+                } else {
+                    Ok(Some(crate::FetchResponse {
+                        status: 200,
+                        headers: vec![],
+                        // Request bodies are echoed back, to check both directions
+                        body: request.body.unwrap_or_else(|| {
+                            format!("{} {}", request.method, request.url).into_bytes()
+                        }),
+                    }))
+                };
+                Box::pin(std::future::ready(result))
+            }
+        }
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const test = async (url) => (await fetch(url)).text();
+            export const echo = async (url, body) => (await fetch(url, { method: 'POST', body })).text();
+        ",
+        );
+
+        let mut runtime = InnerRuntime::new(InnerRuntimeOptions {
+            extension_options: ext::ExtensionOptions {
+                web: ext::web::WebOptions {
+                    fetch_handler: Some(Rc::new(Handler)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .expect("Could not load runtime");
+        let module = runtime
+            .load_modules(Some(&module), vec![])
+            .expect("Could not load module");
+
+        let value: String = runtime
+            .call_function(
+                Some(&module),
+                "test",
+                json_args!("https://allowed.example/"),
+            )
+            .expect("Could not call function");
+        assert_eq!(value, "GET https://allowed.example/");
+
+        let value: String = runtime
+            .call_function(
+                Some(&module),
+                "echo",
+                json_args!("https://allowed.example/", "body"),
+            )
+            .expect("Could not call function");
+        assert_eq!(value, "body");
+
+        runtime
+            .call_function::<String>(Some(&module), "test", json_args!("https://denied.example/"))
+            .expect_err("Did not reject request");
+    }
+
     #[test]
     fn test_serialize_deep_fn() {
         let module = Module::new(
@@ -0,0 +1,423 @@
+//! A DevTools-compatible inspector server
+//! Chrome can connect to it from `chrome://inspect`, to set breakpoints and inspect variables
+//!
+//! Messages from DevTools are handled while the runtime is running javascript, or waiting
+//! on its event loop - a runtime sitting idle between calls will not respond until the next call
+//!
+//! Only requests addressed to `localhost` or an IP address are answered, so that web pages
+//! cannot reach the inspector through DNS rebinding
+use crate::Error;
+use deno_core::{
+    futures::{
+        channel::{mpsc, oneshot},
+        SinkExt, StreamExt,
+    },
+    serde_json::json,
+    v8, InspectorMsg, InspectorSessionProxy, JsRuntime,
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{Role, WebSocketConfig},
+        Message,
+    },
+    WebSocketStream,
+};
+
+/// Options for the inspector server
+#[derive(Debug, Clone)]
+pub struct InspectorOptions {
+    /// Address for the server to listen on
+    /// Chrome looks for targets on `127.0.0.1:9229` by default
+    /// Use port 0 to pick a free port - see `Runtime::inspector_address`
+    pub address: SocketAddr,
+
+    /// If true, the runtime's constructor blocks until DevTools connects,
+    /// and execution pauses on the first statement run
+    pub wait_for_session: bool,
+}
+
+impl Default for InspectorOptions {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from(([127, 0, 0, 1], 9229)),
+            wait_for_session: false,
+        }
+    }
+}
+
+/// How long a client may take to send its request, before the connection is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Largest websocket message accepted from DevTools, in bytes
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Most connections served at once - DevTools needs a few, and any more are dropped
+const MAX_CONNECTIONS: usize = 16;
+
+/// Start an inspector server for the runtime
+/// The runtime must have been created with its inspector enabled
+/// The server stops once the runtime is dropped
+///
+/// Returns the address the server is listening on
+pub(crate) fn start(
+    runtime: &mut JsRuntime,
+    options: &InspectorOptions,
+) -> Result<SocketAddr, Error> {
+    let listener = std::net::TcpListener::bind(options.address)?;
+    listener.set_nonblocking(true)?;
+    let address = listener.local_addr()?;
+
+    let inspector = runtime.inspector();
+    let session_sender = inspector.borrow().get_session_sender();
+    let deregister_rx = inspector.borrow_mut().add_deregister_handler();
+
+    // A single thread serves every connection
+    let target = Arc::new(Target::new(address));
+    let server = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    std::thread::spawn(move || {
+        server.block_on(serve(listener, target, session_sender, deregister_rx));
+    });
+
+    if options.wait_for_session {
+        inspector
+            .borrow_mut()
+            .wait_for_session_and_break_on_next_statement();
+    }
+
+    Ok(address)
+}
+
+/// The debugging target reported to DevTools
+struct Target {
+    id: String,
+    address: SocketAddr,
+}
+
+impl Target {
+    fn new(address: SocketAddr) -> Self {
+        // The id is part of the websocket url, so it must not be guessable
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            address,
+        }
+    }
+
+    fn websocket_url(&self) -> String {
+        format!("{}/ws/{}", self.address, self.id)
+    }
+
+    fn describe(&self) -> deno_core::serde_json::Value {
+        json!([{
+            "description": "rustyscript",
+            "devtoolsFrontendUrl": format!(
+                "devtools://devtools/bundled/js_app.html?ws={}&experiments=true&v8only=true",
+                self.websocket_url()
+            ),
+            "id": self.id,
+            "title": format!("rustyscript [pid: {}]", std::process::id()),
+            "type": "node",
+            "url": "rustyscript",
+            "webSocketDebuggerUrl": format!("ws://{}", self.websocket_url()),
+        }])
+    }
+}
+
+async fn serve(
+    listener: std::net::TcpListener,
+    target: Arc<Target>,
+    session_sender: mpsc::UnboundedSender<InspectorSessionProxy>,
+    mut deregister_rx: oneshot::Receiver<()>,
+) {
+    let Ok(listener) = TcpListener::from_std(listener) else {
+        return;
+    };
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+    loop {
+        let stream = tokio::select! {
+            // Stop once the inspector is dropped along with its runtime
+            _ = &mut deregister_rx => return,
+
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+        };
+
+        // Connections past the limit are closed straight away
+        let Ok(permit) = connections.clone().try_acquire_owned() else {
+            continue;
+        };
+
+        // A slow client must not hold up the others
+        let target = target.clone();
+        let session_sender = session_sender.clone();
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, &target, &session_sender).await;
+            drop(permit);
+        });
+    }
+}
+
+/// The parts of an HTTP request the server looks at
+#[derive(Debug, Default)]
+struct Request {
+    path: String,
+    websocket_key: Option<String>,
+    host: Option<String>,
+    origin: Option<String>,
+}
+
+impl Request {
+    fn parse(head: &str) -> Self {
+        let mut lines = head.lines();
+        let mut request = Self {
+            path: lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .unwrap_or_default()
+                .to_string(),
+            ..Default::default()
+        };
+
+        for line in lines {
+            if let Some((name, value)) = line.split_once(':') {
+                let value = Some(value.trim().to_string());
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "sec-websocket-key" => request.websocket_key = value,
+                    "host" => request.host = value,
+                    "origin" => request.origin = value,
+                    _ => {}
+                }
+            }
+        }
+        request
+    }
+}
+
+/// Read the request line and headers
+/// Returns them along with any bytes the client sent after them
+async fn read_head(stream: &mut (impl AsyncRead + Unpin)) -> std::io::Result<(String, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buffer.split_off(end + 4);
+            return Ok((String::from_utf8_lossy(&buffer).into_owned(), rest));
+        }
+        if buffer.len() > MAX_HEAD_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "request headers are too large",
+            ));
+        }
+
+        let mut chunk = [0; 1024];
+        match stream.read(&mut chunk).await? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            n => buffer.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    target: &Target,
+    session_sender: &mpsc::UnboundedSender<InspectorSessionProxy>,
+) -> std::io::Result<()> {
+    let (head, rest) = tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream))
+        .await
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    let request = Request::parse(&head);
+
+    let trusted = request.host.as_deref().is_some_and(is_local_host)
+        && request.origin.as_deref().is_none_or(is_trusted_origin);
+    if !trusted {
+        return respond(&mut stream, "403 Forbidden", None).await;
+    }
+
+    match (request.path.as_str(), request.websocket_key) {
+        ("/json" | "/json/list", _) => {
+            respond(&mut stream, "200 OK", Some(target.describe())).await
+        }
+        ("/json/version", _) => {
+            let version = json!({
+                "Browser": format!("rustyscript/{}", env!("CARGO_PKG_VERSION")),
+                "Protocol-Version": "1.3",
+                "V8-Version": v8::V8::get_version(),
+            });
+            respond(&mut stream, "200 OK", Some(version)).await
+        }
+        (path, Some(key)) if path == format!("/ws/{}", target.id) => {
+            let accept = derive_accept_key(key.as_bytes());
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+            );
+            stream.write_all(response.as_bytes()).await?;
+
+            let config = WebSocketConfig {
+                max_message_size: Some(MAX_MESSAGE_SIZE),
+                max_frame_size: Some(MAX_MESSAGE_SIZE),
+                ..Default::default()
+            };
+            let socket =
+                WebSocketStream::from_partially_read(stream, rest, Role::Server, Some(config))
+                    .await;
+            run_session(socket, session_sender).await
+        }
+        _ => respond(&mut stream, "404 Not Found", None).await,
+    }
+}
+
+/// Write a response and close the connection
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    body: Option<deno_core::serde_json::Value>,
+) -> std::io::Result<()> {
+    let response = match body {
+        Some(body) => {
+            let body = body.to_string();
+            format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        None => format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Whether a `Host` header names `localhost` or an IP address, with or without a port
+/// Any other name could have been pointed at this machine by a web page
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.ends_with(':') && port.parse::<u16>().is_ok() => name,
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+}
+
+/// Whether an `Origin` header comes from DevTools itself, or a local page
+fn is_trusted_origin(origin: &str) -> bool {
+    if origin.starts_with("devtools://") || origin.starts_with("chrome-devtools://") {
+        return true;
+    }
+    origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .is_some_and(is_local_host)
+}
+
+/// Connect a websocket to the inspector, until either side closes it
+/// Sessions stay open for as long as DevTools is connected
+async fn run_session(
+    socket: WebSocketStream<TcpStream>,
+    session_sender: &mpsc::UnboundedSender<InspectorSessionProxy>,
+) -> std::io::Result<()> {
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded::<InspectorMsg>();
+    let (inbound_tx, inbound_rx) = mpsc::unbounded::<String>();
+    session_sender
+        .unbounded_send(InspectorSessionProxy {
+            tx: outbound_tx,
+            rx: inbound_rx,
+        })
+        .map_err(|_| std::io::Error::other("The inspector has stopped"))?;
+
+    let (mut sink, mut incoming) = socket.split();
+    loop {
+        tokio::select! {
+            message = outbound_rx.next() => match message {
+                Some(message) => {
+                    if sink.send(Message::Text(message.content)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+
+            // Pings are answered as the socket is read
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    if inbound_tx.unbounded_send(text).is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let _ = sink.close().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_inspector {
+    use super::*;
+
+    #[test]
+    fn test_local_host() {
+        assert!(is_local_host("localhost:9229"));
+        assert!(is_local_host("127.0.0.1"));
+        assert!(is_local_host("[::1]:9229"));
+        assert!(!is_local_host("attacker.example:9229"));
+        assert!(!is_local_host("localhost.attacker.example"));
+
+        assert!(is_trusted_origin("devtools://devtools"));
+        assert!(is_trusted_origin("http://localhost:8080"));
+        assert!(!is_trusted_origin("https://attacker.example"));
+    }
+
+    #[test]
+    fn test_request_head() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("Could not build the runtime");
+
+        let bytes = b"GET /json HTTP/1.1\r\nHost: localhost:9229\r\nOrigin: devtools://devtools\r\n\r\nrest";
+        let (head, rest) = runtime
+            .block_on(read_head(&mut bytes.as_slice()))
+            .expect("Could not read");
+        assert_eq!(b"rest".to_vec(), rest);
+
+        let request = Request::parse(&head);
+        assert_eq!("/json", request.path);
+        assert_eq!(Some("localhost:9229"), request.host.as_deref());
+        assert_eq!(Some("devtools://devtools"), request.origin.as_deref());
+        assert_eq!(None, request.websocket_key);
+
+        // Headers larger than the limit are refused, rather than buffered without end
+        let bytes = vec![b'a'; MAX_HEAD_SIZE * 2];
+        let e = runtime
+            .block_on(read_head(&mut bytes.as_slice()))
+            .expect_err("Headers over the limit were read");
+        assert_eq!(std::io::ErrorKind::InvalidData, e.kind());
+    }
+
+    #[test]
+    fn test_target_id() {
+        let address = SocketAddr::from(([127, 0, 0, 1], 9229));
+        let (a, b) = (Target::new(address), Target::new(address));
+        assert_ne!(a.id, b.id);
+        assert!(uuid::Uuid::parse_str(&a.id).is_ok());
+    }
+}
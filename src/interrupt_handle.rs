@@ -0,0 +1,155 @@
+use deno_core::v8;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Instant,
+};
+
+/// Stops the javascript a runtime is running, from any thread
+/// Returned by [crate::Runtime::interrupt_handle]
+///
+/// The interrupted call returns `Error::Cancelled`, and the runtime remains usable afterwards
+/// Unlike a timeout, this also stops synchronous code, such as an infinite loop
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Error };
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let handle = runtime.interrupt_handle();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_millis(50));
+///     handle.terminate();
+/// });
+///
+/// let e = runtime.eval::<()>("while (true) {}").unwrap_err();
+/// assert!(matches!(e, Error::Cancelled(_)));
+///
+/// let value: i64 = runtime.eval("1 + 1")?;
+/// assert_eq!(2, value);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InterruptHandle {
+    isolate: v8::IsolateHandle,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(isolate: v8::IsolateHandle, interrupted: Arc<AtomicBool>) -> Self {
+        Self {
+            isolate,
+            interrupted,
+        }
+    }
+
+    /// Stop the javascript currently running in the runtime
+    /// Has no effect on calls made after this one, or if the runtime has been dropped
+    ///
+    /// Returns false if the runtime has been dropped
+    pub fn terminate(&self) -> bool {
+        self.interrupted.store(true, Ordering::SeqCst);
+        self.isolate.terminate_execution()
+    }
+}
+
+impl std::fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterruptHandle")
+            .field("interrupted", &self.interrupted.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether the call a runtime is running has been interrupted, or has run out of time
+/// Kept in the op state, for ops that block outside of javascript, where termination cannot reach
+#[cfg(feature = "process")]
+#[derive(Clone)]
+pub(crate) struct StopSignal {
+    pub interrupted: Arc<AtomicBool>,
+    pub watchdog_fired: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "process")]
+impl StopSignal {
+    /// Returns true once the current call should stop
+    pub fn is_set(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst) || self.watchdog_fired.load(Ordering::SeqCst)
+    }
+}
+
+/// Stops the javascript a runtime is running once a deadline passes, unless disarmed first
+/// Unlike a timeout, this also stops synchronous code, such as an infinite loop
+///
+/// A single thread serves every call made by the runtime, and stops when the watchdog is dropped
+pub(crate) struct Watchdog {
+    deadlines: Option<Sender<Option<Instant>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Start a disarmed watchdog that terminates the isolate, and sets `fired`, once a deadline passes
+    pub fn new(isolate: v8::IsolateHandle, fired: Arc<AtomicBool>) -> Self {
+        let (deadlines, receiver) = channel::<Option<Instant>>();
+        let thread = std::thread::spawn(move || {
+            let mut deadline: Option<Instant> = None;
+            loop {
+                let next = match deadline {
+                    Some(deadline) => {
+                        receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    }
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+
+                match next {
+                    Ok(next) => deadline = next,
+                    Err(RecvTimeoutError::Timeout) => {
+                        deadline = None;
+                        fired.store(true, Ordering::SeqCst);
+                        isolate.terminate_execution();
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        Self {
+            deadlines: Some(deadlines),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop the runtime's javascript at `deadline`, replacing any earlier deadline
+    pub fn arm(&self, deadline: Instant) {
+        self.send(Some(deadline));
+    }
+
+    /// Cancel the current deadline, if there is one
+    pub fn disarm(&self) {
+        self.send(None);
+    }
+
+    fn send(&self, deadline: Option<Instant>) {
+        if let Some(deadlines) = &self.deadlines {
+            // The thread only stops once the sender is dropped
+            let _ = deadlines.send(deadline);
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        drop(self.deadlines.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Provides one-call execution of a module's entrypoint in its own runtime
+//! Runtimes are taken from a per-thread pool of pre-warmed instances, so the cost of
+//! creating an isolate is paid ahead of the call rather than during it
+use crate::{
+    detached_runtime::DetachedRuntime, Error, FunctionArguments, ImportPolicy, Module,
+    ModuleHandle, Runtime, RuntimeOptions,
+};
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+
+/// Decides what happens to a runtime once an isolated call is done with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RecyclePolicy {
+    /// Drop the runtime after each call, so no state can leak between calls
+    /// A fresh runtime is warmed up in its place, ready for the next call
+    #[default]
+    Discard,
+
+    /// Return the runtime to the pool after a successful call, for up to `max_uses` calls
+    /// Faster, but globals set by one call remain visible to the next
+    /// Runtimes are always discarded after an error
+    ///
+    /// Each module is loaded once per runtime - later calls with a module of the same
+    /// filename only call its entrypoint again
+    Reuse {
+        /// Number of calls after which the runtime is discarded
+        max_uses: usize,
+    },
+}
+
+/// Options for [execute_isolated]
+/// Runtimes are pooled separately for each distinct set of options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IsolationOptions {
+    /// Amount of time the call may run for before timing out
+    /// The call is stopped once it runs out, even in the middle of synchronous code
+    pub timeout: Duration,
+
+    /// Total amount of wall-clock time a runtime may spend in calls, across all of its calls
+    pub time_budget: Option<Duration>,
+
+    /// Maximum number of pending timers
+    pub max_timers: Option<usize>,
+
+    /// What to do with the runtime after the call
+    pub recycle: RecyclePolicy,
+
+    /// Number of idle runtimes to keep warm on each thread
+    pub pool_size: usize,
+}
+
+impl Default for IsolationOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            time_budget: None,
+            max_timers: None,
+            recycle: RecyclePolicy::default(),
+            pool_size: 4,
+        }
+    }
+}
+
+impl IsolationOptions {
+    /// Options for the runtimes in this pool
+    /// Remote imports are always denied
+    fn runtime_options(&self) -> RuntimeOptions {
+        RuntimeOptions {
+            timeout: self.timeout,
+            time_budget: self.time_budget,
+            max_timers: self.max_timers,
+            import_policy: ImportPolicy::Offline,
+            ..Default::default()
+        }
+    }
+}
+
+/// A pooled runtime, the number of calls it has served, and the modules it has loaded by filename
+/// Runtimes are taken and dropped in any order, so each is only entered while in use
+type PooledRuntime = DetachedRuntime<(Runtime, usize, HashMap<String, ModuleHandle>)>;
+
+thread_local! {
+    static POOL: RefCell<HashMap<IsolationOptions, Vec<PooledRuntime>>> = RefCell::new(HashMap::new());
+}
+
+/// Run a module's entrypoint in a runtime of its own, and return the result
+/// The runtime is taken from a per-thread pool, then recycled or discarded according to `options.recycle`
+///
+/// Remote imports are denied; the entrypoint is the module's registered entrypoint, or its default export
+///
+/// # Arguments
+/// * `module` - The module to load
+/// * `args` - Arguments to pass to the entrypoint
+/// * `options` - Limits for the call, and the pooling policy
+///
+/// # Returns
+/// A `Result` containing the deserialized result of the entrypoint,
+/// or an error if the module could not be loaded, or the call fails
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{execute_isolated, json_args, Error, IsolationOptions, Module};
+///
+/// # fn main() -> Result<(), Error> {
+/// let module = Module::new("handler.js", "export default (a, b) => a + b;");
+/// let value: i64 = execute_isolated(&module, json_args!(2, 3), IsolationOptions::default())?;
+/// assert_eq!(5, value);
+/// # Ok(())
+/// # }
+/// ```
+pub fn execute_isolated<T>(
+    module: &Module,
+    args: &FunctionArguments,
+    options: IsolationOptions,
+) -> Result<T, Error>
+where
+    T: deno_core::serde::de::DeserializeOwned,
+{
+    let mut pooled = match take(&options) {
+        Some(pooled) => pooled,
+        None => DetachedRuntime::new((Runtime::new(options.runtime_options())?, 0, HashMap::new())),
+    };
+
+    let (result, reuse) = pooled.with(|(runtime, uses, modules)| {
+        // A module can only be evaluated once, so a reused runtime calls the loaded one again
+        let handle = match modules.get(module.filename()) {
+            Some(handle) => Ok(handle.clone()),
+            None => runtime.load_module(module).inspect(|handle| {
+                modules.insert(module.filename().to_string(), handle.clone());
+            }),
+        };
+        let result = handle.and_then(|handle| {
+            if handle.entrypoint().is_some() {
+                runtime.call_entrypoint(&handle, args)
+            } else {
+                runtime.call_default_export(&handle, args)
+            }
+        });
+
+        *uses += 1;
+        let reuse = match options.recycle {
+            RecyclePolicy::Reuse { max_uses } => result.is_ok() && *uses < max_uses,
+            RecyclePolicy::Discard => false,
+        };
+        if reuse {
+            runtime.reset_budget();
+        }
+        (result, reuse)
+    });
+
+    if reuse {
+        give(&options, pooled);
+    } else {
+        drop(pooled);
+        let _ = prewarm_isolated(options);
+    }
+
+    result
+}
+
+/// Fill this thread's pool for `options` with up to `options.pool_size` idle runtimes
+/// Call ahead of time to take runtime creation off the path of the first calls
+pub fn prewarm_isolated(options: IsolationOptions) -> Result<(), Error> {
+    while idle(&options) < options.pool_size {
+        let runtime = Runtime::new(options.runtime_options())?;
+        give(&options, DetachedRuntime::new((runtime, 0, HashMap::new())));
+    }
+    Ok(())
+}
+
+/// Drop all idle runtimes pooled on this thread
+pub fn clear_isolated_pool() {
+    POOL.with(|pool| pool.borrow_mut().clear());
+}
+
+fn idle(options: &IsolationOptions) -> usize {
+    POOL.with(|pool| pool.borrow().get(options).map_or(0, Vec::len))
+}
+
+fn take(options: &IsolationOptions) -> Option<PooledRuntime> {
+    POOL.with(|pool| pool.borrow_mut().get_mut(options)?.pop())
+}
+
+fn give(options: &IsolationOptions, runtime: PooledRuntime) {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let runtimes = pool.entry(*options).or_default();
+        if runtimes.len() < options.pool_size {
+            runtimes.push(runtime);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test_isolation {
+    use super::*;
+    use crate::json_args;
+
+    #[test]
+    fn test_execute_isolated() {
+        let options = IsolationOptions {
+            pool_size: 1,
+            ..Default::default()
+        };
+
+        let module = Module::new(
+            "test_execute_isolated.js",
+            "
+            globalThis.calls = (globalThis.calls ?? 0) + 1;
+            export default () => globalThis.calls;
+        ",
+        );
+
+        // Discarded runtimes never see each other's globals
+        for _ in 0..3 {
+            let calls: i64 = execute_isolated(&module, json_args!(), options)
+                .expect("Could not call the module");
+            assert_eq!(1, calls);
+        }
+        assert_eq!(1, idle(&options));
+
+        let e = execute_isolated::<i64>(
+            &Module::new("test_execute_isolated_loop.js", "while (true) {}"),
+            json_args!(),
+            IsolationOptions {
+                timeout: Duration::from_millis(50),
+                ..options
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        clear_isolated_pool();
+        assert_eq!(0, idle(&options));
+    }
+
+    #[test]
+    fn test_recycle() {
+        let options = IsolationOptions {
+            recycle: RecyclePolicy::Reuse { max_uses: 2 },
+            pool_size: 1,
+            ..Default::default()
+        };
+
+        // The same module is loaded once, and its entrypoint called on each use
+        let module = Module::new(
+            "test_recycle.js",
+            "
+            export default () => {
+                globalThis.calls = (globalThis.calls ?? 0) + 1;
+                return globalThis.calls;
+            };
+        ",
+        );
+        let calls = || -> i64 {
+            execute_isolated(&module, json_args!(), options).expect("Could not call the module")
+        };
+
+        assert_eq!(1, calls());
+        assert_eq!(2, calls());
+
+        // The runtime was retired after its second use
+        assert_eq!(1, calls());
+
+        // Other modules share the reused runtime's globals
+        let other = Module::new(
+            "test_recycle_other.js",
+            "export default () => globalThis.calls;",
+        );
+        let value: i64 =
+            execute_isolated(&other, json_args!(), options).expect("Could not call the module");
+        assert_eq!(1, value);
+        clear_isolated_pool();
+    }
+
+    #[test]
+    fn test_pool_drop_order() {
+        let module = Module::new("test_pool_drop_order.js", "export default () => 1;");
+        let first = IsolationOptions {
+            pool_size: 3,
+            ..Default::default()
+        };
+        let second = IsolationOptions {
+            timeout: Duration::from_secs(2),
+            ..first
+        };
+
+        // Runtimes are taken from, and discarded from, the middle of the thread's runtimes
+        prewarm_isolated(first).expect("Could not prewarm the runtime");
+        prewarm_isolated(second).expect("Could not prewarm the runtime");
+        for options in [first, second, first] {
+            let value: i64 = execute_isolated(&module, json_args!(), options)
+                .expect("Could not call the module");
+            assert_eq!(1, value);
+        }
+
+        // A runtime created outside the pool is unaffected
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let value: i64 =
+            execute_isolated(&module, json_args!(), second).expect("Could not call the module");
+        assert_eq!(1, value);
+        let value: i64 = runtime.eval("1 + 1").expect("Could not eval");
+        assert_eq!(2, value);
+
+        clear_isolated_pool();
+        assert_eq!(0, idle(&first) + idle(&second));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::Error;
+use deno_core::v8;
+
+/// The result of looking up, or calling a javascript value
+/// Unlike a plain deserialized value, this keeps track of the difference between
+/// a value that is `undefined`, one that is `null`, and one that does not exist at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsValue<T> {
+    /// No value by that name exists in global, or module exports
+    Missing,
+
+    /// The value exists, but is `undefined`
+    Undefined,
+
+    /// The value exists, but is `null`
+    Null,
+
+    /// The value exists, and was deserialized
+    Value(T),
+}
+
+impl<T> JsValue<T> {
+    /// Returns the contained value, or None if it was missing, `undefined` or `null`
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Self::Value(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the value does not exist
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+
+    /// Returns true if the value is `undefined`
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Self::Undefined)
+    }
+
+    /// Returns true if the value is `null`
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns true if a value is present
+    pub fn is_value(&self) -> bool {
+        matches!(self, Self::Value(_))
+    }
+
+    /// Decode a v8 value, keeping `undefined` and `null` distinct
+    pub(crate) fn from_v8<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+    ) -> Result<Self, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if value.is_undefined() {
+            Ok(Self::Undefined)
+        } else if value.is_null() {
+            Ok(Self::Null)
+        } else {
+            Ok(Self::Value(deno_core::serde_v8::from_v8(scope, value)?))
+        }
+    }
+}
+
+impl<T> From<JsValue<T>> for Option<T> {
+    fn from(value: JsValue<T>) -> Self {
+        value.into_option()
+    }
+}
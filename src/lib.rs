@@ -135,6 +135,7 @@
 //!     let worker = DefaultWorker::new(DefaultWorkerOptions {
 //!         default_entrypoint: None,
 //!         timeout: Duration::from_secs(5),
+//!         ..Default::default()
 //!     })?;
 //!
 //!     worker.register_function("add".to_string(), |args, _state| {
@@ -167,11 +168,18 @@
 //! | Feature        | Description                                                                                       | Preserves Sandbox | Dependencies                                                                   |  
 //! |----------------|---------------------------------------------------------------------------------------------------|------------------|---------------------------------------------------------------------------------|
 //! |console         |Provides `console.*` functionality from JS                                                         |yes               |deno_console                                                                     |
-//! |crypto          |Provides `crypto.*` functionality from JS                                                          |yes               |deno_crypto, deno_webidl                                                         |
+//! |crypto          |Provides `crypto.getRandomValues`, `crypto.randomUUID` and `crypto.subtle` from JS                 |yes               |deno_crypto, deno_webidl                                                         |
 //! |url             |Provides the URL, and URLPattern APIs from within JS                                               |yes               |deno_webidl, deno_url                                                            |
+//! |web_stub        |Provides TextEncoder, TextDecoder, atob, btoa and DOMException without the full web feature        |yes               |deno_webidl, deno_console, deno_url                                              |
 //! |io              |Provides IO primitives such as stdio streams and abstraction over File System files.               |**NO**            |deno_io, rustyline, winapi, nix, libc, once_cell                                 |
 //! |web             |Provides the Event, TextEncoder, TextDecoder, File, Web Cryptography, and fetch APIs from within JS|**NO**            |deno_webidl, deno_web, deno_crypto, deno_fetch, deno_url, deno_net               |
 //! |webstorage      |Provides the WebStorage API                                                                        |**NO**            |deno_webidl, deno_webstorage                                                        |
+//! |kv              |Provides the `rustyscript.kv` API, backed by a host-supplied `KvStore`                             |yes               |None                                                                             |
+//! |deno_kv         |Provides a `Deno.openKv()`-compatible API on top of the `kv` feature                               |yes               |None                                                                             |
+//! |websocket       |Provides the `WebSocket` client API, with connections and messages checked by a `WebSocketPolicy`  |**NO**            |tokio-tungstenite                                                                |
+//! |fs              |Provides `Deno.readTextFile`, `Deno.writeTextFile` and `node:fs/promises`, within granted folders  |**NO**            |libc                                                                             |
+//! |process         |Provides `Deno.Command` for running subprocesses, limited to an allow-list of executables          |**NO**            |None                                                                             |
+//! |sqlite          |Provides the `rustyscript.sqlite` API, with an in-memory database or host-allowed database files   |**NO**            |rusqlite                                                                         |
 //! |                |                                                                                                   |                  |                                                                                 |
 //! |default         |Provides only those extensions that preserve sandboxing                                            |yes               |deno_console, deno_crypto, deno_webidl, deno_url                                 |
 //! |no_extensions   |Disables all extensions to the JS runtime - you can still add your own extensions in this mode     |yes               |None                                                                             |
@@ -179,8 +187,18 @@
 //! |                |                                                                                                   |                  |                                                                                 |
 //! |fs_import       | Enables importing arbitrary code from the filesystem through JS                                   |**NO**            |None                                                                             |
 //! |url_import      | Enables importing arbitrary code from network locations through JS                                |**NO**            |reqwest                                                                          |
+//! |npm             | Enables `npm:` imports, loaded from an ES module CDN. Implies url_import                           |**NO**            |reqwest, sha1                                                                    |
 //! |                |                                                                                                   |                  |                                                                                 |
 //! |worker          | Enables access to the threaded worker API [rustyscript::worker]                                   |yes               |None                                                                             |
+//! |bench           | Enables reusable benchmark scenarios through [rustyscript::bench]                                 |yes               |criterion                                                                        |
+//! |memory_pressure | Enables responding to system memory pressure through [rustyscript::memory_pressure]              |yes               |winapi on Windows                                                                |
+//! |worker_compression| Compresses large payloads sent to and from [rustyscript::worker::DefaultWorker]                 |yes               |lz4_flex                                                                         |
+//! |inspector       | Enables a DevTools-compatible inspector server, through [rustyscript::InspectorOptions]           |yes               |tokio-tungstenite, uuid                                                          |
+//! |serve           | Enables an HTTP server passing requests to a JS `fetch` handler, through [rustyscript::Server]    |yes               |hyper, hyper-util, http-body-util                                                |
+//! |tracing         | Instruments module loading, calls, callbacks and worker queries with `tracing` spans              |yes               |tracing                                                                          |
+//! |bin             | Builds the `rustyscript-run` executable, for running scripts and reproducing issues              |**NO**            |None                                                                             |
+//! |conformance     | Enables round-trip checks for values passed to and from JS, through [rustyscript::conformance]  |yes               |None                                                                             |
+//! |structured_clone| Passes Map, Set, Date, binary data and cycles to and from JS, through [rustyscript::StructuredValue]|yes               |None                                                                             |
 //! |snapshot_builder| Enables access to [rustyscript::SnapshotBuilder]                                                  |yes               |None                                                                             |
 //!
 //! There is also a `snapshot_builder` feature enables access to an alternative runtime
@@ -206,22 +224,58 @@ pub use snapshot_builder::SnapshotBuilder;
 
 pub mod cache_provider;
 
+mod arg_buffer;
+mod arg_schema;
+mod compiled_module;
+mod crash_dump;
+mod detached_runtime;
 mod error;
 mod ext;
+mod import_policy;
 mod inner_runtime;
+mod interrupt_handle;
+mod isolation;
 mod js_function;
+mod js_value;
 mod module;
 mod module_handle;
 mod module_loader;
 mod module_wrapper;
+mod profile;
+mod promise_handle;
+mod promise_tracker;
+mod quota;
+mod realm;
+mod resolver_hook;
+mod resource_handle;
 mod runtime;
+mod runtime_builder;
+mod runtime_stats;
+mod spans;
+mod state_cell;
 mod traits;
 mod transpiler;
 mod utilities;
+mod v8_value;
+mod wasm;
 
 #[cfg(feature = "worker")]
 pub mod worker;
 
+#[cfg(feature = "bench")]
+pub mod bench;
+
+#[cfg(feature = "conformance")]
+pub mod conformance;
+
+#[cfg(feature = "memory_pressure")]
+pub mod memory_pressure;
+
+#[cfg(feature = "structured_clone")]
+mod structured_clone;
+#[cfg(feature = "structured_clone")]
+pub use structured_clone::{ArrayBufferContents, StructuredValue, TypedArrayKind, MAX_CLONE_DEPTH};
+
 // Expose a few dependencies that could be useful
 pub use deno_core;
 pub use deno_core::serde_json;
@@ -230,18 +284,74 @@ pub use deno_core::serde_json;
 pub use deno_tls;
 
 #[cfg(feature = "web")]
-pub use ext::web::WebOptions;
+pub use ext::web::{FetchHandler, FetchRequest, FetchResponse, WebOptions};
+
+#[cfg(feature = "kv")]
+pub use ext::kv::{KvStore, MemoryKvStore};
+
+#[cfg(feature = "websocket")]
+pub use ext::websocket::{MessageDirection, WebSocketMessage, WebSocketPolicy};
+
+#[cfg(feature = "fs")]
+pub use ext::fs::FsPermissions;
+
+#[cfg(feature = "process")]
+pub use ext::process::ProcessPermissions;
+
+#[cfg(feature = "sqlite")]
+pub use ext::sqlite::SqlitePermissions;
+
+pub use ext::timers::{DeterministicOptions, PendingTimer};
 pub use ext::ExtensionOptions;
 
+pub use module_loader::{CancelHandle, LoadProgress};
+#[cfg(feature = "npm")]
+pub use module_loader::NpmOptions;
+
+#[cfg(feature = "inspector")]
+mod inspector;
+#[cfg(feature = "inspector")]
+pub use inspector::InspectorOptions;
+
+#[cfg(feature = "serve")]
+mod server;
+#[cfg(feature = "serve")]
+pub use server::{Server, ServerOptions};
+
 // Expose some important stuff from us
-pub use error::Error;
-pub use inner_runtime::{FunctionArguments, RsAsyncFunction, RsFunction};
+pub use arg_buffer::ArgBuffer;
+pub use arg_schema::{ArgSchema, ArgSpec, ArgType};
+pub use compiled_module::CompiledModule;
+pub use error::{CallbackError, Error, ErrorReport, JsException, WorkerCrashReport};
+pub use import_policy::{ImportPolicy, UrlPattern};
+pub use inner_runtime::{
+    CallContext, CallbackLayer, ExceptionHandler, FunctionArguments, FunctionInfo, PollOptions,
+    RegisteredFunction, RsAsyncFunction, RsFunction,
+};
+pub use interrupt_handle::InterruptHandle;
+pub use isolation::{
+    clear_isolated_pool, execute_isolated, prewarm_isolated, IsolationOptions, RecyclePolicy,
+};
 pub use js_function::JsFunction;
+pub use js_value::JsValue;
 pub use module::{Module, StaticModule};
-pub use module_handle::ModuleHandle;
+pub use module_handle::{ExportInfo, ExportKind, LoadResult, ModuleHandle};
 pub use module_wrapper::ModuleWrapper;
+pub use profile::{Capabilities, Profile, ProfileBuilder};
+pub use promise_handle::PromiseHandle;
+pub use quota::{
+    FileQuotaStore, MemoryQuotaStore, Quota, QuotaExceeded, QuotaLimits, QuotaResource, QuotaStore,
+    QuotaUsage,
+};
+pub use realm::RealmHandle;
+pub use resolver_hook::ResolverHook;
+pub use resource_handle::{HandleTable, ResourceHandle};
 pub use runtime::{Runtime, RuntimeOptions, Undefined};
-pub use utilities::{evaluate, import, resolve_path, validate};
+pub use runtime_builder::RuntimeBuilder;
+pub use runtime_stats::RuntimeStats;
+pub use state_cell::StateCell;
+pub use utilities::{evaluate, format_value, import, resolve_path, validate};
+pub use v8_value::V8Value;
 
 #[cfg(test)]
 mod test {
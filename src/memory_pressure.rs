@@ -0,0 +1,175 @@
+//! Provides a way to respond to system memory pressure
+//! Runtimes registered with [crate::Runtime::listen_for_memory_pressure] are sent a low-memory
+//! notification, which makes v8 run a full garbage collection the next time they execute javascript
+//!
+//! On Windows and macOS, [start_listener] subscribes to the operating system's memory pressure
+//! notifications. Elsewhere, [notify] can be called from any host-side source of pressure events
+//! ```rust
+//! use rustyscript::{memory_pressure, Error, Runtime};
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut runtime = Runtime::new(Default::default())?;
+//! runtime.listen_for_memory_pressure();
+//!
+//! memory_pressure::on_pressure(|| println!("Memory is running low"));
+//! memory_pressure::notify();
+//! # Ok(())
+//! # }
+//! ```
+use crate::Error;
+use deno_core::v8;
+use std::sync::Mutex;
+
+type PressureCallback = Box<dyn Fn() + Send + Sync>;
+
+static RUNTIMES: Mutex<Vec<v8::IsolateHandle>> = Mutex::new(Vec::new());
+static CALLBACKS: Mutex<Vec<PressureCallback>> = Mutex::new(Vec::new());
+
+/// Register an isolate to be notified of memory pressure
+pub(crate) fn register(handle: v8::IsolateHandle) {
+    if let Ok(mut runtimes) = RUNTIMES.lock() {
+        runtimes.push(handle);
+    }
+}
+
+/// Register a host callback, called each time memory pressure is signalled
+pub fn on_pressure<F>(callback: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    if let Ok(mut callbacks) = CALLBACKS.lock() {
+        callbacks.push(Box::new(callback));
+    }
+}
+
+/// Signal memory pressure to all registered runtimes, and call all host callbacks
+/// Runtimes that have since been dropped are unregistered
+///
+/// Returns the number of runtimes notified
+pub fn notify() -> usize {
+    extern "C" fn low_memory(isolate: &mut v8::Isolate, _: *mut std::ffi::c_void) {
+        isolate.low_memory_notification();
+    }
+
+    let notified = match RUNTIMES.lock() {
+        Ok(mut runtimes) => {
+            runtimes.retain(|handle| handle.request_interrupt(low_memory, std::ptr::null_mut()));
+            runtimes.len()
+        }
+        Err(_) => 0,
+    };
+
+    if let Ok(callbacks) = CALLBACKS.lock() {
+        for callback in callbacks.iter() {
+            callback();
+        }
+    }
+
+    notified
+}
+
+/// Start listening for the operating system's memory pressure notifications
+/// Each notification calls [notify]
+///
+/// Returns an error if the platform has no memory pressure notifications, or if
+/// the listener could not be started
+pub fn start_listener() -> Result<(), Error> {
+    platform::start_listener()
+}
+
+#[cfg(windows)]
+mod platform {
+    use crate::Error;
+    use std::time::Duration;
+    use winapi::um::{
+        memoryapi::{CreateMemoryResourceNotification, LowMemoryResourceNotification},
+        synchapi::WaitForSingleObject,
+        winbase::INFINITE,
+    };
+
+    /// The notification stays signalled for as long as memory is low,
+    /// so wait a while before checking it again
+    const RENOTIFY_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn start_listener() -> Result<(), Error> {
+        let handle = unsafe { CreateMemoryResourceNotification(LowMemoryResourceNotification) };
+        if handle.is_null() {
+            return Err(Error::Runtime(
+                "Could not create a memory resource notification".to_string(),
+            ));
+        }
+
+        // Raw handles are not Send, but this one is never closed
+        let handle = handle as usize;
+        std::thread::spawn(move || loop {
+            unsafe { WaitForSingleObject(handle as _, INFINITE) };
+            super::notify();
+            std::thread::sleep(RENOTIFY_INTERVAL);
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use crate::Error;
+    use std::ffi::c_void;
+
+    const DISPATCH_MEMORYPRESSURE_WARN: usize = 0x02;
+    const DISPATCH_MEMORYPRESSURE_CRITICAL: usize = 0x04;
+
+    extern "C" {
+        static _dispatch_source_type_memorypressure: c_void;
+        fn dispatch_get_global_queue(identifier: isize, flags: usize) -> *mut c_void;
+        fn dispatch_source_create(
+            source_type: *const c_void,
+            handle: usize,
+            mask: usize,
+            queue: *mut c_void,
+        ) -> *mut c_void;
+        fn dispatch_source_set_event_handler_f(
+            source: *mut c_void,
+            handler: extern "C" fn(*mut c_void),
+        );
+        fn dispatch_resume(object: *mut c_void);
+    }
+
+    extern "C" fn on_event(_: *mut c_void) {
+        super::notify();
+    }
+
+    pub fn start_listener() -> Result<(), Error> {
+        unsafe {
+            let queue = dispatch_get_global_queue(0, 0);
+            let source = dispatch_source_create(
+                &_dispatch_source_type_memorypressure,
+                0,
+                DISPATCH_MEMORYPRESSURE_WARN | DISPATCH_MEMORYPRESSURE_CRITICAL,
+                queue,
+            );
+            if source.is_null() {
+                return Err(Error::Runtime(
+                    "Could not create a memory pressure source".to_string(),
+                ));
+            }
+
+            // The source is never released, so it keeps listening for the life of the process
+            dispatch_source_set_event_handler_f(source, on_event);
+            dispatch_resume(source);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    use crate::Error;
+
+    pub fn start_listener() -> Result<(), Error> {
+        Err(Error::Runtime(
+            "Memory pressure notifications are not supported on this platform".to_string(),
+        ))
+    }
+}
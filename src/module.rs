@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::fmt::Display;
 use std::fs::{read_dir, read_to_string};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// A static representation of a module
 /// use `.to_module()` to get a module instance to use with a runtime
@@ -18,6 +18,16 @@ impl StaticModule {
     pub fn to_module(&self) -> Module {
         Module::new(self.0, self.1)
     }
+
+    /// The filename of the module
+    pub const fn filename(&self) -> &'static str {
+        self.0
+    }
+
+    /// The contents of the module
+    pub const fn contents(&self) -> &'static str {
+        self.1
+    }
 }
 
 /// Creates a static module
@@ -45,6 +55,63 @@ macro_rules! module {
     };
 }
 
+/// Embeds a module's file in the binary at compile time
+///
+/// The path is relative to the crate's root (the directory containing its `Cargo.toml`),
+/// and is kept as the module's filename - so error messages, source maps and relative
+/// imports refer to the original file, as they would if it were loaded from disk
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ include_module, StaticModule, Runtime, Error };
+///
+/// const MY_SCRIPT: StaticModule = include_module!("examples/javascript/example_module.js");
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = runtime.load_module(&MY_SCRIPT.to_module())?;
+/// let food: String = runtime.get_value(Some(&module), "MY_FAVOURITE_FOOD")?;
+/// assert_eq!("saskatoonberries", food);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! include_module {
+    ($path:expr) => {
+        $crate::StaticModule::new(
+            $path,
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)),
+        )
+    };
+}
+
+/// Embeds several modules from one directory at compile time, as an array of [StaticModule]
+///
+/// Macros cannot list a directory, so the files are named explicitly, relative to the directory
+/// As with [crate::include_module], the directory is relative to the crate's root, and each
+/// module's filename is its original path - so the modules can import each other
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ include_dir_modules, StaticModule, Module };
+///
+/// const SCRIPTS: [StaticModule; 2] = include_dir_modules!(
+///     "examples/javascript",
+///     ["example_module.js", "example_extension.js"]
+/// );
+///
+/// let modules: Vec<Module> = SCRIPTS.iter().map(StaticModule::to_module).collect();
+/// assert_eq!("examples/javascript/example_module.js", modules[0].filename());
+/// ```
+#[macro_export]
+macro_rules! include_dir_modules {
+    ($directory:literal, [$($file:literal),* $(,)?]) => {
+        [$($crate::include_module!(concat!($directory, "/", $file))),*]
+    };
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
 /// Represents a pice of javascript for execution.
 /// Must be ESM formatted
@@ -149,6 +216,64 @@ impl Module {
         Ok(files)
     }
 
+    /// Load every js/ts file below a directory whose path matches a pattern, including
+    /// files in subdirectories, sorted by path
+    /// Fails if any of the matching files cannot be loaded
+    ///
+    /// Each module's filename is the directory joined with its path inside it, so relative
+    /// imports between the modules resolve as they would on disk
+    ///
+    /// # Arguments
+    /// * `directory` - A string representing the target directory
+    /// * `pattern` - Matched against each file's path relative to `directory`, using `/` as the separator
+    ///   `*` matches any sequence of characters, including `/` - so `*.js` matches js files at any depth
+    ///
+    /// # Returns
+    /// A `Result` containing a vec of loaded `Module` instances or an `std::io::Error` if there
+    /// are issues reading a file.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let modules = Module::from_directory("src/ext", "rustyscript/*.js")?;
+    /// assert!(modules.iter().all(|m| m.filename().starts_with("src/ext/rustyscript/")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_directory(directory: &str, pattern: &str) -> Result<Vec<Self>, std::io::Error> {
+        let mut paths = Vec::new();
+        collect_files(Path::new(directory), &mut paths)?;
+        paths.sort();
+
+        let mut files = Vec::new();
+        for path in paths {
+            let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+            if !["js", "ts"].contains(&extension) {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(directory) else {
+                continue;
+            };
+            let relative: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            if !crate::import_policy::glob(pattern, &relative.join("/")) {
+                continue;
+            }
+
+            if let Some(filename) = path.to_str() {
+                files.push(Self::load(filename)?);
+            }
+        }
+
+        Ok(files)
+    }
+
     /// Returns the filename of the module.
     ///
     /// # Returns
@@ -182,6 +307,37 @@ impl Module {
     pub fn contents(&self) -> &str {
         &self.contents
     }
+
+    /// Transpiles the module, and compiles it to a V8 code cache, without evaluating it
+    /// Uses a temporary runtime - use `Runtime::compile_module` to compile several modules
+    ///
+    /// # Returns
+    /// The compiled module, which can be loaded with `Runtime::load_compiled`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::Module;
+    ///
+    /// let compiled = Module::new("module.js", "export const value = 1;").compile().unwrap();
+    /// assert!(!compiled.code_cache().is_empty());
+    /// ```
+    pub fn compile(&self) -> Result<crate::CompiledModule, crate::Error> {
+        crate::Runtime::new(Default::default())?.compile_module(self)
+    }
+}
+
+/// Add every file below `directory` to `paths`
+fn collect_files(directory: &Path, paths: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -202,10 +358,46 @@ mod test_module {
         assert_eq!(module.filename(), "src/ext/rustyscript/rustyscript.js");
     }
 
+    #[test]
+    fn test_include_module() {
+        const MODULE: StaticModule = crate::include_module!("src/ext/rustyscript/rustyscript.js");
+        let module = Module::load("src/ext/rustyscript/rustyscript.js").unwrap();
+        assert_eq!(module, MODULE.to_module());
+
+        let modules = crate::include_dir_modules!(
+            "examples/javascript",
+            ["example_module.js", "multiple_modules.js",]
+        );
+        assert_eq!(
+            "examples/javascript/multiple_modules.js",
+            modules[1].filename()
+        );
+        assert!(modules[0].contents().contains("saskatoonberries"));
+    }
+
     #[test]
     fn test_load_dir() {
         let modules =
             Module::load_dir("src/ext/rustyscript").expect("Failed to load modules from directory");
         assert!(modules.len() > 0);
     }
+
+    #[test]
+    fn test_from_directory() {
+        let modules = Module::from_directory("src/ext", "*.js")
+            .expect("Failed to load modules from directory");
+        assert!(modules
+            .iter()
+            .any(|m| m.filename() == "src/ext/rustyscript/rustyscript.js"));
+        assert!(modules
+            .windows(2)
+            .all(|w| w[0].filename() < w[1].filename()));
+
+        let modules = Module::from_directory("src/ext", "rustyscript/*")
+            .expect("Failed to load modules from directory");
+        assert!(!modules.is_empty());
+        assert!(modules
+            .iter()
+            .all(|m| m.filename().starts_with("src/ext/rustyscript/")));
+    }
 }
@@ -1,7 +1,8 @@
 use deno_core::v8;
 use deno_core::ModuleId;
+use std::collections::HashMap;
 
-use crate::Module;
+use crate::{traits::ToModuleSpecifier, Error, Module, Runtime};
 
 /// Represents a loaded instance of a module within a runtime
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
@@ -51,4 +52,71 @@ impl ModuleHandle {
     pub fn entrypoint(&self) -> &Option<v8::Global<v8::Function>> {
         &self.entrypoint
     }
+
+    /// List the values exported by this module, sorted by name
+    /// See `Runtime::module_exports`
+    pub fn exports(&self, runtime: &mut Runtime) -> Result<Vec<ExportInfo>, Error> {
+        runtime.module_exports(self)
+    }
+}
+
+/// The kind of value exported by a module
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ExportKind {
+    /// A function, including async functions
+    Function,
+
+    /// A class
+    Class,
+
+    /// Any other value
+    Value,
+}
+
+/// Describes a single export of a module
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportInfo {
+    /// The name of the export - `default` for a default export
+    pub name: String,
+
+    /// The kind of value exported
+    pub kind: ExportKind,
+
+    /// The number of declared parameters, for functions and classes
+    /// Does not count rest parameters, or parameters after the first with a default value
+    pub arity: Option<u32>,
+}
+
+/// Handles for every module loaded by `Runtime::load_modules_with_handles`, keyed by module specifier
+///
+/// Only the main handle carries the entrypoint registered while loading
+#[derive(Clone, Debug, Default)]
+pub struct LoadResult {
+    main: ModuleHandle,
+    handles: HashMap<String, ModuleHandle>,
+}
+
+impl LoadResult {
+    pub(crate) fn new(main: ModuleHandle, handles: HashMap<String, ModuleHandle>) -> Self {
+        Self { main, handles }
+    }
+
+    /// Return the handle to the main module
+    pub fn main(&self) -> &ModuleHandle {
+        &self.main
+    }
+
+    /// Return the handle to a loaded module
+    /// Accepts either the module's specifier, or the filename it was loaded with
+    pub fn get(&self, specifier: &str) -> Option<&ModuleHandle> {
+        self.handles.get(specifier).or_else(|| {
+            let specifier = specifier.to_module_specifier().ok()?;
+            self.handles.get(specifier.as_str())
+        })
+    }
+
+    /// Iterate over the specifiers and handles of every loaded module
+    pub fn handles(&self) -> impl Iterator<Item = (&str, &ModuleHandle)> {
+        self.handles.iter().map(|(k, v)| (k.as_str(), v))
+    }
 }
@@ -1,37 +1,264 @@
 use crate::{
     cache_provider::{ClonableSource, ModuleCacheProvider},
-    transpiler,
+    transpiler, CompiledModule, Error, ImportPolicy, ResolverHook,
 };
 use deno_core::{
     anyhow::{self, anyhow},
     futures::FutureExt,
-    ModuleLoadResponse, ModuleLoader, ModuleSource, ModuleSourceCode, ModuleSpecifier, ModuleType,
+    v8, CustomModuleEvaluationKind, FastString, ModuleLoadResponse, ModuleLoader, ModuleSource,
+    ModuleSourceCode, ModuleSpecifier, ModuleType, RequestedModuleType, SourceCodeCacheInfo,
     SourceMapGetter,
 };
+use std::borrow::Cow;
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
     rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 type SourceMapCache = HashMap<String, (String, Vec<u8>)>;
 
+/// In-memory module sources, keyed by module specifier
+/// Imports of mounted modules are served from here instead of the filesystem
+type VirtualFs = HashMap<String, String>;
+
+/// Precompiled modules, keyed by module specifier
+/// Served with their code cache, so that V8 can skip compiling them
+type CompiledModules = HashMap<String, Rc<CompiledModule>>;
+
+/// Options for resolving `npm:` specifiers
+///
+/// Packages are fetched as ES modules from a CDN that serves npm packages in that form,
+/// such as esm.sh. `npm:lodash-es@4/fp` is loaded from `{registry_url}/lodash-es@4/fp`
+#[cfg(feature = "npm")]
+#[derive(Debug, Clone)]
+pub struct NpmOptions {
+    /// Base URL of the registry to load packages from
+    pub registry_url: String,
+
+    /// Optional directory to cache downloaded package sources in
+    /// Cached sources are used instead of the registry, including by later runs
+    /// Each source is stored in a file named after a hash of its URL
+    pub cache_dir: Option<std::path::PathBuf>,
+}
+
+#[cfg(feature = "npm")]
+impl Default for NpmOptions {
+    fn default() -> Self {
+        Self {
+            registry_url: "https://esm.sh".to_string(),
+            cache_dir: None,
+        }
+    }
+}
+
+#[cfg(feature = "npm")]
+impl NpmOptions {
+    /// Get the registry URL for an `npm:` specifier
+    fn resolve(&self, specifier: &ModuleSpecifier) -> Result<ModuleSpecifier, anyhow::Error> {
+        let package = specifier.path().trim_start_matches('/');
+        if package.is_empty() {
+            return Err(anyhow!("missing package name in {specifier}"));
+        }
+
+        let registry = self.registry_url.trim_end_matches('/');
+        Ok(ModuleSpecifier::parse(&format!("{registry}/{package}"))?)
+    }
+
+    /// Get the cache path for a module loaded from the registry, if caching is enabled
+    ///
+    /// Named after a hash of the URL, so that distinct URLs never share a file, and a
+    /// package's path can never be both a file and a directory
+    ///
+    /// Only URLs with the registry's origin, under its path, are cached - compared as parsed URLs,
+    /// so that `https://registry.example.evil` is not taken for `https://registry.example`
+    fn cache_path(&self, specifier: &ModuleSpecifier) -> Option<std::path::PathBuf> {
+        use sha1::{Digest, Sha1};
+
+        let cache_dir = self.cache_dir.as_ref()?;
+        let registry = ModuleSpecifier::parse(&self.registry_url).ok()?;
+        if specifier.origin() != registry.origin() {
+            return None;
+        }
+
+        let base = registry.path().trim_end_matches('/');
+        let rest = specifier.path().strip_prefix(base)?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        let hash = Sha1::digest(specifier.as_str().as_bytes());
+        let name: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+        Some(cache_dir.join(name))
+    }
+
+    /// Store a module's source in the cache
+    /// The cache is only an optimization, so failing to write to it does not fail the load
+    async fn write_cache(path: &std::path::Path, code: &[u8]) {
+        // Written to a temporary file first, so a later run never reads a partial source
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&temp, code).await?;
+            tokio::fs::rename(&temp, path).await
+        };
+        if written.await.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+    }
+}
+
+/// A step in loading the modules imported by a module, reported to the callback given to
+/// `Runtime::load_module_with_progress`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadProgress {
+    /// An import was found, and the module is about to be loaded
+    Discovered(ModuleSpecifier),
+
+    /// A module's source was fetched from the network, the filesystem or memory
+    Fetched {
+        /// The module fetched
+        specifier: ModuleSpecifier,
+
+        /// Size of the module's source
+        bytes: usize,
+    },
+
+    /// A module was transpiled - or found in the cache - and is ready to be evaluated
+    Transpiled(ModuleSpecifier),
+}
+
+/// A handle used to cancel a module load, which can be shared with other threads
+/// Once cancelled, the load fails with `Error::Cancelled` as soon as it reaches another import,
+/// or within a few milliseconds if it is waiting on a download
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// How often a pending fetch checks whether it was cancelled
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Create a new handle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the load
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if `cancel` was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the handle is cancelled
+    async fn cancelled(&self) {
+        while !self.is_cancelled() {
+            tokio::time::sleep(Self::POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Receives progress for a module load in progress, and may cancel it
+pub(crate) struct LoadObserver {
+    pub progress: Rc<dyn Fn(&LoadProgress)>,
+    pub cancel: CancelHandle,
+}
+
+impl LoadObserver {
+    fn check(&self, specifier: &ModuleSpecifier) -> Result<(), anyhow::Error> {
+        if self.cancel.is_cancelled() {
+            Err(Error::Cancelled(format!("loading {specifier}")).into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[derive(Clone)]
 struct InnerRustyLoader {
     cache_provider: Rc<Option<Box<dyn ModuleCacheProvider>>>,
     fs_whlist: Rc<RefCell<HashSet<String>>>,
     source_map_cache: Rc<RefCell<SourceMapCache>>,
+    virtual_fs: Rc<RefCell<VirtualFs>>,
+    compiled: Rc<RefCell<CompiledModules>>,
+    observer: Rc<RefCell<Option<Rc<LoadObserver>>>>,
+    import_policy: ImportPolicy,
+    resolver_hooks: Rc<Vec<Box<dyn ResolverHook>>>,
+    disable_wasm: bool,
+
+    #[cfg(feature = "npm")]
+    npm_options: NpmOptions,
 }
 
 impl InnerRustyLoader {
-    fn new(cache_provider: Option<Box<dyn ModuleCacheProvider>>) -> Self {
+    fn new(options: LoaderOptions) -> Self {
         Self {
-            cache_provider: Rc::new(cache_provider),
+            cache_provider: Rc::new(options.cache_provider),
             fs_whlist: Rc::new(RefCell::new(HashSet::new())),
             source_map_cache: Rc::new(RefCell::new(SourceMapCache::new())),
+            virtual_fs: Rc::new(RefCell::new(VirtualFs::new())),
+            compiled: Rc::new(RefCell::new(CompiledModules::new())),
+            observer: Rc::new(RefCell::new(None)),
+            import_policy: options.import_policy,
+            resolver_hooks: Rc::new(options.resolver_hooks),
+            disable_wasm: options.disable_wasm,
+
+            #[cfg(feature = "npm")]
+            npm_options: options.npm_options,
         }
     }
 
+    fn mount(&self, specifier: &ModuleSpecifier, source: &str) {
+        self.virtual_fs
+            .borrow_mut()
+            .insert(specifier.to_string(), source.to_string());
+    }
+
+    fn mounted_source(&self, specifier: &ModuleSpecifier) -> Option<String> {
+        self.virtual_fs.borrow().get(specifier.as_str()).cloned()
+    }
+
+    fn mount_compiled(&self, specifier: &ModuleSpecifier, module: &CompiledModule) {
+        if let Some(source_map) = module.source_map() {
+            self.source_map_cache.borrow_mut().insert(
+                specifier.to_string(),
+                (module.module().contents().to_string(), source_map.to_vec()),
+            );
+        }
+
+        self.compiled
+            .borrow_mut()
+            .insert(specifier.to_string(), Rc::new(module.clone()));
+    }
+
+    fn compiled_source(&self, specifier: &ModuleSpecifier) -> Option<ModuleSource> {
+        let module = self.compiled.borrow().get(specifier.as_str()).cloned()?;
+
+        let mut hasher = std::hash::DefaultHasher::new();
+        std::hash::Hash::hash(module.code(), &mut hasher);
+        let code_cache = SourceCodeCacheInfo {
+            hash: std::hash::Hasher::finish(&hasher),
+            data: Some(module.code_cache().to_vec().into()),
+        };
+
+        Some(ModuleSource::new(
+            ModuleType::JavaScript,
+            ModuleSourceCode::String(module.code().to_string().into()),
+            specifier,
+            Some(code_cache),
+        ))
+    }
+
     fn whitelist_add(&self, specifier: &str) {
         self.fs_whlist.borrow_mut().insert(specifier.to_string());
     }
@@ -43,25 +270,71 @@ impl InnerRustyLoader {
     async fn load<F, Fut>(
         &self,
         module_specifier: ModuleSpecifier,
+        requested_module_type: RequestedModuleType,
         handler: F,
     ) -> Result<ModuleSource, deno_core::error::AnyError>
     where
         F: Fn(ModuleSpecifier) -> Fut,
-        Fut: std::future::Future<Output = Result<String, deno_core::error::AnyError>>,
+        Fut: std::future::Future<Output = Result<Vec<u8>, deno_core::error::AnyError>>,
     {
+        let module_type = module_type(&module_specifier, &requested_module_type)?;
+
+        let observer = self.observer.borrow().clone();
+        let report = |progress: LoadProgress| {
+            if let Some(observer) = &observer {
+                (observer.progress)(&progress);
+            }
+        };
+        if let Some(observer) = &observer {
+            observer.check(&module_specifier)?;
+        }
+        report(LoadProgress::Discovered(module_specifier.clone()));
+
+        // Text and bytes imports are not cached, since the cache is keyed by specifier alone
         let cache_provider = self.cache_provider.clone();
-        let cache_provider = cache_provider.as_ref().as_ref().map(|p| p.as_ref());
+        let cache_provider = cache_provider
+            .as_ref()
+            .as_ref()
+            .map(|p| p.as_ref())
+            .filter(|_| !matches!(module_type, ModuleType::Other(_)));
         match cache_provider.map(|p| p.get(&module_specifier)) {
-            Some(Some(source)) => Ok(source),
+            Some(Some(source)) => {
+                report(LoadProgress::Transpiled(module_specifier));
+                Ok(source)
+            }
             _ => {
-                let module_type = if module_specifier.path().ends_with(".json") {
-                    ModuleType::Json
-                } else {
-                    ModuleType::JavaScript
+                let code = match &observer {
+                    Some(observer) => tokio::select! {
+                        code = handler(module_specifier.clone()) => code?,
+                        () = observer.cancel.cancelled() => {
+                            return Err(observer.check(&module_specifier).unwrap_err());
+                        }
+                    },
+                    None => handler(module_specifier.clone()).await?,
                 };
+                report(LoadProgress::Fetched {
+                    specifier: module_specifier.clone(),
+                    bytes: code.len(),
+                });
 
-                let code = handler(module_specifier.clone()).await?;
+                // Data imports are passed through as they are, without transpiling
+                if let ModuleType::Other(kind) = &module_type {
+                    let code = if kind == "bytes" {
+                        ModuleSourceCode::Bytes(code.into_boxed_slice().into())
+                    } else {
+                        ModuleSourceCode::String(utf8(&module_specifier, code)?.into())
+                    };
+                    return Ok(ModuleSource::new(
+                        module_type,
+                        code,
+                        &module_specifier,
+                        None,
+                    ));
+                }
+
+                let code = utf8(&module_specifier, code)?;
                 let (tcode, source_map) = transpiler::transpile(&module_specifier, &code)?;
+                report(LoadProgress::Transpiled(module_specifier.clone()));
 
                 let source = ModuleSource::new(
                     module_type,
@@ -89,10 +362,102 @@ impl InnerRustyLoader {
     }
 }
 
+/// The type of module to create for an import, from its import attributes
+///
+/// `with { type: "json" }` imports a JSON file as its parsed value, and JSON files
+/// cannot be imported without it
+/// `with { type: "text" }` imports a file's contents as a string, and
+/// `with { type: "bytes" }` as a `Uint8Array`
+fn module_type(
+    specifier: &ModuleSpecifier,
+    requested: &RequestedModuleType,
+) -> Result<ModuleType, anyhow::Error> {
+    match requested {
+        RequestedModuleType::Json => Ok(ModuleType::Json),
+        RequestedModuleType::Other(kind) if kind == "text" || kind == "bytes" => {
+            Ok(ModuleType::Other(kind.clone()))
+        }
+        RequestedModuleType::Other(kind) => {
+            Err(anyhow!("unsupported import type `{kind}`: {specifier}"))
+        }
+        RequestedModuleType::None if specifier.path().ends_with(".json") => Err(anyhow!(
+            "JSON modules must be imported with `with {{ type: \"json\" }}`: {specifier}"
+        )),
+        RequestedModuleType::None => Ok(ModuleType::JavaScript),
+    }
+}
+
+/// Fetch a remote module
+/// Redirects are followed only to URLs the import policy allows
+#[cfg(feature = "url_import")]
+async fn fetch(
+    url: ModuleSpecifier,
+    policy: ImportPolicy,
+) -> Result<reqwest::Response, anyhow::Error> {
+    const MAX_REDIRECTS: usize = 10;
+    let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+        if let Err(e) = policy.check(attempt.url()) {
+            attempt.error(e)
+        } else if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    });
+
+    let client = reqwest::Client::builder().redirect(redirects).build()?;
+    Ok(client.get(url).send().await?.error_for_status()?)
+}
+
+/// Decode a module's source as UTF-8
+fn utf8(specifier: &ModuleSpecifier, code: Vec<u8>) -> Result<String, anyhow::Error> {
+    String::from_utf8(code).map_err(|_| anyhow!("`{specifier}` is not valid UTF-8"))
+}
+
+/// Creates the value of `text` and `bytes` imports
+/// Passed to deno as the runtime's `custom_module_evaluation_cb`
+pub fn evaluate_data_module(
+    scope: &mut v8::HandleScope,
+    module_type: Cow<'_, str>,
+    module_name: &FastString,
+    code: ModuleSourceCode,
+) -> Result<CustomModuleEvaluationKind, anyhow::Error> {
+    let value: v8::Local<v8::Value> = match (module_type.as_ref(), code) {
+        ("text", ModuleSourceCode::String(code)) => v8::String::new(scope, code.as_str())
+            .ok_or_else(|| anyhow!("`{}` is too large", module_name.as_str()))?
+            .into(),
+        ("bytes", ModuleSourceCode::Bytes(code)) => {
+            let bytes = code.as_bytes().to_vec();
+            let len = bytes.len();
+            let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+            let buffer = v8::ArrayBuffer::with_backing_store(scope, &store);
+            v8::Uint8Array::new(scope, buffer, 0, len)
+                .ok_or_else(|| anyhow!("`{}` is too large", module_name.as_str()))?
+                .into()
+        }
+        (kind, _) => return Err(anyhow!("unsupported import type `{kind}`")),
+    };
+
+    Ok(CustomModuleEvaluationKind::Synthetic(v8::Global::new(
+        scope, value,
+    )))
+}
+
+/// Options for the module loader
+#[derive(Default)]
+pub struct LoaderOptions {
+    pub cache_provider: Option<Box<dyn ModuleCacheProvider>>,
+    pub import_policy: ImportPolicy,
+    pub resolver_hooks: Vec<Box<dyn ResolverHook>>,
+    pub disable_wasm: bool,
+
+    #[cfg(feature = "npm")]
+    pub npm_options: NpmOptions,
+}
+
 pub struct RustyLoader {
     inner: Rc<InnerRustyLoader>,
 }
-#[allow(unreachable_code)]
 impl ModuleLoader for RustyLoader {
     fn resolve(
         &self,
@@ -100,28 +465,60 @@ impl ModuleLoader for RustyLoader {
         referrer: &str,
         _kind: deno_core::ResolutionKind,
     ) -> Result<ModuleSpecifier, anyhow::Error> {
+        let specifier = crate::resolver_hook::rewrite_specifier(
+            &self.inner.resolver_hooks,
+            specifier,
+            referrer,
+        );
+        let specifier = specifier.as_str();
+
         let url = deno_core::resolve_import(specifier, referrer)?;
         if referrer == "." {
             self.whitelist_add(url.as_str());
         }
 
+        if self.inner.disable_wasm && crate::wasm::is_wasm(&url) {
+            return Err(anyhow!("WebAssembly modules are disabled: {specifier}"));
+        }
+
         // We check permissions first
         match url.scheme() {
             // Remote fetch imports
             "https" | "http" => {
-                #[cfg(not(feature = "url_import"))]
-                return Err(anyhow!("web imports are not allowed here: {specifier}"));
+                if cfg!(not(feature = "url_import")) {
+                    return Err(anyhow!("web imports are not allowed here: {specifier}"));
+                }
+
+                self.inner.import_policy.check(&url)?;
             }
 
             // Dynamic FS imports
-            "file" =>
-            {
+            "file" => {
                 #[cfg(not(feature = "fs_import"))]
-                if !self.whitelist_has(url.as_str()) {
+                if !self.whitelist_has(url.as_str()) && self.inner.mounted_source(&url).is_none() {
                     return Err(anyhow!("requested module is not loaded: {specifier}"));
                 }
             }
 
+            // Npm package imports, served from the configured registry
+            "npm" => {
+                #[cfg(not(feature = "npm"))]
+                return Err(anyhow!(
+                    "npm imports require the `npm` feature: {specifier}"
+                ));
+
+                #[cfg(feature = "npm")]
+                {
+                    let url = self.inner.npm_options.resolve(&url)?;
+                    self.inner.import_policy.check(&url)?;
+                    return Ok(url);
+                }
+            }
+
+            // Node built-ins provided by extensions
+            #[cfg(feature = "fs")]
+            "node" if matches!(url.path(), "fs/promises") => {}
+
             _ if specifier.starts_with("ext:") => {
                 // Extension import - allow
             }
@@ -141,35 +538,108 @@ impl ModuleLoader for RustyLoader {
         module_specifier: &ModuleSpecifier,
         _maybe_referrer: Option<&ModuleSpecifier>,
         _is_dyn_import: bool,
-        _requested_module_type: deno_core::RequestedModuleType,
+        requested_module_type: RequestedModuleType,
     ) -> deno_core::ModuleLoadResponse {
         let inner = self.inner.clone();
         let module_specifier = module_specifier.clone();
+        if requested_module_type == RequestedModuleType::None {
+            if let Some(source) = inner.compiled_source(&module_specifier) {
+                return ModuleLoadResponse::Sync(Ok(source));
+            }
+        }
+
         // We check permissions first
         match module_specifier.scheme() {
             // Remote fetch imports
             #[cfg(feature = "url_import")]
             "https" | "http" => ModuleLoadResponse::Async(
                 async move {
+                    #[cfg(feature = "npm")]
+                    let cache_path = inner.npm_options.cache_path(&module_specifier);
+                    #[cfg(not(feature = "npm"))]
+                    let cache_path: Option<std::path::PathBuf> = None;
+                    let policy = inner.import_policy.clone();
+
                     inner
-                        .load(module_specifier, |specifier| async move {
-                            let response = reqwest::get(specifier).await?;
-                            Ok(response.text().await?)
+                        .load(module_specifier, requested_module_type, |specifier| {
+                            let cache_path = cache_path.clone();
+                            let policy = policy.clone();
+                            async move {
+                                if let Some(path) = &cache_path {
+                                    if let Ok(code) = tokio::fs::read(path).await {
+                                        return Ok(code);
+                                    }
+                                }
+
+                                let is_wasm = crate::wasm::is_wasm(&specifier);
+                                let response = fetch(specifier, policy).await?;
+                                let code = if is_wasm {
+                                    crate::wasm::wrap(&response.bytes().await?)?.into_bytes()
+                                } else {
+                                    response.bytes().await?.to_vec()
+                                };
+
+                                #[cfg(feature = "npm")]
+                                if let Some(path) = &cache_path {
+                                    NpmOptions::write_cache(path, &code).await;
+                                }
+                                Ok(code)
+                            }
                         })
                         .await
                 }
                 .boxed_local(),
             ),
 
+            // Mounted in-memory modules
+            "file" if inner.mounted_source(&module_specifier).is_some() => {
+                ModuleLoadResponse::Async(
+                    async move {
+                        let loader = inner.clone();
+                        inner
+                            .load(module_specifier, requested_module_type, |specifier| {
+                                let source = loader.mounted_source(&specifier);
+                                async move {
+                                    source
+                                        .map(String::into_bytes)
+                                        .ok_or_else(|| anyhow!("`{specifier}` is not mounted."))
+                                }
+                            })
+                            .await
+                    }
+                    .boxed_local(),
+                )
+            }
+
             // FS imports
             "file" => ModuleLoadResponse::Async(
                 async move {
                     inner
-                        .load(module_specifier, |specifier| async move {
-                            let path = specifier
-                                .to_file_path()
-                                .map_err(|_| anyhow!("`{specifier}` is not a valid file URL."))?;
-                            Ok(tokio::fs::read_to_string(path).await?)
+                        .load(
+                            module_specifier,
+                            requested_module_type,
+                            |specifier| async move {
+                                let path = specifier.to_file_path().map_err(|_| {
+                                    anyhow!("`{specifier}` is not a valid file URL.")
+                                })?;
+                                let code = tokio::fs::read(path).await?;
+                                if crate::wasm::is_wasm(&specifier) {
+                                    return Ok(crate::wasm::wrap(&code)?.into_bytes());
+                                }
+                                Ok(code)
+                            },
+                        )
+                        .await
+                }
+                .boxed_local(),
+            ),
+
+            #[cfg(feature = "fs")]
+            "node" if module_specifier.path() == "fs/promises" => ModuleLoadResponse::Async(
+                async move {
+                    inner
+                        .load(module_specifier, requested_module_type, |_| async move {
+                            Ok(crate::ext::fs::NODE_FS_PROMISES.as_bytes().to_vec())
                         })
                         .await
                 }
@@ -188,8 +658,15 @@ impl ModuleLoader for RustyLoader {
 #[allow(dead_code)]
 impl RustyLoader {
     pub fn new(cache_provider: Option<Box<dyn ModuleCacheProvider>>) -> Self {
+        Self::with_options(LoaderOptions {
+            cache_provider,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_options(options: LoaderOptions) -> Self {
         Self {
-            inner: Rc::new(InnerRustyLoader::new(cache_provider)),
+            inner: Rc::new(InnerRustyLoader::new(options)),
         }
     }
 
@@ -200,6 +677,21 @@ impl RustyLoader {
     pub fn whitelist_has(&self, specifier: &str) -> bool {
         self.inner.whitelist_has(specifier)
     }
+
+    /// Make a module's source available to imports, without it existing on the filesystem
+    pub fn mount(&self, specifier: &ModuleSpecifier, source: &str) {
+        self.inner.mount(specifier, source);
+    }
+
+    /// Serve a precompiled module to imports and loads of its specifier
+    pub fn mount_compiled(&self, specifier: &ModuleSpecifier, module: &CompiledModule) {
+        self.inner.mount_compiled(specifier, module);
+    }
+
+    /// Set or clear the observer notified of each module loaded
+    pub(crate) fn observe(&self, observer: Option<LoadObserver>) {
+        *self.inner.observer.borrow_mut() = observer.map(Rc::new);
+    }
 }
 
 impl SourceMapGetter for RustyLoader {
@@ -274,4 +766,116 @@ mod test {
             _ => panic!("Unexpected response"),
         }
     }
+
+    #[tokio::test]
+    #[cfg(feature = "url_import")]
+    async fn test_fetch_redirect() {
+        use std::io::{Read, Write};
+
+        // Redirects every request to a host the policy denies
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 302 Found\r\nLocation: http://denied.invalid/a.js\r\nContent-Length: 0\r\n\r\n",
+                );
+            }
+        });
+
+        let url = ModuleSpecifier::parse(&format!("http://127.0.0.1:{port}/a.js")).unwrap();
+        let policy = ImportPolicy::AllowList(vec!["http://127.0.0.1:*".into()]);
+        let e = fetch(url, policy).await.unwrap_err();
+        assert!(format!("{e:?}").contains("denied.invalid"), "{e:?}");
+    }
+
+    #[test]
+    #[cfg(feature = "npm")]
+    fn test_npm_resolve() {
+        let loader = RustyLoader::with_options(LoaderOptions {
+            npm_options: NpmOptions {
+                registry_url: "https://registry.example/".to_string(),
+                cache_dir: Some("/cache".into()),
+            },
+            ..Default::default()
+        });
+
+        let url = loader
+            .resolve(
+                "npm:@scope/pkg@1.2/sub",
+                "file:///main.js",
+                deno_core::ResolutionKind::Import,
+            )
+            .expect("Could not resolve npm specifier");
+        assert_eq!("https://registry.example/@scope/pkg@1.2/sub", url.as_str());
+
+        let options = &loader.inner.npm_options;
+        assert_eq!(
+            Some(std::path::PathBuf::from(
+                "/cache/f4cd5d7837f5bd74b8d594956787cec84d206d67"
+            )),
+            options.cache_path(&url)
+        );
+
+        let url = ModuleSpecifier::parse("https://registry.example/../pkg?target=es2022").unwrap();
+        assert_eq!(
+            Some(std::path::PathBuf::from(
+                "/cache/17abc2608f7bd5c5b5d74ffca152955a8bc94568"
+            )),
+            options.cache_path(&url)
+        );
+
+        // URLs that used to map to the same file no longer do
+        let url = ModuleSpecifier::parse("https://registry.example/pkg_target_es2022").unwrap();
+        assert_ne!(
+            Some(std::path::PathBuf::from(
+                "/cache/17abc2608f7bd5c5b5d74ffca152955a8bc94568"
+            )),
+            options.cache_path(&url)
+        );
+
+        // Other origins are never cached, even if the URL starts with the registry's
+        for url in [
+            "https://other.example/pkg",
+            "https://registry.example.evil/pkg",
+            "https://registry.example@evil.example/pkg",
+            "https://registry.example:8443/pkg",
+            "http://registry.example/pkg",
+        ] {
+            let url = ModuleSpecifier::parse(url).unwrap();
+            assert_eq!(None, options.cache_path(&url), "{url}");
+        }
+
+        // Nor are paths outside a registry's base path
+        let options = NpmOptions {
+            registry_url: "https://registry.example/npm".to_string(),
+            cache_dir: Some("/cache".into()),
+        };
+        let url = ModuleSpecifier::parse("https://registry.example/npm/pkg").unwrap();
+        assert!(options.cache_path(&url).is_some());
+        let url = ModuleSpecifier::parse("https://registry.example/npmevil/pkg").unwrap();
+        assert_eq!(None, options.cache_path(&url));
+
+        loader
+            .resolve("npm:", "file:///main.js", deno_core::ResolutionKind::Import)
+            .expect_err("Empty package name should not resolve");
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "npm")]
+    async fn test_npm_cache_write() {
+        let dir = std::env::temp_dir().join(format!("rustyscript_test_npm_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("cached");
+        NpmOptions::write_cache(&path, b"export default 1;").await;
+        assert_eq!(b"export default 1;".to_vec(), std::fs::read(&path).unwrap());
+
+        // A cache that cannot be written to is skipped, rather than failing the load
+        NpmOptions::write_cache(&path.join("nested"), b"export default 2;").await;
+        assert!(!path.join("nested").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -102,6 +102,22 @@ impl ModuleWrapper {
             .call_function(Some(&self.module_context), name, args)
     }
 
+    /// Calls the module's `export default` function with the given arguments and deserializes the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments to pass to the function.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the deserialized result of type `T` on success or an `Error` on failure.
+    pub fn call_default<T>(&mut self, args: &[serde_json::Value]) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.runtime.call_default_export(&self.module_context, args)
+    }
+
     /// Calls a function using the module's runtime that was previously stored
     /// as a JsFunction object
     ///
@@ -181,6 +197,18 @@ mod test_runtime {
         assert_eq!(4, value);
     }
 
+    #[test]
+    fn test_call_default() {
+        let module = Module::new("test.js", "export default (a) => a * 2;");
+
+        let mut module = ModuleWrapper::new_from_module(&module, RuntimeOptions::default())
+            .expect("Could not create wrapper");
+        let value: usize = module
+            .call_default(json_args!(2))
+            .expect("Could not call default export");
+        assert_eq!(4, value);
+    }
+
     #[test]
     fn test_get() {
         let module = Module::new(
@@ -0,0 +1,269 @@
+//! Pre-baked security profiles, selecting which capabilities scripts are given
+//! A profile only takes access away - a capability it allows is still limited by the
+//! permissions granted in the rest of the runtime's options
+use crate::{ImportPolicy, RuntimeOptions};
+
+/// The capabilities a [Profile] grants to scripts
+/// A disabled capability overrides the matching runtime options; an enabled one leaves them as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// Remote imports, subject to `RuntimeOptions::import_policy`
+    pub remote_imports: bool,
+
+    /// Outbound requests through `fetch` and `WebSocket`
+    pub network: bool,
+
+    /// The `WebAssembly` global, and `.wasm` imports
+    pub wasm: bool,
+
+    /// Files, subject to `ExtensionOptions::fs_permissions`
+    pub filesystem: bool,
+
+    /// Child processes, subject to `ExtensionOptions::process_permissions`
+    pub processes: bool,
+
+    /// Database files, subject to `ExtensionOptions::sqlite_permissions`
+    /// The runtime's in-memory database remains available
+    pub databases: bool,
+}
+
+/// A coherent set of capabilities, selected with `RuntimeOptions::profile`
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Profile, Runtime, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// // Pure computation, but without WebAssembly
+/// let profile = Profile::builder().wasm(false).build();
+///
+/// let mut runtime = Runtime::builder().profile(profile).build()?;
+/// let kind: String = runtime.eval("typeof WebAssembly")?;
+/// assert_eq!("undefined", kind);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// Computation only - no remote imports, network, files, processes or databases
+    /// WebAssembly remains available
+    Pure,
+
+    /// As `Pure`, but with remote imports and network access
+    WebLight,
+
+    /// Everything the runtime's options grant
+    Trusted,
+
+    /// A custom set of capabilities, built with [Profile::builder]
+    Custom(Capabilities),
+}
+
+impl Profile {
+    /// Start building a custom profile, from the capabilities of `Profile::Pure`
+    pub fn builder() -> ProfileBuilder {
+        ProfileBuilder(Self::Pure.capabilities())
+    }
+
+    /// The capabilities this profile grants
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Self::Pure => Capabilities {
+                remote_imports: false,
+                network: false,
+                wasm: true,
+                filesystem: false,
+                processes: false,
+                databases: false,
+            },
+
+            Self::WebLight => Capabilities {
+                remote_imports: true,
+                network: true,
+                ..Self::Pure.capabilities()
+            },
+
+            Self::Trusted => Capabilities {
+                remote_imports: true,
+                network: true,
+                wasm: true,
+                filesystem: true,
+                processes: true,
+                databases: true,
+            },
+
+            Self::Custom(capabilities) => *capabilities,
+        }
+    }
+
+    /// Override the options for each capability this profile does not grant
+    pub(crate) fn apply(&self, options: &mut RuntimeOptions) {
+        let capabilities = self.capabilities();
+
+        if !capabilities.remote_imports {
+            options.import_policy = ImportPolicy::Offline;
+        }
+
+        if !capabilities.wasm {
+            options.disable_wasm = true;
+        }
+
+        if !capabilities.network {
+            #[cfg(feature = "web")]
+            {
+                options.extension_options.web.fetch_handler = Some(std::rc::Rc::new(DenyNetwork));
+            }
+
+            #[cfg(feature = "websocket")]
+            {
+                options.extension_options.websocket_policy = Some(std::rc::Rc::new(DenyNetwork));
+            }
+        }
+
+        #[cfg(feature = "fs")]
+        if !capabilities.filesystem {
+            options.extension_options.fs_permissions = Default::default();
+        }
+
+        #[cfg(feature = "process")]
+        if !capabilities.processes {
+            options.extension_options.process_permissions = Default::default();
+        }
+
+        #[cfg(feature = "sqlite")]
+        if !capabilities.databases {
+            options.extension_options.sqlite_permissions = Default::default();
+        }
+    }
+}
+
+/// Builds a [Profile::Custom]
+/// Start from `Profile::builder`, or from one of the presets with `ProfileBuilder::from`
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileBuilder(Capabilities);
+
+impl ProfileBuilder {
+    /// Allow remote imports, subject to the runtime's import policy
+    pub fn remote_imports(mut self, allow: bool) -> Self {
+        self.0.remote_imports = allow;
+        self
+    }
+
+    /// Allow outbound requests through `fetch` and `WebSocket`
+    pub fn network(mut self, allow: bool) -> Self {
+        self.0.network = allow;
+        self
+    }
+
+    /// Allow WebAssembly
+    pub fn wasm(mut self, allow: bool) -> Self {
+        self.0.wasm = allow;
+        self
+    }
+
+    /// Allow access to files, subject to the runtime's filesystem permissions
+    pub fn filesystem(mut self, allow: bool) -> Self {
+        self.0.filesystem = allow;
+        self
+    }
+
+    /// Allow running child processes, subject to the runtime's process permissions
+    pub fn processes(mut self, allow: bool) -> Self {
+        self.0.processes = allow;
+        self
+    }
+
+    /// Allow opening database files, subject to the runtime's sqlite permissions
+    pub fn databases(mut self, allow: bool) -> Self {
+        self.0.databases = allow;
+        self
+    }
+
+    /// Finish building the profile
+    pub fn build(self) -> Profile {
+        Profile::Custom(self.0)
+    }
+}
+
+impl From<Profile> for ProfileBuilder {
+    fn from(profile: Profile) -> Self {
+        Self(profile.capabilities())
+    }
+}
+
+/// Refuses all network access, for profiles without the network capability
+#[cfg(any(feature = "web", feature = "websocket"))]
+struct DenyNetwork;
+
+#[cfg(feature = "web")]
+impl crate::FetchHandler for DenyNetwork {
+    fn fetch(
+        &self,
+        request: crate::FetchRequest,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Option<crate::FetchResponse>, crate::Error>>>,
+    > {
+        Box::pin(async move {
+            Err(crate::Error::Runtime(format!(
+                "network access to {} is not allowed by the runtime's profile",
+                request.url
+            )))
+        })
+    }
+}
+
+#[cfg(feature = "websocket")]
+impl crate::WebSocketPolicy for DenyNetwork {
+    fn allow_connect(&self, _url: &deno_core::url::Url) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test_profile {
+    use super::*;
+    use crate::Runtime;
+
+    #[test]
+    fn test_profile_capabilities() {
+        assert!(!Profile::Pure.capabilities().network);
+        assert!(Profile::WebLight.capabilities().network);
+        assert!(!Profile::WebLight.capabilities().filesystem);
+
+        let profile = ProfileBuilder::from(Profile::WebLight)
+            .network(false)
+            .filesystem(true)
+            .build();
+        let capabilities = profile.capabilities();
+        assert!(capabilities.remote_imports);
+        assert!(!capabilities.network);
+        assert!(capabilities.filesystem);
+
+        let mut options = RuntimeOptions::default();
+        Profile::Pure.apply(&mut options);
+        assert!(matches!(options.import_policy, ImportPolicy::Offline));
+        assert!(!options.disable_wasm);
+
+        let mut options = RuntimeOptions::default();
+        Profile::Trusted.apply(&mut options);
+        assert!(matches!(options.import_policy, ImportPolicy::AllowAll));
+    }
+
+    #[test]
+    fn test_profile_runtime() {
+        let mut runtime = Runtime::builder()
+            .profile(Profile::Pure)
+            .build()
+            .expect("Could not create the runtime");
+        let kind: String = runtime.eval("typeof WebAssembly").unwrap();
+        assert_eq!("object", kind);
+
+        let mut runtime = Runtime::builder()
+            .profile(Profile::builder().wasm(false).build())
+            .build()
+            .expect("Could not create the runtime");
+        let kind: String = runtime.eval("typeof WebAssembly").unwrap();
+        assert_eq!("undefined", kind);
+    }
+}
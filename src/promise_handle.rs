@@ -0,0 +1,157 @@
+use crate::{Error, Runtime};
+use deno_core::v8;
+use std::{marker::PhantomData, time::Duration};
+
+/// The result of a javascript call that has been started, but not yet awaited
+/// Returned by [Runtime::call_function_promise]
+///
+/// Several calls can be started one after the other, and then joined in any order -
+/// while one is awaited, the event loop keeps running, so the others make progress too
+///
+/// Must be used with the runtime it was created by
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ json_args, Runtime, Module, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = Module::new("test.js", "
+///     export const delayed = (ms, value) => new Promise(r => setTimeout(() => r(value), ms));
+/// ");
+/// let module = runtime.load_module(&module)?;
+///
+/// let slow = runtime.call_function_promise::<String>(Some(&module), "delayed", json_args!(20, "slow"))?;
+/// let fast = runtime.call_function_promise::<String>(Some(&module), "delayed", json_args!(10, "fast"))?;
+///
+/// assert_eq!("slow", slow.await_result(&mut runtime)?);
+/// assert!(fast.is_settled(&mut runtime));
+/// assert_eq!("fast", fast.await_result(&mut runtime)?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PromiseHandle<T> {
+    value: v8::Global<v8::Value>,
+    timeout: Option<Duration>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> PromiseHandle<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    pub(crate) fn new(value: v8::Global<v8::Value>) -> Self {
+        Self {
+            value,
+            timeout: None,
+            _type: PhantomData,
+        }
+    }
+
+    /// Limit how long [PromiseHandle::await_result] waits for the result
+    /// The runtime's own timeout still applies, if it is shorter
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns true if the call has finished, successfully or not
+    /// This does not run the event loop - pending work only progresses while
+    /// awaiting a result, or with `Runtime::await_event_loop`
+    pub fn is_settled(&self, runtime: &mut Runtime) -> bool {
+        runtime.is_promise_settled(self)
+    }
+
+    /// Run the event loop until the call has finished, and deserialize its result
+    /// Returns an error if the call rejected, or if it did not finish in time
+    pub fn await_result(self, runtime: &mut Runtime) -> Result<T, Error> {
+        runtime.await_promise(self)
+    }
+
+    pub(crate) fn value(&self) -> &v8::Global<v8::Value> {
+        &self.value
+    }
+
+    pub(crate) fn into_parts(self) -> (v8::Global<v8::Value>, Option<Duration>) {
+        (self.value, self.timeout)
+    }
+}
+
+impl<T> std::fmt::Debug for PromiseHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromiseHandle")
+            .field("type", &std::any::type_name::<T>())
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test_promise_handle {
+    use super::*;
+    use crate::{json_args, Module};
+
+    #[test]
+    fn test_promise_handles() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_promise_handle.js",
+            "
+            export const delayed = (ms, value) => new Promise(r => setTimeout(() => r(value), ms));
+            export const fails = async () => { throw new Error('rejected'); };
+            export const now = (value) => value;
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let slow = runtime
+            .call_function_promise::<String>(Some(&module), "delayed", json_args!(50, "slow"))
+            .expect("Could not call function");
+        let fast = runtime
+            .call_function_promise::<String>(Some(&module), "delayed", json_args!(10, "fast"))
+            .expect("Could not call function");
+        assert!(!slow.is_settled(&mut runtime));
+        assert!(!fast.is_settled(&mut runtime));
+
+        // Waiting on the slow call lets the fast one finish too
+        assert_eq!(
+            "slow",
+            slow.await_result(&mut runtime)
+                .expect("Could not await the result")
+        );
+        assert!(fast.is_settled(&mut runtime));
+        assert_eq!(
+            "fast",
+            fast.await_result(&mut runtime)
+                .expect("Could not await the result")
+        );
+
+        // Values that are not promises are settled straight away
+        let now = runtime
+            .call_function_promise::<i64>(Some(&module), "now", json_args!(2))
+            .expect("Could not call function");
+        assert!(now.is_settled(&mut runtime));
+        assert_eq!(
+            2,
+            now.await_result(&mut runtime)
+                .expect("Could not await the result")
+        );
+
+        let e = runtime
+            .call_function_promise::<i64>(Some(&module), "fails", json_args!())
+            .expect("Could not call function")
+            .await_result(&mut runtime)
+            .unwrap_err();
+        assert!(e.to_string().contains("rejected"), "{e}");
+
+        let e = runtime
+            .call_function_promise::<String>(Some(&module), "delayed", json_args!(10_000, "late"))
+            .expect("Could not call function")
+            .timeout(Duration::from_millis(20))
+            .await_result(&mut runtime)
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+    }
+}
@@ -0,0 +1,40 @@
+use deno_core::v8;
+use std::cell::Cell;
+
+/// Number of promises created in an isolate that have not yet settled
+#[derive(Default)]
+struct PendingPromises(Cell<usize>);
+
+/// Start counting the promises created in this isolate
+pub(crate) fn install(isolate: &mut v8::Isolate) {
+    isolate.set_slot(PendingPromises::default());
+    isolate.set_promise_hook(on_promise);
+}
+
+/// Number of promises that have not yet settled, or None if they are not being counted
+pub(crate) fn pending(isolate: &v8::Isolate) -> Option<usize> {
+    isolate
+        .get_slot::<PendingPromises>()
+        .map(|pending| pending.0.get())
+}
+
+extern "C" fn on_promise(
+    hook: v8::PromiseHookType,
+    promise: v8::Local<v8::Promise>,
+    _: v8::Local<v8::Value>,
+) {
+    // SAFETY: the hook is called by V8, from inside the isolate that owns the promise
+    let scope = unsafe { &mut v8::CallbackScope::new(promise) };
+    let Some(pending) = scope.get_slot::<PendingPromises>() else {
+        return;
+    };
+
+    match hook {
+        v8::PromiseHookType::Init => pending.0.set(pending.0.get() + 1),
+
+        // Promises created before the hook was installed can still settle
+        v8::PromiseHookType::Resolve => pending.0.set(pending.0.get().saturating_sub(1)),
+
+        v8::PromiseHookType::Before | v8::PromiseHookType::After => {}
+    }
+}
@@ -0,0 +1,387 @@
+//! Provides execution quotas that persist across runtimes
+//! Usage is recorded against a tenant in a [QuotaStore], so it survives runtimes being dropped,
+//! and - with a persistent store - process restarts
+//!
+//! Once any of a tenant's limits is reached, the runtime refuses to run more javascript,
+//! returning `Error::QuotaExceeded`
+//! ```rust
+//! use rustyscript::{Error, MemoryQuotaStore, Quota, QuotaLimits, Runtime, RuntimeOptions};
+//! use std::sync::Arc;
+//!
+//! # fn main() -> Result<(), Error> {
+//! let store = Arc::new(MemoryQuotaStore::default());
+//! let quota = Quota {
+//!     tenant: "tenant-1".to_string(),
+//!     limits: QuotaLimits {
+//!         invocations: Some(1),
+//!         ..Default::default()
+//!     },
+//!     store: store.clone(),
+//! };
+//!
+//! let mut runtime = Runtime::new(RuntimeOptions {
+//!     quota: Some(quota.clone()),
+//!     ..Default::default()
+//! })?;
+//! runtime.eval::<i64>("1 + 1")?;
+//!
+//! // The usage carries over to new runtimes for the same tenant
+//! let mut runtime = Runtime::new(RuntimeOptions {
+//!     quota: Some(quota),
+//!     ..Default::default()
+//! })?;
+//! assert!(matches!(runtime.eval::<i64>("1 + 1"), Err(Error::QuotaExceeded(_))));
+//! # Ok(())
+//! # }
+//! ```
+use crate::Error;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Cumulative resource usage for a tenant
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QuotaUsage {
+    /// Wall-clock time spent running javascript, including time spent awaiting promises
+    pub run_time: Duration,
+
+    /// Number of evaluations, function calls and module loads
+    pub invocations: u64,
+
+    /// Bytes sent over the network, as reported with `Runtime::record_egress`
+    pub egress_bytes: u64,
+}
+
+impl QuotaUsage {
+    fn add(&mut self, other: &QuotaUsage) {
+        self.run_time += other.run_time;
+        self.invocations += other.invocations;
+        self.egress_bytes += other.egress_bytes;
+    }
+}
+
+/// Limits on a tenant's cumulative usage
+/// Limits that are `None` are not enforced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuotaLimits {
+    /// Maximum wall-clock time spent running javascript
+    pub run_time: Option<Duration>,
+
+    /// Maximum number of evaluations, function calls and module loads
+    pub invocations: Option<u64>,
+
+    /// Maximum bytes sent over the network
+    pub egress_bytes: Option<u64>,
+}
+
+/// A resource limited by a quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum QuotaResource {
+    /// Wall-clock time spent running javascript, in milliseconds
+    RunTime,
+
+    /// Number of evaluations, function calls and module loads
+    Invocations,
+
+    /// Bytes sent over the network
+    EgressBytes,
+}
+
+impl std::fmt::Display for QuotaResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RunTime => write!(f, "run time (ms)"),
+            Self::Invocations => write!(f, "invocations"),
+            Self::EgressBytes => write!(f, "egress bytes"),
+        }
+    }
+}
+
+/// Describes an exhausted quota
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QuotaExceeded {
+    /// The tenant whose quota is exhausted
+    pub tenant: String,
+
+    /// The resource that reached its limit
+    pub resource: QuotaResource,
+
+    /// The tenant's usage of that resource
+    pub used: u64,
+
+    /// The limit for that resource
+    pub limit: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "quota exhausted for {}: {} {} used of {}",
+            self.tenant, self.used, self.resource, self.limit
+        )
+    }
+}
+
+/// Quota store trait
+/// Implement this trait to keep usage in persistent storage, such as a database
+/// You will need to use interior mutability, such as a `Mutex`, since the store is shared
+/// between runtimes - including runtimes on other threads, such as those of workers
+pub trait QuotaStore: Send + Sync {
+    /// Get the cumulative usage for a tenant
+    /// Tenants with no recorded usage should return `QuotaUsage::default()`
+    fn usage(&self, tenant: &str) -> Result<QuotaUsage, Error>;
+
+    /// Add to the cumulative usage for a tenant
+    fn add_usage(&self, tenant: &str, usage: &QuotaUsage) -> Result<(), Error>;
+
+    /// Clear the usage for a tenant, such as at the start of a billing period
+    fn reset(&self, tenant: &str) -> Result<(), Error>;
+}
+
+/// In-memory quota store
+/// Usage is kept for the life of the store, across any runtimes sharing it
+#[derive(Default)]
+pub struct MemoryQuotaStore(Mutex<HashMap<String, QuotaUsage>>);
+impl MemoryQuotaStore {
+    fn usages(&self) -> std::sync::MutexGuard<'_, HashMap<String, QuotaUsage>> {
+        // Usage is only ever added to, so it is still valid after a panic elsewhere
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn usage(&self, tenant: &str) -> Result<QuotaUsage, Error> {
+        Ok(self.usages().get(tenant).cloned().unwrap_or_default())
+    }
+
+    fn add_usage(&self, tenant: &str, usage: &QuotaUsage) -> Result<(), Error> {
+        self.usages()
+            .entry(tenant.to_string())
+            .or_default()
+            .add(usage);
+        Ok(())
+    }
+
+    fn reset(&self, tenant: &str) -> Result<(), Error> {
+        self.usages().remove(tenant);
+        Ok(())
+    }
+}
+
+/// Quota store kept in a JSON file, so usage survives process restarts
+/// The file is read and rewritten on each update, so it is best suited to a small number of tenants
+///
+/// Updates from runtimes sharing the store are made one at a time, and the file is replaced
+/// in a single step, so a crash part-way through an update never leaves it truncated
+pub struct FileQuotaStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+impl FileQuotaStore {
+    /// Create a store backed by the file at `path`, which is created on first use
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, QuotaUsage>, Error> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => Ok(deno_core::serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(Error::Runtime(e.to_string())),
+        }
+    }
+
+    /// Write to a temporary file next to the store, then rename it over the store
+    fn write(&self, usage: &HashMap<String, QuotaUsage>) -> Result<(), Error> {
+        let json = deno_core::serde_json::to_string(usage)?;
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".{}.tmp", std::process::id()));
+
+        std::fs::write(&temp, json)
+            .and_then(|()| std::fs::rename(&temp, &self.path))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&temp);
+                Error::Runtime(e.to_string())
+            })
+    }
+
+    /// Read the store, change it with `f`, and write it back, without other updates in between
+    fn update(&self, f: impl FnOnce(&mut HashMap<String, QuotaUsage>)) -> Result<(), Error> {
+        let _lock = self
+            .lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut all = self.read()?;
+        f(&mut all);
+        self.write(&all)
+    }
+}
+
+impl QuotaStore for FileQuotaStore {
+    fn usage(&self, tenant: &str) -> Result<QuotaUsage, Error> {
+        Ok(self.read()?.remove(tenant).unwrap_or_default())
+    }
+
+    fn add_usage(&self, tenant: &str, usage: &QuotaUsage) -> Result<(), Error> {
+        self.update(|all| all.entry(tenant.to_string()).or_default().add(usage))
+    }
+
+    fn reset(&self, tenant: &str) -> Result<(), Error> {
+        self.update(|all| {
+            all.remove(tenant);
+        })
+    }
+}
+
+/// A tenant's quota, enforced by `RuntimeOptions::quota`
+#[derive(Clone)]
+pub struct Quota {
+    /// The tenant that usage is recorded against
+    pub tenant: String,
+
+    /// The limits to enforce
+    pub limits: QuotaLimits,
+
+    /// Where usage is recorded
+    /// Can be shared with runtimes on other threads, such as those of workers
+    pub store: Arc<dyn QuotaStore>,
+}
+
+impl Quota {
+    /// Check the tenant's usage against its limits
+    /// Returns the run time remaining, if that is limited
+    pub(crate) fn check(&self) -> Result<Option<Duration>, Error> {
+        let usage = self.store.usage(&self.tenant)?;
+        let exceeded = |resource, used: u64, limit: u64| {
+            Error::QuotaExceeded(QuotaExceeded {
+                tenant: self.tenant.clone(),
+                resource,
+                used,
+                limit,
+            })
+        };
+
+        if let Some(limit) = self.limits.invocations {
+            if usage.invocations >= limit {
+                return Err(exceeded(
+                    QuotaResource::Invocations,
+                    usage.invocations,
+                    limit,
+                ));
+            }
+        }
+
+        if let Some(limit) = self.limits.egress_bytes {
+            if usage.egress_bytes >= limit {
+                return Err(exceeded(
+                    QuotaResource::EgressBytes,
+                    usage.egress_bytes,
+                    limit,
+                ));
+            }
+        }
+
+        match self.limits.run_time {
+            Some(limit) if usage.run_time >= limit => Err(exceeded(
+                QuotaResource::RunTime,
+                usage.run_time.as_millis() as u64,
+                limit.as_millis() as u64,
+            )),
+            Some(limit) => Ok(Some(limit - usage.run_time)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record usage against the tenant
+    pub(crate) fn record(&self, usage: &QuotaUsage) -> Result<(), Error> {
+        self.store.add_usage(&self.tenant, usage)
+    }
+}
+
+#[cfg(test)]
+mod test_quota {
+    use super::*;
+
+    #[test]
+    fn test_file_quota_store() {
+        let path = std::env::temp_dir().join("rustyscript_test_file_quota_store.json");
+        let _ = std::fs::remove_file(&path);
+
+        let usage = QuotaUsage {
+            run_time: Duration::from_millis(5),
+            invocations: 1,
+            egress_bytes: 10,
+        };
+
+        let store = FileQuotaStore::new(&path);
+        store
+            .add_usage("a", &usage)
+            .expect("Could not record usage");
+        store
+            .add_usage("a", &usage)
+            .expect("Could not record usage");
+
+        // A new store sees the same usage, as a restarted process would
+        let store = FileQuotaStore::new(&path);
+        let recorded = store.usage("a").expect("Could not read usage");
+        assert_eq!(2, recorded.invocations);
+        assert_eq!(Duration::from_millis(10), recorded.run_time);
+        assert_eq!(
+            QuotaUsage::default(),
+            store.usage("b").expect("Could not get usage")
+        );
+
+        store.reset("a").expect("Could not reset usage");
+        assert_eq!(
+            QuotaUsage::default(),
+            store.usage("a").expect("Could not get usage")
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_quota_check() {
+        let quota = Quota {
+            tenant: "a".to_string(),
+            limits: QuotaLimits {
+                run_time: Some(Duration::from_millis(100)),
+                egress_bytes: Some(1000),
+                ..Default::default()
+            },
+            store: Arc::new(MemoryQuotaStore::default()),
+        };
+
+        let usage = QuotaUsage {
+            run_time: Duration::from_millis(40),
+            ..Default::default()
+        };
+        quota.record(&usage).expect("Could not record usage");
+        assert_eq!(
+            Some(Duration::from_millis(60)),
+            quota.check().expect("Could not check the quota")
+        );
+
+        let usage = QuotaUsage {
+            egress_bytes: 1000,
+            ..Default::default()
+        };
+        quota.record(&usage).expect("Could not record usage");
+        match quota.check() {
+            Err(Error::QuotaExceeded(e)) => {
+                assert_eq!(QuotaResource::EgressBytes, e.resource);
+                assert_eq!(1000, e.used);
+            }
+            _ => panic!("Expected the quota to be exhausted"),
+        }
+    }
+}
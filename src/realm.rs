@@ -0,0 +1,161 @@
+use deno_core::v8;
+
+/// A separate set of globals within a runtime's isolate
+/// Returned by [crate::Runtime::create_realm]
+///
+/// Realms are far cheaper to create than runtimes, and code evaluated in one cannot see or
+/// change the globals of another - useful for isolating requests from each other
+///
+/// A realm only holds the standard javascript built-ins: the runtime's extensions, such as
+/// `console`, timers and `rustyscript`, are not available in it, and it cannot load modules
+/// Promises must settle without the event loop, or the call returns an error
+///
+/// Calls into a realm count as calls on the runtime: they are stopped by its timeout, even in
+/// synchronous code, and count against its time budget and quota
+///
+/// Must be used with the runtime it was created by
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ json_args, Runtime, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let a = runtime.create_realm()?;
+/// let b = runtime.create_realm()?;
+///
+/// runtime.eval_in_realm::<()>(&a, "globalThis.greet = (name) => `hello ${name}`")?;
+/// let greeting: String = runtime.call_function_in_realm(&a, "greet", json_args!("a"))?;
+/// assert_eq!("hello a", greeting);
+///
+/// let kind: String = runtime.eval_in_realm(&b, "typeof greet")?;
+/// assert_eq!("undefined", kind);
+/// # Ok(())
+/// # }
+/// ```
+pub struct RealmHandle {
+    context: v8::Global<v8::Context>,
+}
+
+impl RealmHandle {
+    pub(crate) fn new(context: v8::Global<v8::Context>) -> Self {
+        Self { context }
+    }
+
+    pub(crate) fn context(&self) -> &v8::Global<v8::Context> {
+        &self.context
+    }
+}
+
+impl std::fmt::Debug for RealmHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RealmHandle").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test_realm {
+    use crate::{json_args, Error, Runtime};
+
+    #[test]
+    fn test_realms() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<()>("globalThis.shared = 'main'")
+            .expect("Could not eval");
+
+        let a = runtime.create_realm().expect("Could not create realm");
+        let b = runtime.create_realm().expect("Could not create realm");
+
+        runtime
+            .eval_in_realm::<()>(
+                &a,
+                "globalThis.counter = 0; globalThis.add = (n) => counter += n",
+            )
+            .expect("Could not eval");
+        let value: i64 = runtime
+            .call_function_in_realm(&a, "add", json_args!(2))
+            .expect("Could not call function");
+        assert_eq!(2, value);
+
+        // Globals are not shared with other realms, or the main one
+        let kind: String = runtime
+            .eval_in_realm(&b, "typeof counter")
+            .expect("Could not eval in the realm");
+        assert_eq!("undefined", kind);
+        let kind: String = runtime
+            .eval_in_realm(&a, "typeof shared")
+            .expect("Could not eval in the realm");
+        assert_eq!("undefined", kind);
+        let kind: String = runtime.eval("typeof counter").expect("Could not eval");
+        assert_eq!("undefined", kind);
+
+        // Promises settled by microtasks are resolved
+        let value: i64 = runtime
+            .eval_in_realm(&a, "Promise.resolve(1).then(v => v + 1)")
+            .expect("Could not eval in the realm");
+        assert_eq!(2, value);
+
+        let e = runtime
+            .eval_in_realm::<()>(&a, "throw new Error('realm error')")
+            .unwrap_err();
+        assert!(matches!(e, Error::JsError(_)), "{e}");
+
+        let e = runtime
+            .eval_in_realm::<()>(&a, "new Promise(() => {})")
+            .unwrap_err();
+        assert!(e.to_string().contains("did not settle"), "{e}");
+
+        let e = runtime
+            .call_function_in_realm::<()>(&b, "add", json_args!(1))
+            .unwrap_err();
+        assert!(matches!(e, Error::ValueNotCallable(_)), "{e}");
+    }
+
+    #[test]
+    fn test_realm_limits() {
+        use crate::{MemoryQuotaStore, Quota, QuotaLimits, RuntimeOptions};
+        use std::time::Duration;
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_millis(200),
+            quota: Some(Quota {
+                tenant: "realm".to_string(),
+                limits: QuotaLimits {
+                    invocations: Some(4),
+                    ..Default::default()
+                },
+                store: std::sync::Arc::new(MemoryQuotaStore::default()),
+            }),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let realm = runtime.create_realm().expect("Could not create realm");
+
+        // Loops, and microtasks that never stop queueing more, are stopped by the timeout
+        for script in [
+            "while (true) {}",
+            "Promise.resolve().then(function again() { return Promise.resolve().then(again); })",
+        ] {
+            let e = runtime
+                .eval_in_realm::<()>(&realm, script)
+                .expect_err("The realm ran past its timeout");
+            assert!(matches!(e, Error::Timeout(_)), "{e}");
+        }
+
+        let value: i64 = runtime
+            .eval_in_realm(&realm, "1 + 1")
+            .expect("Could not eval after a timeout");
+        assert_eq!(2, value);
+
+        // Each call, including those stopped, counts as an invocation
+        runtime
+            .eval_in_realm::<i64>(&realm, "1 + 1")
+            .expect("Could not eval");
+        let e = runtime
+            .eval_in_realm::<i64>(&realm, "1 + 1")
+            .expect_err("The quota was not applied");
+        assert!(matches!(e, Error::QuotaExceeded(_)), "{e}");
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+/// Rewrites import specifiers before they are resolved
+/// Set with `RuntimeOptions::resolver_hooks`, or `RuntimeBuilder::resolver_hook`
+///
+/// Hooks run in the order they were added, each seeing the specifier returned by the one before it
+/// The result is then resolved against the importing module as usual, so it is still subject
+/// to the runtime's import policy - a hook cannot reach a module the runtime would otherwise refuse
+///
+/// Rewrites can point at modules mounted with `Runtime::mount_module`, to serve bare
+/// specifiers such as `app:config` from memory
+///
+/// Implemented for closures taking the specifier and referrer, and for `HashMap<String, String>`,
+/// which works like an import map: keys ending in `/` replace a prefix, and other keys must match exactly
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Module, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::builder()
+///     .resolver_hook(|specifier: &str, _referrer: &str| {
+///         specifier.strip_prefix("app:").map(|name| format!("./app/{name}.js"))
+///     })
+///     .build()?;
+/// runtime.mount_module(&Module::new("app/config.js", "export default { debug: true };"))?;
+///
+/// let module = Module::new("main.js", "import config from 'app:config'; export const debug = config.debug;");
+/// let handle = runtime.load_module(&module)?;
+/// let debug: bool = runtime.get_value(Some(&handle), "debug")?;
+/// assert!(debug);
+/// # Ok(())
+/// # }
+/// ```
+pub trait ResolverHook {
+    /// Returns the specifier to import instead of `specifier`, or `None` to leave it as it is
+    /// `referrer` is the specifier of the importing module
+    fn rewrite(&self, specifier: &str, referrer: &str) -> Option<String>;
+}
+
+impl<F> ResolverHook for F
+where
+    F: Fn(&str, &str) -> Option<String>,
+{
+    fn rewrite(&self, specifier: &str, referrer: &str) -> Option<String> {
+        self(specifier, referrer)
+    }
+}
+
+impl ResolverHook for HashMap<String, String> {
+    fn rewrite(&self, specifier: &str, _referrer: &str) -> Option<String> {
+        if let Some(target) = self.get(specifier) {
+            return Some(target.clone());
+        }
+
+        // The longest matching prefix wins, as in an import map
+        self.iter()
+            .filter(|(prefix, _)| prefix.ends_with('/') && specifier.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, target)| format!("{target}{}", &specifier[prefix.len()..]))
+    }
+}
+
+/// Applies each hook in turn to a specifier
+pub(crate) fn rewrite_specifier(
+    hooks: &[Box<dyn ResolverHook>],
+    specifier: &str,
+    referrer: &str,
+) -> String {
+    hooks.iter().fold(specifier.to_string(), |specifier, hook| {
+        hook.rewrite(&specifier, referrer).unwrap_or(specifier)
+    })
+}
+
+#[cfg(test)]
+mod test_resolver_hook {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    #[test]
+    fn test_import_map() {
+        let map: HashMap<String, String> = [
+            ("lodash", "https://cdn.example.com/lodash@4.17.21/lodash.js"),
+            ("https://cdn.example.com/", "https://mirror.example.com/"),
+            (
+                "https://cdn.example.com/pinned/",
+                "https://mirror.example.com/v2/",
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(
+            Some("https://cdn.example.com/lodash@4.17.21/lodash.js".to_string()),
+            map.rewrite("lodash", ".")
+        );
+        assert_eq!(
+            Some("https://mirror.example.com/a.js".to_string()),
+            map.rewrite("https://cdn.example.com/a.js", ".")
+        );
+        assert_eq!(
+            Some("https://mirror.example.com/v2/b.js".to_string()),
+            map.rewrite("https://cdn.example.com/pinned/b.js", ".")
+        );
+        assert_eq!(None, map.rewrite("./local.js", "."));
+
+        // Hooks are chained
+        let hooks: Vec<Box<dyn ResolverHook>> = vec![
+            Box::new(|s: &str, _: &str| (s == "app").then(|| "lodash".to_string())),
+            Box::new(map),
+        ];
+        assert_eq!(
+            "https://cdn.example.com/lodash@4.17.21/lodash.js",
+            rewrite_specifier(&hooks, "app", ".")
+        );
+    }
+
+    #[test]
+    fn test_resolver_hook_runtime() {
+        let mut runtime = Runtime::builder()
+            .resolver_hook(|specifier: &str, _: &str| {
+                specifier
+                    .strip_prefix("app:")
+                    .map(|name| format!("./test_app/{name}.js"))
+            })
+            .resolver_hook(
+                [("lib".to_string(), "./test_app/lib.js".to_string())]
+                    .into_iter()
+                    .collect::<HashMap<_, _>>(),
+            )
+            .build()
+            .expect("Could not create the runtime");
+        runtime
+            .mount_module(&Module::new(
+                "test_app/config.js",
+                "export default { name: 'app' };",
+            ))
+            .expect("Could not mount module");
+        runtime
+            .mount_module(&Module::new(
+                "test_app/lib.js",
+                "export const greet = (name) => `hello ${name}`;",
+            ))
+            .expect("Could not mount module");
+
+        let module = Module::new(
+            "test_resolver_hook.js",
+            "
+            import config from 'app:config';
+            import { greet } from 'lib';
+            export const value = greet(config.name);
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: String = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!("hello app", value);
+
+        runtime
+            .load_module(&Module::new(
+                "test_resolver_hook_missing.js",
+                "import 'app:missing';",
+            ))
+            .expect_err("Imported a module that does not exist");
+    }
+}
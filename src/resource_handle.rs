@@ -0,0 +1,232 @@
+use crate::Error;
+use deno_core::serde_json;
+use std::{any::Any, cell::RefCell, collections::HashMap, marker::PhantomData, rc::Rc};
+
+/// Name of the property holding a handle's id, in the object JS receives
+const HANDLE_KEY: &str = "$rustyscript_handle";
+
+/// An opaque reference to a rust object stored in a [HandleTable]
+/// JS receives the handle as a small object it cannot look into, and can pass back
+/// to registered functions, which resolve it to the original object with [HandleTable::get]
+///
+/// This avoids serializing large rust structs to JSON only for JS to hand them back
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Error };
+///
+/// struct Document {
+///     words: Vec<String>,
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let handles = runtime.handles();
+///
+/// let document = handles.insert(Document { words: vec!["a".to_string(), "b".to_string()] });
+/// runtime.set_global("doc", &document)?;
+///
+/// runtime.register_function("wordCount", move |args| {
+///     let document = handles.get::<Document>(&args[0])?;
+///     Ok(document.words.len().into())
+/// })?;
+///
+/// let count: usize = runtime.eval("rustyscript.functions.wordCount(doc)")?;
+/// assert_eq!(2, count);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ResourceHandle<T> {
+    id: u32,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> ResourceHandle<T> {
+    /// The id of the handle, unique within its table
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The handle as JS sees it
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::json!({ HANDLE_KEY: self.id })
+    }
+}
+
+impl<T> Clone for ResourceHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for ResourceHandle<T> {}
+
+impl<T> std::fmt::Debug for ResourceHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ResourceHandle<{}>({})",
+            std::any::type_name::<T>(),
+            self.id
+        )
+    }
+}
+
+impl<T> PartialEq for ResourceHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T> Eq for ResourceHandle<T> {}
+
+impl<T> serde::Serialize for ResourceHandle<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl<T> From<ResourceHandle<T>> for serde_json::Value {
+    fn from(handle: ResourceHandle<T>) -> Self {
+        handle.to_value()
+    }
+}
+
+#[derive(Default)]
+struct HandleTableInner {
+    next_id: u32,
+    objects: HashMap<u32, Rc<dyn Any>>,
+}
+
+/// The rust objects a runtime has handed to JS as [ResourceHandle]s
+/// Tables are cheap to clone, and clones share the same objects, so one can be moved
+/// into each registered function that needs to resolve handles
+///
+/// Objects stay in the table until removed, or until the table and all its clones are dropped
+#[derive(Clone, Default)]
+pub struct HandleTable(Rc<RefCell<HandleTableInner>>);
+
+impl HandleTable {
+    /// Create a new, empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store an object, returning a handle that can be passed to JS
+    pub fn insert<T: 'static>(&self, value: T) -> ResourceHandle<T> {
+        let mut inner = self.0.borrow_mut();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.objects.insert(id, Rc::new(value));
+
+        ResourceHandle {
+            id,
+            _type: PhantomData,
+        }
+    }
+
+    /// Resolve a handle received from JS to the object it refers to
+    /// Returns an error if the value is not a handle from this table, or refers to another type
+    pub fn get<T: 'static>(&self, value: &serde_json::Value) -> Result<Rc<T>, Error> {
+        let id = Self::id_of(value)?;
+        let object = self
+            .0
+            .borrow()
+            .objects
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::Runtime(format!("resource handle {id} does not exist")))?;
+
+        object.downcast().map_err(|_| {
+            Error::Runtime(format!(
+                "resource handle {id} does not refer to a {}",
+                std::any::type_name::<T>()
+            ))
+        })
+    }
+
+    /// Remove an object from the table
+    /// Returns false if the value is not a handle to an object in this table
+    pub fn remove(&self, value: &serde_json::Value) -> bool {
+        match Self::id_of(value) {
+            Ok(id) => self.0.borrow_mut().objects.remove(&id).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    /// The number of objects in the table
+    pub fn len(&self) -> usize {
+        self.0.borrow().objects.len()
+    }
+
+    /// Returns true if the table holds no objects
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn id_of(value: &serde_json::Value) -> Result<u32, Error> {
+        value
+            .get(HANDLE_KEY)
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| Error::Runtime(format!("{value} is not a resource handle")))
+    }
+}
+
+#[cfg(test)]
+mod test_resource_handle {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    #[derive(Debug)]
+    struct Big(Vec<u8>);
+
+    #[test]
+    fn test_handle_table() {
+        let handles = HandleTable::new();
+        let big = handles.insert(Big(vec![1, 2, 3]));
+        let name = handles.insert("name".to_string());
+        assert_ne!(big.id(), name.id());
+        assert_eq!(2, handles.len());
+
+        let value = big.to_value();
+        assert_eq!(3, handles.get::<Big>(&value).unwrap().0.len());
+
+        let e = handles.get::<String>(&value).unwrap_err();
+        assert!(e.to_string().contains("does not refer to a"), "{e}");
+        handles
+            .get::<Big>(&serde_json::json!({ "a": 1 }))
+            .unwrap_err();
+
+        assert!(handles.remove(&value));
+        assert!(!handles.remove(&value));
+        handles.get::<Big>(&value).unwrap_err();
+        assert_eq!(1, handles.len());
+    }
+
+    #[test]
+    fn test_handles_in_callbacks() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let handles = runtime.handles();
+        runtime
+            .register_function("sum", move |args| {
+                let big = handles.get::<Big>(&args[0])?;
+                Ok(big.0.iter().map(|&b| u64::from(b)).sum::<u64>().into())
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test_handles.js",
+            "export const sum = (handle) => rustyscript.functions.sum(handle);",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let big = runtime.handles().insert(Big(vec![1; 1000]));
+        let value: u64 = runtime
+            .call_function(Some(&module), "sum", &[big.into()])
+            .expect("Could not call function");
+        assert_eq!(1000, value);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    inner_runtime::{InnerRuntime, InnerRuntimeOptions, RsAsyncFunction, RsFunction},
-    Error, FunctionArguments, JsFunction, Module, ModuleHandle,
+    inner_runtime::{FunctionInfo, InnerRuntime, InnerRuntimeOptions, RsAsyncFunction, RsFunction},
+    CompiledModule, Error, FunctionArguments, InterruptHandle, JsFunction, JsValue, Module,
+    ModuleHandle, PendingTimer, RealmHandle, RuntimeStats, V8Value,
 };
 use deno_core::serde_json;
 
@@ -57,16 +58,49 @@ impl Runtime {
         Ok(Self(InnerRuntime::new(options)?))
     }
 
+    /// Creates a builder for a new runtime, with chainable configuration that is checked
+    /// before the runtime is created - an alternative to `RuntimeOptions`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Error};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::builder()
+    ///     .timeout(Duration::from_millis(500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> crate::RuntimeBuilder {
+        crate::RuntimeBuilder::new()
+    }
+
     /// Access the underlying deno runtime instance directly
     pub fn deno_runtime(&mut self) -> &mut deno_core::JsRuntime {
         self.0.deno_runtime()
     }
 
+    /// The address the inspector server is listening on, if `RuntimeOptions::inspector` was set
+    /// Useful when the inspector was asked to listen on port 0, to pick a free port
+    #[cfg(feature = "inspector")]
+    pub fn inspector_address(&self) -> Option<std::net::SocketAddr> {
+        self.0.inspector_address()
+    }
+
     /// Access the options used to create this runtime
     pub fn options(&self) -> &RuntimeOptions {
         &self.0.options
     }
 
+    /// Lower the timeout of each call to `limit`, if it is longer
+    #[cfg(feature = "worker")]
+    pub(crate) fn limit_timeout(&mut self, limit: std::time::Duration) {
+        self.0.options.timeout = self.0.options.timeout.min(limit);
+    }
+
     /// Encode an argument as a json value for use as a function argument
     /// ```rust
     /// use rustyscript::{ Runtime, RuntimeOptions, Module };
@@ -173,14 +207,32 @@ impl Runtime {
         self.0.put(value)
     }
 
+    /// Store a value in the application state shared with stateful functions
+    /// Only one value of each type is stored - additional calls replace the old value
+    ///
+    /// See [crate::StateCell] for an example
+    pub fn put_state<T>(&mut self, value: T) -> Result<(), Error>
+    where
+        T: 'static,
+    {
+        self.0.state().put(value).map(|_| ())
+    }
+
+    /// The application state shared with stateful functions
+    /// Clones share the same values, so the host can read state the functions have changed
+    pub fn state(&mut self) -> crate::StateCell {
+        self.0.state()
+    }
+
     /// Register a rust function to be callable from JS
+    /// Use `register_stateful_function` for functions that need the application state
     /// ```rust
     /// use rustyscript::{ Runtime, Module, serde_json::Value };
     ///
     /// # fn main() -> Result<(), rustyscript::Error> {
     /// let module = Module::new("test.js", " rustyscript.functions.foo(); ");
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// runtime.register_function("foo", |args, _state| {
+    /// runtime.register_function("foo", |args| {
     ///     if let Some(value) = args.get(0) {
     ///         println!("called with: {}", value);
     ///     }
@@ -197,6 +249,206 @@ impl Runtime {
         self.0.register_function(name, callback)
     }
 
+    /// Register a rust function to be callable from JS, which is given the runtime's application state
+    /// Values are put into the state with `put_state`, and borrowed by type
+    ///
+    /// See [crate::StateCell] for an example
+    pub fn register_stateful_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: Fn(&crate::FunctionArguments, &crate::StateCell) -> Result<serde_json::Value, Error>
+            + 'static,
+    {
+        self.0.register_stateful_function(name, callback)
+    }
+
+    /// Register a rust function to be callable from JS, which is given the context of each call -
+    /// the module it was made from, and the request ID set with `set_request_id`
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function_with_context("whoami", |_args, context| {
+    ///     Ok(context.request_id.clone().into())
+    /// })?;
+    ///
+    /// runtime.set_request_id(Some("req-1".to_string()))?;
+    /// let id: String = runtime.eval("rustyscript.functions.whoami()")?;
+    /// assert_eq!("req-1", id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_function_with_context<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&crate::FunctionArguments, &crate::CallContext) -> Result<serde_json::Value, Error>
+            + 'static,
+    {
+        self.0.register_function_with_context(name, callback)
+    }
+
+    /// Set the request ID passed to functions registered with a call context
+    /// The ID applies to every call made until it is changed, so set it before
+    /// handling each request, and clear it with `None` afterwards
+    pub fn set_request_id(&mut self, request_id: Option<String>) -> Result<(), Error> {
+        self.0.set_request_id(request_id)
+    }
+
+    /// Replace a registered rust function
+    /// Calls made from JS after this point will use the new function
+    /// Returns an error if no function is registered under that name
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function("feature", |_| Ok(Value::Bool(false)))?;
+    /// runtime.replace_function("feature", |_| Ok(Value::Bool(true)))?;
+    ///
+    /// let enabled: bool = runtime.eval("rustyscript.functions.feature()")?;
+    /// assert!(enabled);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        self.0.replace_function(name, callback)
+    }
+
+    /// Replace a registered non-blocking rust function
+    /// Calls made from JS after this point will use the new function
+    /// Returns an error if no async function is registered under that name
+    pub fn replace_async_function<F>(&mut self, name: &str, callback: F) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        self.0.replace_async_function(name, callback)
+    }
+
+    /// Remove a registered rust function, sync or async
+    /// Calls made from JS after this point will fail
+    /// Returns true if a function was removed
+    pub fn unregister_function(&mut self, name: &str) -> Result<bool, Error> {
+        self.0.unregister_function(name)
+    }
+
+    /// Register a rust function under a namespace, to be callable from JS
+    /// as `rustyscript.namespace.name`
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function_ns("db", "query", |args| {
+    ///     Ok(Value::from(format!("results for {}", args[0])))
+    /// })?;
+    ///
+    /// let value: String = runtime.eval("rustyscript.db.query('users')")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_function_ns<F>(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        self.0.register_function_ns(namespace, name, callback)
+    }
+
+    /// Check the arguments of calls from JS to a registered function, sync or async, against a schema
+    /// Calls with invalid arguments throw a `TypeError` in JS, without calling the function
+    /// See [crate::ArgSchema] for an example
+    ///
+    /// Returns an error if no function is registered under that name
+    pub fn set_function_schema(
+        &mut self,
+        name: &str,
+        schema: crate::ArgSchema,
+    ) -> Result<(), Error> {
+        self.0.set_function_schema(name, schema)
+    }
+
+    /// Enable or disable all functions registered under a namespace, sync or async
+    /// While disabled, `rustyscript.namespace` is undefined in JS, calls to its functions fail,
+    /// and its functions are left out of `registered_functions`
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function_ns("db", "query", |_| Ok(Value::Null))?;
+    ///
+    /// runtime.set_namespace_enabled("db", false)?;
+    /// let kind: String = runtime.eval("typeof rustyscript.db")?;
+    /// assert_eq!("undefined", kind);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_namespace_enabled(&mut self, namespace: &str, enabled: bool) -> Result<(), Error> {
+        self.0.set_namespace_enabled(namespace, enabled)
+    }
+
+    /// Register a rust function to be callable from JS, along with metadata describing it
+    /// The metadata is returned to JS by `rustyscript.functions()`
+    /// ```rust
+    /// use rustyscript::{ Runtime, FunctionInfo, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function_with_info(
+    ///     "add",
+    ///     FunctionInfo {
+    ///         arity: Some(2),
+    ///         signature: Some("add(a: number, b: number): number".to_string()),
+    ///     },
+    ///     |args| Ok(Value::from(args[0].as_i64().unwrap_or_default() + args[1].as_i64().unwrap_or_default())),
+    /// )?;
+    ///
+    /// let functions: Value = runtime.eval("rustyscript.functions()")?;
+    /// assert_eq!(2, functions[0]["arity"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_function_with_info<F>(
+        &mut self,
+        name: &str,
+        info: FunctionInfo,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsFunction,
+    {
+        self.0.register_function_with_info(name, info, callback)
+    }
+
+    /// List the rust functions registered with the runtime, sorted by name
+    /// Scripts can get the same list from `rustyscript.functions()`
+    /// ```rust
+    /// use rustyscript::{ Runtime, serde_json::Value };
+    ///
+    /// # fn main() -> Result<(), rustyscript::Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.register_function("add", |args| Ok(Value::from(args.len())))?;
+    ///
+    /// let functions = runtime.registered_functions()?;
+    /// assert_eq!("add", functions[0].name);
+    /// assert!(!functions[0].is_async);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn registered_functions(&mut self) -> Result<Vec<crate::RegisteredFunction>, Error> {
+        self.0.registered_functions()
+    }
+
     /// Register a non-blocking rust function to be callable from JS
     /// ```rust
     /// use rustyscript::{ Runtime, Module, serde_json::Value };
@@ -220,6 +472,72 @@ impl Runtime {
         self.0.register_async_function(name, callback)
     }
 
+    /// Register a non-blocking rust function to be callable from JS, which is given
+    /// a clone of the runtime's application state
+    /// Borrows of the state must not be held across an `await`
+    pub fn register_async_stateful_function<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+                Vec<serde_json::Value>,
+                crate::StateCell,
+            ) -> std::pin::Pin<
+                Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>,
+            > + 'static,
+    {
+        self.0.register_async_stateful_function(name, callback)
+    }
+
+    /// Register a non-blocking rust function to be callable from JS, which is given the context
+    /// of each call, as it was when the call was made
+    pub fn register_async_function_with_context<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(
+                Vec<serde_json::Value>,
+                crate::CallContext,
+            ) -> std::pin::Pin<
+                Box<dyn std::future::Future<Output = Result<serde_json::Value, Error>>>,
+            > + 'static,
+    {
+        self.0.register_async_function_with_context(name, callback)
+    }
+
+    /// Register a non-blocking rust function under a namespace, to be callable from JS
+    /// as `rustyscript.namespace.name`
+    pub fn register_async_function_ns<F>(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        self.0.register_async_function_ns(namespace, name, callback)
+    }
+
+    /// Register a non-blocking rust function to be callable from JS, along with metadata describing it
+    /// The metadata is returned to JS by `rustyscript.functions()`
+    pub fn register_async_function_with_info<F>(
+        &mut self,
+        name: &str,
+        info: FunctionInfo,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: RsAsyncFunction,
+    {
+        self.0
+            .register_async_function_with_info(name, info, callback)
+    }
+
     /// Evaluate a piece of non-ECMAScript-module JavaScript code
     /// The expression is evaluated in the global context, so changes persist
     ///
@@ -250,6 +568,71 @@ impl Runtime {
         self.0.eval(expr)
     }
 
+    /// Creates a new realm - a separate set of globals, sharing this runtime's isolate
+    /// See [RealmHandle] for what is available inside a realm
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to the realm, for use with `eval_in_realm`
+    /// and `call_function_in_realm`
+    pub fn create_realm(&mut self) -> Result<RealmHandle, Error> {
+        self.0.create_realm()
+    }
+
+    /// Evaluates a piece of non-ECMAScript-module JavaScript code in a realm
+    /// Effects are limited to the realm's own globals
+    ///
+    /// # Arguments
+    /// * `realm` - A realm created by this runtime
+    /// * `expr` - A string representing the JavaScript expression to evaluate
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the expression (`T`)
+    /// or an error (`Error`) if the expression cannot be evaluated or if the
+    /// result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let realm = runtime.create_realm()?;
+    /// let value: usize = runtime.eval_in_realm(&realm, "globalThis.value = 2; value + 2")?;
+    /// assert_eq!(4, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn eval_in_realm<T>(&mut self, realm: &RealmHandle, expr: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.eval_in_realm(realm, expr)
+    }
+
+    /// Calls a function on a realm's global object, and deserializes its return value
+    ///
+    /// # Arguments
+    /// * `realm` - A realm created by this runtime
+    /// * `name` - The name of a global function in the realm
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub fn call_function_in_realm<T>(
+        &mut self,
+        realm: &RealmHandle,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.call_function_in_realm(realm, name, args)
+    }
+
     /// Calls a stored javascript function and deserializes its return value.
     ///
     /// # Arguments
@@ -310,227 +693,3773 @@ impl Runtime {
         self.0.call_function(module_context, name, args)
     }
 
-    /// Get a value from a runtime instance
+    /// Like `call_function`, but returns a future instead of blocking on the result
+    /// The future runs the event loop until the result is resolved, so it must be driven
+    /// by [Runtime::run_async] - the blocking methods cannot be used from inside it
     ///
     /// # Arguments
     /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
-    /// * `name` - A string representing the name of the value to find
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result or an error (`Error`) if the
-    /// value cannot be found, if there are issues with, or if the result cannot be
-    ///  deserialized.
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    pub async fn call_function_async<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.call_function_async(module_context, name, args).await
+    }
+
+    /// Calls a javascript function by its name, passing arguments and returning its result
+    /// with the structured clone algorithm instead of JSON
+    ///
+    /// This preserves values JSON cannot represent, such as `Map`, `Set`, `Date`, `BigInt`,
+    /// typed arrays and cyclic objects - see [crate::StructuredValue]
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing a clone of the function's result, or an error (`Error`) if the
+    /// function cannot be found, if there are issues with calling the function, or if the
+    /// result cannot be cloned.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use rustyscript::{ Runtime, Module, Error };
+    /// use rustyscript::{ Runtime, Module, Error, StructuredValue };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = Module::new("/path/to/module.js", "export const f = (s) => new Set([...s, 3]);");
     /// let module = runtime.load_module(&module)?;
-    /// let value: usize = runtime.get_value(&module, "my_value")?;
+    /// let arg = StructuredValue::Set(vec![StructuredValue::Number(1.0)]);
+    /// let value = runtime.call_function_structured(Some(&module), "f", vec![arg])?;
+    /// assert_eq!(
+    ///     StructuredValue::Set(vec![StructuredValue::Number(1.0), StructuredValue::Number(3.0)]),
+    ///     value
+    /// );
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get_value<T>(
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_structured(
         &mut self,
         module_context: Option<&ModuleHandle>,
         name: &str,
-    ) -> Result<T, Error>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        self.0.get_value(module_context, name)
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        self.0.call_function_structured(module_context, name, args)
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// And call functions
-    ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// Like [Runtime::call_function_structured], but the function returns `[value, transfer]`,
+    /// as with `postMessage`
     ///
-    /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading modules, executing the
-    /// module, or if the result cannot be deserialized.
+    /// The `ArrayBuffer`s in `transfer` are moved out of the runtime instead of being copied,
+    /// and are left detached there - other buffers in the value are copied
     ///
     /// # Example
     ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ Runtime, Module, Error, StructuredValue };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
-    /// runtime.load_module(&module);
+    /// let module = Module::new("/path/to/module.js", "
+    ///     export const f = () => { const buffer = new ArrayBuffer(4); return [buffer, [buffer]]; };
+    /// ");
+    /// let module = runtime.load_module(&module)?;
+    /// let value = runtime.call_function_structured_transfer(Some(&module), "f", vec![])?;
+    /// assert_eq!(StructuredValue::ArrayBuffer(vec![0; 4].into()), value);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
-        self.0.load_modules(None, vec![module])
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_structured_transfer(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        self.0
+            .call_function_structured_transfer(module_context, name, args)
     }
 
-    /// Executes the given module, and returns a handle allowing you to extract values
-    /// And call functions.
+    /// Calls the entrypoint function registered by a module, passing arguments and returning
+    /// its result with the structured clone algorithm instead of JSON
+    /// See [Runtime::call_function_structured]
     ///
-    /// This will load 'module' as the main module, and the others as side-modules.
-    /// Only one main module can be loaded per runtime
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing a clone of the function's result, or an error (`Error`) if the
+    /// module has no entrypoint, if there are issues with calling the function, or if the
+    /// result cannot be cloned.
+    #[cfg(feature = "structured_clone")]
+    pub fn call_entrypoint_structured(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        match module_context.entrypoint() {
+            Some(entrypoint) => self.0.call_function_by_ref_structured(
+                Some(module_context),
+                entrypoint.clone(),
+                args,
+            ),
+            None => Err(Error::MissingEntrypoint(module_context.module().clone())),
+        }
+    }
+
+    /// Calls a javascript function by its name, without waiting for its result
+    /// The returned [crate::PromiseHandle] can be awaited later, so several calls can be
+    /// started one after the other and then joined - see [crate::PromiseHandle] for an example
     ///
     /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
     ///
     /// # Returns
-    /// A `Result` containing a handle for the loaded module
-    /// or an error (`Error`) if there are issues with loading modules, executing the
-    /// module, or if the result cannot be deserialized.
+    /// A `Result` containing a handle to the function's pending result
+    /// or an error (`Error`) if the function cannot be found, or if it throws before returning
+    pub fn call_function_promise<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<crate::PromiseHandle<T>, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let value = self.0.call_function_promise(module_context, name, args)?;
+        Ok(crate::PromiseHandle::new(value))
+    }
+
+    /// Run the event loop until the result behind a [crate::PromiseHandle] is resolved,
+    /// and deserialize it
+    /// Same as [crate::PromiseHandle::await_result]
+    pub fn await_promise<T>(&mut self, promise: crate::PromiseHandle<T>) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let (value, timeout) = promise.into_parts();
+        self.0.await_promise(value, timeout)
+    }
+
+    /// Returns true if the call behind a [crate::PromiseHandle] has finished
+    /// Same as [crate::PromiseHandle::is_settled]
+    pub fn is_promise_settled<T>(&mut self, promise: &crate::PromiseHandle<T>) -> bool
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.is_promise_settled(promise.value())
+    }
+
+    /// Run a future to completion, blocking until it is done
+    /// The future is given the runtime, so it can await javascript calls made with
+    /// [Runtime::call_function_async] alongside other futures - the event loop keeps
+    /// running while any of them is pending
+    ///
+    /// The blocking methods of the runtime, like `call_function`, must not be used inside
+    /// the future, since they would start a second async runtime on the same thread
+    ///
+    /// The future is subject to the runtime's timeout, and time budget or quota if there is one
     ///
     /// # Example
     ///
     /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{Runtime, Module, Error};
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    /// use std::time::Duration;
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
-    /// runtime.load_modules(&module, vec![]);
+    /// let module = Module::new("test.js", "export const f = async (n) => n * 2;");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let (js, rust) = runtime.run_async(|runtime| async {
+    ///     let js = runtime.call_function_async::<i64>(Some(&module), "f", json_args!(2));
+    ///     let rust = async {
+    ///         tokio::time::sleep(Duration::from_millis(10)).await;
+    ///         Ok::<_, Error>(3)
+    ///     };
+    ///     tokio::try_join!(js, rust)
+    /// })?;
+    /// assert_eq!((4, 3), (js, rust));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn load_modules(
-        &mut self,
-        module: &Module,
-        side_modules: Vec<&Module>,
-    ) -> Result<ModuleHandle, Error> {
-        self.0.load_modules(Some(module), side_modules)
+    pub fn run_async<'a, T, F, Fut>(&'a mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&'a mut Self) -> Fut,
+        Fut: std::future::Future<Output = Result<T, Error>>,
+    {
+        let timeout = self.0.call_timeout()?;
+        InnerRuntime::run_async_task(f(self), timeout)
     }
 
-    /// Executes the entrypoint function of a module within the Deno runtime.
+    /// Calls a javascript function by its name, passing binary data as `Uint8Array`s
+    /// The binary arguments are passed after the JSON arguments, and never go through JSON
+    ///
+    /// Binary return values can be read back by using `deno_core::JsBuffer` as the return type,
+    /// or with `call_function_raw`
     ///
     /// # Arguments
-    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The JSON arguments to pass to the function
+    /// * `bytes` - Binary arguments, passed after `args`
     ///
     /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
     ///
     /// # Example
     ///
     /// ```rust
-    /// use rustyscript::{json_args, Runtime, Module, Error};
+    /// use rustyscript::{ json_args, Runtime, Module, Error, deno_core::JsBuffer };
     ///
     /// # fn main() -> Result<(), Error> {
     /// let mut runtime = Runtime::new(Default::default())?;
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
-    /// let module = runtime.load_module(&module)?;
-    ///
-    /// // Run the entrypoint and handle the result
-    /// let value: String = runtime.call_entrypoint(&module, json_args!())?;
+    /// let module = Module::new("/path/to/module.js", "export function f(n, data) { return data.map(b => b * n); };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let value: JsBuffer = runtime.call_function_with_bytes(Some(&module), "f", json_args!(2), &[&[1, 2, 3]])?;
+    /// assert_eq!(&[2, 4, 6], &value[..]);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn call_entrypoint<T>(
+    pub fn call_function_with_bytes<T>(
         &mut self,
-        module_context: &ModuleHandle,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
         args: &FunctionArguments,
+        bytes: &[&[u8]],
     ) -> Result<T, Error>
     where
         T: deno_core::serde::de::DeserializeOwned,
     {
-        if let Some(entrypoint) = module_context.entrypoint() {
-            let value: serde_json::Value = self.0.call_function_by_ref_async(
-                Some(module_context),
-                entrypoint.clone(),
-                args,
-            )?;
-            Ok(serde_json::from_value(value)?)
-        } else {
-            Err(Error::MissingEntrypoint(module_context.module().clone()))
+        self.0
+            .call_function_with_bytes(module_context, name, args, bytes)
+    }
+
+    /// Calls a javascript function by its name, and returns its result as a `JsValue`
+    /// Unlike `call_function`, this distinguishes between a function returning `undefined`,
+    /// returning `null`, and a function that does not exist
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the `JsValue` envelope of the function's result
+    /// or an error (`Error`) if the value is not a function, if there are issues with
+    /// calling the function, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error, JsValue };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f() { return null; };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let value: JsValue<usize> = runtime.call_function_js_value(Some(&module), "f", json_args!())?;
+    /// assert_eq!(JsValue::Null, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_function_js_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<JsValue<T>, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.call_function_js_value(module_context, name, args)
+    }
+
+    /// Calls a javascript function by its name, and returns its result as a `V8Value`
+    /// The result stays in the runtime until viewed with `as_str`, `as_bytes`, or `deserialize`,
+    /// which avoids building a `serde_json::Value` for very large strings or binary data
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing a `V8Value` that borrows the runtime
+    /// or an error (`Error`) if the function cannot be found, or if there are issues with
+    /// calling the function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f() { return new Uint8Array([1, 2, 3]); };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let bytes = runtime.call_function_raw(Some(&module), "f", json_args!())?.as_bytes()?;
+    /// assert_eq!(vec![1, 2, 3], bytes);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_function_raw(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+    ) -> Result<V8Value<'_>, Error> {
+        self.0.call_function_raw(module_context, name, args)
+    }
+
+    /// Calls a javascript function by its name, and feeds its result to a serde `Visitor`
+    /// The visitor reads the result straight out of the runtime, so very large results can be
+    /// summarized or streamed elsewhere without first building a `serde_json::Value`
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the javascript function to call.
+    /// * `args` - The arguments to pass to the function
+    /// * `visitor` - The visitor to drive with the function's result
+    ///
+    /// # Returns
+    /// A `Result` containing the visitor's output
+    /// or an error (`Error`) if the function cannot be found, if there are issues with
+    /// calling the function, or if the visitor rejects the result.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ json_args, Runtime, Module, Error };
+    /// use serde::de::{SeqAccess, Visitor};
+    ///
+    /// // Counts the entries of an array without keeping any of them
+    /// struct Count;
+    /// impl<'de> Visitor<'de> for Count {
+    ///     type Value = usize;
+    ///     fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    ///         f.write_str("an array")
+    ///     }
+    ///     fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+    ///         let mut count = 0;
+    ///         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+    ///             count += 1;
+    ///         }
+    ///         Ok(count)
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export function f() { return [1, 'a', {}]; };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let count = runtime.call_function_visit(Some(&module), "f", json_args!(), Count)?;
+    /// assert_eq!(3, count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_function_visit<'de, V>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+        args: &FunctionArguments,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.0
+            .call_function_visit(module_context, name, args, visitor)
+    }
+
+    /// The table of rust objects handed to JS as [crate::ResourceHandle]s
+    /// Clones share the same objects - move one into each registered function
+    /// that needs to resolve handles passed back from JS
+    pub fn handles(&mut self) -> crate::HandleTable {
+        self.0.handles()
+    }
+
+    /// Remove and return all messages sent from JS using `rustyscript.postMessage`
+    /// Messages are returned in the order they were sent
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "
+    ///     for (let i = 1; i <= 3; i++) {
+    ///         rustyscript.postMessage({ progress: i });
+    ///     }
+    /// ");
+    /// runtime.load_modules(&module, vec![])?;
+    ///
+    /// let messages = runtime.poll_messages();
+    /// assert_eq!(3, messages.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn poll_messages(&mut self) -> Vec<serde_json::Value> {
+        self.0.poll_messages()
+    }
+
+    /// Remove and return the oldest message sent from JS using `rustyscript.postMessage`
+    /// Returns None if there are no pending messages
+    pub fn next_message(&mut self) -> Option<serde_json::Value> {
+        self.0.next_message()
+    }
+
+    /// Reset the runtime's time budget, set with `RuntimeOptions::time_budget`
+    /// Time spent running JS before this call will no longer count against the budget
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions, Error };
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     time_budget: Some(Duration::from_millis(500)),
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// runtime.eval::<usize>("1 + 1")?;
+    /// runtime.reset_budget();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reset_budget(&mut self) {
+        self.0.reset_budget()
+    }
+
+    /// Register the runtime to be notified of memory pressure, through [crate::memory_pressure]
+    /// When notified, the runtime runs a full garbage collection the next time it executes javascript
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ memory_pressure, Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.listen_for_memory_pressure();
+    /// assert!(memory_pressure::notify() > 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "memory_pressure")]
+    pub fn listen_for_memory_pressure(&mut self) {
+        self.0.listen_for_memory_pressure()
+    }
+
+    /// List the timers created with `setTimeout` or `setInterval` that have not yet fired, soonest first
+    /// Returns an error if the `web` feature is enabled, since deno_web's timers cannot be inspected
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, Undefined };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("setTimeout(() => {}, 1000)")?;
+    ///
+    /// let timers = runtime.pending_timers()?;
+    /// assert_eq!(1, timers.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pending_timers(&mut self) -> Result<Vec<PendingTimer>, Error> {
+        self.0.pending_timers()
+    }
+
+    /// Run a pending timer's callback now, instead of waiting for it to be due
+    /// Timers created with `setTimeout` are removed, and ones created with `setInterval`
+    /// are rescheduled from now
+    ///
+    /// Returns false if there is no pending timer with that id
+    /// Returns an error if the `web` feature is enabled, since deno_web's timers cannot be inspected
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let id: u32 = runtime.eval("setTimeout(() => globalThis.fired = true, 60_000)")?;
+    ///
+    /// assert!(runtime.fire_timer(id)?);
+    /// let fired: bool = runtime.get_value(None, "fired")?;
+    /// assert!(fired);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fire_timer(&mut self, id: u32) -> Result<bool, Error> {
+        self.0.fire_timer(id)
+    }
+
+    /// Record bytes sent over the network on the runtime's behalf, against its quota
+    /// Call this from registered functions that perform network requests,
+    /// so that `QuotaLimits::egress_bytes` can be enforced
+    ///
+    /// Does nothing if the runtime has no quota
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.record_egress(1024)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_egress(&mut self, bytes: u64) -> Result<(), Error> {
+        self.0.record_egress(bytes)
+    }
+
+    /// Move the virtual clock of a deterministic runtime forward, running the callbacks
+    /// of timers that become due in the order they are due
+    /// The runtime must have been created with `RuntimeOptions::deterministic`
+    ///
+    /// Returns the number of timer callbacks run
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions, Error, Undefined };
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     deterministic: Some(Default::default()),
+    ///     ..Default::default()
+    /// })?;
+    /// runtime.eval::<Undefined>("setTimeout(() => globalThis.done = Date.now(), 5000)")?;
+    ///
+    /// assert_eq!(0, runtime.advance_time(Duration::from_secs(4))?);
+    /// assert_eq!(1, runtime.advance_time(Duration::from_secs(1))?);
+    ///
+    /// let done: u64 = runtime.get_value(None, "done")?;
+    /// assert_eq!(5000, done);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn advance_time(&mut self, duration: std::time::Duration) -> Result<usize, Error> {
+        self.0.advance_time(duration)
+    }
+
+    /// Set the virtual clock of a deterministic runtime to a specific time
+    /// Timers due up to that time are fired in order, as with `advance_time`
+    /// Setting an earlier time moves the clock back, leaving pending timers due at the same times
+    ///
+    /// Returns the number of timer callbacks run
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions, Error };
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     deterministic: Some(Default::default()),
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// runtime.set_time(UNIX_EPOCH + Duration::from_secs(86_400))?;
+    /// let day: u64 = runtime.eval("new Date().getUTCDate()")?;
+    /// assert_eq!(2, day);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_time(&mut self, time: std::time::SystemTime) -> Result<usize, Error> {
+        self.0.set_time(time)
+    }
+
+    /// Run the callbacks of all timers that are due, without waiting for the event loop
+    /// Returns an error if the `web` feature is enabled, since deno_web's timers cannot be controlled
+    ///
+    /// Returns the number of timer callbacks run
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, Undefined };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("setTimeout(() => {}, 0)")?;
+    /// assert_eq!(1, runtime.tick_timers()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tick_timers(&mut self) -> Result<usize, Error> {
+        self.0.tick_timers()
+    }
+
+    /// Run the callbacks of all timers due before `until`, in the order they are due,
+    /// without waiting for them - so the host can drive timers instead of wall time
+    /// In a deterministic runtime, the virtual clock is also moved forward to `until`
+    /// Returns an error if the `web` feature is enabled, since deno_web's timers cannot be controlled
+    ///
+    /// Returns the number of timer callbacks run
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, Undefined };
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("setTimeout(() => {}, 60_000)")?;
+    ///
+    /// let fired = runtime.run_timers_until(Instant::now() + Duration::from_secs(120))?;
+    /// assert_eq!(1, fired);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_timers_until(&mut self, until: std::time::Instant) -> Result<usize, Error> {
+        self.0.run_timers_until(until)
+    }
+
+    /// Run the event loop until all pending async work is complete, or until `timeout`
+    /// Returns a timeout error if work was still pending when the timeout expired
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, Undefined };
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("(async () => { await null; globalThis.done = true; })();")?;
+    /// runtime.await_event_loop(Duration::from_secs(1))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn await_event_loop(&mut self, timeout: std::time::Duration) -> Result<(), Error> {
+        self.0.await_event_loop(timeout)
+    }
+
+    /// Run the event loop until there are no more pending ops or promises
+    /// Unlike `await_event_loop`, this runs on the caller's own tokio runtime, and has no timeout -
+    /// wrap it in `tokio::time::timeout` if needed
+    ///
+    /// The blocking methods of the runtime, like `eval`, must not be called from inside
+    /// the caller's tokio runtime, since they start an async runtime of their own
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<()>("globalThis.done = false; setTimeout(() => done = true, 10)")?;
+    ///
+    /// let tokio_runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    /// tokio_runtime.block_on(runtime.run_event_loop(Default::default()))?;
+    ///
+    /// let done: bool = runtime.eval("done")?;
+    /// assert!(done);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_event_loop(&mut self, options: crate::PollOptions) -> Result<(), Error> {
+        self.0.run_event_loop(options).await
+    }
+
+    /// Poll the event loop once, running whatever timers, ops and promises are ready, then return
+    /// Lets a host on its own tokio runtime drive the runtime between other work
+    ///
+    /// Returns true if there is no more pending work
+    pub async fn poll_once(&mut self) -> Result<bool, Error> {
+        self.0.poll_once().await
+    }
+
+    /// Returns a handle that stops the javascript this runtime is running, from any thread
+    /// The interrupted call returns `Error::Cancelled` - see [InterruptHandle]
+    ///
+    /// This is the only way to stop synchronous code, such as an infinite loop,
+    /// which timeouts cannot interrupt
+    pub fn interrupt_handle(&mut self) -> InterruptHandle {
+        self.0.interrupt_handle()
+    }
+
+    /// The number of promises that have been created and not yet settled
+    /// Checking this after each call lets a host find scripts that leak promises
+    ///
+    /// Returns an error unless the runtime was created with `RuntimeOptions::track_promises`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, RuntimeOptions, Undefined, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(RuntimeOptions {
+    ///     track_promises: true,
+    ///     ..Default::default()
+    /// })?;
+    ///
+    /// let before = runtime.pending_promise_count()?;
+    /// runtime.eval::<Undefined>("globalThis.leaked = new Promise(() => {}); undefined")?;
+    /// assert_eq!(before + 1, runtime.pending_promise_count()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pending_promise_count(&mut self) -> Result<usize, Error> {
+        self.0.pending_promise_count()
+    }
+
+    /// Get resource usage statistics for this runtime
+    /// Includes heap usage, pending async ops, the number of loaded modules,
+    /// total time spent running JS, and the number of calls made to each op
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<usize>("1 + 1")?;
+    ///
+    /// let stats = runtime.stats();
+    /// println!("Heap in use: {} bytes", stats.used_heap_size);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&mut self) -> RuntimeStats {
+        self.0.stats()
+    }
+
+    /// Get a value from a runtime instance
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) if the
+    /// value cannot be found, if there are issues with, or if the result cannot be
+    ///  deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = 2;");
+    /// let module = runtime.load_module(&module)?;
+    /// let value: usize = runtime.get_value(&module, "my_value")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.get_value(module_context, name)
+    }
+
+    /// Format a global value the way `console.log` would display it
+    /// See [crate::format_value] for details
+    ///
+    /// # Arguments
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the formatted value, or an error (`Error`) if the
+    /// value cannot be found, or cannot be converted to JSON
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error, Undefined };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.eval::<Undefined>("globalThis.point = { x: 1, y: [2, 3] }")?;
+    /// assert_eq!("{ x: 1, y: [ 2, 3 ] }", runtime.inspect("point")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect(&mut self, name: &str) -> Result<String, Error> {
+        let value: serde_json::Value = self.get_value(None, name)?;
+        Ok(crate::format_value(&value))
+    }
+
+    /// Get a value from a runtime instance as a `JsValue`
+    /// Unlike `get_value`, this distinguishes between a value that is `undefined`,
+    /// one that is `null`, and one that does not exist
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `name` - A string representing the name of the value to find
+    ///
+    /// # Returns
+    /// A `Result` containing the `JsValue` envelope, or an error (`Error`)
+    /// if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error, JsValue };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "globalThis.my_value = undefined;");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let value: JsValue<usize> = runtime.get_js_value(Some(&module), "my_value")?;
+    /// assert!(value.is_undefined());
+    ///
+    /// let value: JsValue<usize> = runtime.get_js_value(Some(&module), "other_value")?;
+    /// assert!(value.is_missing());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_js_value<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        name: &str,
+    ) -> Result<JsValue<T>, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.get_js_value(module_context, name)
+    }
+
+    /// Get a value from a runtime instance by following a dot-separated property path
+    /// The first segment is found in the module's exports or the global context,
+    /// and each following segment is a property of the previous one
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `path` - A path to the value, such as `config.limits.maxUsers`
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result or an error (`Error`) naming the
+    /// segment of the path that could not be found, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export const config = { limits: { maxUsers: 10 } };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// let value: usize = runtime.get_path(Some(&module), "config.limits.maxUsers")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_path<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        path: &str,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.0.get_path(module_context, path)
+    }
+
+    /// Set a value in a runtime instance by following a dot-separated property path
+    /// A path with a single segment will set a value in the global context
+    ///
+    /// # Arguments
+    /// * `module_context` - Optional handle to a module to search - if None, or if the search fails, the global context is used
+    /// * `path` - A path to the value, such as `config.limits.maxUsers`
+    /// * `value` - The value to set
+    ///
+    /// # Returns
+    /// A `Result` containing an error (`Error`) naming the segment of the path
+    /// that could not be found, or if the value could not be set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Module, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("/path/to/module.js", "export const config = { limits: { maxUsers: 10 } };");
+    /// let module = runtime.load_modules(&module, vec![])?;
+    /// runtime.set_path(Some(&module), "config.limits.maxUsers", &20)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_path<T>(
+        &mut self,
+        module_context: Option<&ModuleHandle>,
+        path: &str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        self.0.set_path(module_context, path, value)
+    }
+
+    /// Set a property of `globalThis`, visible to all modules loaded into the runtime
+    /// Unlike `set_path`, the name is used as-is, even if it contains dots
+    ///
+    /// To set globals before any module is evaluated, use `RuntimeOptions::globals`
+    ///
+    /// # Arguments
+    /// * `name` - The name of the global
+    /// * `value` - The value to set
+    ///
+    /// # Returns
+    /// A `Result` containing an error (`Error`) if the value could not be set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ Runtime, Error };
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.set_global("config", &rustyscript::serde_json::json!({ "maxUsers": 10 }))?;
+    /// let value: usize = runtime.eval("config.maxUsers")?;
+    /// assert_eq!(10, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_global<T>(&mut self, name: &str, value: &T) -> Result<(), Error>
+    where
+        T: serde::Serialize,
+    {
+        self.0.set_global(name, value)
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// And call functions
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading modules, executing the
+    /// module, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// runtime.load_module(&module);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        self.0.load_modules(None, vec![module])
+    }
+
+    /// Executes the given module like `load_module`, calling `progress` as each of its imports is
+    /// discovered, fetched and transpiled - useful for showing progress on large remote graphs
+    ///
+    /// The load can be aborted from another thread with `cancel`, returning `Error::Cancelled`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{CancelHandle, Error, LoadProgress, Module, Runtime};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.mount_module(&Module::new("dep.js", "export const value = 2;"))?;
+    ///
+    /// let module = Module::new("main.js", "import { value } from './dep.js';");
+    /// let cancel = CancelHandle::new();
+    /// runtime.load_module_with_progress(&module, &cancel, |progress| {
+    ///     if let LoadProgress::Fetched { specifier, bytes } = progress {
+    ///         println!("fetched {specifier} ({bytes} bytes)");
+    ///     }
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_module_with_progress<F>(
+        &mut self,
+        module: &Module,
+        cancel: &crate::CancelHandle,
+        progress: F,
+    ) -> Result<ModuleHandle, Error>
+    where
+        F: Fn(&crate::LoadProgress) + 'static,
+    {
+        self.0
+            .load_modules_with_progress(None, vec![module], cancel, std::rc::Rc::new(progress))
+    }
+
+    /// Executes the given module, and returns a handle allowing you to extract values
+    /// And call functions.
+    ///
+    /// This will load 'module' as the main module, and the others as side-modules.
+    /// Only one main module can be loaded per runtime
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading modules, executing the
+    /// module, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// runtime.load_modules(&module, vec![]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_modules(
+        &mut self,
+        module: &Module,
+        side_modules: Vec<&Module>,
+    ) -> Result<ModuleHandle, Error> {
+        self.0.load_modules(Some(module), side_modules)
+    }
+
+    /// Executes the given module as the main module, and the others as side-modules, like
+    /// `load_modules` - but returns handles to every module loaded, keyed by module specifier
+    ///
+    /// Use this to call functions exported by side-modules, without loading them again
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("main.js", "export const name = () => 'main';");
+    /// let side = Module::new("side.js", "export const name = () => 'side';");
+    ///
+    /// let result = runtime.load_modules_with_handles(&module, vec![&side])?;
+    /// let side_handle = result.get("side.js").unwrap();
+    /// let name: String = runtime.call_function(Some(side_handle), "name", json_args!())?;
+    /// assert_eq!("side", name);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_modules_with_handles(
+        &mut self,
+        module: &Module,
+        side_modules: Vec<&Module>,
+    ) -> Result<crate::LoadResult, Error> {
+        self.0.load_modules_with_handles(Some(module), side_modules)
+    }
+
+    /// Loads and evaluates every js/ts file below a directory whose path matches `pattern`
+    /// See `Module::from_directory` for how files are matched
+    ///
+    /// The modules can import each other with relative specifiers - imports between them are
+    /// served from memory, and each module is evaluated once, after the modules it imports
+    ///
+    /// # Returns
+    /// A `Result` containing a handle to each module, sorted by path,
+    /// or an error if a file cannot be read, or a module fails to load or evaluate
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let handles = runtime.load_modules_dir("src/ext/rustyscript", "*.js")?;
+    /// assert!(!handles.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_modules_dir(
+        &mut self,
+        directory: &str,
+        pattern: &str,
+    ) -> Result<Vec<ModuleHandle>, Error> {
+        let modules = Module::from_directory(directory, pattern)?;
+        self.0.load_module_set(&modules)
+    }
+
+    /// Lists the values exported by a module, sorted by name, with the kind of each
+    /// and the number of parameters of exported functions and classes
+    ///
+    /// Useful for checking that a module provides the exports a host expects, before calling them
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{ExportKind, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export const add = (a, b) => a + b;");
+    /// let handle = runtime.load_module(&module)?;
+    ///
+    /// let exports = runtime.module_exports(&handle)?;
+    /// assert_eq!("add", exports[0].name);
+    /// assert_eq!(ExportKind::Function, exports[0].kind);
+    /// assert_eq!(Some(2), exports[0].arity);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn module_exports(
+        &mut self,
+        module: &ModuleHandle,
+    ) -> Result<Vec<crate::ExportInfo>, Error> {
+        self.0.module_exports(module)
+    }
+
+    /// Makes a module available to imports from other modules, without loading it
+    /// Imports that resolve to the module's filename are served from memory,
+    /// so bundled scripts can import each other without touching the filesystem
+    ///
+    /// The module is only evaluated once something imports it
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// An error if the module's filename is not a valid path
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// runtime.mount_module(&Module::new("utils.js", "export const answer = 42;"))?;
+    ///
+    /// let module = Module::new("main.js", "import { answer } from './utils.js'; export default answer;");
+    /// let handle = runtime.load_module(&module)?;
+    /// let value: i64 = runtime.get_value(Some(&handle), "default")?;
+    /// assert_eq!(42, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn mount_module(&mut self, module: &Module) -> Result<(), Error> {
+        self.0.mount_module(module)
+    }
+
+    /// Re-evaluates an updated version of a module, and returns a handle to it
+    ///
+    /// Handles to the previous version of the module remain valid, and keep
+    /// returning the old exports. Use the returned handle to reach the new ones.
+    ///
+    /// This is not hot module replacement - only the returned handle sees the new code:
+    /// - Modules importing the module's filename keep the bindings of the first version,
+    ///   whether they were loaded before or after the reload
+    /// - Old versions are never freed, since V8 cannot unload a module. Each reload grows
+    ///   the runtime's memory, so recreate the runtime if modules are reloaded often
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the updated module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the reloaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = runtime.load_module(&Module::new("test.js", "export const value = 1;"))?;
+    /// let module = runtime.reload_module(&Module::new("test.js", "export const value = 2;"))?;
+    ///
+    /// let value: usize = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(2, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reload_module(&mut self, module: &Module) -> Result<ModuleHandle, Error> {
+        self.0.reload_module(module)
+    }
+
+    /// Transpiles a module, and compiles it to a V8 code cache, without evaluating it
+    /// Load the result with `Runtime::load_compiled`, in this runtime or any other
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    ///
+    /// # Returns
+    /// A `Result` containing the compiled module,
+    /// or an error (`Error`) if the module could not be transpiled or compiled
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let compiled = runtime.compile_module(&Module::new("test.js", "export const value = 1;"))?;
+    /// assert!(!compiled.code_cache().is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compile_module(&mut self, module: &Module) -> Result<CompiledModule, Error> {
+        self.0.compile_module(module)
+    }
+
+    /// Loads and evaluates a precompiled module, and returns a handle to it
+    /// V8 reuses the module's code cache instead of parsing and compiling it again
+    ///
+    /// # Arguments
+    /// * `compiled` - A module compiled with `Module::compile` or `Runtime::compile_module`
+    ///
+    /// # Returns
+    /// A `Result` containing a handle for the loaded module
+    /// or an error (`Error`) if there are issues with loading or executing the module
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let compiled = Module::new("test.js", "export const value = 1;").compile()?;
+    ///
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = runtime.load_compiled(&compiled)?;
+    /// let value: usize = runtime.get_value(Some(&module), "value")?;
+    /// assert_eq!(1, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_compiled(&mut self, compiled: &CompiledModule) -> Result<ModuleHandle, Error> {
+        self.0.load_compiled(compiled)
+    }
+
+    /// Executes the entrypoint function of a module within the Deno runtime.
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 'test')");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// // Run the entrypoint and handle the result
+    /// let value: String = runtime.call_entrypoint(&module, json_args!())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_entrypoint<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        if let Some(entrypoint) = module_context.entrypoint() {
+            let value: serde_json::Value = self.0.call_function_by_ref_async(
+                Some(module_context),
+                entrypoint.clone(),
+                args,
+            )?;
+            Ok(serde_json::from_value(value)?)
+        } else {
+            Err(Error::MissingEntrypoint(module_context.module().clone()))
+        }
+    }
+
+    /// Calls the `export default` function of a module
+    /// This does not require the module to register an entrypoint
+    ///
+    /// # Arguments
+    /// * `module_context` - A handle returned by loading a module into the runtime
+    /// * `args` - The arguments to pass to the function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the function call (`T`)
+    /// or an error (`Error`) if the module has no default export, if it is not a function,
+    /// if there are issues with calling the function, or if the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let mut runtime = Runtime::new(Default::default())?;
+    /// let module = Module::new("test.js", "export default (a, b) => a + b;");
+    /// let module = runtime.load_module(&module)?;
+    ///
+    /// let value: usize = runtime.call_default_export(&module, json_args!(1, 2))?;
+    /// assert_eq!(3, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_default_export<T>(
+        &mut self,
+        module_context: &ModuleHandle,
+        args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        self.0.call_default_export(module_context, args)
+    }
+
+    /// Loads a module into a new runtime, executes the entry function and returns the
+    /// result of the module's execution, deserialized into the specified Rust type (`T`).
+    ///
+    /// # Arguments
+    /// * `module` - A `Module` object containing the module's filename and contents.
+    /// * `side_modules` - A set of additional modules to be loaded into memory for use
+    /// * `runtime_options` - Options for the creation of the runtime
+    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
+    ///
+    /// # Returns
+    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
+    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
+    /// or the result cannot be deserialized.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// // Create a module with filename and contents
+    /// use rustyscript::{json_args, Runtime, Module, Error};
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 2)");
+    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_module<T>(
+        module: &Module,
+        side_modules: Vec<&Module>,
+        runtime_options: RuntimeOptions,
+        entrypoint_args: &FunctionArguments,
+    ) -> Result<T, Error>
+    where
+        T: deno_core::serde::de::DeserializeOwned,
+    {
+        let mut runtime = Runtime::new(runtime_options)?;
+        let module = runtime.load_modules(module, side_modules)?;
+        let value: T = runtime.call_entrypoint(&module, entrypoint_args)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test_runtime {
+    use crate::json_args;
+    use std::time::Duration;
+
+    use super::*;
+    use deno_core::extension;
+
+    #[test]
+    fn test_new() {
+        Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        extension!(test_extension);
+        Runtime::new(RuntimeOptions {
+            extensions: vec![test_extension::init_ops_and_esm()],
+            ..Default::default()
+        })
+        .expect("Could not create runtime with extensions");
+    }
+
+    #[test]
+    fn test_into_arg() {
+        assert_eq!(2, Runtime::into_arg(2));
+        assert_eq!("test", Runtime::into_arg("test"));
+        assert_ne!("test", Runtime::into_arg(2));
+    }
+
+    #[test]
+    fn test_get_value() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.a = 2;
+            export const b = 'test';
+            export const fnc = null;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        assert_eq!(
+            2,
+            runtime
+                .get_value::<usize>(Some(&module), "a")
+                .expect("Could not find global")
+        );
+        assert_eq!(
+            "test",
+            runtime
+                .get_value::<String>(Some(&module), "b")
+                .expect("Could not find export")
+        );
+        runtime
+            .get_value::<Undefined>(Some(&module), "c")
+            .expect_err("Could not detect null");
+        runtime
+            .get_value::<Undefined>(Some(&module), "d")
+            .expect_err("Could not detect undeclared");
+    }
+
+    #[test]
+    fn test_arg_buffer() {
+        let module = Module::new(
+            "test.js",
+            "export const join = (...args) => args.map(JSON.stringify).join(',');",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let mut args = crate::ArgBuffer::with_capacity(4);
+        args.push(1).push("a");
+        args.push_serialized(&vec![true, false])
+            .expect("Could not serialize argument");
+        let value: String = runtime
+            .call_function(Some(&module), "join", &args)
+            .expect("Could not call function");
+        assert_eq!("1,\"a\",[true,false]", value);
+
+        let capacity = args.capacity();
+        args.clear();
+        args.push(2);
+        let value: String = runtime
+            .call_function(Some(&module), "join", &args)
+            .expect("Could not call function");
+        assert_eq!("2", value);
+        assert_eq!(capacity, args.capacity());
+    }
+
+    #[test]
+    fn test_call_function_with_bytes() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const sum = (a, b) => [a, b].map(v => v.reduce((s, n) => s + n, 0));
+            export const reverse = (data) => data.reverse();
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let value: Vec<usize> = runtime
+            .call_function_with_bytes(Some(&module), "sum", json_args!(), &[&[1, 2], &[3, 4]])
+            .expect("Could not call function");
+        assert_eq!(vec![3, 7], value);
+
+        let value: deno_core::JsBuffer = runtime
+            .call_function_with_bytes(Some(&module), "reverse", json_args!(), &[&[1, 2, 3]])
+            .expect("Could not call function");
+        assert_eq!(&[3, 2, 1], &value[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "memory_pressure")]
+    fn test_memory_pressure() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        crate::memory_pressure::on_pressure(|| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime.listen_for_memory_pressure();
+        assert!(crate::memory_pressure::notify() > 0);
+        assert!(CALLS.load(Ordering::SeqCst) > 0);
+
+        // The notification is handled the next time JS runs
+        let value: usize = runtime.eval("1 + 1").expect("Could not eval");
+        assert_eq!(2, value);
+    }
+
+    #[test]
+    fn test_call_default_export() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        let module = Module::new("test.js", "export default (a, b) => a + b;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_default_export(&module, json_args!(1, 2))
+            .expect("Could not call default export");
+        assert_eq!(3, value);
+
+        let module = Module::new("test2.js", "export default 5;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_default_export::<usize>(&module, json_args!())
+            .expect_err("Default export is not a function");
+
+        let module = Module::new("test3.js", "export const a = 5;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_default_export::<usize>(&module, json_args!())
+            .expect_err("There is no default export");
+    }
+
+    #[test]
+    fn test_crash_dump_path() {
+        let path = std::env::temp_dir().join("rustyscript_test_crash_dump.txt");
+        let mut runtime = Runtime::new(RuntimeOptions {
+            crash_dump_path: Some(path.clone()),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new("test.js", "export const f = () => 2;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .call_function(Some(&module), "f", json_args!())
+            .expect("Could not call function");
+        assert_eq!(2, value);
+
+        // Reports are only written when the isolate aborts
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_await_event_loop() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>(
+                "(async () => { for (let i = 0; i < 10; i++) await null; globalThis.done = true; })();",
+            )
+            .expect("Could not eval");
+        runtime
+            .await_event_loop(Duration::from_secs(1))
+            .expect("Event loop did not finish");
+
+        let done: bool = runtime
+            .get_value(None, "done")
+            .expect("Could not get value");
+        assert!(done);
+    }
+
+    #[test]
+    #[cfg(not(feature = "web"))]
+    fn test_timers() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let timeout: u32 = runtime
+            .eval("setTimeout((v) => globalThis.timeout = v, 60_000, 'fired')")
+            .expect("Could not set timeout");
+        let interval: u32 = runtime
+            .eval("globalThis.ticks = 0; setInterval(() => globalThis.ticks++, 30_000)")
+            .expect("Could not set interval");
+
+        let timers = runtime.pending_timers().expect("Could not list timers");
+        assert_eq!(2, timers.len());
+        assert_eq!(interval, timers[0].id);
+        assert!(timers[0].repeat);
+        assert_eq!(timeout, timers[1].id);
+        assert!(!timers[1].repeat);
+        assert!(timers[1].due_in > Duration::from_secs(30));
+
+        assert!(runtime.fire_timer(timeout).expect("Could not fire timer"));
+        assert!(!runtime.fire_timer(timeout).expect("Could not fire timer"));
+        let value: String = runtime
+            .get_value(None, "timeout")
+            .expect("Could not get value");
+        assert_eq!("fired", value);
+
+        runtime.fire_timer(interval).expect("Could not fire timer");
+        runtime.fire_timer(interval).expect("Could not fire timer");
+        let ticks: usize = runtime
+            .get_value(None, "ticks")
+            .expect("Could not get value");
+        assert_eq!(2, ticks);
+
+        let timers = runtime.pending_timers().expect("Could not list timers");
+        assert_eq!(1, timers.len());
+
+        runtime
+            .eval::<Undefined>(&format!("clearInterval({interval})"))
+            .expect("Could not clear interval");
+        assert!(runtime
+            .pending_timers()
+            .expect("Could not list timers")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_mount_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .mount_module(&Module::new(
+                "virtual/utils.js",
+                "export function double(x) { return x * 2; }",
+            ))
+            .expect("Could not mount module");
+        runtime
+            .mount_module(&Module::new(
+                "virtual/lib/index.ts",
+                "import { double } from '../utils.js'; export const value: number = double(21);",
+            ))
+            .expect("Could not mount module");
+
+        let module = Module::new(
+            "virtual/main.js",
+            "import { value } from './lib/index.ts'; export default value;",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .get_value(Some(&handle), "default")
+            .expect("Could not get value");
+        assert_eq!(42, value);
+
+        let module = Module::new("virtual/missing.js", "import './nothing.js';");
+        runtime
+            .load_module(&module)
+            .expect_err("Unmounted module should not be found");
+    }
+
+    #[test]
+    fn test_data_imports() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .mount_module(&Module::new(
+                "data/config.json",
+                r#"{ "name": "app", "ports": [80, 443] }"#,
+            ))
+            .expect("Could not mount module");
+        runtime
+            .mount_module(&Module::new("data/notes.txt", "héllo"))
+            .expect("Could not mount module");
+
+        let module = Module::new(
+            "data/main.js",
+            "
+            import config from './config.json' with { type: 'json' };
+            import notes from './notes.txt' with { type: 'text' };
+            import raw from './notes.txt' with { type: 'bytes' };
+            export const value = [config.name, config.ports[1], notes, raw instanceof Uint8Array, raw.length];
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: serde_json::Value = runtime.get_value(Some(&handle), "value").unwrap();
+        assert_eq!(serde_json::json!(["app", 443, "héllo", true, 6]), value);
+
+        // Dynamic imports take the same attributes
+        let module = Module::new(
+            "data/dynamic.js",
+            "export const notes = (await import('./notes.txt', { with: { type: 'text' } })).default;",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+        let value: String = runtime.get_value(Some(&handle), "notes").unwrap();
+        assert_eq!("héllo", value);
+
+        let module = Module::new("data/untyped.js", "import config from './config.json';");
+        let e = runtime.load_module(&module).unwrap_err();
+        assert!(e.to_string().contains("type: \"json\""), "{e}");
+
+        let module = Module::new(
+            "data/unknown.js",
+            "import notes from './notes.txt' with { type: 'css' };",
+        );
+        let e = runtime.load_module(&module).unwrap_err();
+        assert!(e.to_string().contains("unsupported import type"), "{e}");
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .eval::<Undefined>(
+                "globalThis.result = { name: 'test', values: [1, 2.5, null], ok: true }",
+            )
+            .expect("Could not set value");
+        assert_eq!(
+            r#"{ name: "test", values: [ 1, 2.5, null ], ok: true }"#,
+            runtime.inspect("result").expect("Could not inspect value")
+        );
+        runtime
+            .inspect("missing")
+            .expect_err("Missing values should not be inspected");
+    }
+
+    #[test]
+    fn test_call_function_visit() {
+        struct Sum;
+        impl<'de> serde::de::Visitor<'de> for Sum {
+            type Value = f64;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an array of numbers")
+            }
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<f64, A::Error> {
+                let mut sum = 0.0;
+                while let Some(n) = seq.next_element::<f64>()? {
+                    sum += n;
+                }
+                Ok(sum)
+            }
+        }
+
+        let module = Module::new(
+            "test.js",
+            "export const range = (n) => Array.from({ length: n }, (_, i) => i);",
+        );
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let sum = runtime
+            .call_function_visit(Some(&module), "range", json_args!(1000), Sum)
+            .expect("Could not visit result");
+        assert_eq!(499500.0, sum);
+
+        runtime
+            .call_function_visit(Some(&module), "missing", json_args!(), Sum)
+            .expect_err("Missing function should not be called");
+    }
+
+    #[test]
+    #[cfg(feature = "url_import")]
+    fn test_import_policy() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            import_policy: crate::ImportPolicy::Offline,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new("test.js", "import 'https://example.com/util.js';");
+        let error = runtime
+            .load_module(&module)
+            .expect_err("Remote import should be denied");
+        assert!(matches!(error, Error::ImportDenied(_)));
+    }
+
+    #[test]
+    #[cfg(feature = "inspector")]
+    fn test_inspector() {
+        use std::io::{Read, Write};
+
+        let runtime = Runtime::new(RuntimeOptions {
+            inspector: Some(crate::InspectorOptions {
+                address: std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let address = runtime.inspector_address().expect("Inspector not started");
+        assert_ne!(0, address.port());
+
+        let request = |host: &str| {
+            let mut stream = std::net::TcpStream::connect(address).expect("Could not connect");
+            write!(stream, "GET /json/list HTTP/1.1\r\nHost: {host}\r\n\r\n")
+                .expect("Could not send request");
+            // Refused connections are closed without reading the request, which may reset them
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        };
+
+        // A connection that never sends its request does not hold up the others
+        let _idle = std::net::TcpStream::connect(address).expect("Could not connect");
+
+        let response = request("localhost");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(&format!("\"webSocketDebuggerUrl\":\"ws://{address}/ws/")));
+
+        // Names other than localhost could come from DNS rebinding
+        let response = request("attacker.example:9229");
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden"), "{response}");
+
+        // Connections past the limit are closed, until others finish
+        let idle: Vec<_> = (0..16)
+            .map(|_| std::net::TcpStream::connect(address).expect("Could not connect"))
+            .collect();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!("", request("localhost"));
+
+        drop(idle);
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(request("localhost").starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    #[cfg(feature = "web")]
+    fn test_web_timer_options() {
+        // deno_web's timers cannot be controlled, so the options are refused up front
+        let error = Runtime::new(RuntimeOptions {
+            max_timers: Some(10),
+            ..Default::default()
+        })
+        .err()
+        .expect("Created a runtime with max_timers");
+        assert!(error.to_string().contains("max_timers"));
+
+        let error = Runtime::new(RuntimeOptions {
+            deterministic: Some(crate::DeterministicOptions {
+                seed: 42,
+                start_time: std::time::UNIX_EPOCH,
+            }),
+            ..Default::default()
+        })
+        .err()
+        .expect("Created a deterministic runtime");
+        assert!(error.to_string().contains("deterministic"));
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .pending_timers()
+            .expect_err("Inspected deno_web's timers");
+        runtime.fire_timer(1).expect_err("Fired a deno_web timer");
+    }
+
+    #[test]
+    #[cfg(not(feature = "web"))]
+    fn test_deterministic() {
+        let options = || RuntimeOptions {
+            deterministic: Some(crate::DeterministicOptions {
+                seed: 42,
+                start_time: std::time::UNIX_EPOCH + Duration::from_secs(1000),
+            }),
+            ..Default::default()
+        };
+
+        let mut runtime = Runtime::new(options()).expect("Could not create the runtime");
+        let random: Vec<f64> = runtime
+            .eval("[Math.random(), Math.random()]")
+            .expect("Could not get random numbers");
+        let mut other = Runtime::new(options()).expect("Could not create the runtime");
+        let other: Vec<f64> = other
+            .eval("[Math.random(), Math.random()]")
+            .expect("Could not get random numbers");
+        assert_eq!(random, other);
+        assert_ne!(random[0], random[1]);
+
+        let now: u64 = runtime.eval("Date.now()").expect("Could not get time");
+        assert_eq!(1_000_000, now);
+        let now: u64 = runtime
+            .eval("new Date().getTime()")
+            .expect("Could not get time");
+        assert_eq!(1_000_000, now);
+
+        // Called as a function, Date returns the virtual time as a string
+        let checks: Vec<bool> = runtime
+            .eval(
+                "[
+                    Date() === new Date().toString(),
+                    new Date(5).getTime() === 5,
+                    new Date() instanceof Date,
+                    new Date().constructor === Date,
+                    typeof Date.UTC(2000, 0) === 'number',
+                ]",
+            )
+            .expect("Could not call Date");
+        assert_eq!(vec![true; 5], checks);
+
+        runtime
+            .eval::<Undefined>(
+                "
+                globalThis.log = [];
+                setTimeout(() => log.push(['timeout', Date.now()]), 250);
+                setInterval(() => log.push(['interval', Date.now()]), 100);
+            ",
+            )
+            .expect("Could not set timers");
+
+        let fired = runtime
+            .advance_time(Duration::from_millis(300))
+            .expect("Could not advance time");
+        assert_eq!(4, fired);
+
+        let log: Vec<(String, u64)> = runtime.get_value(None, "log").expect("Could not get log");
+        assert_eq!(
+            vec![
+                ("interval".to_string(), 1_000_100),
+                ("interval".to_string(), 1_000_200),
+                ("timeout".to_string(), 1_000_250),
+                ("interval".to_string(), 1_000_300),
+            ],
+            log
+        );
+
+        let now: u64 = runtime.eval("Date.now()").expect("Could not get time");
+        assert_eq!(1_000_300, now);
+
+        Runtime::new(Default::default())
+            .expect("Could not create the runtime")
+            .advance_time(Duration::from_secs(1))
+            .expect_err("Only deterministic runtimes can advance time");
+    }
+
+    #[test]
+    #[cfg(not(feature = "web"))]
+    fn test_set_time() {
+        let start = std::time::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut runtime = Runtime::new(RuntimeOptions {
+            deterministic: Some(crate::DeterministicOptions {
+                seed: 0,
+                start_time: start,
+            }),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        runtime
+            .eval::<Undefined>(
+                "
+                globalThis.log = [];
+                setTimeout(() => log.push(Date.now()), 60_000);
+                setTimeout(() => log.push(Date.now()), 120_000);
+            ",
+            )
+            .expect("Could not set timers");
+
+        let fired = runtime
+            .set_time(start + Duration::from_secs(90))
+            .expect("Could not set time");
+        assert_eq!(1, fired);
+        let now: u64 = runtime.eval("Date.now()").expect("Could not get time");
+        assert_eq!(1_090_000, now);
+
+        // Moving the clock back leaves the remaining timer due at the same time
+        let fired = runtime.set_time(start).expect("Could not set time");
+        assert_eq!(0, fired);
+        let now: u64 = runtime.eval("Date.now()").expect("Could not get time");
+        assert_eq!(1_000_000, now);
+
+        let fired = runtime
+            .set_time(start + Duration::from_secs(120))
+            .expect("Could not set time");
+        assert_eq!(1, fired);
+
+        let log: Vec<u64> = runtime.get_value(None, "log").expect("Could not get log");
+        assert_eq!(vec![1_060_000, 1_120_000], log);
+    }
+
+    #[test]
+    fn test_quota() {
+        let store = std::sync::Arc::new(crate::MemoryQuotaStore::default());
+        let quota = crate::Quota {
+            tenant: "tenant".to_string(),
+            limits: crate::QuotaLimits {
+                invocations: Some(3),
+                ..Default::default()
+            },
+            store: store.clone(),
+        };
+        let options = || RuntimeOptions {
+            quota: Some(quota.clone()),
+            ..Default::default()
+        };
+
+        let mut runtime = Runtime::new(options()).expect("Could not create the runtime");
+        runtime.eval::<i64>("1").expect("Could not evaluate");
+        runtime.eval::<i64>("2").expect("Could not evaluate");
+
+        // Usage is shared with a new runtime for the same tenant
+        let mut runtime = Runtime::new(options()).expect("Could not create the runtime");
+        runtime.eval::<i64>("3").expect("Could not evaluate");
+        match runtime.eval::<i64>("4") {
+            Err(Error::QuotaExceeded(e)) => {
+                assert_eq!("tenant", e.tenant);
+                assert_eq!(crate::QuotaResource::Invocations, e.resource);
+                assert_eq!(3, e.used);
+                assert_eq!(3, e.limit);
+            }
+            _ => panic!("Expected the quota to be exhausted"),
+        }
+
+        crate::QuotaStore::reset(store.as_ref(), "tenant").expect("Could not reset usage");
+        runtime.eval::<i64>("5").expect("Could not evaluate");
+
+        // Usage is shared with runtimes on other threads
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let quota = quota.clone();
+                std::thread::spawn(move || {
+                    let mut runtime = Runtime::new(RuntimeOptions {
+                        quota: Some(quota),
+                        ..Default::default()
+                    })
+                    .expect("Could not create the runtime");
+                    runtime.eval::<i64>("6").expect("Could not evaluate");
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("Thread panicked");
+        }
+        let usage = crate::QuotaStore::usage(store.as_ref(), "tenant").unwrap();
+        assert_eq!(3, usage.invocations);
+    }
+
+    #[test]
+    #[cfg(not(feature = "web"))]
+    fn test_timer_control() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            max_timers: Some(3),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        runtime
+            .eval::<Undefined>(
+                "
+                globalThis.log = [];
+                setTimeout(() => log.push('now'), 0);
+                setTimeout(() => log.push('soon'), 30_000);
+                setTimeout(() => log.push('later'), 60_000);
+            ",
+            )
+            .expect("Could not set timers");
+        runtime
+            .eval::<Undefined>("setTimeout(() => {}, 0)")
+            .expect_err("Timer limit should be enforced");
+
+        assert_eq!(1, runtime.tick_timers().expect("Could not tick timers"));
+        let until = std::time::Instant::now() + Duration::from_secs(45);
+        assert_eq!(
+            1,
+            runtime
+                .run_timers_until(until)
+                .expect("Could not run timers")
+        );
+
+        let log: Vec<String> = runtime.get_value(None, "log").expect("Could not get log");
+        assert_eq!(vec!["now", "soon"], log);
+        assert_eq!(1, runtime.pending_timers().unwrap().len());
+
+        // Fired timers no longer count towards the limit
+        runtime
+            .eval::<Undefined>("setTimeout(() => {}, 0); setTimeout(() => {}, 0)")
+            .expect("Could not set timers");
+    }
+
+    #[test]
+    fn test_error_report() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let e = runtime
+            .eval::<Undefined>("let x = 1;\nthrow new TypeError('bad value')")
+            .unwrap_err();
+
+        let report = e.report();
+        assert_eq!("JsError", report.code);
+        assert_eq!("Uncaught TypeError: bad value", report.message);
+        assert_eq!(Some(2), report.line_number);
+        assert!(report.stack.is_some());
+
+        let json = serde_json::to_value(&report).expect("Could not serialize the report");
+        assert_eq!("JsError", json["code"]);
+
+        let report = Error::Timeout("slow".to_string()).report();
+        assert_eq!("Timeout", report.code);
+        assert_eq!(None, report.stack);
+    }
+
+    #[test]
+    fn test_load_module_with_progress() {
+        use crate::{CancelHandle, LoadProgress};
+        use std::{cell::RefCell, rc::Rc};
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let dep = Module::new("dep.js", "export const value = 2;");
+        runtime.mount_module(&dep).expect("Could not mount module");
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let module = Module::new("main.js", "import { value } from './dep.js';");
+        let cancel = CancelHandle::new();
+        runtime
+            .load_module_with_progress(&module, &cancel, {
+                let events = events.clone();
+                move |progress| events.borrow_mut().push(progress.clone())
+            })
+            .expect("Could not load module");
+
+        let events = events.borrow();
+        assert_eq!(3, events.len());
+        assert!(matches!(&events[0], LoadProgress::Discovered(s) if s.path().ends_with("dep.js")));
+        assert!(matches!(
+            &events[1],
+            LoadProgress::Fetched { bytes: 23, .. }
+        ));
+        assert!(matches!(&events[2], LoadProgress::Transpiled(_)));
+
+        // A cancelled load stops at the first import
+        let module = Module::new("other.js", "import { value } from './dep.js';");
+        cancel.cancel();
+        let e = runtime
+            .load_module_with_progress(&module, &cancel, |_| {})
+            .unwrap_err();
+        assert!(matches!(e, Error::Cancelled(_)), "{e}");
+    }
+
+    #[test]
+    fn test_load_modules_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("rustyscript_test_modules_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(
+            dir.join("a.js"),
+            "import { count } from './lib/b.ts'; export const value = count();",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("lib/b.ts"),
+            "let loads = 0; loads++; export const count = (): number => loads;",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib/notes.txt"), "not a module").unwrap();
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let handles = runtime
+            .load_modules_dir(dir.to_str().unwrap(), "*")
+            .expect("Could not load modules");
+        assert_eq!(2, handles.len());
+        assert!(handles[0].module().filename().ends_with("a.js"));
+
+        // b.ts is imported by a.js, and loaded on its own, but only evaluated once
+        let value: i64 = runtime.get_value(Some(&handles[0]), "value").unwrap();
+        assert_eq!(1, value);
+        let value: i64 = runtime
+            .call_function(Some(&handles[1]), "count", json_args!())
+            .unwrap();
+        assert_eq!(1, value);
+
+        let handles = runtime
+            .load_modules_dir(dir.to_str().unwrap(), "lib/*")
+            .expect("Could not load modules");
+        assert_eq!(1, handles.len());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_modules_with_handles() {
+        use crate::traits::ToModuleSpecifier;
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "main.js",
+            "
+            export const name = () => 'main';
+            rustyscript.register_entrypoint(() => 'entry');
+        ",
+        );
+        let side_a = Module::new("side_a.js", "export const name = () => 'a';");
+        let side_b = Module::new("side_b.js", "export const name = () => 'b';");
+
+        let result = runtime
+            .load_modules_with_handles(&module, vec![&side_a, &side_b])
+            .expect("Could not load modules");
+        assert_eq!(3, result.handles().count());
+        assert!(result.main().entrypoint().is_some());
+
+        for (filename, expected) in [("main.js", "main"), ("side_a.js", "a"), ("side_b.js", "b")] {
+            let handle = result.get(filename).expect("Missing module handle");
+            let name: String = runtime
+                .call_function(Some(handle), "name", json_args!())
+                .expect("Could not call function");
+            assert_eq!(expected, name);
+        }
+
+        let specifier = side_a.filename().to_module_specifier().unwrap();
+        assert!(result.get(specifier.as_str()).is_some());
+        assert!(result.get("missing.js").is_none());
+    }
+
+    #[test]
+    fn test_module_exports() {
+        use crate::ExportKind;
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export function add(a, b) { return a + b; }
+            export async function fetchAll(urls, options = {}) {}
+            export class Point { constructor(x, y) {} }
+            export const answer = 42;
+            export default 'text';
+        ",
+        );
+        let handle = runtime.load_module(&module).expect("Could not load module");
+
+        let exports = handle
+            .exports(&mut runtime)
+            .expect("Could not list exports");
+        let summary: Vec<_> = exports
+            .iter()
+            .map(|e| (e.name.as_str(), e.kind, e.arity))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Point", ExportKind::Class, Some(2)),
+                ("add", ExportKind::Function, Some(2)),
+                ("answer", ExportKind::Value, None),
+                ("default", ExportKind::Value, None),
+                ("fetchAll", ExportKind::Function, Some(1)),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_call_function_raw() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const s = () => 'a'.repeat(1024);
+            export const b = (n) => new Uint8Array(n).fill(7);
+            export const p = async () => ({ a: 1 });
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let mut value = runtime
+            .call_function_raw(Some(&module), "s", json_args!())
+            .expect("Could not call function");
+        assert_eq!(1024, value.as_str().expect("Not a string").len());
+        value.as_bytes().expect_err("Should not be binary data");
+
+        let bytes = runtime
+            .call_function_raw(Some(&module), "b", json_args!(3))
+            .expect("Could not call function")
+            .as_bytes()
+            .expect("Not binary data");
+        assert_eq!(vec![7, 7, 7], bytes);
+
+        let value: serde_json::Value = runtime
+            .call_function_raw(Some(&module), "p", json_args!())
+            .expect("Could not call function")
+            .deserialize()
+            .expect("Could not deserialize");
+        assert_eq!(serde_json::json!({ "a": 1 }), value);
+    }
+
+    #[test]
+    fn test_get_js_value() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.a = 2;
+            export const b = undefined;
+            export const c = null;
+            export const f = () => undefined;
+            export const g = () => null;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        assert_eq!(
+            JsValue::Value(2),
+            runtime
+                .get_js_value::<usize>(Some(&module), "a")
+                .expect("Could not find global")
+        );
+        assert_eq!(
+            JsValue::Undefined,
+            runtime
+                .get_js_value::<usize>(Some(&module), "b")
+                .expect("Could not find export")
+        );
+        assert_eq!(
+            JsValue::Null,
+            runtime
+                .get_js_value::<usize>(Some(&module), "c")
+                .expect("Could not find export")
+        );
+        assert_eq!(
+            JsValue::Missing,
+            runtime
+                .get_js_value::<usize>(Some(&module), "d")
+                .expect("Could not detect undeclared")
+        );
+
+        assert_eq!(
+            JsValue::Undefined,
+            runtime
+                .call_function_js_value::<usize>(Some(&module), "f", json_args!())
+                .expect("Could not call export")
+        );
+        assert_eq!(
+            JsValue::Null,
+            runtime
+                .call_function_js_value::<usize>(Some(&module), "g", json_args!())
+                .expect("Could not call export")
+        );
+        assert_eq!(
+            JsValue::Missing,
+            runtime
+                .call_function_js_value::<usize>(Some(&module), "h", json_args!())
+                .expect("Could not detect undeclared")
+        );
+        runtime
+            .call_function_js_value::<usize>(Some(&module), "a", json_args!())
+            .expect_err("Did not detect non-function");
+    }
+
+    #[test]
+    fn test_list_functions() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("b", |_| Ok(serde_json::Value::Null))
+            .expect("Could not register function");
+        runtime
+            .register_function_with_info(
+                "a",
+                FunctionInfo {
+                    arity: Some(1),
+                    signature: Some("a(x: number)".to_string()),
+                },
+                |args| Ok(args[0].clone()),
+            )
+            .expect("Could not register function");
+        runtime
+            .register_async_function("c", |_| Box::pin(async { Ok(serde_json::Value::Null) }))
+            .expect("Could not register function");
+
+        let functions: serde_json::Value = runtime
+            .eval("rustyscript.functions()")
+            .expect("Could not list functions");
+        assert_eq!(
+            serde_json::json!([
+                { "name": "a", "async": false, "arity": 1, "signature": "a(x: number)" },
+                { "name": "b", "async": false, "arity": null, "signature": null },
+                { "name": "c", "async": true, "arity": null, "signature": null },
+            ]),
+            functions
+        );
+
+        // The same list is available to the host
+        let functions = runtime
+            .registered_functions()
+            .expect("Could not list functions");
+        let names: Vec<_> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(vec!["a", "b", "c"], names);
+        assert_eq!(Some(1), functions[0].info.arity);
+        assert!(!functions[1].is_async);
+        assert!(functions[2].is_async);
+
+        let value: usize = runtime
+            .eval("rustyscript.functions.a(2)")
+            .expect("Could not call function");
+        assert_eq!(2, value);
+    }
+
+    #[test]
+    fn test_replace_function() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("f", |_| Ok(serde_json::json!(1)))
+            .expect("Could not register function");
+        runtime
+            .register_async_function("g", |_| Box::pin(async { Ok(serde_json::json!(1)) }))
+            .expect("Could not register function");
+
+        runtime
+            .replace_function("f", |_| Ok(serde_json::json!(2)))
+            .expect("Could not replace function");
+        let value: usize = runtime
+            .eval("rustyscript.functions.f()")
+            .expect("Could not call function");
+        assert_eq!(2, value);
+
+        runtime
+            .replace_function("g", |_| Ok(serde_json::json!(2)))
+            .expect_err("Replaced an async function with a sync one");
+        runtime
+            .replace_function("h", |_| Ok(serde_json::json!(2)))
+            .expect_err("Replaced an unregistered function");
+
+        assert!(runtime
+            .unregister_function("f")
+            .expect("Could not unregister function"));
+        assert!(runtime
+            .unregister_function("g")
+            .expect("Could not unregister function"));
+        assert!(!runtime
+            .unregister_function("f")
+            .expect("Could not unregister function"));
+
+        runtime
+            .eval::<usize>("rustyscript.functions.f()")
+            .expect_err("Called an unregistered function");
+        let functions: Vec<serde_json::Value> = runtime
+            .eval("rustyscript.functions()")
+            .expect("Could not list functions");
+        assert!(functions.is_empty());
+    }
+
+    #[test]
+    fn test_call_context() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function_with_context("context", |_, context| {
+                Ok(serde_json::json!([
+                    context.function,
+                    context.module,
+                    context.request_id
+                ]))
+            })
+            .expect("Could not register function");
+        runtime
+            .register_async_function_with_context("contextAsync", |_, context| {
+                Box::pin(
+                    async move { Ok(serde_json::json!([context.function, context.request_id])) },
+                )
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test_context.js",
+            "
+            export const sync = () => rustyscript.functions.context();
+            export const async_ = () => rustyscript.async_functions.contextAsync();
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let (function, specifier, request_id): (String, Option<String>, Option<String>) = runtime
+            .call_function(Some(&module), "sync", json_args!())
+            .expect("Could not call function");
+        assert_eq!("context", function);
+        assert!(specifier.is_some_and(|s| s.ends_with("test_context.js")));
+        assert_eq!(None, request_id);
+
+        runtime
+            .set_request_id(Some("req-42".to_string()))
+            .expect("Could not set request ID");
+        let (function, request_id): (String, Option<String>) = runtime
+            .call_function(Some(&module), "async_", json_args!())
+            .expect("Could not call function");
+        assert_eq!("contextAsync", function);
+        assert_eq!(Some("req-42".to_string()), request_id);
+    }
+
+    #[test]
+    fn test_strict_top_level_await() {
+        let stuck = Module::new("test_tla_stuck.js", "await new Promise(() => {});");
+        let slow = Module::new(
+            "test_tla_slow.js",
+            "await new Promise(r => setTimeout(r, 10_000));",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            strict_top_level_await: true,
+            timeout: std::time::Duration::from_millis(100),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        for module in [&stuck, &slow] {
+            match runtime.load_module(module) {
+                Err(Error::UnresolvedTopLevelAwait(name)) => {
+                    assert_eq!(module.filename(), name);
+                }
+                other => panic!("Expected an unresolved top-level await, got {other:?}"),
+            }
+        }
+
+        let module = Module::new(
+            "test_tla_ok.js",
+            "export const value = await new Promise(r => setTimeout(() => r(2), 10));",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .get_value(Some(&module), "value")
+            .expect("Could not get value");
+        assert_eq!(2, value);
+
+        // Without the option, a stuck await is reported some other way
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let e = runtime.load_module(&stuck).unwrap_err();
+        assert!(!matches!(e, Error::UnresolvedTopLevelAwait(_)), "{e}");
+    }
+
+    #[test]
+    fn test_pending_promise_count() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime.pending_promise_count().unwrap_err();
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            track_promises: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = Module::new(
+            "test_pending_promises.js",
+            "
+            export const settled = async () => 1;
+            export const leak = () => { globalThis.leaked = new Promise(() => {}); };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let before = runtime.pending_promise_count().unwrap();
+
+        let _: i64 = runtime
+            .call_function(Some(&module), "settled", json_args!())
+            .expect("Could not call function");
+        assert_eq!(before, runtime.pending_promise_count().unwrap());
+
+        let _: Undefined = runtime
+            .call_function(Some(&module), "leak", json_args!())
+            .expect("Could not call function");
+        assert_eq!(before + 1, runtime.pending_promise_count().unwrap());
+    }
+
+    #[test]
+    fn test_exception_handlers() {
+        let errors = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let (rejections, exceptions) = (errors.clone(), errors.clone());
+        let mut runtime = Runtime::builder()
+            .on_unhandled_rejection(move |e| rejections.borrow_mut().push(e.value.clone()))
+            .on_uncaught_exception(move |e| exceptions.borrow_mut().push(e.value.clone()))
+            .build()
+            .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test_exception_handlers.js",
+            "
+            export const f = () => new Promise(resolve => {
+                Promise.reject({ code: 1 });
+                setTimeout(() => { resolve(3); throw { code: 4 }; }, 1);
+            });
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .call_function(Some(&module), "f", json_args!())
+            .expect("Handled errors should not fail the call");
+        assert_eq!(3, value);
+
+        let mut codes: Vec<_> = errors
+            .borrow()
+            .iter()
+            .map(|value| value.as_ref().and_then(|v| v["code"].as_i64()))
+            .collect();
+        codes.sort();
+        assert_eq!(vec![Some(1), Some(4)], codes);
+
+        // Without handlers, the errors fail the call
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_exception_handlers.js",
+            "export const f = () => new Promise(r => setTimeout(() => { r(1); throw new Error('timer'); }, 1));",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        runtime
+            .call_function::<i64>(Some(&module), "f", json_args!())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_drive_event_loop() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        runtime
+            .eval::<()>("globalThis.fired = 0; setTimeout(() => fired++, 50)")
+            .expect("Could not eval");
+
+        let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // The timer is not due yet, so a single poll leaves it pending
+        let idle = tokio_runtime.block_on(runtime.poll_once()).unwrap();
+        assert!(!idle);
+
+        tokio_runtime
+            .block_on(runtime.run_event_loop(Default::default()))
+            .expect("Could not run the event loop");
+        let fired: i64 = runtime.eval("fired").unwrap();
+        assert_eq!(1, fired);
+
+        let idle = tokio_runtime.block_on(runtime.poll_once()).unwrap();
+        assert!(idle);
+    }
+
+    #[test]
+    fn test_interrupt_handle() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_interrupt_handle.js",
+            "
+            export const spin = () => { while (true) {} };
+            export const spin_async = async () => {
+                await new Promise(r => setTimeout(r, 1));
+                while (true) {}
+            };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        for function in ["spin", "spin_async"] {
+            let handle = runtime.interrupt_handle();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                handle.terminate();
+            });
+
+            let e = runtime
+                .call_function::<()>(Some(&module), function, json_args!())
+                .unwrap_err();
+            assert!(matches!(e, Error::Cancelled(_)), "{function}: {e}");
+        }
+
+        // An interrupt while nothing is running does not stop the next call
+        runtime.interrupt_handle().terminate();
+        let value: i64 = runtime.eval("1 + 1").expect("Could not eval");
+        assert_eq!(2, value);
+    }
+
+    #[test]
+    fn test_run_async() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        // JS waits on a value that is only sent once the rust future runs
+        let (sender, receiver) = tokio::sync::oneshot::channel::<i64>();
+        let receiver = std::rc::Rc::new(std::cell::RefCell::new(Some(receiver)));
+        runtime
+            .register_async_function("receive", move |_| {
+                let receiver = receiver.borrow_mut().take();
+                Box::pin(async move {
+                    let receiver =
+                        receiver.ok_or_else(|| Error::Runtime("already received".to_string()))?;
+                    let value = receiver.await.map_err(|e| Error::Runtime(e.to_string()))?;
+                    Ok(value.into())
+                })
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test_run_async.js",
+            "export const f = async () => (await rustyscript.async_functions.receive()) + 1;",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let (value, ()) = runtime
+            .run_async(|runtime| async {
+                let js = runtime.call_function_async::<i64>(Some(&module), "f", json_args!());
+                let rust = async {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    sender
+                        .send(41)
+                        .map_err(|_| Error::Runtime("JS stopped waiting".to_string()))
+                };
+                tokio::try_join!(js, rust)
+            })
+            .expect("Could not run future");
+        assert_eq!(42, value);
+
+        // Errors from the future are returned as-is
+        let e = runtime
+            .run_async(|runtime| runtime.call_function_async::<i64>(None, "missing", json_args!()))
+            .unwrap_err();
+        assert!(matches!(e, Error::ValueNotFound(_)), "{e}");
+    }
+
+    #[test]
+    fn test_callback_errors() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("rich", |_| {
+                Err(crate::CallbackError::new("rich failure")
+                    .with_name("LimitError")
+                    .with_code("E_LIMIT")
+                    .with_data(serde_json::json!({ "limit": 5 }))
+                    .into())
+            })
+            .expect("Could not register function");
+        runtime
+            .register_function("plain", |_| {
+                Err(Error::Runtime("plain failure".to_string()))
+            })
+            .expect("Could not register function");
+        runtime
+            .register_async_function("rich_async", |_| {
+                Box::pin(async {
+                    Err(crate::CallbackError::new("async failure")
+                        .with_code("E_ASYNC")
+                        .into())
+                })
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test_callback_errors.js",
+            "
+            const describe = (e) => [
+                e instanceof Error, e instanceof rustyscript.CallbackError,
+                e.name, e.message, e.code ?? null, e.data ?? null,
+            ];
+            export const call = (name) => {
+                try { rustyscript.functions[name](); } catch (e) { return describe(e); }
+            };
+            export const callAsync = async (name) => {
+                try { await rustyscript.async_functions[name](); } catch (e) { return describe(e); }
+            };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let value: serde_json::Value = runtime
+            .call_function(Some(&module), "call", json_args!("rich"))
+            .unwrap();
+        assert_eq!(
+            serde_json::json!([true, true, "LimitError", "rich failure", "E_LIMIT", { "limit": 5 }]),
+            value
+        );
+
+        let value: serde_json::Value = runtime
+            .call_function(Some(&module), "call", json_args!("plain"))
+            .unwrap();
+        assert_eq!(
+            serde_json::json!([true, true, "Error", "plain failure", "Runtime", null]),
+            value
+        );
+
+        let value: serde_json::Value = runtime
+            .call_function(Some(&module), "callAsync", json_args!("rich_async"))
+            .unwrap();
+        assert_eq!(
+            serde_json::json!([
+                true,
+                true,
+                "CallbackError",
+                "async failure",
+                "E_ASYNC",
+                null
+            ]),
+            value
+        );
+
+        // Uncaught, the error reaches the host as an exception with the same name and message
+        let e = runtime
+            .eval::<()>("rustyscript.functions.rich()")
+            .unwrap_err();
+        assert!(e.to_string().contains("LimitError: rich failure"), "{e}");
+    }
+
+    #[test]
+    fn test_namespaced_functions() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function_ns("db", "query", |args| Ok(args[0].clone()))
+            .expect("Could not register function");
+        runtime
+            .register_async_function_ns("db", "fetch", |args| {
+                let value = args[0].clone();
+                Box::pin(async move { Ok(value) })
+            })
+            .expect("Could not register function");
+
+        let value: usize = runtime
+            .eval("rustyscript.db.query(2)")
+            .expect("Could not call namespaced function");
+        assert_eq!(2, value);
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const f = async () => await rustyscript.db.fetch(3);
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        let value: usize = runtime
+            .call_function(Some(&module), "f", json_args!())
+            .expect("Could not call async namespaced function");
+        assert_eq!(3, value);
+
+        let value: bool = runtime
+            .eval("rustyscript.other === undefined")
+            .expect("Could not check unknown namespace");
+        assert!(value);
+
+        // Disabled namespaces hide all of their functions, until enabled again
+        let kind: String = runtime
+            .eval("globalThis.query = rustyscript.db.query; typeof query")
+            .expect("Could not get namespaced function");
+        assert_eq!("function", kind);
+        runtime
+            .set_namespace_enabled("db", false)
+            .expect("Could not disable namespace");
+        let value: bool = runtime
+            .eval("rustyscript.db === undefined")
+            .expect("Could not check disabled namespace");
+        assert!(value);
+        let e = runtime.eval::<usize>("query(2)").unwrap_err();
+        assert!(e.to_string().contains("namespace is disabled"), "{e}");
+        runtime
+            .call_function::<usize>(Some(&module), "f", json_args!())
+            .expect_err("Called a function in a disabled namespace");
+        assert!(runtime.registered_functions().unwrap().is_empty());
+
+        runtime
+            .set_namespace_enabled("db", true)
+            .expect("Could not enable namespace");
+        let value: usize = runtime
+            .eval("query(4)")
+            .expect("Could not call namespaced function");
+        assert_eq!(4, value);
+    }
+
+    #[cfg(feature = "kv")]
+    #[test]
+    fn test_kv_store() {
+        use crate::{ExtensionOptions, KvStore, MemoryKvStore};
+
+        let store = MemoryKvStore::default();
+        store
+            .set("a", serde_json::json!(1))
+            .expect("Could not set value");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                kv_store: Some(Box::new(store.clone())),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let value: usize = runtime
+            .eval("rustyscript.kv.get('a')")
+            .expect("Could not get value");
+        assert_eq!(1, value);
+
+        runtime
+            .eval::<Undefined>("rustyscript.kv.set('b', { c: 2 })")
+            .expect("Could not set value");
+        assert_eq!(
+            Some(serde_json::json!({ "c": 2 })),
+            store.get("b").expect("Could not get value")
+        );
+
+        let deleted: bool = runtime
+            .eval("rustyscript.kv.delete('a')")
+            .expect("Could not delete value");
+        assert!(deleted);
+        assert_eq!(None, store.get("a").expect("Could not get value"));
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_crypto() {
+        let module = Module::new(
+            "test.js",
+            "
+            const hex = (buffer) => [...new Uint8Array(buffer)]
+                .map((b) => b.toString(16).padStart(2, '0'))
+                .join('');
+
+            export async function test() {
+                const data = new Uint8Array([97, 98, 99]);
+                const random = crypto.getRandomValues(new Uint8Array(16));
+                const uuid = crypto.randomUUID();
+                const digest = hex(await crypto.subtle.digest('SHA-256', data));
+
+                const hmacKey = await crypto.subtle.generateKey(
+                    { name: 'HMAC', hash: 'SHA-256' }, false, ['sign', 'verify']
+                );
+                const mac = await crypto.subtle.sign('HMAC', hmacKey, data);
+                const hmac = await crypto.subtle.verify('HMAC', hmacKey, mac, data);
+
+                const aesKey = await crypto.subtle.generateKey(
+                    { name: 'AES-GCM', length: 256 }, false, ['encrypt', 'decrypt']
+                );
+                const iv = crypto.getRandomValues(new Uint8Array(12));
+                const sealed = await crypto.subtle.encrypt({ name: 'AES-GCM', iv }, aesKey, data);
+                const opened = hex(await crypto.subtle.decrypt({ name: 'AES-GCM', iv }, aesKey, sealed));
+
+                const rsa = { name: 'RSASSA-PKCS1-v1_5', hash: 'SHA-256' };
+                const rsaKeys = await crypto.subtle.generateKey(
+                    { ...rsa, modulusLength: 1024, publicExponent: new Uint8Array([1, 0, 1]) },
+                    false,
+                    ['sign', 'verify']
+                );
+                const signature = await crypto.subtle.sign(rsa, rsaKeys.privateKey, data);
+                const rsaValid = await crypto.subtle.verify(rsa, rsaKeys.publicKey, signature, data);
+
+                return [random.length, uuid, digest, hmac, opened, rsaValid];
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        let (len, uuid, digest, hmac, opened, rsa): (usize, String, String, bool, String, bool) =
+            runtime
+                .call_function(Some(&module), "test", json_args!())
+                .expect("Could not call function");
+
+        assert_eq!(16, len);
+        assert_eq!(36, uuid.len());
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            digest
+        );
+        assert!(hmac);
+        assert_eq!("616263", opened);
+        assert!(rsa);
+    }
+
+    #[cfg(all(feature = "web_stub", not(feature = "web")))]
+    #[test]
+    fn test_web_stub() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        let value: (Vec<u8>, String, String) = runtime
+            .eval(
+                "
+                const bytes = new TextEncoder().encode('h\\u00e9 \\u{1F980}');
+                const decoder = new TextDecoder();
+                const streamed = decoder.decode(bytes.subarray(0, 4), { stream: true })
+                    + decoder.decode(bytes.subarray(4));
+                [[...bytes], streamed, new URL('/a?b=1', 'https://example.com').searchParams.get('b')]
+            ",
+            )
+            .expect("Could not encode text");
+        assert_eq!(vec![104, 195, 169, 32, 240, 159, 166, 128], value.0);
+        assert_eq!("hé 🦀", value.1);
+        assert_eq!("1", value.2);
+
+        let value: (String, String, String) = runtime
+            .eval("[btoa('hello'), atob('aGVsbG8='), new TextDecoder().decode(new Uint8Array([0xff]))]")
+            .expect("Could not encode base64");
+        assert_eq!(
+            ("aGVsbG8=".into(), "hello".into(), "\u{fffd}".into()),
+            value
+        );
+
+        let e = runtime
+            .eval::<Undefined>(
+                "new TextDecoder('utf-8', { fatal: true }).decode(new Uint8Array([0xff]))",
+            )
+            .unwrap_err();
+        assert!(e.to_string().contains("not valid"), "{e}");
+
+        let name: String = runtime
+            .eval("try { btoa('\\u{1F980}') } catch (e) { e instanceof DOMException && e.name }")
+            .expect("Could not encode base64");
+        assert_eq!("InvalidCharacterError", name);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn test_websocket() {
+        use crate::{ExtensionOptions, MessageDirection, WebSocketMessage, WebSocketPolicy};
+        use deno_core::url::Url;
+        use std::{cell::RefCell, rc::Rc};
+        use tokio_tungstenite::tungstenite::{self, Message};
+
+        struct Policy(Rc<RefCell<Vec<String>>>);
+        impl WebSocketPolicy for Policy {
+            fn allow_connect(&self, url: &Url) -> bool {
+                url.host_str() == Some("127.0.0.1")
+            }
+
+            fn allow_message(
+                &self,
+                _url: &Url,
+                direction: MessageDirection,
+                message: WebSocketMessage,
+            ) -> bool {
+                if let WebSocketMessage::Text(text) = message {
+                    self.0.borrow_mut().push(format!("{direction:?} {text}"));
+                    return !text.contains("secret");
+                }
+                true
+            }
+        }
+
+        // Echoes text messages back, then answers the client's close
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Could not bind");
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("Could not accept");
+            let mut socket = tungstenite::accept(stream).expect("Could not accept the websocket");
+            loop {
+                match socket.read().expect("Could not read a message") {
+                    message @ Message::Text(_) => {
+                        socket.send(message).expect("Could not send a message");
+                    }
+
+                    // The reply to the close is queued by `read`, and sent by the flush
+                    Message::Close(_) => {
+                        let _ = socket.flush();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                websocket_policy: Some(Rc::new(Policy(seen.clone()))),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export function echo(url) {
+                return new Promise((resolve) => {
+                    const messages = [];
+                    const ws = new WebSocket(url);
+                    ws.onopen = () => {
+                        try {
+                            ws.send('a secret');
+                        } catch {
+                            messages.push('blocked');
+                        }
+                        ws.send('hello');
+                    };
+                    ws.onmessage = (event) => {
+                        messages.push(event.data);
+                        ws.close(1000, 'done');
+                    };
+                    ws.onclose = (event) => resolve([messages, event.code, event.reason, event.wasClean]);
+                });
+            }
+
+            export function denied(url) {
+                return new Promise((resolve) => {
+                    const ws = new WebSocket(url);
+                    ws.addEventListener('close', (event) => resolve(event.code));
+                });
+            }
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let (messages, code, reason, clean): (Vec<String>, u16, String, bool) = runtime
+            .call_function(
+                Some(&module),
+                "echo",
+                json_args!(format!("ws://127.0.0.1:{port}/echo")),
+            )
+            .expect("Could not call function");
+        server.join().expect("Server failed");
+
+        assert_eq!(vec!["blocked".to_string(), "hello".to_string()], messages);
+        assert_eq!((1000, "done".to_string(), true), (code, reason, clean));
+        assert_eq!(
+            vec!["Sent a secret", "Sent hello", "Received hello"],
+            *seen.borrow()
+        );
+
+        // The policy sees the host the connection would be made to, not just the string
+        for url in ["ws://example.com", "ws://127.0.0.1@example.com"] {
+            let code: u16 = runtime
+                .call_function(Some(&module), "denied", json_args!(url))
+                .expect("Could not call function");
+            assert_eq!(1006, code);
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_fs() {
+        use crate::{ExtensionOptions, FsPermissions};
+
+        let dir = std::env::temp_dir().join(format!("rustyscript_test_fs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("Could not create directory");
+        let file = dir.join("data.txt").to_string_lossy().replace('\\', "/");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            extension_options: ExtensionOptions {
+                fs_permissions: FsPermissions {
+                    read: vec![dir.clone()],
+                    write: vec![dir.clone()],
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            import { readFile, writeFile, appendFile } from 'node:fs/promises';
+
+            export async function test(path) {
+                await Deno.writeTextFile(path, 'hello');
+                await appendFile(path, ' world', 'utf8');
+                const bytes = await readFile(path);
+                return [await readFile(path, 'utf8'), bytes.length];
+            }
+
+            export async function denied(path) {
+                try {
+                    await Deno.readTextFile(path);
+                } catch (e) {
+                    return e.message;
+                }
+            }
+
+            export async function deniedWrite(path) {
+                try {
+                    await Deno.writeTextFile(path, 'escaped');
+                } catch (e) {
+                    return e.message;
+                }
+            }
+
+            export async function writeNumber(path) {
+                try {
+                    await writeFile(path, 5);
+                } catch (e) {
+                    return e instanceof TypeError;
+                }
+                return false;
+            }
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let (text, len): (String, usize) = runtime
+            .call_function(Some(&module), "test", json_args!(file.clone()))
+            .expect("Could not call function");
+        assert_eq!("hello world", text);
+        assert_eq!(11, len);
+
+        // Numbers are not taken as a length of zero bytes
+        let rejected: bool = runtime
+            .call_function(Some(&module), "writeNumber", json_args!(file.clone()))
+            .expect("Could not call function");
+        assert!(rejected);
+
+        // Paths outside the granted directory, including through `..`, are refused
+        let name = format!("rustyscript_test_fs_{}.txt", std::process::id());
+        let outside = std::env::temp_dir().join(&name);
+        std::fs::write(&outside, "secret").expect("Could not write file");
+        let message: String = runtime
+            .call_function(
+                Some(&module),
+                "denied",
+                json_args!(format!("{}/../{name}", dir.display())),
+            )
+            .expect("Could not call function");
+        assert!(message.contains("denied"), "{message}");
+
+        // A dangling symlink cannot be used to create a file outside the granted directory
+        #[cfg(unix)]
+        {
+            let target = std::env::temp_dir().join(format!("{name}.missing"));
+            let link = dir.join("link.txt");
+            std::os::unix::fs::symlink(&target, &link).expect("Could not create symlink");
+            let message: String = runtime
+                .call_function(
+                    Some(&module),
+                    "deniedWrite",
+                    json_args!(link.to_string_lossy()),
+                )
+                .expect("Could not call function");
+            assert!(message.contains("denied"), "{message}");
+            assert!(!target.exists());
+        }
+
+        std::fs::remove_file(&outside).expect("Could not remove file");
+        std::fs::remove_dir_all(&dir).expect("Could not remove directory");
+    }
+
+    #[cfg(all(feature = "process", unix))]
+    #[test]
+    fn test_process() {
+        use crate::{ExtensionOptions, ProcessPermissions};
+
+        let dir =
+            std::env::temp_dir().join(format!("rustyscript_test_process_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("inner")).expect("Could not create directory");
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            timeout: Duration::from_secs(2),
+            extension_options: ExtensionOptions {
+                process_permissions: ProcessPermissions {
+                    allow: ["echo", "./echo", "pwd", "sleep"]
+                        .map(String::from)
+                        .to_vec(),
+                    env: vec!["PATH".to_string(), "GREETING".to_string()],
+                    cwd: vec![dir.join("inner")],
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export async function test() {
+                const command = new Deno.Command('echo', { args: ['hello'] });
+                const { code, success, stdout } = await command.output();
+                const sync = command.outputSync();
+                return [code, success, Deno.core.decode(stdout), sync.stdout.length];
+            }
+
+            export function denied(command = 'sh', options = { args: ['-c', 'echo no'] }) {
+                try {
+                    new Deno.Command(command, options).outputSync();
+                } catch (e) {
+                    return e.message;
+                }
+            }
+
+            export function run(options, command = 'echo') {
+                return Deno.core.decode(new Deno.Command(command, options).outputSync().stdout);
+            }
+
+            export async function runAsync(options, command = 'echo') {
+                return Deno.core.decode((await new Deno.Command(command, options).output()).stdout);
+            }
+        ",
+        );
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let (code, success, stdout, len): (i32, bool, String, usize) = runtime
+            .call_function(Some(&module), "test", json_args!())
+            .expect("Could not call function");
+        assert_eq!((0, true), (code, success));
+        assert_eq!("hello\n", stdout);
+        assert_eq!(6, len);
+
+        let message: String = runtime
+            .call_function(Some(&module), "denied", json_args!())
+            .expect("Could not call function");
+        assert!(message.contains("denied"), "{message}");
+
+        // Allowed names are found in the host's PATH, not the script's
+        use std::os::unix::fs::PermissionsExt;
+        let fake = dir.join("echo");
+        std::fs::write(&fake, "#!/bin/sh\necho fake\n").expect("Could not write file");
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755))
+            .expect("Could not set permissions");
+        let dir = dir.to_string_lossy().to_string();
+
+        let stdout: String = runtime
+            .call_function(
+                Some(&module),
+                "run",
+                json_args!(serde_json::json!({
+                    "args": ["real"],
+                    "env": { "PATH": dir },
+                })),
+            )
+            .expect("Could not call function");
+        assert_eq!("real\n", stdout);
+
+        // Relative paths would be resolved against the script's working directory
+        let message: String = runtime
+            .call_function(
+                Some(&module),
+                "denied",
+                json_args!("./echo", serde_json::json!({ "cwd": dir })),
+            )
+            .expect("Could not call function");
+        assert!(message.contains("denied"), "{message}");
+
+        // Only the environment variables and working directories granted can be set
+        for options in [
+            serde_json::json!({ "env": { "LD_PRELOAD": "/tmp/evil.so" } }),
+            serde_json::json!({ "env": { "NODE_OPTIONS": "--require evil" } }),
+            serde_json::json!({ "cwd": dir }),
+            serde_json::json!({ "cwd": format!("{dir}/inner/..") }),
+            serde_json::json!({ "cwd": "." }),
+        ] {
+            let message: String = runtime
+                .call_function(Some(&module), "denied", json_args!("echo", options))
+                .expect("Could not call function");
+            assert!(message.contains("denied"), "{message}");
+        }
+
+        let stdout: String = runtime
+            .call_function(
+                Some(&module),
+                "run",
+                json_args!(serde_json::json!({ "cwd": format!("{dir}/inner") }), "pwd"),
+            )
+            .expect("Could not call function");
+        let inner = std::fs::canonicalize(format!("{dir}/inner")).expect("Could not resolve");
+        assert_eq!(format!("{}\n", inner.display()), stdout);
+
+        // A command still running when the call times out is killed, rather than waited for
+        for function in ["run", "runAsync"] {
+            let started = std::time::Instant::now();
+            let e = runtime
+                .call_function::<String>(
+                    Some(&module),
+                    function,
+                    json_args!(serde_json::json!({ "args": ["30"] }), "sleep"),
+                )
+                .unwrap_err();
+            assert!(matches!(e, Error::Timeout(_)), "{e}");
+            assert!(started.elapsed() < Duration::from_secs(10));
+        }
+
+        std::fs::remove_dir_all(&dir).expect("Could not remove directory");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+
+        let rows: Vec<serde_json::Value> = runtime
+            .eval(
+                "
+                const db = rustyscript.sqlite.open();
+                db.execute('CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL)');
+                db.execute('INSERT INTO users (name, score) VALUES (?, ?), (?, ?)', ['a', 1.5, 'b', null]);
+
+                // Connections to the in-memory database share its data
+                rustyscript.sqlite.open(':memory:').query('SELECT * FROM users ORDER BY id')
+            ",
+            )
+            .expect("Could not query the database");
+        assert_eq!(
+            vec![
+                serde_json::json!({ "id": 1, "name": "a", "score": 1.5 }),
+                serde_json::json!({ "id": 2, "name": "b", "score": null }),
+            ],
+            rows
+        );
+
+        let e = runtime
+            .eval::<Undefined>("rustyscript.sqlite.open('test.db')")
+            .unwrap_err();
+        assert!(e.to_string().contains("denied"), "{e}");
+
+        // Other files cannot be reached through SQL either
+        let outside = std::env::temp_dir()
+            .join(format!("rustyscript_test_sqlite_{}.db", std::process::id()))
+            .to_string_lossy()
+            .replace('\\', "/");
+        for sql in [
+            format!("ATTACH DATABASE '{outside}' AS other"),
+            format!("VACUUM INTO '{outside}'"),
+        ] {
+            runtime
+                .eval::<Undefined>(&format!("rustyscript.sqlite.open().execute(\"{sql}\")"))
+                .expect_err("Did not reject a statement opening another file");
+            assert!(!std::path::Path::new(&outside).exists());
         }
     }
 
-    /// Loads a module into a new runtime, executes the entry function and returns the
-    /// result of the module's execution, deserialized into the specified Rust type (`T`).
-    ///
-    /// # Arguments
-    /// * `module` - A `Module` object containing the module's filename and contents.
-    /// * `side_modules` - A set of additional modules to be loaded into memory for use
-    /// * `runtime_options` - Options for the creation of the runtime
-    /// * `entrypoint_args` - Arguments to pass to the entrypoint function
-    ///
-    /// # Returns
-    /// A `Result` containing the deserialized result of the entrypoint execution (`T`)
-    /// if successful, or an error (`Error`) if the entrypoint is missing, the execution fails,
-    /// or the result cannot be deserialized.
-    ///
-    /// # Example
-    ///
-    /// ```rust
-    /// // Create a module with filename and contents
-    /// use rustyscript::{json_args, Runtime, Module, Error};
-    ///
-    /// # fn main() -> Result<(), Error> {
-    /// let module = Module::new("test.js", "rustyscript.register_entrypoint(() => 2)");
-    /// let value: usize = Runtime::execute_module(&module, vec![], Default::default(), json_args!())?;
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn execute_module<T>(
-        module: &Module,
-        side_modules: Vec<&Module>,
-        runtime_options: RuntimeOptions,
-        entrypoint_args: &FunctionArguments,
-    ) -> Result<T, Error>
-    where
-        T: deno_core::serde::de::DeserializeOwned,
-    {
-        let mut runtime = Runtime::new(runtime_options)?;
-        let module = runtime.load_modules(module, side_modules)?;
-        let value: T = runtime.call_entrypoint(&module, entrypoint_args)?;
-        Ok(value)
+    #[cfg(feature = "deno_kv")]
+    #[test]
+    fn test_deno_kv() {
+        let module = Module::new(
+            "test.js",
+            "
+            export async function test() {
+                const kv = await Deno.openKv();
+                await kv.set(['users', 1], { name: 'a' });
+                await kv.set(['users', 2], { name: 'b' });
+                await kv.set(['other'], 3);
+
+                const entry = await kv.get(['users', 1]);
+                const missing = await kv.get(['users', 3]);
+
+                const names = [];
+                for await (const user of kv.list({ prefix: ['users'] })) {
+                    names.push(user.value.name);
+                }
+
+                const stale = await kv.atomic()
+                    .check({ key: ['users', 1], versionstamp: null })
+                    .set(['users', 1], { name: 'c' })
+                    .commit();
+
+                await kv.delete(['other']);
+
+                await kv.set(['bin', new Uint8Array([0, 15, 255])], 4);
+                const bytes = [];
+                for await (const entry of kv.list({ prefix: ['bin'] })) {
+                    bytes.push(Array.from(entry.key[1]));
+                }
+                return [entry.value.name, missing.versionstamp, names, stale.ok, (await kv.get(['other'])).value, bytes];
+            }
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+        let value: serde_json::Value = runtime
+            .call_function(Some(&module), "test", json_args!())
+            .expect("Could not call function");
+        assert_eq!(
+            serde_json::json!(["a", null, ["a", "b"], false, null, [[0, 15, 255]]]),
+            value
+        );
     }
-}
 
-#[cfg(test)]
-mod test_runtime {
-    use crate::json_args;
-    use std::time::Duration;
+    #[cfg(feature = "deno_kv")]
+    #[test]
+    fn test_deno_kv_shared_versionstamps() {
+        use crate::{ExtensionOptions, MemoryKvStore};
 
-    use super::*;
-    use deno_core::extension;
+        let module = Module::new(
+            "test.js",
+            "
+            export async function set() {
+                const kv = await Deno.openKv();
+                return (await kv.set(['a'], 1)).versionstamp;
+            }
+
+            export async function update(versionstamp) {
+                const kv = await Deno.openKv();
+                const result = await kv.atomic()
+                    .check({ key: ['a'], versionstamp })
+                    .set(['a'], 2)
+                    .commit();
+                return result.ok;
+            }
+        ",
+        );
+
+        let store = MemoryKvStore::default();
+        let mut runtimes = (0..2)
+            .map(|_| {
+                let mut runtime = Runtime::new(RuntimeOptions {
+                    extension_options: ExtensionOptions {
+                        kv_store: Some(Box::new(store.clone())),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .expect("Could not create the runtime");
+                let module = runtime
+                    .load_modules(&module, vec![])
+                    .expect("Could not load module");
+                (runtime, module)
+            })
+            .collect::<Vec<_>>();
+
+        let mut set = |i: usize| -> String {
+            let (runtime, module) = &mut runtimes[i];
+            runtime
+                .call_function(Some(module), "set", json_args!())
+                .expect("Could not set value")
+        };
+        let first = set(0);
+        let second = set(1);
+        assert!(second > first, "{second} <= {first}");
+
+        // A versionstamp from one runtime is valid in the other
+        let (runtime, module) = &mut runtimes[0];
+        let ok: bool = runtime
+            .call_function(Some(module), "update", json_args!(second))
+            .expect("Could not commit");
+        assert!(ok);
+    }
 
     #[test]
-    fn test_new() {
-        Runtime::new(Default::default()).expect("Could not create the runtime");
+    fn test_callback_middleware() {
+        struct Layer;
+        impl crate::CallbackLayer for Layer {
+            fn on_call(&self, name: &str, args: &mut Vec<serde_json::Value>) -> Result<(), Error> {
+                if name == "secret" {
+                    return Err(Error::Runtime("Access denied".to_string()));
+                }
+                args.push(serde_json::json!(1));
+                Ok(())
+            }
 
-        extension!(test_extension);
-        Runtime::new(RuntimeOptions {
-            extensions: vec![test_extension::init_ops_and_esm()],
+            fn on_result(
+                &self,
+                _name: &str,
+                result: Result<serde_json::Value, Error>,
+            ) -> Result<serde_json::Value, Error> {
+                let value = result?.as_i64().unwrap_or_default();
+                Ok(serde_json::json!(value * 10))
+            }
+        }
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            callback_middleware: vec![Box::new(Layer)],
             ..Default::default()
         })
-        .expect("Could not create runtime with extensions");
+        .expect("Could not create the runtime");
+        runtime
+            .register_function("sum", |args| {
+                Ok(serde_json::json!(args
+                    .iter()
+                    .filter_map(|v| v.as_i64())
+                    .sum::<i64>()))
+            })
+            .expect("Could not register function");
+        runtime
+            .register_function("secret", |_| Ok(serde_json::json!(0)))
+            .expect("Could not register function");
+
+        let value: i64 = runtime
+            .eval("rustyscript.functions.sum(2)")
+            .expect("Could not call function");
+        assert_eq!(30, value);
+
+        runtime
+            .eval::<Undefined>("rustyscript.functions.secret()")
+            .expect_err("Did not reject call");
     }
 
     #[test]
-    fn test_into_arg() {
-        assert_eq!(2, Runtime::into_arg(2));
-        assert_eq!("test", Runtime::into_arg("test"));
-        assert_ne!("test", Runtime::into_arg(2));
+    fn test_path() {
+        let module = Module::new(
+            "test.js",
+            "
+            globalThis.a = { b: { c: 2 } };
+            export const config = { limits: { maxUsers: 10 } };
+            export const getMaxUsers = () => config.limits.maxUsers;
+        ",
+        );
+
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let value: usize = runtime
+            .get_path(Some(&module), "a.b.c")
+            .expect("Could not find global path");
+        assert_eq!(2, value);
+
+        let value: usize = runtime
+            .get_path(Some(&module), "config.limits.maxUsers")
+            .expect("Could not find export path");
+        assert_eq!(10, value);
+
+        let e = runtime
+            .get_path::<usize>(Some(&module), "config.bounds.maxUsers")
+            .expect_err("Did not detect missing segment");
+        assert!(matches!(e, Error::PathNotFound(segment, _) if segment == "bounds"));
+
+        runtime
+            .get_path::<usize>(Some(&module), "config.limits.maxUsers.value")
+            .expect_err("Did not detect non-object segment");
+
+        runtime
+            .set_path(Some(&module), "config.limits.maxUsers", &20)
+            .expect("Could not set export path");
+        let value: usize = runtime
+            .call_function(Some(&module), "getMaxUsers", json_args!())
+            .expect("Could not call export");
+        assert_eq!(20, value);
+
+        runtime
+            .set_path(None, "d", &"test")
+            .expect("Could not set global");
+        let value: String = runtime.eval("d").expect("Could not eval");
+        assert_eq!("test", value);
+
+        runtime
+            .set_path(Some(&module), "config.bounds.maxUsers", &20)
+            .expect_err("Did not detect missing segment");
     }
 
     #[test]
-    fn test_get_value() {
+    fn test_globals() {
+        let mut runtime = Runtime::new(RuntimeOptions {
+            globals: [("config".to_string(), serde_json::json!({ "maxUsers": 10 }))].into(),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+
+        // Globals are set before the module is evaluated
+        let module = Module::new("test.js", "export const maxUsers = config.maxUsers;");
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: usize = runtime
+            .get_value(Some(&module), "maxUsers")
+            .expect("Could not get value");
+        assert_eq!(10, value);
+
+        runtime
+            .set_global("a.b", &"dotted")
+            .expect("Could not set global");
+        let value: String = runtime.eval("globalThis['a.b']").expect("Could not eval");
+        assert_eq!("dotted", value);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .register_function("f", |_| Ok(serde_json::Value::Null))
+            .expect("Could not register function");
+
         let module = Module::new(
             "test.js",
             "
-            globalThis.a = 2;
-            export const b = 'test';
-            export const fnc = null;
+            rustyscript.functions.f();
+            rustyscript.functions.f();
+        ",
+        );
+        runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let stats = runtime.stats();
+        assert_eq!(1, stats.module_count);
+        assert_eq!(0, stats.pending_ops);
+        assert_eq!(Some(&2), stats.op_calls.get("call_registered_function"));
+        assert!(stats.used_heap_size > 0);
+        assert!(stats.eval_time > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const spin = async (ms) => {
+                const end = Date.now() + ms;
+                while (Date.now() < end) {
+                    await Promise.resolve();
+                }
+            };
+        ",
+        );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            time_budget: Some(Duration::from_millis(100)),
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
+        let e = runtime
+            .call_function::<Undefined>(Some(&module), "spin", json_args!(150))
+            .expect_err("Did not stop the call once the budget ran out");
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+        let e = runtime
+            .call_function::<Undefined>(Some(&module), "spin", json_args!(1))
+            .expect_err("Did not detect exhausted budget");
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+        let e = runtime
+            .eval::<usize>("1 + 1")
+            .expect_err("Did not detect exhausted budget");
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        // Synchronous code is stopped too
+        runtime.reset_budget();
+        let e = runtime
+            .eval::<Undefined>("while (true) {}")
+            .expect_err("Did not stop synchronous code");
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        runtime.reset_budget();
+        let value: usize = runtime.eval("1 + 1").expect("Did not reset budget");
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_messages() {
+        let module = Module::new(
+            "test.js",
+            "
+            rustyscript.postMessage('start');
+            export const f = (i) => rustyscript.postMessage({ progress: i });
         ",
         );
 
@@ -539,24 +4468,53 @@ mod test_runtime {
             .load_modules(&module, vec![])
             .expect("Could not load module");
 
+        runtime
+            .call_function::<Undefined>(Some(&module), "f", json_args!(1))
+            .expect("Could not call export");
+        runtime
+            .call_function::<Undefined>(Some(&module), "f", json_args!(2))
+            .expect("Could not call export");
+
+        assert_eq!(Some(serde_json::json!("start")), runtime.next_message());
         assert_eq!(
-            2,
-            runtime
-                .get_value::<usize>(Some(&module), "a")
-                .expect("Could not find global")
+            vec![
+                serde_json::json!({ "progress": 1 }),
+                serde_json::json!({ "progress": 2 })
+            ],
+            runtime.poll_messages()
         );
-        assert_eq!(
-            "test",
-            runtime
-                .get_value::<String>(Some(&module), "b")
-                .expect("Could not find export")
+        assert!(runtime.poll_messages().is_empty());
+        assert_eq!(None, runtime.next_message());
+    }
+
+    #[test]
+    fn test_strict_undefined() {
+        let module = Module::new(
+            "test.js",
+            "
+            export const f = () => undefined;
+            export const g = () => null;
+        ",
         );
+
+        let mut runtime = Runtime::new(RuntimeOptions {
+            strict_undefined: true,
+            ..Default::default()
+        })
+        .expect("Could not create the runtime");
+        let module = runtime
+            .load_modules(&module, vec![])
+            .expect("Could not load module");
+
         runtime
-            .get_value::<Undefined>(Some(&module), "c")
-            .expect_err("Could not detect null");
+            .call_function::<Undefined>(Some(&module), "f", json_args!())
+            .expect_err("Did not detect undefined");
         runtime
-            .get_value::<Undefined>(Some(&module), "d")
-            .expect_err("Could not detect undeclared");
+            .call_function::<Undefined>(Some(&module), "g", json_args!())
+            .expect("Did not allow null");
+        runtime
+            .eval::<Undefined>("undefined")
+            .expect_err("Did not detect undefined");
     }
 
     #[test]
@@ -666,6 +4624,90 @@ mod test_runtime {
             .expect_err("Did not interupt after timeout");
     }
 
+    #[test]
+    fn test_reload_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test.js",
+            "
+            export const value = 1;
+            rustyscript.register_entrypoint(() => 1);
+        ",
+        );
+        let old_module = runtime.load_module(&module).expect("Could not load module");
+
+        let module = Module::new(
+            "test.js",
+            "
+            export const value = 2;
+            rustyscript.register_entrypoint(() => 2);
+        ",
+        );
+        let new_module = runtime
+            .reload_module(&module)
+            .expect("Could not reload module");
+        assert_ne!(old_module.id(), new_module.id());
+
+        let value: usize = runtime
+            .get_value(Some(&new_module), "value")
+            .expect("Could not get reloaded export");
+        assert_eq!(2, value);
+
+        let value: usize = runtime
+            .call_entrypoint(&new_module, json_args!())
+            .expect("Could not call reloaded entrypoint");
+        assert_eq!(2, value);
+
+        let value: usize = runtime
+            .get_value(Some(&old_module), "value")
+            .expect("Could not get old export");
+        assert_eq!(1, value);
+    }
+
+    #[test]
+    fn test_reload_module_importers() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .load_module(&Module::new("reload_dep.js", "export const value = 1;"))
+            .expect("Could not load module");
+        let importer = Module::new(
+            "reload_importer.js",
+            "
+            import { value } from './reload_dep.js';
+            export const imported = value;
+        ",
+        );
+        let importer = runtime
+            .load_module(&importer)
+            .expect("Could not load importer");
+
+        let reloaded = runtime
+            .reload_module(&Module::new("reload_dep.js", "export const value = 2;"))
+            .expect("Could not reload module");
+        let value: usize = runtime
+            .get_value(Some(&reloaded), "value")
+            .expect("Could not get reloaded export");
+        assert_eq!(2, value);
+
+        // Importers keep the bindings of the first version, even if they are loaded after the reload
+        let late_importer = Module::new(
+            "reload_late_importer.js",
+            "
+            import { value } from './reload_dep.js';
+            export const imported = value;
+        ",
+        );
+        let late_importer = runtime
+            .load_module(&late_importer)
+            .expect("Could not load late importer");
+        for importer in [importer, late_importer] {
+            let value: usize = runtime
+                .get_value(Some(&importer), "imported")
+                .expect("Could not get imported value");
+            assert_eq!(1, value);
+        }
+    }
+
     #[test]
     fn test_call_entrypoint() {
         let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
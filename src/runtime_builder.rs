@@ -0,0 +1,333 @@
+use crate::{
+    cache_provider::ModuleCacheProvider, serde_json, CallbackLayer, DeterministicOptions, Error,
+    ExtensionOptions, ImportPolicy, JsException, Profile, Quota, ResolverHook, Runtime,
+    RuntimeOptions,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// A builder for [Runtime], as an alternative to filling in a [RuntimeOptions] struct
+/// The configuration is checked by `build`, before the isolate is created
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{Error, Runtime};
+/// use std::time::Duration;
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::builder()
+///     .timeout(Duration::from_secs(5))
+///     .strict_undefined(true)
+///     .build()?;
+///
+/// let value: i64 = runtime.eval("1 + 1")?;
+/// assert_eq!(2, value);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RuntimeBuilder(RuntimeOptions);
+
+impl RuntimeBuilder {
+    /// Create a new builder, with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder starting from an existing set of options
+    pub fn from_options(options: RuntimeOptions) -> Self {
+        Self(options)
+    }
+
+    /// Amount of time each call may run for before timing out
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.0.timeout = timeout;
+        self
+    }
+
+    /// Total amount of wall-clock time the runtime may spend in calls, across all calls
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.0.time_budget = Some(budget);
+        self
+    }
+
+    /// Add a deno_core extension to the runtime
+    pub fn with_extension(mut self, extension: deno_core::Extension) -> Self {
+        self.0.extensions.push(extension);
+        self
+    }
+
+    /// Options for the built-in extensions
+    pub fn extension_options(mut self, options: ExtensionOptions) -> Self {
+        self.0.extension_options = options;
+        self
+    }
+
+    /// Function to use as entrypoint if a module does not register one
+    pub fn default_entrypoint(mut self, name: &str) -> Self {
+        self.0.default_entrypoint = Some(name.to_string());
+        self
+    }
+
+    /// Cache provider for the module loader
+    pub fn module_cache(mut self, cache: impl ModuleCacheProvider + 'static) -> Self {
+        self.0.module_cache = Some(Box::new(cache));
+        self
+    }
+
+    /// Snapshot to load into the runtime
+    /// User-supplied extensions must be instantiated with `init_ops` instead of `init_ops_and_esm`
+    pub fn startup_snapshot(mut self, snapshot: &'static [u8]) -> Self {
+        self.0.startup_snapshot = Some(snapshot);
+        self
+    }
+
+    /// Maximum size of the V8 heap, in bytes
+    /// V8 aborts the process if the limit is reached
+    pub fn max_heap(mut self, bytes: usize) -> Self {
+        self.0.max_heap_size = Some(bytes);
+        self
+    }
+
+    /// If true, function calls and `eval` will return an error instead of deserializing `undefined`
+    pub fn strict_undefined(mut self, strict: bool) -> Self {
+        self.0.strict_undefined = strict;
+        self
+    }
+
+    /// If true, loading a module whose top-level await never settles fails with
+    /// `Error::UnresolvedTopLevelAwait`
+    pub fn strict_top_level_await(mut self, strict: bool) -> Self {
+        self.0.strict_top_level_await = strict;
+        self
+    }
+
+    /// If true, count promises that have not yet settled - see `Runtime::pending_promise_count`
+    pub fn track_promises(mut self, track: bool) -> Self {
+        self.0.track_promises = track;
+        self
+    }
+
+    /// Add middleware applied to every call from JS to a registered rust function
+    pub fn with_callback_middleware(mut self, layer: impl CallbackLayer + 'static) -> Self {
+        self.0.callback_middleware.push(Box::new(layer));
+        self
+    }
+
+    /// Handle promise rejections that nothing handled, instead of failing the call they happened during
+    pub fn on_unhandled_rejection(mut self, handler: impl Fn(&JsException) + 'static) -> Self {
+        self.0.on_unhandled_rejection = Some(Box::new(handler));
+        self
+    }
+
+    /// Handle exceptions thrown from timer callbacks, instead of failing the call they happened during
+    pub fn on_uncaught_exception(mut self, handler: impl Fn(&JsException) + 'static) -> Self {
+        self.0.on_uncaught_exception = Some(Box::new(handler));
+        self
+    }
+
+    /// Path to write a crash report to, if the isolate runs out of memory or hits a fatal error
+    pub fn crash_dump_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.0.crash_dump_path = Some(path.into());
+        self
+    }
+
+    /// If true, the `WebAssembly` global is removed, and `.wasm` modules cannot be imported
+    pub fn disable_wasm(mut self, disable: bool) -> Self {
+        self.0.disable_wasm = disable;
+        self
+    }
+
+    /// Set a value on `globalThis` as the runtime is created, before any module is evaluated
+    pub fn global(mut self, name: &str, value: serde_json::Value) -> Self {
+        self.0.globals.insert(name.to_string(), value);
+        self
+    }
+
+    /// Controls which remote modules may be imported
+    pub fn import_policy(mut self, policy: ImportPolicy) -> Self {
+        self.0.import_policy = policy;
+        self
+    }
+
+    /// Rewrite import specifiers before they are resolved
+    /// Hooks run in the order they are added
+    pub fn resolver_hook(mut self, hook: impl ResolverHook + 'static) -> Self {
+        self.0.resolver_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Select a security profile, disabling each capability it does not grant
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.0.profile = Some(profile);
+        self
+    }
+
+    /// Start a DevTools-compatible inspector server for the runtime
+    #[cfg(feature = "inspector")]
+    pub fn inspector(mut self, options: crate::InspectorOptions) -> Self {
+        self.0.inspector = Some(options);
+        self
+    }
+
+    /// Make the runtime deterministic, with a seeded `Math.random` and a virtual clock
+    /// Building the runtime fails with the `web` feature
+    pub fn deterministic(mut self, options: DeterministicOptions) -> Self {
+        self.0.deterministic = Some(options);
+        self
+    }
+
+    /// Maximum number of pending timers
+    /// Building the runtime fails with the `web` feature
+    pub fn max_timers(mut self, max: usize) -> Self {
+        self.0.max_timers = Some(max);
+        self
+    }
+
+    /// Record usage against a tenant's quota, and enforce its limits
+    pub fn quota(mut self, quota: Quota) -> Self {
+        self.0.quota = Some(quota);
+        self
+    }
+
+    /// Options for resolving `npm:` specifiers
+    #[cfg(feature = "npm")]
+    pub fn npm_options(mut self, options: crate::NpmOptions) -> Self {
+        self.0.npm_options = options;
+        self
+    }
+
+    /// Check the configuration, without building the runtime
+    /// Returns `Error::Configuration` describing the first problem found
+    pub fn validate(&self) -> Result<(), Error> {
+        let options = &self.0;
+        let invalid = |msg: &str| Err(Error::Configuration(msg.to_string()));
+
+        if options.timeout.is_zero() {
+            return invalid("timeout must be greater than zero");
+        }
+
+        if options.time_budget.is_some_and(|budget| budget.is_zero()) {
+            return invalid("time_budget must be greater than zero");
+        }
+
+        if options.max_heap_size == Some(0) {
+            return invalid("max_heap_size must be greater than zero");
+        }
+
+        // deno_core panics while creating the isolate if these sources are loaded twice
+        if options.startup_snapshot.is_some() {
+            if let Some(extension) = options.extensions.iter().find(|extension| {
+                !extension.get_js_sources().is_empty() || !extension.get_esm_sources().is_empty()
+            }) {
+                return invalid(&format!(
+                    "extension {} includes javascript sources, and cannot be used with a startup_snapshot - instantiate it with `init_ops`",
+                    extension.name
+                ));
+            }
+        }
+
+        if options
+            .default_entrypoint
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return invalid("default_entrypoint must not be empty");
+        }
+
+        if let Some(path) = &options.crash_dump_path {
+            match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => {
+                    return invalid(&format!(
+                        "crash_dump_path directory {} does not exist",
+                        dir.display()
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if cfg!(feature = "web") && options.deterministic.is_some() {
+            return invalid("deterministic mode is not available with the `web` feature");
+        }
+
+        if cfg!(feature = "web") && options.max_timers.is_some() {
+            return invalid("max_timers is not available with the `web` feature");
+        }
+
+        Ok(())
+    }
+
+    /// Consume the builder, returning the options it holds
+    pub fn into_options(self) -> RuntimeOptions {
+        self.0
+    }
+
+    /// Check the configuration, and build the runtime
+    pub fn build(self) -> Result<Runtime, Error> {
+        self.validate()?;
+        Runtime::new(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test_runtime_builder {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let e = RuntimeBuilder::new()
+            .timeout(Duration::ZERO)
+            .validate()
+            .unwrap_err();
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        let e = RuntimeBuilder::new()
+            .default_entrypoint(" ")
+            .validate()
+            .unwrap_err();
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        let e = RuntimeBuilder::new()
+            .crash_dump_path("/missing/rustyscript/dir/crash.json")
+            .validate()
+            .unwrap_err();
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        let e = RuntimeBuilder::new().max_heap(0).validate().unwrap_err();
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        let e = RuntimeBuilder::new()
+            .startup_snapshot(&[])
+            .with_extension(deno_core::Extension {
+                name: "test_builder_esm",
+                esm_files: std::borrow::Cow::Owned(vec![deno_core::ExtensionFileSource::new(
+                    "ext:test_builder_esm/mod.js",
+                    deno_core::ascii_str!(""),
+                )]),
+                ..Default::default()
+            })
+            .validate()
+            .unwrap_err();
+        assert!(e.to_string().contains("test_builder_esm"), "{e}");
+
+        RuntimeBuilder::new()
+            .timeout(Duration::from_secs(1))
+            .max_heap(64 * 1024 * 1024)
+            .crash_dump_path("crash.json")
+            .validate()
+            .expect("Valid configuration was rejected");
+    }
+
+    #[test]
+    fn test_build() {
+        let mut runtime = RuntimeBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .default_entrypoint("main")
+            .global("answer", serde_json::json!(2))
+            .build()
+            .expect("Could not build the runtime");
+        let value: i64 = runtime.eval("answer + 2").expect("Could not eval");
+        assert_eq!(4, value);
+    }
+}
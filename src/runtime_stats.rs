@@ -0,0 +1,98 @@
+use deno_core::{v8, OpDecl, OpId, OpMetricsFactoryFn, OpMetricsSummaryTracker};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+
+/// Resource usage statistics for a runtime instance
+/// Retrieved with `Runtime::stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeStats {
+    /// Bytes of heap currently in use by the isolate
+    pub used_heap_size: usize,
+
+    /// Total bytes of heap allocated by the isolate
+    pub total_heap_size: usize,
+
+    /// Number of async ops that have been started, but have not yet completed
+    pub pending_ops: u64,
+
+    /// Number of modules loaded into the runtime
+    pub module_count: usize,
+
+    /// Total time spent evaluating code, calling functions and loading modules
+    pub eval_time: Duration,
+
+    /// Number of times each op has been called, by op name
+    /// Ops that were never called are omitted
+    pub op_calls: HashMap<String, u64>,
+}
+
+/// Collects the statistics reported in `RuntimeStats`
+#[derive(Default)]
+pub(crate) struct StatsTracker {
+    ops: Rc<OpMetricsSummaryTracker>,
+    op_names: Rc<RefCell<HashMap<OpId, &'static str>>>,
+    module_count: usize,
+    eval_time: Duration,
+    budget_start: Duration,
+}
+
+impl StatsTracker {
+    /// Get a metrics factory to pass to the deno runtime, so that op calls are counted
+    pub fn op_metrics_factory_fn(&self) -> OpMetricsFactoryFn {
+        let factory = self.ops.clone().op_metrics_factory_fn(|_| true);
+        let op_names = self.op_names.clone();
+        Box::new(move |id, total, decl: &OpDecl| {
+            op_names.borrow_mut().insert(id, decl.name);
+            factory(id, total, decl)
+        })
+    }
+
+    /// Record newly loaded modules
+    pub fn add_modules(&mut self, count: usize) {
+        self.module_count += count;
+    }
+
+    /// Record time spent running JS
+    pub fn add_eval_time(&mut self, time: Duration) {
+        self.eval_time += time;
+    }
+
+    /// Time spent running JS since the budget was last reset
+    pub fn budget_used(&self) -> Duration {
+        self.eval_time - self.budget_start
+    }
+
+    /// Start counting budget usage from zero again
+    pub fn reset_budget(&mut self) {
+        self.budget_start = self.eval_time;
+    }
+
+    /// Build a `RuntimeStats` from the collected data, and the isolate's heap statistics
+    pub fn to_stats(&self, heap: &v8::HeapStatistics) -> RuntimeStats {
+        let op_names = self.op_names.borrow();
+        let mut pending_ops = 0;
+        let mut op_calls = HashMap::new();
+        // Metrics are stored by op id, so names are looked up by it rather than relying on order
+        for (id, metrics) in self.ops.per_op().iter().enumerate() {
+            pending_ops += metrics
+                .ops_dispatched_async
+                .saturating_sub(metrics.ops_completed_async);
+
+            let calls = metrics.ops_dispatched_sync + metrics.ops_dispatched_async;
+            if calls == 0 {
+                continue;
+            }
+            if let Some(name) = OpId::try_from(id).ok().and_then(|id| op_names.get(&id)) {
+                op_calls.insert(name.to_string(), calls);
+            }
+        }
+
+        RuntimeStats {
+            used_heap_size: heap.used_heap_size(),
+            total_heap_size: heap.total_heap_size(),
+            pending_ops,
+            module_count: self.module_count,
+            eval_time: self.eval_time,
+            op_calls,
+        }
+    }
+}
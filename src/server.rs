@@ -0,0 +1,393 @@
+//! Provides an HTTP server that passes requests to a module's `fetch` handler
+//! The host owns the listener; each request is forwarded to a worker thread as a `Request`,
+//! and the handler's `Response` is sent back to the client:
+//! ```js
+//! export default {
+//!     async fetch(request) {
+//!         return new Response(`Hello from ${new URL(request.url).pathname}`);
+//!     }
+//! }
+//! ```
+use crate::{
+    serde_json::{self, json},
+    traits::ToModuleSpecifier,
+    worker::{DefaultWorker, DefaultWorkerOptions, ErrorHandler},
+    Error, Module,
+};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{body::Bytes, body::Incoming, server::conn::http1, service::service_fn, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// Options for [Server]
+#[derive(Clone)]
+pub struct ServerOptions {
+    /// Address to listen on - use port 0 to pick a free port
+    pub address: SocketAddr,
+
+    /// Options for the worker running the handler
+    pub worker: DefaultWorkerOptions,
+
+    /// Largest request body accepted, in bytes
+    /// Larger requests are refused with `413 Payload Too Large`
+    pub max_body_size: usize,
+
+    /// Called with errors thrown by the handler, which are otherwise only
+    /// reported to the client as `500 Internal Server Error`
+    pub error_handler: Option<ErrorHandler>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            address: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            worker: DefaultWorkerOptions {
+                timeout: Duration::from_secs(30),
+                ..Default::default()
+            },
+            max_body_size: 1024 * 1024,
+            error_handler: None,
+        }
+    }
+}
+
+/// A request, as sent to the worker
+struct ServeRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// Status and headers of a response, as returned by the worker
+#[derive(serde::Deserialize)]
+struct ResponseMeta {
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// The JS side of a server - a loaded handler, and the module dispatching requests to it
+struct Handler {
+    worker: DefaultWorker,
+    dispatch: deno_core::ModuleId,
+}
+
+impl Handler {
+    fn new(module: Module, options: DefaultWorkerOptions) -> Result<Self, Error> {
+        let worker = DefaultWorker::new(options)?;
+        let specifier = module.filename().to_module_specifier()?;
+        worker.load_module(module)?;
+
+        let dispatch = worker.load_main_module(Module::new(
+            "rustyscript_serve.js",
+            &format!(
+                "import handler from {specifier};\nexport const dispatch = (...args) => rustyscript.serve.dispatch(handler, ...args);",
+                specifier = serde_json::to_string(specifier.as_str())?
+            ),
+        ))?;
+
+        Ok(Self { worker, dispatch })
+    }
+
+    fn handle(&self, request: ServeRequest) -> Result<(ResponseMeta, Vec<u8>), Error> {
+        let output = self.worker.call_function_bytes(
+            Some(self.dispatch),
+            "dispatch".to_string(),
+            vec![
+                json!(request.method),
+                json!(request.url),
+                json!(request.headers),
+            ],
+            vec![request.body],
+        )?;
+
+        let invalid = || Error::Runtime("Invalid response from the fetch handler".to_string());
+        let (length, rest) = output.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let length = u32::from_be_bytes(*length) as usize;
+        if rest.len() < length {
+            return Err(invalid());
+        }
+
+        let (meta, body) = rest.split_at(length);
+        Ok((serde_json::from_slice(meta)?, body.to_vec()))
+    }
+}
+
+/// An HTTP server passing requests to a module's `fetch` handler, Cloudflare-Workers-style
+/// The module's default export must be an object with a `fetch(request)` method,
+/// returning a `Response` or a promise of one
+///
+/// Requests are handled one at a time, on a single worker thread
+/// The server stops when [Server::stop] is called, or when it is dropped
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{Error, Module, Server, ServerOptions};
+///
+/// # fn main() -> Result<(), Error> {
+/// let module = Module::new(
+///     "handler.js",
+///     "export default { fetch: (request) => new Response(`${request.method} ${request.url}`) };",
+/// );
+///
+/// let server = Server::start(module, ServerOptions {
+///     address: "127.0.0.1:0".parse().unwrap(),
+///     ..Default::default()
+/// })?;
+/// println!("Listening on http://{}", server.address());
+/// server.stop()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Server {
+    address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl Server {
+    /// Load the handler module into a new worker, and start listening for requests
+    /// Returns an error if the module cannot be loaded, or the address cannot be bound
+    pub fn start(module: Module, options: ServerOptions) -> Result<Self, Error> {
+        let listener = std::net::TcpListener::bind(options.address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let handler = Arc::new(Mutex::new(Handler::new(module, options.worker.clone())?));
+        let (shutdown, stopped) = oneshot::channel();
+        let handle = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime.block_on(listen(listener, handler.clone(), options, stopped))?;
+
+            // Wait for requests still being handled to let go of the worker
+            drop(runtime);
+
+            match Arc::try_unwrap(handler) {
+                Ok(handler) => handler
+                    .into_inner()
+                    .map_err(|e| Error::Runtime(e.to_string()))?
+                    .worker
+                    .stop(),
+                Err(_) => Ok(()),
+            }
+        });
+
+        Ok(Self {
+            address,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        })
+    }
+
+    /// The address the server is listening on
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Stop accepting requests, and wait for the server and its worker to finish
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::Runtime("Server thread panicked".to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// Accept connections until a shutdown is requested
+async fn listen(
+    listener: std::net::TcpListener,
+    handler: Arc<Mutex<Handler>>,
+    options: ServerOptions,
+    mut stopped: oneshot::Receiver<()>,
+) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let options = Arc::new(options);
+    let mut connections = tokio::task::JoinSet::new();
+
+    loop {
+        let (stream, _) = tokio::select! {
+            _ = &mut stopped => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => continue,
+            },
+        };
+
+        let handler = handler.clone();
+        let options = options.clone();
+        connections.spawn(async move {
+            let service =
+                service_fn(move |request| respond(request, handler.clone(), options.clone()));
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+
+    connections.shutdown().await;
+    Ok(())
+}
+
+/// Forward a request to the worker, and build the response sent to the client
+async fn respond(
+    request: hyper::Request<Incoming>,
+    handler: Arc<Mutex<Handler>>,
+    options: Arc<ServerOptions>,
+) -> Result<hyper::Response<Full<Bytes>>, std::convert::Infallible> {
+    let (parts, body) = request.into_parts();
+    let body = match Limited::new(body, options.max_body_size).collect().await {
+        Ok(body) => body.to_bytes().to_vec(),
+        Err(_) => return Ok(status_response(StatusCode::PAYLOAD_TOO_LARGE)),
+    };
+
+    let host = parts
+        .headers
+        .get(hyper::header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .map_or_else(|| options.address.to_string(), ToString::to_string);
+    let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+
+    let request = ServeRequest {
+        method: parts.method.to_string(),
+        url: format!("http://{host}{path}"),
+        headers: parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body,
+    };
+
+    let result = tokio::task::spawn_blocking(move || match handler.lock() {
+        Ok(handler) => handler.handle(request),
+        Err(e) => Err(Error::Runtime(e.to_string())),
+    })
+    .await
+    .unwrap_or_else(|e| Err(Error::Runtime(e.to_string())));
+
+    let response = result.and_then(|(meta, body)| {
+        let mut response = hyper::Response::builder().status(meta.status);
+        for (name, value) in meta.headers {
+            response = response.header(name, value);
+        }
+        response
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| Error::Runtime(e.to_string()))
+    });
+
+    Ok(response.unwrap_or_else(|e| {
+        if let Some(error_handler) = &options.error_handler {
+            error_handler(e);
+        }
+        status_response(StatusCode::INTERNAL_SERVER_ERROR)
+    }))
+}
+
+fn status_response(status: StatusCode) -> hyper::Response<Full<Bytes>> {
+    let mut response = hyper::Response::new(Full::new(Bytes::from(
+        status.canonical_reason().unwrap_or_default(),
+    )));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::*;
+    use std::io::{Read, Write};
+
+    fn request(address: SocketAddr, request: &str) -> String {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let module = Module::new(
+            "test_serve.js",
+            "
+            export default {
+                async fetch(request) {
+                    const url = new URL(request.url);
+                    if (url.pathname === '/throw') throw new Error('handler failed');
+
+                    const body = await request.text();
+                    return Response.json(
+                        { method: request.method, path: url.pathname, body, agent: request.headers.get('User-Agent') },
+                        { status: 201, headers: { 'X-Handled-By': 'rustyscript' } },
+                    );
+                }
+            }
+        ",
+        );
+
+        let errors = Arc::new(Mutex::new(vec![]));
+        let errors_ = errors.clone();
+        let server = Server::start(
+            module,
+            ServerOptions {
+                address: SocketAddr::from(([127, 0, 0, 1], 0)),
+                max_body_size: 16,
+                error_handler: Some(Arc::new(move |e| {
+                    errors_.lock().unwrap().push(e.to_string())
+                })),
+                ..Default::default()
+            },
+        )
+        .expect("Could not start the server");
+        let address = server.address();
+
+        let response = request(
+            address,
+            "POST /echo HTTP/1.1\r\nHost: localhost\r\nUser-Agent: test\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        );
+        assert!(response.starts_with("HTTP/1.1 201 Created"), "{response}");
+        assert!(response.contains("x-handled-by: rustyscript"), "{response}");
+        assert!(
+            response.ends_with(r#"{"method":"POST","path":"/echo","body":"hello","agent":"test"}"#)
+        );
+
+        let response = request(
+            address,
+            "GET /throw HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 500"), "{response}");
+        assert!(errors.lock().unwrap()[0].contains("handler failed"));
+
+        let response = request(
+            address,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 17\r\nConnection: close\r\n\r\n12345678901234567",
+        );
+        assert!(response.starts_with("HTTP/1.1 413"), "{response}");
+
+        server.stop().expect("Could not stop the server");
+    }
+}
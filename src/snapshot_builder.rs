@@ -72,6 +72,9 @@ impl SnapshotBuilder {
                 })),
 
                 source_map_getter: Some(loader),
+                custom_module_evaluation_cb: Some(Box::new(
+                    crate::module_loader::evaluate_data_module,
+                )),
 
                 startup_snapshot: options.startup_snapshot,
                 extensions,
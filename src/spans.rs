@@ -0,0 +1,87 @@
+//! Helpers for the `tracing` feature
+//! Without the feature, the macros here compile to nothing
+
+/// Enter a `debug` level span for the rest of the enclosing block
+/// Takes the same arguments as `tracing::debug_span!`
+macro_rules! enter_span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($args)*).entered();
+    };
+}
+pub(crate) use enter_span;
+
+#[cfg(all(test, feature = "tracing"))]
+mod test_spans {
+    use crate::{Module, Runtime};
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+    use tracing::{span, subscriber::Subscriber, Event, Metadata};
+
+    /// Records the names of the spans created
+    #[derive(Default)]
+    struct SpanNames(Arc<Mutex<Vec<String>>>, AtomicU64);
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            self.0
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_string());
+            span::Id::from_u64(self.1.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_spans() {
+        let subscriber = SpanNames::default();
+        let names = subscriber.0.clone();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut runtime =
+                Runtime::new(Default::default()).expect("Could not create the runtime");
+            runtime
+                .register_function("double", |args| {
+                    Ok((args[0].as_i64().unwrap_or_default() * 2).into())
+                })
+                .expect("Could not register function");
+
+            let module = Module::new(
+                "test_spans.js",
+                "export const f = (n) => rustyscript.functions.double(n);",
+            );
+            let module = runtime.load_module(&module).expect("Could not load module");
+            let value: i64 = runtime
+                .call_function(Some(&module), "f", &[2.into()])
+                .expect("Could not call function");
+            assert_eq!(4, value);
+
+            let value: i64 = runtime.eval("1 + 1").expect("Could not eval");
+            assert_eq!(2, value);
+        });
+
+        let names = names.lock().unwrap();
+        for name in [
+            "rustyscript.load_modules",
+            "rustyscript.call_function",
+            "rustyscript.callback",
+            "rustyscript.eval",
+        ] {
+            assert!(
+                names.iter().any(|n| n == name),
+                "{name} missing from {names:?}"
+            );
+        }
+    }
+}
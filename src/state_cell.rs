@@ -0,0 +1,214 @@
+use crate::Error;
+use std::{
+    any::{Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+    rc::Rc,
+};
+
+/// Application state shared between the host and the rust functions registered with a runtime
+/// Holds one value of each type, borrowed by type with [StateCell::borrow] and [StateCell::borrow_mut]
+///
+/// Cells are cheap to clone, and clones share the same values
+/// Functions registered with `Runtime::register_stateful_function` or
+/// `Runtime::register_async_stateful_function` are given the runtime's cell
+///
+/// A value borrowed mutably cannot be borrowed again until the borrow ends -
+/// doing so returns an error instead of panicking
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Error };
+///
+/// struct Counter(i64);
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// runtime.put_state(Counter(0))?;
+///
+/// runtime.register_stateful_function("increment", |_args, state| {
+///     let mut counter = state.borrow_mut::<Counter>()?;
+///     counter.0 += 1;
+///     Ok(counter.0.into())
+/// })?;
+///
+/// let value: i64 = runtime.eval("rustyscript.functions.increment() + rustyscript.functions.increment()")?;
+/// assert_eq!(3, value);
+/// assert_eq!(2, runtime.state().borrow::<Counter>()?.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct StateCell(Rc<RefCell<HashMap<TypeId, Box<dyn Any>>>>);
+
+impl StateCell {
+    /// Create a new, empty cell
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a value, replacing and returning any value of the same type
+    /// Returns an error if the cell is currently borrowed
+    pub fn put<T: 'static>(&self, value: T) -> Result<Option<T>, Error> {
+        let mut values = self.0.try_borrow_mut().map_err(|_| Self::busy::<T>())?;
+        let old = values.insert(TypeId::of::<T>(), Box::new(value));
+        Ok(old.and_then(|old| old.downcast().ok()).map(|old| *old))
+    }
+
+    /// Remove and return the value of a type, if there is one
+    /// Returns an error if the cell is currently borrowed
+    pub fn take<T: 'static>(&self) -> Result<Option<T>, Error> {
+        let mut values = self.0.try_borrow_mut().map_err(|_| Self::busy::<T>())?;
+        let old = values.remove(&TypeId::of::<T>());
+        Ok(old.and_then(|old| old.downcast().ok()).map(|old| *old))
+    }
+
+    /// Returns true if the cell holds a value of the given type
+    pub fn has<T: 'static>(&self) -> bool {
+        self.0
+            .try_borrow()
+            .is_ok_and(|values| values.contains_key(&TypeId::of::<T>()))
+    }
+
+    /// Borrow the value of a type
+    /// Returns an error if there is no such value, or it is borrowed mutably
+    pub fn borrow<T: 'static>(&self) -> Result<Ref<'_, T>, Error> {
+        let values = self.0.try_borrow().map_err(|_| Self::busy::<T>())?;
+        Ref::filter_map(values, |values| {
+            values
+                .get(&TypeId::of::<T>())
+                .and_then(|value| value.downcast_ref())
+        })
+        .map_err(|_| Self::missing::<T>())
+    }
+
+    /// Borrow the value of a type mutably
+    /// Returns an error if there is no such value, or it is already borrowed
+    pub fn borrow_mut<T: 'static>(&self) -> Result<RefMut<'_, T>, Error> {
+        let values = self.0.try_borrow_mut().map_err(|_| Self::busy::<T>())?;
+        RefMut::filter_map(values, |values| {
+            values
+                .get_mut(&TypeId::of::<T>())
+                .and_then(|value| value.downcast_mut())
+        })
+        .map_err(|_| Self::missing::<T>())
+    }
+
+    fn busy<T>() -> Error {
+        Error::Runtime(format!(
+            "state {} is already borrowed",
+            std::any::type_name::<T>()
+        ))
+    }
+
+    fn missing<T>() -> Error {
+        Error::Runtime(format!(
+            "no state of type {} was provided",
+            std::any::type_name::<T>()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_state_cell {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        name: String,
+    }
+
+    #[test]
+    fn test_state_cell() {
+        let state = StateCell::new();
+        assert!(!state.has::<Config>());
+        state.borrow::<Config>().unwrap_err();
+
+        let old = state
+            .put(Config {
+                name: "a".to_string(),
+            })
+            .expect("Could not put the state");
+        assert_eq!(None, old);
+        assert_eq!(
+            "a",
+            state
+                .borrow::<Config>()
+                .expect("Could not borrow the state")
+                .name
+        );
+
+        {
+            let mut config = state
+                .borrow_mut::<Config>()
+                .expect("Could not borrow the state");
+            config.name = "b".to_string();
+
+            // Overlapping borrows are errors
+            let e = state.borrow::<Config>().unwrap_err();
+            assert!(e.to_string().contains("already borrowed"), "{e}");
+        }
+
+        let clone = state.clone();
+        assert_eq!(
+            Some(Config {
+                name: "b".to_string()
+            }),
+            clone.take::<Config>().expect("Could not take the state")
+        );
+        assert!(!state.has::<Config>());
+    }
+
+    #[test]
+    fn test_stateful_functions() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .put_state(Config {
+                name: "rustyscript".to_string(),
+            })
+            .expect("Could not put state");
+
+        runtime
+            .register_stateful_function("rename", |args, state| {
+                let mut config = state.borrow_mut::<Config>()?;
+                config.name = args[0].as_str().unwrap_or_default().to_string();
+                Ok(config.name.clone().into())
+            })
+            .expect("Could not register function");
+
+        runtime
+            .register_async_stateful_function("name", |_args, state| {
+                Box::pin(async move {
+                    let config = state.borrow::<Config>()?;
+                    Ok(config.name.clone().into())
+                })
+            })
+            .expect("Could not register function");
+
+        let module = Module::new(
+            "test_state.js",
+            "
+            export const run = async () => {
+                const before = await rustyscript.async_functions.name();
+                rustyscript.functions.rename('deno');
+                return [before, await rustyscript.async_functions.name()];
+            };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: Vec<String> = runtime
+            .call_function(Some(&module), "run", &[])
+            .expect("Could not call function");
+        assert_eq!(vec!["rustyscript", "deno"], value);
+        assert_eq!(
+            "deno",
+            runtime
+                .state()
+                .borrow::<Config>()
+                .expect("Could not borrow the state")
+                .name
+        );
+    }
+}
@@ -0,0 +1,1113 @@
+//! Structured clone of javascript values, as an alternative to JSON
+//! Unlike JSON, it keeps `Map`, `Set`, `Date`, `BigInt`, binary data, `undefined`,
+//! and objects referenced more than once - including cyclic ones
+use crate::Error;
+use deno_core::{serde_json, v8};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// How deeply values can be nested before cloning fails with `Error::DataClone`
+/// Cloning recurses, so this keeps deeply nested values from overflowing the stack
+pub const MAX_CLONE_DEPTH: usize = 512;
+
+/// A javascript value copied out of a runtime with the structured clone algorithm
+/// Returned by `Runtime::call_function_structured`, and by the worker's `call_function_structured`
+///
+/// Objects are numbered in the order they are first reached, starting from 0
+/// An object reached again - through a cycle, or from more than one place - is a [StructuredValue::Ref]
+/// to that number, so the shape of the object graph is kept when the value is passed back into a runtime
+///
+/// Functions, symbols, promises, proxies, weak collections, regular expressions,
+/// `SharedArrayBuffer` and `DataView` cannot be cloned, nor can values nested
+/// more than [MAX_CLONE_DEPTH] levels deep
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{ Runtime, Module, StructuredValue, Error };
+///
+/// # fn main() -> Result<(), Error> {
+/// let mut runtime = Runtime::new(Default::default())?;
+/// let module = runtime.load_module(&Module::new("test.js", "
+///     export const make = () => { const node = { tags: new Set(['a']) }; node.self = node; return node; };
+///     export const check = (node) => node.self === node && node.tags.has('a');
+/// "))?;
+///
+/// // Cycles survive the round trip, where JSON would fail
+/// let node = runtime.call_function_structured(Some(&module), "make", vec![])?;
+/// let result = runtime.call_function_structured(Some(&module), "check", vec![node])?;
+/// assert_eq!(StructuredValue::Boolean(true), result);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StructuredValue {
+    /// `undefined`
+    Undefined,
+
+    /// `null`
+    Null,
+
+    /// `true` or `false`
+    Boolean(bool),
+
+    /// A number
+    Number(#[serde(with = "number")] f64),
+
+    /// A `BigInt` that fits in 64 bits
+    BigInt(i64),
+
+    /// A string
+    String(String),
+
+    /// A `Date`, as milliseconds since the unix epoch
+    Date(#[serde(with = "number")] f64),
+
+    /// An `ArrayBuffer` - see [ArrayBufferContents]
+    ArrayBuffer(ArrayBufferContents),
+
+    /// A typed array, such as a `Uint8Array`, with a copy of the bytes it views
+    /// Each typed array gets a buffer of its own, even if several shared one in javascript
+    TypedArray(TypedArrayKind, Vec<u8>),
+
+    /// An array - holes in sparse arrays become `undefined`
+    Array(Vec<StructuredValue>),
+
+    /// A plain object, with its own enumerable string-keyed properties in order
+    Object(Vec<(String, StructuredValue)>),
+
+    /// A `Map`, with its entries in insertion order
+    Map(Vec<(StructuredValue, StructuredValue)>),
+
+    /// A `Set`, with its values in insertion order
+    Set(Vec<StructuredValue>),
+
+    /// Another reference to an object already reached, by its number
+    Ref(usize),
+}
+
+/// The kind of a [StructuredValue::TypedArray]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TypedArrayKind {
+    /// `Int8Array`
+    Int8,
+
+    /// `Uint8Array`
+    Uint8,
+
+    /// `Uint8ClampedArray`
+    Uint8Clamped,
+
+    /// `Int16Array`
+    Int16,
+
+    /// `Uint16Array`
+    Uint16,
+
+    /// `Int32Array`
+    Int32,
+
+    /// `Uint32Array`
+    Uint32,
+
+    /// `Float32Array`
+    Float32,
+
+    /// `Float64Array`
+    Float64,
+
+    /// `BigInt64Array`
+    BigInt64,
+
+    /// `BigUint64Array`
+    BigUint64,
+}
+
+impl TypedArrayKind {
+    /// The size of each element, in bytes
+    pub fn element_size(&self) -> usize {
+        match self {
+            Self::Int8 | Self::Uint8 | Self::Uint8Clamped => 1,
+            Self::Int16 | Self::Uint16 => 2,
+            Self::Int32 | Self::Uint32 | Self::Float32 => 4,
+            Self::Float64 | Self::BigInt64 | Self::BigUint64 => 8,
+        }
+    }
+
+    fn of(value: v8::Local<v8::Value>) -> Option<Self> {
+        Some(if value.is_int8_array() {
+            Self::Int8
+        } else if value.is_uint8_array() {
+            Self::Uint8
+        } else if value.is_uint8_clamped_array() {
+            Self::Uint8Clamped
+        } else if value.is_int16_array() {
+            Self::Int16
+        } else if value.is_uint16_array() {
+            Self::Uint16
+        } else if value.is_int32_array() {
+            Self::Int32
+        } else if value.is_uint32_array() {
+            Self::Uint32
+        } else if value.is_float32_array() {
+            Self::Float32
+        } else if value.is_float64_array() {
+            Self::Float64
+        } else if value.is_big_int64_array() {
+            Self::BigInt64
+        } else if value.is_big_uint64_array() {
+            Self::BigUint64
+        } else {
+            return None;
+        })
+    }
+
+    fn create<'s>(
+        &self,
+        scope: &mut v8::HandleScope<'s>,
+        buffer: v8::Local<v8::ArrayBuffer>,
+        length: usize,
+    ) -> Option<v8::Local<'s, v8::Value>> {
+        Some(match self {
+            Self::Int8 => v8::Int8Array::new(scope, buffer, 0, length)?.into(),
+            Self::Uint8 => v8::Uint8Array::new(scope, buffer, 0, length)?.into(),
+            Self::Uint8Clamped => v8::Uint8ClampedArray::new(scope, buffer, 0, length)?.into(),
+            Self::Int16 => v8::Int16Array::new(scope, buffer, 0, length)?.into(),
+            Self::Uint16 => v8::Uint16Array::new(scope, buffer, 0, length)?.into(),
+            Self::Int32 => v8::Int32Array::new(scope, buffer, 0, length)?.into(),
+            Self::Uint32 => v8::Uint32Array::new(scope, buffer, 0, length)?.into(),
+            Self::Float32 => v8::Float32Array::new(scope, buffer, 0, length)?.into(),
+            Self::Float64 => v8::Float64Array::new(scope, buffer, 0, length)?.into(),
+            Self::BigInt64 => v8::BigInt64Array::new(scope, buffer, 0, length)?.into(),
+            Self::BigUint64 => v8::BigUint64Array::new(scope, buffer, 0, length)?.into(),
+        })
+    }
+}
+
+/// The memory of an `ArrayBuffer`
+///
+/// Buffers cloned out of a runtime are copied, as with javascript's `structuredClone`,
+/// unless they are in the transfer list given to `Runtime::call_function_structured_transfer`
+/// A transferred buffer is detached in the runtime - its memory is handed over without
+/// being copied, and the buffer is left empty
+///
+/// Passing the value back into a runtime moves its memory into a new `ArrayBuffer`
+///
+/// Each `ArrayBufferContents` owns its memory - cloning one copies the bytes
+pub struct ArrayBufferContents(v8::SharedRef<v8::BackingStore>);
+
+// Safety: the backing store is only ever reachable through this value, so it can be moved
+// to another thread. It is either created from a `Vec` for this value, or taken from a
+// transferred buffer - which must not be a `SharedArrayBuffer` or resizable, and is detached
+// before the clone is returned, so no javascript can reach the memory afterwards
+// It is not `Sync`: reading the memory is only sound from the thread that owns the value
+unsafe impl Send for ArrayBufferContents {}
+
+impl ArrayBufferContents {
+    /// The contents, as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        match self.0.data() {
+            // Safety: the memory is owned by this value, see above
+            Some(data) => unsafe {
+                std::slice::from_raw_parts(data.as_ptr().cast::<u8>(), self.0.byte_length())
+            },
+            None => &[],
+        }
+    }
+}
+
+impl From<Vec<u8>> for ArrayBufferContents {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared())
+    }
+}
+
+impl std::ops::Deref for ArrayBufferContents {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Clone for ArrayBufferContents {
+    fn clone(&self) -> Self {
+        self.as_bytes().to_vec().into()
+    }
+}
+
+impl PartialEq for ArrayBufferContents {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl std::fmt::Debug for ArrayBufferContents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_bytes().fmt(f)
+    }
+}
+
+impl Serialize for ArrayBufferContents {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ArrayBufferContents {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<u8>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Serde support for numbers that may not be finite
+/// JSON has no `NaN` or `Infinity`, so human-readable formats get them as strings instead
+mod number {
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match *n {
+            n if n.is_finite() || !serializer.is_human_readable() => serializer.serialize_f64(n),
+            n if n.is_nan() => serializer.serialize_str("NaN"),
+            n if n > 0.0 => serializer.serialize_str("Infinity"),
+            _ => serializer.serialize_str("-Infinity"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        struct Visitor;
+        impl de::Visitor<'_> for Visitor {
+            type Value = f64;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a number, \"NaN\", \"Infinity\" or \"-Infinity\"")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<f64, E> {
+                Ok(v)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<f64, E> {
+                Ok(v as f64)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<f64, E> {
+                Ok(v as f64)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+                match v {
+                    "NaN" => Ok(f64::NAN),
+                    "Infinity" => Ok(f64::INFINITY),
+                    "-Infinity" => Ok(f64::NEG_INFINITY),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(Visitor)
+        } else {
+            deserializer.deserialize_f64(Visitor)
+        }
+    }
+}
+
+impl StructuredValue {
+    /// Convert the value to JSON, as `JSON.stringify` would
+    /// `undefined` and non-finite numbers become `null`
+    ///
+    /// Returns `Error::DataClone` for values JSON cannot represent, such as a `Map`, a `Date`,
+    /// binary data, or a reference to an object already reached
+    pub fn to_json(&self) -> Result<serde_json::Value, Error> {
+        Ok(match self {
+            Self::Undefined | Self::Null => serde_json::Value::Null,
+            Self::Boolean(b) => (*b).into(),
+            Self::Number(n) => serde_json::Number::from_f64(*n)
+                .map(serde_json::Value::Number)
+                .unwrap_or_default(),
+            Self::String(s) => s.as_str().into(),
+            Self::Array(items) => items.iter().map(Self::to_json).collect::<Result<_, _>>()?,
+            Self::Object(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), v.to_json()?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            other => {
+                return Err(Error::DataClone(format!(
+                    "{} cannot be represented as JSON",
+                    other.kind()
+                )))
+            }
+        })
+    }
+
+    /// Deserialize the value into a rust type
+    ///
+    /// Unlike [StructuredValue::to_json], values JSON cannot represent are converted where
+    /// serde has a place for them: a `Map` becomes a map, keyed by its keys' string form, a `Set`
+    /// becomes a sequence, a `Date` its timestamp, and binary data a sequence of its elements
+    /// Objects reached more than once are copied to each place
+    ///
+    /// Returns `Error::DataClone` for cyclic values, and for `Map` keys that are not primitives
+    pub fn deserialize<T>(&self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut refs = HashSet::new();
+        self.find_refs(&mut refs);
+        let data = Flattener {
+            refs,
+            resolved: HashMap::new(),
+            next_id: 0,
+            depth: 0,
+        }
+        .flatten(self)?;
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// Collect the numbers of the objects referenced more than once
+    fn find_refs(&self, refs: &mut HashSet<usize>) {
+        match self {
+            Self::Ref(id) => {
+                refs.insert(*id);
+            }
+            Self::Array(items) | Self::Set(items) => {
+                items.iter().for_each(|item| item.find_refs(refs));
+            }
+            Self::Object(entries) => entries.iter().for_each(|(_, item)| item.find_refs(refs)),
+            Self::Map(entries) => entries.iter().for_each(|(key, item)| {
+                key.find_refs(refs);
+                item.find_refs(refs);
+            }),
+            _ => {}
+        }
+    }
+
+    /// A short description of the kind of value, for error messages
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Undefined => "undefined",
+            Self::Null => "null",
+            Self::Boolean(_) => "a boolean",
+            Self::Number(_) => "a number",
+            Self::BigInt(_) => "a BigInt",
+            Self::String(_) => "a string",
+            Self::Date(_) => "a Date",
+            Self::ArrayBuffer(_) => "an ArrayBuffer",
+            Self::TypedArray(..) => "a typed array",
+            Self::Array(_) => "an array",
+            Self::Object(_) => "an object",
+            Self::Map(_) => "a Map",
+            Self::Set(_) => "a Set",
+            Self::Ref(_) => "a repeated reference",
+        }
+    }
+
+    /// Copy a javascript value out of the runtime, moving the memory of the buffers in `transfer`
+    /// instead of copying it - every buffer in `transfer` is detached, as with `structuredClone`
+    pub(crate) fn from_v8<'s>(
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+        transfer: Vec<v8::Local<'s, v8::ArrayBuffer>>,
+    ) -> Result<Self, Error> {
+        for (i, buffer) in transfer.iter().enumerate() {
+            // Only memory nothing else can reach may be handed over - see `ArrayBufferContents`
+            let store = buffer.get_backing_store();
+            if !buffer.is_detachable()
+                || store.is_shared()
+                || store.is_resizable_by_user_javascript()
+                || store.byte_length() != buffer.byte_length()
+            {
+                return Err(Error::DataClone(
+                    "an ArrayBuffer that cannot be detached cannot be transferred".to_string(),
+                ));
+            }
+            if transfer[..i]
+                .iter()
+                .any(|other| other.strict_equals((*buffer).into()))
+            {
+                return Err(Error::DataClone(
+                    "an ArrayBuffer listed more than once cannot be transferred".to_string(),
+                ));
+            }
+        }
+
+        let mut encoder = Encoder {
+            seen: v8::Map::new(scope),
+            next_id: 0,
+            depth: 0,
+            transfer,
+        };
+        let value = encoder.encode(scope, value)?;
+
+        // Buffers are only detached once the whole value is cloned, since typed arrays
+        // reached later may still need to read them
+        for buffer in encoder.transfer {
+            buffer.detach(None);
+        }
+        Ok(value)
+    }
+
+    /// Create a javascript value from this one
+    /// Binary data is moved into the runtime, rather than copied
+    pub(crate) fn into_v8<'s>(
+        self,
+        scope: &mut v8::HandleScope<'s>,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        Decoder {
+            objects: Vec::new(),
+            depth: 0,
+        }
+        .decode(scope, self)
+    }
+}
+
+impl From<serde_json::Value> for StructuredValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Self::Null,
+            serde_json::Value::Bool(b) => Self::Boolean(b),
+            serde_json::Value::Number(n) => Self::Number(n.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(s) => Self::String(s),
+            serde_json::Value::Array(items) => {
+                Self::Array(items.into_iter().map(Self::from).collect())
+            }
+            serde_json::Value::Object(entries) => Self::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, Self::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Fails once values are nested more than [MAX_CLONE_DEPTH] levels deep
+fn nest(depth: &mut usize) -> Result<(), Error> {
+    if *depth == MAX_CLONE_DEPTH {
+        return Err(Error::DataClone(format!(
+            "a value nested more than {MAX_CLONE_DEPTH} levels deep"
+        )));
+    }
+    *depth += 1;
+    Ok(())
+}
+
+/// Walks a javascript value, numbering each object as it is first reached
+struct Encoder<'s> {
+    seen: v8::Local<'s, v8::Map>,
+    next_id: usize,
+    depth: usize,
+    transfer: Vec<v8::Local<'s, v8::ArrayBuffer>>,
+}
+
+impl<'s> Encoder<'s> {
+    fn encode(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+    ) -> Result<StructuredValue, Error> {
+        nest(&mut self.depth)?;
+        let result = self.encode_value(scope, value);
+        self.depth -= 1;
+        result
+    }
+
+    fn encode_value(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        value: v8::Local<'s, v8::Value>,
+    ) -> Result<StructuredValue, Error> {
+        let unsupported = |kind: &str| Err(Error::DataClone(format!("{kind} cannot be cloned")));
+
+        if value.is_undefined() {
+            return Ok(StructuredValue::Undefined);
+        } else if value.is_null() {
+            return Ok(StructuredValue::Null);
+        } else if value.is_boolean() {
+            return Ok(StructuredValue::Boolean(value.boolean_value(scope)));
+        } else if value.is_number() {
+            return Ok(StructuredValue::Number(
+                value.number_value(scope).unwrap_or(f64::NAN),
+            ));
+        } else if value.is_string() {
+            return Ok(StructuredValue::String(value.to_rust_string_lossy(scope)));
+        } else if value.is_big_int() {
+            let big = v8::Local::<v8::BigInt>::try_from(value)?;
+            return match big.i64_value() {
+                (n, true) => Ok(StructuredValue::BigInt(n)),
+                (_, false) => unsupported("a BigInt larger than 64 bits"),
+            };
+        } else if value.is_symbol() {
+            return unsupported("a symbol");
+        } else if value.is_function() {
+            return unsupported("a function");
+        } else if value.is_promise() {
+            return unsupported("a promise");
+        } else if value.is_proxy() {
+            return unsupported("a proxy");
+        } else if value.is_weak_map() || value.is_weak_set() {
+            return unsupported("a weak collection");
+        } else if value.is_reg_exp() {
+            return unsupported("a regular expression");
+        } else if value.is_shared_array_buffer() {
+            return unsupported("a SharedArrayBuffer");
+        } else if value.is_array_buffer_view() && !value.is_typed_array() {
+            return unsupported("a DataView");
+        }
+
+        // Objects reached before become references, which is what makes cycles work
+        if let Some(id) = self.seen.get(scope, value).filter(|id| id.is_number()) {
+            return Ok(StructuredValue::Ref(
+                id.uint32_value(scope).unwrap_or(0) as usize
+            ));
+        }
+        let id = v8::Number::new(scope, self.next_id as f64);
+        self.seen.set(scope, value, id.into());
+        self.next_id += 1;
+
+        if value.is_date() {
+            let date = v8::Local::<v8::Date>::try_from(value)?;
+            Ok(StructuredValue::Date(date.value_of()))
+        } else if value.is_array_buffer() {
+            let buffer = v8::Local::<v8::ArrayBuffer>::try_from(value)?;
+            let store = buffer.get_backing_store();
+            if self.transfer.iter().any(|other| other.strict_equals(value)) {
+                return Ok(StructuredValue::ArrayBuffer(ArrayBufferContents(store)));
+            }
+
+            let bytes: Vec<u8> = store
+                .iter()
+                .take(buffer.byte_length())
+                .map(std::cell::Cell::get)
+                .collect();
+            Ok(StructuredValue::ArrayBuffer(bytes.into()))
+        } else if let Some(kind) = TypedArrayKind::of(value) {
+            let view = v8::Local::<v8::ArrayBufferView>::try_from(value)?;
+            let mut bytes = vec![0; view.byte_length()];
+            view.copy_contents(&mut bytes);
+            Ok(StructuredValue::TypedArray(kind, bytes))
+        } else if value.is_array() {
+            let array = v8::Local::<v8::Array>::try_from(value)?;
+            let mut items = Vec::with_capacity(array.length() as usize);
+            for i in 0..array.length() {
+                let item = array
+                    .get_index(scope, i)
+                    .unwrap_or_else(|| v8::undefined(scope).into());
+                items.push(self.encode(scope, item)?);
+            }
+            Ok(StructuredValue::Array(items))
+        } else if value.is_map() {
+            let entries = v8::Local::<v8::Map>::try_from(value)?.as_array(scope);
+            let mut pairs = Vec::with_capacity(entries.length() as usize / 2);
+            for i in (0..entries.length()).step_by(2) {
+                let key = self.entry(scope, entries, i)?;
+                let value = self.entry(scope, entries, i + 1)?;
+                pairs.push((key, value));
+            }
+            Ok(StructuredValue::Map(pairs))
+        } else if value.is_set() {
+            let entries = v8::Local::<v8::Set>::try_from(value)?.as_array(scope);
+            let mut items = Vec::with_capacity(entries.length() as usize);
+            for i in 0..entries.length() {
+                items.push(self.entry(scope, entries, i)?);
+            }
+            Ok(StructuredValue::Set(items))
+        } else {
+            let object = v8::Local::<v8::Object>::try_from(value)?;
+            let args = v8::GetPropertyNamesArgsBuilder::new()
+                .mode(v8::KeyCollectionMode::OwnOnly)
+                .key_conversion(v8::KeyConversionMode::ConvertToString)
+                .build();
+            let Some(keys) = object.get_own_property_names(scope, args) else {
+                return Ok(StructuredValue::Object(Vec::new()));
+            };
+
+            let mut entries = Vec::with_capacity(keys.length() as usize);
+            for i in 0..keys.length() {
+                let Some(key) = keys.get_index(scope, i) else {
+                    continue;
+                };
+                let item = object
+                    .get(scope, key)
+                    .unwrap_or_else(|| v8::undefined(scope).into());
+                let key = key.to_rust_string_lossy(scope);
+                entries.push((key, self.encode(scope, item)?));
+            }
+            Ok(StructuredValue::Object(entries))
+        }
+    }
+
+    fn entry(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        entries: v8::Local<'s, v8::Array>,
+        index: u32,
+    ) -> Result<StructuredValue, Error> {
+        let item = entries
+            .get_index(scope, index)
+            .unwrap_or_else(|| v8::undefined(scope).into());
+        self.encode(scope, item)
+    }
+}
+
+/// Builds a javascript value, numbering each object as it is created, in the same order as [Encoder]
+struct Decoder<'s> {
+    objects: Vec<v8::Local<'s, v8::Value>>,
+    depth: usize,
+}
+
+impl<'s> Decoder<'s> {
+    fn decode(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        value: StructuredValue,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        nest(&mut self.depth)?;
+        let result = self.decode_value(scope, value);
+        self.depth -= 1;
+        result
+    }
+
+    fn decode_value(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        value: StructuredValue,
+    ) -> Result<v8::Local<'s, v8::Value>, Error> {
+        let encoding = |kind: &str| Error::V8Encoding(kind.to_string());
+
+        Ok(match value {
+            StructuredValue::Undefined => v8::undefined(scope).into(),
+            StructuredValue::Null => v8::null(scope).into(),
+            StructuredValue::Boolean(b) => v8::Boolean::new(scope, b).into(),
+            StructuredValue::Number(n) => v8::Number::new(scope, n).into(),
+            StructuredValue::BigInt(n) => v8::BigInt::new_from_i64(scope, n).into(),
+            StructuredValue::String(s) => v8::String::new(scope, &s)
+                .ok_or_else(|| encoding("string"))?
+                .into(),
+
+            StructuredValue::Ref(id) => *self.objects.get(id).ok_or_else(|| {
+                Error::DataClone(format!("reference to object {id}, which comes after it"))
+            })?,
+
+            StructuredValue::Date(time) => {
+                let date = v8::Date::new(scope, time).ok_or_else(|| encoding("Date"))?;
+                self.created(date.into())
+            }
+
+            StructuredValue::ArrayBuffer(contents) => {
+                let buffer = v8::ArrayBuffer::with_backing_store(scope, &contents.0);
+                self.created(buffer.into())
+            }
+
+            StructuredValue::TypedArray(kind, bytes) => {
+                if bytes.len() % kind.element_size() != 0 {
+                    return Err(Error::DataClone(format!(
+                        "{kind:?} array of {} bytes is not a whole number of elements",
+                        bytes.len()
+                    )));
+                }
+
+                let length = bytes.len() / kind.element_size();
+                let buffer = Self::buffer(scope, bytes);
+                let array = kind
+                    .create(scope, buffer, length)
+                    .ok_or_else(|| encoding("typed array"))?;
+                self.created(array)
+            }
+
+            StructuredValue::Array(items) => {
+                let array = v8::Array::new(scope, items.len() as i32);
+                self.created(array.into());
+                for (i, item) in items.into_iter().enumerate() {
+                    let item = self.decode(scope, item)?;
+                    array.set_index(scope, i as u32, item);
+                }
+                array.into()
+            }
+
+            StructuredValue::Object(entries) => {
+                let object = v8::Object::new(scope);
+                self.created(object.into());
+                for (key, item) in entries {
+                    let key = v8::String::new(scope, &key).ok_or_else(|| encoding(&key))?;
+                    let item = self.decode(scope, item)?;
+                    object.set(scope, key.into(), item);
+                }
+                object.into()
+            }
+
+            StructuredValue::Map(entries) => {
+                let map = v8::Map::new(scope);
+                self.created(map.into());
+                for (key, item) in entries {
+                    let key = self.decode(scope, key)?;
+                    let item = self.decode(scope, item)?;
+                    map.set(scope, key, item);
+                }
+                map.into()
+            }
+
+            StructuredValue::Set(items) => {
+                let set = v8::Set::new(scope);
+                self.created(set.into());
+                for item in items {
+                    let item = self.decode(scope, item)?;
+                    set.add(scope, item);
+                }
+                set.into()
+            }
+        })
+    }
+
+    /// Number a newly created object, so later references can find it
+    fn created(&mut self, object: v8::Local<'s, v8::Value>) -> v8::Local<'s, v8::Value> {
+        self.objects.push(object);
+        object
+    }
+
+    /// Move bytes into a new `ArrayBuffer`, without copying them
+    fn buffer(scope: &mut v8::HandleScope<'s>, bytes: Vec<u8>) -> v8::Local<'s, v8::ArrayBuffer> {
+        let store = v8::ArrayBuffer::new_backing_store_from_vec(bytes).make_shared();
+        v8::ArrayBuffer::with_backing_store(scope, &store)
+    }
+}
+
+/// Converts a value to JSON for deserializing, numbering each object in the same order as [Encoder]
+struct Flattener {
+    refs: HashSet<usize>,
+
+    // Objects referenced more than once - `None` while they are still being converted
+    resolved: HashMap<usize, Option<serde_json::Value>>,
+    next_id: usize,
+    depth: usize,
+}
+
+impl Flattener {
+    fn flatten(&mut self, value: &StructuredValue) -> Result<serde_json::Value, Error> {
+        nest(&mut self.depth)?;
+        let result = self.flatten_value(value);
+        self.depth -= 1;
+        result
+    }
+
+    fn flatten_value(&mut self, value: &StructuredValue) -> Result<serde_json::Value, Error> {
+        let id = match value {
+            StructuredValue::Ref(id) => {
+                return match self.resolved.get(id) {
+                    Some(Some(value)) => Ok(value.clone()),
+                    Some(None) => Err(Error::DataClone(
+                        "a cyclic value cannot be deserialized".to_string(),
+                    )),
+                    None => Err(Error::DataClone(format!(
+                        "reference to object {id}, which comes after it"
+                    ))),
+                };
+            }
+            StructuredValue::Undefined
+            | StructuredValue::Null
+            | StructuredValue::Boolean(_)
+            | StructuredValue::Number(_)
+            | StructuredValue::String(_) => return value.to_json(),
+            StructuredValue::BigInt(n) => return Ok((*n).into()),
+            _ => {
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        };
+
+        let referenced = self.refs.contains(&id);
+        if referenced {
+            self.resolved.insert(id, None);
+        }
+
+        let data = match value {
+            StructuredValue::Date(time) => StructuredValue::Number(*time).to_json()?,
+            StructuredValue::ArrayBuffer(bytes) => bytes.as_bytes().into(),
+            StructuredValue::TypedArray(kind, bytes) => kind.elements(bytes),
+            StructuredValue::Array(items) | StructuredValue::Set(items) => items
+                .iter()
+                .map(|item| self.flatten(item))
+                .collect::<Result<_, _>>()?,
+            StructuredValue::Object(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, item)| Ok((key.clone(), self.flatten(item)?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            StructuredValue::Map(entries) => serde_json::Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, item)| Ok((Self::key(key)?, self.flatten(item)?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            _ => unreachable!("primitives and references are handled above"),
+        };
+
+        if referenced {
+            self.resolved.insert(id, Some(data.clone()));
+        }
+        Ok(data)
+    }
+
+    /// The string form of a `Map` key, as javascript would print it
+    fn key(key: &StructuredValue) -> Result<String, Error> {
+        match key {
+            StructuredValue::String(s) => Ok(s.clone()),
+            StructuredValue::Boolean(b) => Ok(b.to_string()),
+            StructuredValue::BigInt(n) => Ok(n.to_string()),
+            StructuredValue::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                Ok((*n as i64).to_string())
+            }
+            StructuredValue::Number(n) => Ok(n.to_string()),
+            other => Err(Error::DataClone(format!(
+                "a Map with {} keys cannot be deserialized",
+                other.kind()
+            ))),
+        }
+    }
+}
+
+impl TypedArrayKind {
+    /// The elements of a typed array, as JSON numbers
+    fn elements(&self, bytes: &[u8]) -> serde_json::Value {
+        let size = self.element_size();
+        bytes
+            .chunks_exact(size)
+            .map(|chunk| {
+                let mut raw = [0; 8];
+                raw[..size].copy_from_slice(chunk);
+                let number = |n: f64| StructuredValue::Number(n).to_json().unwrap_or_default();
+                match self {
+                    Self::Int8 => i8::from_ne_bytes([raw[0]]).into(),
+                    Self::Uint8 | Self::Uint8Clamped => raw[0].into(),
+                    Self::Int16 => i16::from_ne_bytes([raw[0], raw[1]]).into(),
+                    Self::Uint16 => u16::from_ne_bytes([raw[0], raw[1]]).into(),
+                    Self::Int32 => i32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]).into(),
+                    Self::Uint32 => u32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]).into(),
+                    Self::Float32 => {
+                        number(f32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]).into())
+                    }
+                    Self::Float64 => number(f64::from_ne_bytes(raw)),
+                    Self::BigInt64 => i64::from_ne_bytes(raw).into(),
+                    Self::BigUint64 => u64::from_ne_bytes(raw).into(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_structured_clone {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    #[test]
+    fn test_json_conversion() {
+        let json = serde_json::json!({ "a": [1, "two", null], "b": { "c": true } });
+        let value = StructuredValue::from(json.clone());
+        assert_eq!(json, value.to_json().unwrap());
+
+        let e = StructuredValue::Map(vec![]).to_json().unwrap_err();
+        assert!(matches!(e, Error::DataClone(_)), "{e}");
+
+        let list: Vec<i64> = StructuredValue::Array(vec![
+            StructuredValue::Number(1.0),
+            StructuredValue::Number(2.0),
+        ])
+        .deserialize()
+        .unwrap();
+        assert_eq!(vec![1, 2], list);
+
+        // Serialized values keep numbers JSON has no place for
+        let value = StructuredValue::Array(vec![
+            StructuredValue::Number(f64::NAN),
+            StructuredValue::Number(f64::NEG_INFINITY),
+            StructuredValue::Date(1.5),
+        ]);
+        let json = serde_json::to_string(&value).unwrap();
+        let (StructuredValue::Array(items), StructuredValue::Array(expected)) =
+            (serde_json::from_str(&json).unwrap(), value)
+        else {
+            panic!("Expected an array from {json}");
+        };
+        assert!(
+            matches!(items[0], StructuredValue::Number(n) if n.is_nan()),
+            "{json}"
+        );
+        assert_eq!(expected[1..], items[1..]);
+    }
+
+    #[test]
+    fn test_structured_round_trip() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_structured_clone.js",
+            "
+            export const make = () => {
+                const shared = { name: 'shared' };
+                const root = {
+                    when: new Date(86400000),
+                    big: 12345678901234n,
+                    lookup: new Map([['x', shared], [1, undefined]]),
+                    tags: new Set(['a', 'b']),
+                    bytes: new Uint16Array([1, 2, 3]),
+                    buffer: new Uint8Array([9, 8]).buffer,
+                    list: [shared, shared],
+                };
+                root.self = root;
+                return root;
+            };
+
+            export const check = (root) => [
+                root.self === root,
+                root.list[0] === root.list[1],
+                root.lookup.get('x') === root.list[0],
+                root.lookup.has(1),
+                root.when instanceof Date && root.when.getTime() === 86400000,
+                root.big === 12345678901234n,
+                root.tags.has('b'),
+                root.bytes instanceof Uint16Array && root.bytes[2] === 3,
+                root.buffer instanceof ArrayBuffer && new Uint8Array(root.buffer)[1] === 8,
+            ].every(Boolean);
+
+            export const fail = () => ({ f: () => 1 });
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let root = runtime
+            .call_function_structured(Some(&module), "make", vec![])
+            .expect("Could not clone value");
+        let StructuredValue::Object(entries) = &root else {
+            panic!("expected an object, got {root:?}");
+        };
+        let get = |name: &str| &entries.iter().find(|(k, _)| k == name).unwrap().1;
+        assert_eq!(&StructuredValue::Date(86400000.0), get("when"));
+        assert_eq!(&StructuredValue::BigInt(12345678901234), get("big"));
+        assert_eq!(&StructuredValue::Ref(0), get("self"));
+        assert_eq!(
+            &StructuredValue::TypedArray(TypedArrayKind::Uint16, vec![1, 0, 2, 0, 3, 0]),
+            get("bytes")
+        );
+        assert_eq!(
+            &StructuredValue::ArrayBuffer(vec![9, 8].into()),
+            get("buffer")
+        );
+
+        let result = runtime
+            .call_function_structured(Some(&module), "check", vec![root])
+            .expect("Could not call function");
+        assert_eq!(StructuredValue::Boolean(true), result);
+
+        let e = runtime
+            .call_function_structured(Some(&module), "fail", vec![])
+            .unwrap_err();
+        assert!(matches!(e, Error::DataClone(_)), "{e}");
+    }
+
+    #[test]
+    fn test_structured_limits() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_structured_limits.js",
+            "
+            export const nested = (depth) => {
+                let value = [];
+                for (let i = 0; i < depth; i++) value = [value];
+                return value;
+            };
+            export const wrap = (value) => [value];
+
+            let kept;
+            export const clone = () => {
+                const bytes = new Uint8Array([1, 2, 3]);
+                kept = bytes.buffer;
+                return { buffer: kept, view: bytes };
+            };
+            export const transfer = () => {
+                const value = clone();
+                return [value, [kept]];
+            };
+            export const keptLength = () => kept.byteLength;
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+
+        let depth = StructuredValue::Number((MAX_CLONE_DEPTH - 1) as f64);
+        let value = runtime
+            .call_function_structured(Some(&module), "nested", vec![depth])
+            .expect("Could not clone value");
+        let e = runtime
+            .call_function_structured(Some(&module), "wrap", vec![value])
+            .unwrap_err();
+        assert!(matches!(e, Error::DataClone(_)), "{e}");
+
+        let depth = StructuredValue::Number(MAX_CLONE_DEPTH as f64 * 4.0);
+        let e = runtime
+            .call_function_structured(Some(&module), "nested", vec![depth])
+            .unwrap_err();
+        assert!(matches!(e, Error::DataClone(_)), "{e}");
+
+        // Buffers are copied, unless they are in the transfer list
+        // A transferred buffer is detached in the runtime, after the view on it was copied
+        for (transfer, kept_length) in [(false, 3), (true, 0)] {
+            let value = if transfer {
+                runtime.call_function_structured_transfer(Some(&module), "transfer", vec![])
+            } else {
+                runtime.call_function_structured(Some(&module), "clone", vec![])
+            }
+            .expect("Could not clone value");
+            let StructuredValue::Object(entries) = &value else {
+                panic!("expected an object, got {value:?}");
+            };
+            assert_eq!(
+                StructuredValue::ArrayBuffer(vec![1, 2, 3].into()),
+                entries[0].1
+            );
+            assert_eq!(
+                StructuredValue::TypedArray(TypedArrayKind::Uint8, vec![1, 2, 3]),
+                entries[1].1
+            );
+            let length: usize = runtime
+                .call_function(Some(&module), "keptLength", crate::json_args!())
+                .expect("Could not read the buffer's length");
+            assert_eq!(kept_length, length);
+        }
+    }
+
+    #[test]
+    fn test_structured_deserialize() {
+        let shared = StructuredValue::Set(vec![StructuredValue::String("a".to_string())]);
+        let value = StructuredValue::Object(vec![
+            (
+                "counts".to_string(),
+                StructuredValue::Map(vec![(StructuredValue::Number(1.0), shared)]),
+            ),
+            ("again".to_string(), StructuredValue::Ref(2)),
+            (
+                "words".to_string(),
+                StructuredValue::TypedArray(TypedArrayKind::Int16, (-2i16).to_ne_bytes().into()),
+            ),
+            ("when".to_string(), StructuredValue::Date(5.0)),
+        ]);
+
+        #[derive(serde::Deserialize)]
+        struct Data {
+            counts: std::collections::HashMap<i64, Vec<String>>,
+            again: std::collections::HashSet<String>,
+            words: Vec<i16>,
+            when: f64,
+        }
+        let data: Data = value.deserialize().unwrap();
+        assert_eq!(vec!["a".to_string()], data.counts[&1]);
+        assert!(data.again.contains("a"));
+        assert_eq!(vec![-2], data.words);
+        assert_eq!(5.0, data.when);
+
+        let cyclic = StructuredValue::Array(vec![StructuredValue::Ref(0)]);
+        let e = cyclic.deserialize::<Vec<()>>().unwrap_err();
+        assert!(matches!(e, Error::DataClone(_)), "{e}");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::traits::ToModuleSpecifier;
 use crate::{Error, Module, ModuleWrapper, Runtime};
+use deno_core::serde_json;
 
 /// Evaluate a piece of non-ECMAScript-module JavaScript code
 /// Effects on the global scope will not persist
@@ -84,6 +85,118 @@ pub fn resolve_path(path: &str) -> Result<String, Error> {
     Ok(path.to_module_specifier()?.to_string())
 }
 
+/// Format a value the way `console.log` would display it
+///
+/// Strings are printed as-is at the top level, and quoted when nested.
+/// Objects and arrays nested more than 4 levels deep are abbreviated,
+/// and ones too long to fit on one line are split across several
+///
+/// # Arguments
+/// * `value` - The value to format
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{format_value, serde_json::json};
+///
+/// let value = json!({ "name": "test", "tags": ["a", "b"], "size": 2.0 });
+/// assert_eq!(r#"{ name: "test", tags: [ "a", "b" ], size: 2 }"#, format_value(&value));
+/// ```
+pub fn format_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        _ => format::value(value, 0, 0),
+    }
+}
+
+mod format {
+    use deno_core::serde_json::{Number, Value};
+
+    /// Objects nested deeper than this are shown as `[Object]` or `[Array]`
+    const MAX_DEPTH: usize = 4;
+
+    /// Maximum line width before entries are split across lines
+    const BREAK_LENGTH: usize = 80;
+
+    pub fn value(value: &Value, depth: usize, indent: usize) -> String {
+        match value {
+            Value::Null => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => number(n),
+            Value::String(s) => string(s),
+            Value::Array(a) if a.is_empty() => "[]".to_string(),
+            Value::Object(o) if o.is_empty() => "{}".to_string(),
+            Value::Array(_) if depth > MAX_DEPTH => "[Array]".to_string(),
+            Value::Object(_) if depth > MAX_DEPTH => "[Object]".to_string(),
+            Value::Array(a) => {
+                let entries = a
+                    .iter()
+                    .map(|v| self::value(v, depth + 1, indent + 2))
+                    .collect();
+                group(entries, ("[", "]"), indent)
+            }
+            Value::Object(o) => {
+                let entries = o
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", key(k), self::value(v, depth + 1, indent + 2)))
+                    .collect();
+                group(entries, ("{", "}"), indent)
+            }
+        }
+    }
+
+    /// Join entries on one line if they fit, or one per line otherwise
+    fn group(entries: Vec<String>, braces: (&str, &str), indent: usize) -> String {
+        let start = entries.len() + indent + braces.0.len() + 10;
+        let length: usize = entries.iter().map(String::len).sum::<usize>() + entries.len();
+        let multiline = entries.iter().any(|e| e.contains('\n'));
+        if !multiline && start + length <= BREAK_LENGTH {
+            return format!("{} {} {}", braces.0, entries.join(", "), braces.1);
+        }
+
+        let padding = " ".repeat(indent);
+        format!(
+            "{}\n{padding}  {}\n{padding}{}",
+            braces.0,
+            entries.join(&format!(",\n{padding}  ")),
+            braces.1
+        )
+    }
+
+    /// Integral floats are shown without a fractional part, as JS numbers are
+    fn number(n: &Number) -> String {
+        match n.as_f64() {
+            Some(f) if !n.is_i64() && !n.is_u64() && f.fract() == 0.0 && f.abs() < 1e21 => {
+                format!("{f:.0}")
+            }
+            _ => n.to_string(),
+        }
+    }
+
+    /// Strings are double-quoted, unless that would need escaping and single quotes would not
+    fn string(s: &str) -> String {
+        if s.contains('"') && !s.contains('\'') {
+            format!("'{}'", s.replace('\\', "\\\\").replace('\n', "\\n"))
+        } else {
+            Value::String(s.to_string()).to_string()
+        }
+    }
+
+    /// Keys that are valid identifiers are left unquoted
+    fn key(k: &str) -> String {
+        let mut chars = k.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+        if is_identifier {
+            k.to_string()
+        } else {
+            string(k)
+        }
+    }
+}
+
 #[macro_use]
 mod runtime_macros {
     /// Map a series of values to a slice of `serde_json::Value` objects
@@ -187,7 +300,7 @@ mod runtime_macros {
 #[cfg(test)]
 mod test_runtime {
     use super::*;
-    use deno_core::{futures::FutureExt, serde_json};
+    use deno_core::futures::FutureExt;
 
     #[test]
     fn test_callback() {
@@ -218,6 +331,31 @@ mod test_runtime {
         assert_eq!(false, validate("5;+-").expect("invalid expression"));
     }
 
+    #[test]
+    fn test_format_value() {
+        assert_eq!("text", format_value(&serde_json::json!("text")));
+        assert_eq!("1.5", format_value(&serde_json::json!(1.5)));
+        assert_eq!("null", format_value(&serde_json::json!(null)));
+        assert_eq!("[ 1, 2, 3 ]", format_value(&serde_json::json!([1, 2.0, 3])));
+        assert_eq!(
+            r#"{ a: [], "b-c": {}, d: 'say "hi"' }"#,
+            format_value(&serde_json::json!({ "a": [], "b-c": {}, "d": "say \"hi\"" }))
+        );
+        assert_eq!(
+            "{ a: { b: { c: { d: { e: [Object] } } } } }",
+            format_value(
+                &serde_json::json!({ "a": { "b": { "c": { "d": { "e": { "f": 1 } } } } } })
+            )
+        );
+
+        let long: Vec<String> = (0..8).map(|i| format!("entry number {i}")).collect();
+        let value = serde_json::json!({ "list": long });
+        assert_eq!(
+            "{\n  list: [\n    \"entry number 0\",\n    \"entry number 1\",\n    \"entry number 2\",\n    \"entry number 3\",\n    \"entry number 4\",\n    \"entry number 5\",\n    \"entry number 6\",\n    \"entry number 7\"\n  ]\n}",
+            format_value(&value)
+        );
+    }
+
     #[test]
     fn test_resolve_path() {
         assert!(resolve_path("test.js")
@@ -0,0 +1,93 @@
+use crate::Error;
+use deno_core::{v8, JsRuntime};
+
+/// A javascript value that is still held by the runtime it came from
+/// Rather than deserializing the whole value up-front, it can be viewed as the type needed,
+/// which avoids building a `serde_json::Value` for large strings and binary data
+///
+/// Borrows the runtime, and so cannot outlive it
+pub struct V8Value<'a> {
+    runtime: &'a mut JsRuntime,
+    value: v8::Global<v8::Value>,
+}
+
+impl<'a> V8Value<'a> {
+    pub(crate) fn new(runtime: &'a mut JsRuntime, value: v8::Global<v8::Value>) -> Self {
+        Self { runtime, value }
+    }
+
+    /// Returns true if the value is `undefined`
+    pub fn is_undefined(&mut self) -> bool {
+        let mut scope = self.runtime.handle_scope();
+        v8::Local::new(&mut scope, &self.value).is_undefined()
+    }
+
+    /// Returns true if the value is `null`
+    pub fn is_null(&mut self) -> bool {
+        let mut scope = self.runtime.handle_scope();
+        v8::Local::new(&mut scope, &self.value).is_null()
+    }
+
+    /// Copy the value out as a string
+    /// Returns an error if the value is not a string
+    pub fn as_str(&mut self) -> Result<String, Error> {
+        let mut scope = self.runtime.handle_scope();
+        let value = v8::Local::new(&mut scope, &self.value);
+        let value: v8::Local<v8::String> = value
+            .try_into()
+            .or::<Error>(Err(Error::JsonDecode("value was not a string".to_string())))?;
+        Ok(value.to_rust_string_lossy(&mut scope))
+    }
+
+    /// Copy the value out as bytes
+    /// Works for `ArrayBuffer`s, and views into them such as `Uint8Array` or `DataView`
+    /// Returns an error if the value is not binary data
+    pub fn as_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let mut scope = self.runtime.handle_scope();
+        let value = v8::Local::new(&mut scope, &self.value);
+
+        if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(value) {
+            let mut bytes = vec![0; view.byte_length()];
+            view.copy_contents(&mut bytes);
+            Ok(bytes)
+        } else if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+            let store = buffer.get_backing_store();
+            Ok(store.iter().map(std::cell::Cell::get).collect())
+        } else {
+            Err(Error::JsonDecode(
+                "value was not an ArrayBuffer or typed array".to_string(),
+            ))
+        }
+    }
+
+    /// Deserialize the value directly from the isolate
+    pub fn deserialize<T>(&mut self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut scope = self.runtime.handle_scope();
+        let value = v8::Local::new(&mut scope, &self.value);
+        Ok(deno_core::serde_v8::from_v8(&mut scope, value)?)
+    }
+
+    /// Drive a serde visitor directly from the isolate
+    /// The visitor sees each part of the value as it is read, so large values can be
+    /// processed without building an intermediate copy of the whole value
+    pub fn visit<'de, V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let mut scope = self.runtime.handle_scope();
+        let value = v8::Local::new(&mut scope, &self.value);
+        let mut deserializer = deno_core::serde_v8::Deserializer::new(&mut scope, value, None);
+        Ok(serde::Deserializer::deserialize_any(
+            &mut deserializer,
+            visitor,
+        )?)
+    }
+
+    /// Release the borrow on the runtime, returning the underlying v8 value
+    pub fn into_v8(self) -> v8::Global<v8::Value> {
+        self.value
+    }
+}
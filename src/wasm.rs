@@ -0,0 +1,242 @@
+//! Support for importing WebAssembly modules from javascript
+//! A `.wasm` module is wrapped in a generated javascript module, which imports the modules the
+//! WebAssembly module depends on, instantiates it, and re-exports each of its exports
+use crate::{serde_json, Error};
+use deno_core::ModuleSpecifier;
+
+/// Returns true if a specifier names a WebAssembly module
+pub fn is_wasm(specifier: &ModuleSpecifier) -> bool {
+    specifier.path().ends_with(".wasm")
+}
+
+/// Generate a javascript module that instantiates the WebAssembly module in `bytes`
+pub fn wrap(bytes: &[u8]) -> Result<String, Error> {
+    let (imports, exports) = read_module(bytes)
+        .ok_or_else(|| Error::Runtime("Invalid WebAssembly module".to_string()))?;
+
+    let mut code = String::new();
+    let mut import_object = vec![];
+    for (i, module) in imports.iter().enumerate() {
+        let module = serde_json::to_string(module)?;
+        code += &format!("import * as import_{i} from {module};\n");
+        import_object.push(format!("{module}: import_{i}"));
+    }
+
+    let bytes: Vec<_> = bytes.iter().map(u8::to_string).collect();
+    code += &format!("const bytes = new Uint8Array([{}]);\n", bytes.join(","));
+    code += &format!(
+        "const {{ instance }} = await WebAssembly.instantiate(bytes, {{ {} }});\n",
+        import_object.join(", ")
+    );
+
+    for (i, name) in exports.iter().enumerate() {
+        let name = serde_json::to_string(name)?;
+        code += &format!("const export_{i} = instance.exports[{name}];\n");
+        code += &format!("export {{ export_{i} as {name} }};\n");
+    }
+
+    Ok(code)
+}
+
+/// Read the names of the modules imported, and the names exported, by a WebAssembly module
+fn read_module(bytes: &[u8]) -> Option<(Vec<String>, Vec<String>)> {
+    let mut reader = Reader(bytes.strip_prefix(b"\0asm\x01\0\0\0")?);
+    let mut imports: Vec<String> = vec![];
+    let mut exports = vec![];
+
+    while !reader.0.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.leb()? as usize;
+        let mut section = Reader(reader.take(size)?);
+        match id {
+            // Imports
+            2 => {
+                for _ in 0..section.leb()? {
+                    let module = section.name()?;
+                    section.name()?;
+                    section.import_desc()?;
+                    if !imports.contains(&module) {
+                        imports.push(module);
+                    }
+                }
+            }
+
+            // Exports
+            7 => {
+                for _ in 0..section.leb()? {
+                    exports.push(section.name()?);
+                    section.byte()?;
+                    section.leb()?;
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Some((imports, exports))
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.0.len() {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    /// An unsigned LEB128 integer
+    fn leb(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let len = self.leb()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn limits(&mut self) -> Option<()> {
+        let flags = self.byte()?;
+        self.leb()?;
+        if flags & 1 != 0 {
+            self.leb()?;
+        }
+        Some(())
+    }
+
+    /// Skip the description of an import
+    fn import_desc(&mut self) -> Option<()> {
+        match self.byte()? {
+            // Function, or tag
+            0 => self.leb().map(|_| ()),
+            4 => self.byte().and_then(|_| self.leb()).map(|_| ()),
+
+            // Table
+            1 => self.byte().and_then(|_| self.limits()),
+
+            // Memory
+            2 => self.limits(),
+
+            // Global
+            3 => self.take(2).map(|_| ()),
+
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_wasm {
+    use super::*;
+    use crate::{Module, Runtime};
+
+    /// (func (export "add") (param i32 i32) (result i32) (i32.add (local.get 0) (local.get 1)))
+    const ADD: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f,
+        0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00,
+        0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+    ];
+
+    /// (import "./test_wasm_env.js" "offset" (func (result i32))), re-exported as "get"
+    const IMPORT: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f,
+        0x02, 0x1d, 0x01, 0x12, 0x2e, 0x2f, 0x74, 0x65, 0x73, 0x74, 0x5f, 0x77, 0x61, 0x73, 0x6d,
+        0x5f, 0x65, 0x6e, 0x76, 0x2e, 0x6a, 0x73, 0x06, 0x6f, 0x66, 0x66, 0x73, 0x65, 0x74, 0x00,
+        0x00, 0x07, 0x07, 0x01, 0x03, 0x67, 0x65, 0x74, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_read_module() {
+        assert_eq!(Some((vec![], vec!["add".to_string()])), read_module(ADD));
+        assert_eq!(
+            Some((
+                vec!["./test_wasm_env.js".to_string()],
+                vec!["get".to_string()]
+            )),
+            read_module(IMPORT)
+        );
+
+        assert_eq!(None, read_module(b"not wasm"));
+        assert_eq!(None, read_module(&ADD[..ADD.len() - 2]));
+    }
+
+    #[test]
+    fn test_wasm_module() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        runtime
+            .load_module(&Module::new(
+                "test_wasm_env.js",
+                "export const offset = () => 40;",
+            ))
+            .expect("Could not load module");
+
+        let module = Module::new("test_wasm_import.js", &wrap(IMPORT).unwrap());
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .call_function(Some(&module), "get", &[])
+            .expect("Could not call function");
+        assert_eq!(40, value);
+
+        let module = Module::new("test_wasm_add.js", &wrap(ADD).unwrap());
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .call_function(Some(&module), "add", &[2.into(), 3.into()])
+            .expect("Could not call function");
+        assert_eq!(5, value);
+    }
+
+    #[test]
+    fn test_wasm_streaming() {
+        let mut runtime = Runtime::new(Default::default()).expect("Could not create the runtime");
+        let module = Module::new(
+            "test_wasm_streaming.js",
+            "
+            export const add = async (bytes, a, b) => {
+                const { instance } = await WebAssembly.instantiateStreaming(Promise.resolve(bytes));
+                return instance.exports.add(a, b);
+            };
+        ",
+        );
+        let module = runtime.load_module(&module).expect("Could not load module");
+        let value: i64 = runtime
+            .call_function(Some(&module), "add", &[ADD.into(), 2.into(), 3.into()])
+            .expect("Could not call function");
+        assert_eq!(5, value);
+    }
+
+    #[test]
+    fn test_disable_wasm() {
+        let mut runtime = Runtime::builder()
+            .disable_wasm(true)
+            .build()
+            .expect("Could not create the runtime");
+        let kind: String = runtime.eval("typeof WebAssembly").unwrap();
+        assert_eq!("undefined", kind);
+
+        let e = runtime
+            .load_module(&Module::new(
+                "test_disable_wasm.js",
+                "import * as wasm from './test_disable_wasm.wasm';",
+            ))
+            .unwrap_err();
+        assert!(
+            e.to_string().contains("WebAssembly modules are disabled"),
+            "{e}"
+        );
+    }
+}
@@ -8,6 +8,7 @@
 //!     let worker = DefaultWorker::new(DefaultWorkerOptions {
 //!         default_entrypoint: None,
 //!         timeout: Duration::from_secs(5),
+//!         ..Default::default()
 //!     })?;
 //!
 //!     worker.register_function("add".to_string(), |args, _state| {
@@ -21,8 +22,14 @@
 //!     Ok(())
 //! }
 
-use crate::Error;
-use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::{Error, WorkerCrashReport};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{
+    channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError,
+    TrySendError,
+};
 use std::thread::{spawn, JoinHandle};
 
 /// A worker thread that can be used to run javascript code in a separate thread
@@ -36,9 +43,89 @@ pub struct Worker<W>
 where
     W: InnerWorker,
 {
-    handle: JoinHandle<()>,
-    tx: Sender<W::Query>,
+    handle: JoinHandle<Option<WorkerCrashReport>>,
+    tx: QuerySender<W::Query>,
     rx: Receiver<W::Response>,
+
+    // Responses still owed to queries whose receive timed out, to be discarded when they arrive
+    late: std::cell::Cell<usize>,
+}
+
+/// Crash details for the current worker thread
+#[derive(Default)]
+struct CrashContext {
+    query: Option<String>,
+    report: Option<WorkerCrashReport>,
+}
+
+thread_local! {
+    static CRASH_CONTEXT: RefCell<Option<CrashContext>> = const { RefCell::new(None) };
+}
+
+/// Install a panic hook that records crash reports on worker threads
+/// The hook then calls the previously installed one, so panics are still reported as usual
+fn install_panic_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = CRASH_CONTEXT.try_with(|context| {
+                if let Ok(mut context) = context.try_borrow_mut() {
+                    if let Some(context) = context.as_mut() {
+                        context.report = Some(WorkerCrashReport {
+                            message: panic_message(info.payload()),
+                            location: info.location().map(ToString::to_string),
+                            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+                            query: context.query.clone(),
+                        });
+                    }
+                }
+            });
+            previous(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Record the query the current worker thread is handling, for crash reports
+fn set_current_query(query: Option<String>) {
+    CRASH_CONTEXT.with(|context| {
+        if let Some(context) = context.borrow_mut().as_mut() {
+            context.query = query;
+        }
+    });
+}
+
+/// Run a worker thread's main loop, returning a crash report if it panics
+fn run_catching_panics(f: impl FnOnce()) -> Option<WorkerCrashReport> {
+    install_panic_hook();
+    CRASH_CONTEXT.with(|context| *context.borrow_mut() = Some(CrashContext::default()));
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    let context = CRASH_CONTEXT
+        .with(|context| context.borrow_mut().take())
+        .unwrap_or_default();
+
+    let payload = result.err()?;
+    Some(context.report.unwrap_or_else(|| WorkerCrashReport {
+        message: panic_message(payload.as_ref()),
+        location: None,
+        backtrace: String::new(),
+        query: context.query,
+    }))
+}
+
+/// The sending half of a worker's query channel
+enum QuerySender<Q> {
+    Unbounded(Sender<Q>),
+    Bounded(SyncSender<Q>, usize),
 }
 
 impl<W> Worker<W>
@@ -48,6 +135,27 @@ where
     /// Create a new worker instance
     pub fn new(options: W::RuntimeOptions) -> Result<Self, Error> {
         let (qtx, qrx) = channel();
+        Self::start(options, QuerySender::Unbounded(qtx), qrx)
+    }
+
+    /// Create a new worker instance, whose queue holds at most `capacity` pending queries
+    /// Once the queue is full, `send` returns `Error::WorkerBusy` instead of queuing more work
+    pub fn new_bounded(options: W::RuntimeOptions, capacity: usize) -> Result<Self, Error> {
+        if capacity == 0 {
+            return Err(Error::Configuration(
+                "worker queue capacity must be greater than zero".to_string(),
+            ));
+        }
+
+        let (qtx, qrx) = sync_channel(capacity);
+        Self::start(options, QuerySender::Bounded(qtx, capacity), qrx)
+    }
+
+    fn start(
+        options: W::RuntimeOptions,
+        qtx: QuerySender<W::Query>,
+        qrx: Receiver<W::Query>,
+    ) -> Result<Self, Error> {
         let (rtx, rrx) = channel();
         let (init_tx, init_rx) = channel::<Option<Error>>();
 
@@ -60,18 +168,19 @@ where
                 Ok(rt) => rt,
                 Err(e) => {
                     itx.send(Some(e)).unwrap();
-                    return;
+                    return None;
                 }
             };
 
             itx.send(None).unwrap();
-            W::thread(runtime, rx, tx);
+            run_catching_panics(|| W::thread(runtime, rx, tx))
         });
 
         let worker = Self {
             handle,
             tx: qtx,
             rx: rrx,
+            late: std::cell::Cell::new(0),
         };
 
         // Wait for initialization to complete
@@ -109,18 +218,72 @@ where
 
     /// Send a request to the worker
     /// This will not block the current thread
-    /// Will return an error if the worker has stopped or panicked
+    /// Will return an error if the worker has stopped or panicked,
+    /// or `Error::WorkerBusy` if the worker was created with `new_bounded` and its queue is full
     pub fn send(&self, query: W::Query) -> Result<(), Error> {
-        self.tx
-            .send(query)
-            .map_err(|e| Error::Runtime(e.to_string()))
+        match &self.tx {
+            QuerySender::Unbounded(tx) => tx.send(query).map_err(|e| Error::Runtime(e.to_string())),
+            QuerySender::Bounded(tx, capacity) => tx.try_send(query).map_err(|e| match e {
+                TrySendError::Full(_) => Error::WorkerBusy(*capacity),
+                TrySendError::Disconnected(_) => Error::Runtime(e.to_string()),
+            }),
+        }
     }
 
     /// Receive a response from the worker
     /// This will block the current thread until a response is received
     /// Will return an error if the worker has stopped or panicked
+    ///
+    /// Late responses to queries whose receive timed out are discarded first
     pub fn receive(&self) -> Result<W::Response, Error> {
-        self.rx.recv().map_err(|e| Error::Runtime(e.to_string()))
+        self.receive_after_late(None)
+            .map_err(|e| Error::Runtime(e.to_string()))
+    }
+
+    /// Receive a response from the worker, waiting at most `timeout` for it
+    /// Will return `Error::Timeout` if no response arrives in time,
+    /// or an error if the worker has stopped or panicked
+    ///
+    /// After a timeout, the response is still owed - it is discarded when it arrives,
+    /// so that it is not mistaken for the response to the next query
+    /// This relies on each query being answered by exactly one response
+    pub fn receive_timeout(&self, timeout: std::time::Duration) -> Result<W::Response, Error> {
+        let deadline = std::time::Instant::now().checked_add(timeout);
+        self.receive_after_late(deadline).map_err(|e| match e {
+            RecvTimeoutError::Timeout => {
+                self.late.set(self.late.get() + 1);
+                Error::Timeout(format!(
+                    "no response from the worker within {}ms",
+                    timeout.as_millis()
+                ))
+            }
+            RecvTimeoutError::Disconnected => Error::Runtime(e.to_string()),
+        })
+    }
+
+    /// Discard the responses owed to queries that timed out, then receive the next one
+    fn receive_after_late(
+        &self,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<W::Response, RecvTimeoutError> {
+        while self.late.get() > 0 {
+            self.receive_until(deadline)?;
+            self.late.set(self.late.get() - 1);
+        }
+        self.receive_until(deadline)
+    }
+
+    /// Receive the next response as is, waiting until `deadline` if set
+    fn receive_until(
+        &self,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<W::Response, RecvTimeoutError> {
+        match deadline {
+            Some(deadline) => self
+                .rx
+                .recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())),
+            None => self.rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        }
     }
 
     /// Send a request to the worker and wait for a response
@@ -131,13 +294,35 @@ where
         self.receive()
     }
 
+    /// Send a request to the worker and wait at most `timeout` for a response
+    /// Will return `Error::Timeout` if no response arrives in time,
+    /// or an error if the worker has stopped or panicked
+    pub fn send_and_await_timeout(
+        &self,
+        query: W::Query,
+        timeout: std::time::Duration,
+    ) -> Result<W::Response, Error> {
+        self.send(query)?;
+        self.receive_timeout(timeout)
+    }
+
+    /// Returns false if the worker thread has stopped, or panicked
+    pub fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+
     /// Consume the worker and wait for the thread to finish
     /// WARNING: This will block the current thread until the worker has finished
     ///          Make sure to send a stop message to the worker before calling this!
+    ///
+    /// If the thread panicked, returns `Error::WorkerCrashed` with the panic's message and backtrace,
+    /// and the query being handled at the time
     pub fn join(self) -> Result<(), Error> {
-        self.handle
-            .join()
-            .map_err(|_| Error::Runtime("Worker thread panicked".to_string()))
+        match self.handle.join() {
+            Ok(None) => Ok(()),
+            Ok(Some(report)) => Err(Error::WorkerCrashed(Box::new(report))),
+            Err(_) => Err(Error::Runtime("Worker thread panicked".to_string())),
+        }
     }
 }
 
@@ -178,6 +363,13 @@ where
     /// Must always return a response of some kind
     fn handle_query(runtime: &mut Self::Runtime, query: Self::Query) -> Self::Response;
 
+    /// Describe a query for crash reports, if the worker thread panics while handling it
+    /// Returns None by default, leaving the query out of the report
+    fn describe_query(query: &Self::Query) -> Option<String> {
+        let _ = query;
+        None
+    }
+
     /// The main thread function that will be run by the worker
     /// This should handle all incoming queries and send responses back
     fn thread(mut runtime: Self::Runtime, rx: Receiver<Self::Query>, tx: Sender<Self::Response>) {
@@ -187,42 +379,336 @@ where
                 Err(_) => break,
             };
 
+            set_current_query(Self::describe_query(&msg));
             let response = Self::handle_query(&mut runtime, msg);
+            set_current_query(None);
             tx.send(response).unwrap();
         }
     }
 }
 
+/// Generates a custom worker from a list of queries, instead of implementing [InnerWorker] by hand
+///
+/// Produces the worker type, its query and response enums, the [InnerWorker] implementation
+/// dispatching each query to its body, and a typed client method for each query
+///
+/// Each query is written as `Variant => fn method(runtime, args...) -> T { body }`, where the body
+/// runs on the worker thread, with `runtime` bound to the worker's runtime, and returns `Result<T, Error>`
+///
+/// The worker thread stops once the worker is dropped
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ worker_protocol, Error, Runtime, RuntimeOptions };
+/// use std::time::Duration;
+///
+/// worker_protocol! {
+///     /// A worker that does arithmetic in javascript
+///     pub struct MathWorker {
+///         query: MathQuery,
+///         response: MathResponse,
+///         runtime: Runtime,
+///
+///         fn init(timeout: Duration) {
+///             Runtime::new(RuntimeOptions { timeout, ..Default::default() })
+///         }
+///
+///         /// Evaluate an expression
+///         Eval => fn eval(runtime, code: String) -> i64 {
+///             runtime.eval(&code)
+///         }
+///
+///         /// Add two numbers
+///         Add => fn add(runtime, a: i64, b: i64) -> i64 {
+///             runtime.eval(&format!("{a} + {b}"))
+///         }
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// let worker = MathWorker::new(Duration::from_secs(1))?;
+/// assert_eq!(4, worker.eval("2 * 2".to_string())?);
+/// assert_eq!(5, worker.add(2, 3)?);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! worker_protocol {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            query: $query:ident,
+            response: $response:ident,
+            runtime: $runtime:ty,
+
+            fn init($options:ident: $options_ty:ty) $init:block
+
+            $(
+                $(#[$query_meta:meta])*
+                $variant:ident => fn $method:ident($rt:ident $(, $arg:ident: $arg_ty:ty)* $(,)?) -> $ret:ty $body:block
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name($crate::worker::Worker<$name>);
+
+        #[doc = concat!("Queries handled by [", stringify!($name), "]")]
+        $vis enum $query {
+            $(
+                $(#[$query_meta])*
+                $variant($($arg_ty),*),
+            )*
+        }
+
+        #[doc = concat!("Responses sent by [", stringify!($name), "], one for each query")]
+        $vis enum $response {
+            $(
+                $(#[$query_meta])*
+                $variant(::std::result::Result<$ret, $crate::Error>),
+            )*
+        }
+
+        impl $crate::worker::InnerWorker for $name {
+            type Runtime = $runtime;
+            type RuntimeOptions = $options_ty;
+            type Query = $query;
+            type Response = $response;
+
+            fn init_runtime(
+                $options: Self::RuntimeOptions,
+            ) -> ::std::result::Result<Self::Runtime, $crate::Error> {
+                $init
+            }
+
+            #[allow(clippy::redundant_closure_call)]
+            fn handle_query(runtime: &mut Self::Runtime, query: Self::Query) -> Self::Response {
+                match query {
+                    $(
+                        $query::$variant($($arg),*) => {
+                            let $rt = &mut *runtime;
+                            $response::$variant((|| -> ::std::result::Result<$ret, $crate::Error> {
+                                $body
+                            })())
+                        }
+                    )*
+                }
+            }
+
+            fn describe_query(query: &Self::Query) -> Option<String> {
+                match query {
+                    $($query::$variant(..) => Some(stringify!($variant).to_string()),)*
+                }
+            }
+        }
+
+        impl $name {
+            /// Start the worker, initializing its runtime with `options`
+            $vis fn new(options: $options_ty) -> ::std::result::Result<Self, $crate::Error> {
+                $crate::worker::Worker::new(options).map(Self)
+            }
+
+            /// Start the worker, with a queue holding at most `capacity` pending queries
+            $vis fn new_bounded(
+                options: $options_ty,
+                capacity: usize,
+            ) -> ::std::result::Result<Self, $crate::Error> {
+                $crate::worker::Worker::new_bounded(options, capacity).map(Self)
+            }
+
+            /// The underlying worker, for sending queries directly
+            $vis fn worker(&self) -> &$crate::worker::Worker<Self> {
+                &self.0
+            }
+
+            $(
+                $(#[$query_meta])*
+                $vis fn $method(&self $(, $arg: $arg_ty)*) -> ::std::result::Result<$ret, $crate::Error> {
+                    #[allow(unreachable_patterns)]
+                    match self.0.send_and_await($query::$variant($($arg),*))? {
+                        $response::$variant(result) => result,
+                        _ => Err($crate::Error::Runtime(
+                            concat!("Unexpected response to ", stringify!($variant)).to_string(),
+                        )),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+/// Generates a typed client over a [DefaultWorker], from a list of JS function signatures
+///
+/// Each method serializes its arguments, calls the function of the same name in the worker,
+/// and deserializes the result, so call sites don't need to build argument lists by hand
+/// Functions whose JS name differs from the method name can be written as `fn method(..) -> T = "jsName";`
+///
+/// Functions are looked up in the client's module, if it has one, and in the global context otherwise
+///
+/// # Example
+/// ```rust
+/// use rustyscript::{ worker_client, Error, Module };
+/// use rustyscript::worker::DefaultWorker;
+/// use std::time::Duration;
+///
+/// worker_client! {
+///     /// A client for `math.js`
+///     pub struct MathClient {
+///         /// Add two numbers
+///         fn add(a: i64, b: i64) -> i64;
+///
+///         /// Describe a number
+///         fn describe(n: i64) -> String = "describeNumber";
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// let worker = DefaultWorker::builder().timeout(Duration::from_secs(1)).build()?;
+/// let module = worker.load_module(Module::new("math.js", "
+///     export const add = (a, b) => a + b;
+///     export const describeNumber = (n) => n % 2 ? 'odd' : 'even';
+/// "))?;
+///
+/// let client = MathClient::with_module(worker, module);
+/// assert_eq!(5, client.add(2, 3)?);
+/// assert_eq!("even", client.describe(4)?);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! worker_client {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$fn_meta:meta])*
+                fn $method:ident($($arg:ident: $arg_ty:ty),* $(,)?) -> $ret:ty $(= $js_name:literal)?;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            worker: $crate::worker::DefaultWorker,
+            module: ::std::option::Option<$crate::deno_core::ModuleId>,
+        }
+
+        impl $name {
+            /// Wrap a worker, calling functions from its global context
+            $vis fn new(worker: $crate::worker::DefaultWorker) -> Self {
+                Self { worker, module: None }
+            }
+
+            /// Wrap a worker, calling functions exported by a module loaded into it
+            $vis fn with_module(
+                worker: $crate::worker::DefaultWorker,
+                module: $crate::deno_core::ModuleId,
+            ) -> Self {
+                Self { worker, module: Some(module) }
+            }
+
+            /// The underlying worker, for sending other queries
+            $vis fn worker(&self) -> &$crate::worker::DefaultWorker {
+                &self.worker
+            }
+
+            /// Unwrap the client, returning the underlying worker
+            $vis fn into_inner(self) -> $crate::worker::DefaultWorker {
+                self.worker
+            }
+
+            $(
+                $(#[$fn_meta])*
+                $vis fn $method(&self $(, $arg: $arg_ty)*) -> ::std::result::Result<$ret, $crate::Error> {
+                    let args = ::std::vec![$($crate::serde_json::to_value(&$arg)?),*];
+                    self.worker.call_function(
+                        self.module,
+                        $crate::worker_client!(@js_name $method $($js_name)?).to_string(),
+                        args,
+                    )
+                }
+            )*
+        }
+    };
+
+    (@js_name $method:ident $js_name:literal) => { $js_name };
+    (@js_name $method:ident) => { stringify!($method) };
+}
+
 /// A worker implementation that uses the default runtime
 /// This is the simplest way to use the worker, as it requires no additional setup
 /// It attempts to provide as much functionality as possible from the standard runtime
 ///
 /// Please note that it uses serde_json::Value for queries and responses, which comes with a performance cost
 /// For a more performant worker, or to use extensions and/or loader caches, you'll need to implement your own worker
-pub struct DefaultWorker(Worker<DefaultWorker>);
+pub struct DefaultWorker(
+    Worker<DefaultWorker>,
+    DefaultWorkerOptions,
+    AtomicU64,
+    crate::InterruptHandle,
+    std::sync::Arc<WorkerActivity>,
+);
 impl InnerWorker for DefaultWorker {
     type Runtime = (
         crate::Runtime,
         std::collections::HashMap<deno_core::ModuleId, crate::ModuleHandle>,
+        DefaultWorkerOptions,
     );
     type RuntimeOptions = DefaultWorkerOptions;
     type Query = DefaultWorkerQuery;
     type Response = DefaultWorkerResponse;
 
     fn init_runtime(options: Self::RuntimeOptions) -> Result<Self::Runtime, Error> {
-        let runtime = crate::Runtime::new(crate::RuntimeOptions {
-            default_entrypoint: options.default_entrypoint,
-            timeout: options.timeout,
-            ..Default::default()
-        })?;
-        let modules = std::collections::HashMap::new();
-        Ok((runtime, modules))
+        let mut runtime = crate::Runtime::new(options.runtime_options())?;
+        if let Some(setup) = &options.setup {
+            setup(&mut runtime)?;
+        }
+
+        // Bootstrap modules are loaded before the worker starts serving queries
+        let mut modules = std::collections::HashMap::new();
+        for module in &options.bootstrap {
+            let handle = runtime.load_module(module)?;
+            modules.insert(handle.id(), handle);
+        }
+
+        Ok((runtime, modules, options))
     }
 
     fn handle_query(runtime: &mut Self::Runtime, query: Self::Query) -> Self::Response {
-        let (runtime, modules) = runtime;
+        let query = match Self::unpack_query(query) {
+            Ok(query) => query,
+            Err(e) => return Self::Response::Error(e),
+        };
+
+        let (runtime, modules, options) = runtime;
         match query {
-            DefaultWorkerQuery::Stop => Self::Response::Ok(()),
+            DefaultWorkerQuery::Stop | DefaultWorkerQuery::Shutdown(_) => Self::Response::Ok(()),
+
+            DefaultWorkerQuery::Ping(tx) => {
+                let _ = tx.send(());
+                Self::Response::Ok(())
+            }
+
+            DefaultWorkerQuery::NoReply(_) => Self::Response::Error(Error::Runtime(
+                "NoReply queries cannot be nested".to_string(),
+            )),
+
+            DefaultWorkerQuery::Prioritized(..) => Self::Response::Error(Error::Runtime(
+                "Prioritized queries cannot be nested".to_string(),
+            )),
+
+            DefaultWorkerQuery::Traced(..) => Self::Response::Error(Error::Runtime(
+                "Traced queries cannot be nested".to_string(),
+            )),
+
+            #[cfg(feature = "tracing")]
+            DefaultWorkerQuery::Spanned(..) => Self::Response::Error(Error::Runtime(
+                "Spanned queries cannot be nested".to_string(),
+            )),
+
+            #[cfg(feature = "worker_compression")]
+            DefaultWorkerQuery::CallEntrypointPacked(..)
+            | DefaultWorkerQuery::CallFunctionPacked(..) => {
+                unreachable!("Packed queries are unpacked above")
+            }
 
             DefaultWorkerQuery::Eval(code) => match runtime.eval(&code) {
                 Ok(v) => Self::Response::Value(v),
@@ -247,6 +733,35 @@ impl InnerWorker for DefaultWorker {
                 Err(e) => Self::Response::Error(e),
             },
 
+            DefaultWorkerQuery::ReloadModule(id, module) => {
+                if !modules.contains_key(&id) {
+                    return Self::Response::Error(Error::Runtime("Module not found".to_string()));
+                }
+
+                match runtime.reload_module(&module) {
+                    Ok(handle) => {
+                        modules.insert(id, handle);
+                        Self::Response::ModuleId(id)
+                    }
+                    Err(e) => Self::Response::Error(e),
+                }
+            }
+
+            // With structured clone enabled, results are sent back by structured clone rather
+            // than JSON, so that the host can deserialize values JSON cannot represent
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerQuery::CallEntrypoint(id, args) => match modules.get(&id) {
+                Some(handle) => {
+                    let args = args.into_iter().map(Into::into).collect();
+                    match runtime.call_entrypoint_structured(handle, args) {
+                        Ok(v) => Self::Response::Structured(v),
+                        Err(e) => Self::Response::Error(e),
+                    }
+                }
+                None => Self::Response::Error(Error::Runtime("Module not found".to_string())),
+            },
+
+            #[cfg(not(feature = "structured_clone"))]
             DefaultWorkerQuery::CallEntrypoint(id, args) => match modules.get(&id) {
                 Some(handle) => match runtime.call_entrypoint(handle, &args) {
                     Ok(v) => Self::Response::Value(v),
@@ -269,12 +784,126 @@ impl InnerWorker for DefaultWorker {
                     None
                 };
 
-                match runtime.call_function(handle, &name, &args) {
-                    Ok(v) => Self::Response::Value(v),
+                #[cfg(feature = "structured_clone")]
+                let result = {
+                    let args = args.into_iter().map(Into::into).collect();
+                    runtime
+                        .call_function_structured(handle, &name, args)
+                        .map(Self::Response::Structured)
+                };
+
+                #[cfg(not(feature = "structured_clone"))]
+                let result = runtime
+                    .call_function(handle, &name, &args)
+                    .map(Self::Response::Value);
+
+                result.unwrap_or_else(Self::Response::Error)
+            }
+
+            DefaultWorkerQuery::CallFunctionBytes(id, name, args, bytes) => {
+                let handle = if let Some(id) = id {
+                    match modules.get(&id) {
+                        Some(handle) => Some(handle),
+                        None => {
+                            return Self::Response::Error(Error::Runtime(
+                                "Module not found".to_string(),
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                let bytes: Vec<&[u8]> = bytes.iter().map(Vec::as_slice).collect();
+                match runtime
+                    .call_function_with_bytes::<deno_core::JsBuffer>(handle, &name, &args, &bytes)
+                {
+                    Ok(v) => Self::Response::Bytes(v.to_vec()),
+                    Err(e) => Self::Response::Error(e),
+                }
+            }
+
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerQuery::CallFunctionStructured(id, name, args) => {
+                let handle = if let Some(id) = id {
+                    match modules.get(&id) {
+                        Some(handle) => Some(handle),
+                        None => {
+                            return Self::Response::Error(Error::Runtime(
+                                "Module not found".to_string(),
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                match runtime.call_function_structured(handle, &name, args) {
+                    Ok(v) => Self::Response::Structured(v),
                     Err(e) => Self::Response::Error(e),
                 }
             }
 
+            DefaultWorkerQuery::RecvMessage => Self::Response::Message(runtime.next_message()),
+
+            DefaultWorkerQuery::GetStats => Self::Response::Stats(runtime.stats()),
+
+            DefaultWorkerQuery::ExportReplayLog => {
+                // Module ids are assigned in load order
+                let mut ids: Vec<_> = modules.keys().copied().collect();
+                ids.sort_unstable();
+                let modules = ids
+                    .into_iter()
+                    .map(|id| (id, modules[&id].module().clone()))
+                    .filter(|(_, module)| !options.bootstrap.contains(module))
+                    .collect();
+
+                let mut names: Vec<_> = options.globals.keys().cloned().collect();
+                names.sort_unstable();
+                let mut globals = Vec::with_capacity(names.len());
+                for name in names {
+                    let expr = format!("globalThis[{}]", crate::serde_json::Value::from(&*name));
+                    match runtime.eval(&expr) {
+                        Ok(value) => globals.push((name, value)),
+                        Err(e) => return Self::Response::Error(e),
+                    }
+                }
+
+                Self::Response::ReplayLog(WorkerReplayLog { modules, globals })
+            }
+
+            DefaultWorkerQuery::AdvanceTime(duration) => match runtime.advance_time(duration) {
+                Ok(fired) => Self::Response::Value(fired.into()),
+                Err(e) => Self::Response::Error(e),
+            },
+
+            DefaultWorkerQuery::SetTime(time) => match runtime.set_time(time) {
+                Ok(fired) => Self::Response::Value(fired.into()),
+                Err(e) => Self::Response::Error(e),
+            },
+
+            DefaultWorkerQuery::SetGlobal(name, value) => match runtime.set_global(&name, &value) {
+                Ok(()) => {
+                    // Recorded so that the global is included in exported replay logs
+                    options.globals.insert(name, value);
+                    Self::Response::Ok(())
+                }
+                Err(e) => Self::Response::Error(e),
+            },
+
+            DefaultWorkerQuery::SetRequestId(id) => match runtime.set_request_id(id) {
+                Ok(()) => Self::Response::Ok(()),
+                Err(e) => Self::Response::Error(e),
+            },
+
+            DefaultWorkerQuery::ListExports(id) => match modules.get(&id) {
+                Some(handle) => match runtime.module_exports(handle) {
+                    Ok(exports) => Self::Response::Exports(exports),
+                    Err(e) => Self::Response::Error(e),
+                },
+                None => Self::Response::Error(Error::Runtime("Module not found".to_string())),
+            },
+
             DefaultWorkerQuery::GetValue(id, name) => {
                 let handle = if let Some(id) = id {
                     match modules.get(&id) {
@@ -297,47 +926,792 @@ impl InnerWorker for DefaultWorker {
         }
     }
 
+    fn describe_query(query: &Self::Query) -> Option<String> {
+        Some(query.describe())
+    }
+
     // Custom thread impl to handle stop
     fn thread(mut runtime: Self::Runtime, rx: Receiver<Self::Query>, tx: Sender<Self::Response>) {
+        // Queries waiting to run with their IDs, one queue per priority, highest first
+        let mut queues: [VecDeque<QueuedQuery>; 3] = Default::default();
+
+        // Shared with `DefaultWorker::ping` - missing for runtimes not started by a `DefaultWorker`
+        let activity = runtime.0.take::<std::sync::Arc<WorkerActivity>>();
+
+        // Set once a shutdown arrives - the queries queued ahead of it must finish by then too
+        let mut shutdown: Option<std::time::Instant> = None;
         loop {
-            let msg = match rx.recv() {
-                Ok(msg) => msg,
-                Err(_) => break,
+            // Only block when nothing is waiting, then drain the channel
+            // so that queries sent later at a higher priority run first
+            if queues.iter().all(VecDeque::is_empty) {
+                let msg = if runtime.2.background_event_loop {
+                    Self::drive_until_query(&mut runtime, &rx)
+                } else {
+                    rx.recv().ok()
+                };
+                match msg {
+                    Some(msg) => Self::enqueue(&mut queues, &mut shutdown, msg),
+                    None => break,
+                }
+            }
+            while let Ok(msg) = rx.try_recv() {
+                Self::enqueue(&mut queues, &mut shutdown, msg);
+            }
+
+            let Some((id, msg, _parent)) = queues.iter_mut().find_map(VecDeque::pop_front) else {
+                continue;
             };
 
-            match &msg {
-                DefaultWorkerQuery::Stop => {
-                    tx.send(Self::Response::Ok(())).unwrap();
-                    break;
+            // While shutting down, queries only get the time left before the deadline
+            if let Some(deadline) = shutdown {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                match msg {
+                    DefaultWorkerQuery::Shutdown(_) => {}
+                    _ if remaining.is_zero() => {
+                        let error = Error::Timeout(
+                            "the worker shut down before this query could run".to_string(),
+                        );
+                        Self::reject(&runtime, &tx, id, msg, error);
+                        continue;
+                    }
+                    _ => runtime.0.limit_timeout(remaining),
+                }
+            }
+
+            // Handle the query within the span that was active when it was sent
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!(
+                parent: &_parent,
+                "rustyscript.worker_query",
+                query_id = ?id,
+                query = %msg.describe(),
+            )
+            .entered();
+
+            Self::trace(&runtime.2, id, TraceStage::Started);
+            let started = std::time::Instant::now();
+            if let Some(activity) = &activity {
+                activity.start(id.is_some());
+            }
+            set_current_query(match (id, Self::describe_query(&msg)) {
+                (Some(id), Some(query)) => Some(format!("query {id}: {query}")),
+                (_, query) => query,
+            });
+
+            // Queries sent without waiting for a reply only report errors, to the error handler
+            let msg = match msg {
+                DefaultWorkerQuery::NoReply(query) => {
+                    let error = match Self::handle_query(&mut runtime, *query) {
+                        Self::Response::Error(e) => Some(e),
+                        _ => None,
+                    };
+                    Self::trace(
+                        &runtime.2,
+                        id,
+                        TraceStage::Finished {
+                            elapsed: started.elapsed(),
+                            error: error.clone(),
+                        },
+                    );
+
+                    if let (Some(e), Some(handler)) = (error, &runtime.2.error_handler) {
+                        handler(e);
+                    }
+                    if let Some(activity) = &activity {
+                        activity.finish();
+                    }
+                    continue;
                 }
-                _ => {
-                    let response = Self::handle_query(&mut runtime, msg);
-                    tx.send(response).unwrap();
+                msg => msg,
+            };
+
+            let (response, stop) = match msg {
+                DefaultWorkerQuery::Stop => (Self::Response::Ok(()), true),
+                DefaultWorkerQuery::Shutdown(deadline) => {
+                    let timeout = deadline.map_or(std::time::Duration::MAX, |deadline| {
+                        deadline.saturating_duration_since(std::time::Instant::now())
+                    });
+                    match runtime.0.await_event_loop(timeout) {
+                        Ok(()) => (Self::Response::Ok(()), true),
+                        Err(e) => (Self::Response::Error(e), true),
+                    }
                 }
+                msg => (Self::handle_query(&mut runtime, msg), false),
+            };
+
+            let response = Self::transform_response(response, &runtime.2);
+            let error = match &response {
+                Self::Response::Error(e) => Some(e.clone()),
+                _ => None,
+            };
+            Self::trace(
+                &runtime.2,
+                id,
+                TraceStage::Finished {
+                    elapsed: started.elapsed(),
+                    error,
+                },
+            );
+
+            // Responses carry the ID of their query, so the host can match them up
+            Self::send_response(response, &runtime.2, |response| {
+                let response = match id {
+                    Some(id) => Self::Response::Traced(id, Box::new(response)),
+                    None => response,
+                };
+                tx.send(response).unwrap();
+            });
+
+            if let Some(activity) = &activity {
+                activity.finish();
             }
+            set_current_query(None);
+            if stop {
+                break;
+            }
+        }
+
+        // Anything still waiting is answered, rather than left for the client to time out on
+        while let Ok(msg) = rx.try_recv() {
+            Self::enqueue(&mut queues, &mut shutdown, msg);
+        }
+        for (id, msg, _) in queues.into_iter().flatten() {
+            let error =
+                Error::Runtime("The worker stopped before this query could run".to_string());
+            Self::reject(&runtime, &tx, id, msg, error);
         }
     }
 }
 impl DefaultWorker {
-    /// Create a new worker instance
-    pub fn new(options: DefaultWorkerOptions) -> Result<Self, Error> {
-        Worker::new(options).map(Self)
-    }
+    /// Answer a query that will not be run with an error
+    /// Queries sent without waiting for a reply report it to the error handler instead
+    fn reject(
+        runtime: &<Self as InnerWorker>::Runtime,
+        tx: &Sender<DefaultWorkerResponse>,
+        id: Option<QueryId>,
+        query: DefaultWorkerQuery,
+        error: Error,
+    ) {
+        Self::trace(
+            &runtime.2,
+            id,
+            TraceStage::Finished {
+                elapsed: std::time::Duration::ZERO,
+                error: Some(error.clone()),
+            },
+        );
 
-    /// Stop the worker and wait for it to finish
-    /// Consumes the worker and returns an error if the worker panicked
-    pub fn stop(self) -> Result<(), Error> {
-        self.0.send(DefaultWorkerQuery::Stop)?;
-        self.0.join()
-    }
+        if let DefaultWorkerQuery::NoReply(_) = query {
+            if let Some(handler) = &runtime.2.error_handler {
+                handler(error);
+            }
+            return;
+        }
 
-    /// Evaluate a string of javascript code
+        let response = DefaultWorkerResponse::Error(error);
+        let response = match id {
+            Some(id) => DefaultWorkerResponse::Traced(id, Box::new(response)),
+            None => response,
+        };
+
+        // The client may already be gone
+        let _ = tx.send(response);
+    }
+
+    /// Apply `DefaultWorkerOptions::response_transform`, if set
+    fn transform_response(
+        response: DefaultWorkerResponse,
+        options: &DefaultWorkerOptions,
+    ) -> DefaultWorkerResponse {
+        match &options.response_transform {
+            Some(transform) => transform(response),
+            None => response,
+        }
+    }
+
+    /// Report a step in the handling of a query to `DefaultWorkerOptions::trace_handler`, if set
+    /// Also emitted as a `tracing` event, with the `tracing` feature
+    fn trace(options: &DefaultWorkerOptions, query_id: Option<QueryId>, stage: TraceStage) {
+        let Some(query_id) = query_id else {
+            return;
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(query_id, stage = ?stage, "rustyscript worker query");
+
+        if let Some(handler) = &options.trace_handler {
+            handler(&TraceEvent { query_id, stage });
+        }
+    }
+
+    /// Run the event loop while the worker is idle, until a query arrives
+    /// Used with `DefaultWorkerOptions::background_event_loop` - returns None once the channel closes
+    fn drive_until_query(
+        runtime: &mut <Self as InnerWorker>::Runtime,
+        rx: &Receiver<DefaultWorkerQuery>,
+    ) -> Option<DefaultWorkerQuery> {
+        // How long the event loop runs before checking for queries again
+        const SLICE: std::time::Duration = std::time::Duration::from_millis(10);
+
+        loop {
+            match rx.try_recv() {
+                Ok(msg) => return Some(msg),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+
+            match runtime.0.await_event_loop(SLICE) {
+                // Nothing left to run, so there is no need to wake up until the next query
+                Ok(()) => return rx.recv().ok(),
+                Err(Error::Timeout(_)) => {}
+                Err(e) => {
+                    if let Some(handler) = &runtime.2.error_handler {
+                        handler(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add a query to the queue for its priority, along with its ID
+    /// A shutdown sets `shutdown` to its deadline
+    fn enqueue(
+        queues: &mut [VecDeque<QueuedQuery>; 3],
+        shutdown: &mut Option<std::time::Instant>,
+        query: DefaultWorkerQuery,
+    ) {
+        // Pings skip the queues, so they are not held up by queries waiting to run
+        if let DefaultWorkerQuery::Ping(tx) = query {
+            let _ = tx.send(());
+            return;
+        }
+
+        #[cfg(feature = "tracing")]
+        let (parent, query) = match query {
+            DefaultWorkerQuery::Spanned(span, query) => (span, *query),
+            query => (tracing::Span::none(), query),
+        };
+
+        #[cfg(not(feature = "tracing"))]
+        let parent = ();
+
+        let (id, query) = match query {
+            DefaultWorkerQuery::Traced(id, query) => (Some(id), *query),
+            query => (None, query),
+        };
+
+        if let DefaultWorkerQuery::Shutdown(deadline) = &query {
+            *shutdown = shutdown.or(*deadline);
+        }
+
+        match query {
+            DefaultWorkerQuery::Prioritized(priority, query) => {
+                queues[priority as usize].push_back((id, *query, parent))
+            }
+            query => queues[Priority::Normal as usize].push_back((id, query, parent)),
+        }
+    }
+
+    /// Creates a builder for a new worker, with chainable configuration that is checked
+    /// before the worker thread is started - an alternative to `DefaultWorkerOptions`
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{worker::DefaultWorker, Error};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let worker = DefaultWorker::builder()
+    ///     .timeout(Duration::from_secs(5))
+    ///     .queue_capacity(16)
+    ///     .build()?;
+    ///
+    /// let value: i64 = worker.eval("1 + 1".to_string())?;
+    /// assert_eq!(2, value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> DefaultWorkerBuilder {
+        DefaultWorkerBuilder::new()
+    }
+
+    /// Create a new worker instance
+    /// The options are not checked ahead of time - see `DefaultWorkerOptions::validate`
+    pub fn new(options: DefaultWorkerOptions) -> Result<Self, Error> {
+        // The runtime's interrupt handle is sent back from the worker thread during setup
+        let (handle_tx, handle_rx) = channel();
+        let activity = std::sync::Arc::new(WorkerActivity::new());
+        let thread_activity = activity.clone();
+        let setup = options.setup.clone();
+        let runtime_options = DefaultWorkerOptions {
+            setup: Some(std::sync::Arc::new(move |runtime: &mut crate::Runtime| {
+                let _ = handle_tx.send(runtime.interrupt_handle());
+                runtime.put(thread_activity.clone())?;
+                match &setup {
+                    Some(setup) => setup(runtime),
+                    None => Ok(()),
+                }
+            })),
+            ..options.clone()
+        };
+
+        let worker = match options.queue_capacity {
+            Some(capacity) => Worker::new_bounded(runtime_options, capacity),
+            None => Worker::new(runtime_options),
+        }?;
+        let interrupt = handle_rx
+            .try_recv()
+            .map_err(|_| Error::Runtime("Worker did not start its runtime".to_string()))?;
+        Ok(Self(
+            worker,
+            options,
+            AtomicU64::new(1),
+            interrupt,
+            activity,
+        ))
+    }
+
+    /// Check that the worker is alive, without waiting behind the queries already queued
+    ///
+    /// The worker answers as soon as it finishes the query it is handling, so a worker that
+    /// does not answer within `timeout` is stuck on that query - see `WorkerHealth::busy_for`
+    /// Returns an error only if the worker thread has stopped
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyscript::{worker::DefaultWorker, Error};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let worker = DefaultWorker::new(Default::default())?;
+    /// let health = worker.ping(Duration::from_secs(1))?;
+    /// assert!(health.responsive);
+    /// assert_eq!(0, health.queue_depth);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ping(&self, timeout: std::time::Duration) -> Result<WorkerHealth, Error> {
+        if !self.is_running() {
+            return Err(Error::Runtime("Worker has stopped".to_string()));
+        }
+
+        let (tx, rx) = channel();
+        let responsive = match self.0.send(DefaultWorkerQuery::Ping(tx)) {
+            Ok(()) => rx.recv_timeout(timeout).is_ok(),
+            Err(Error::WorkerBusy(_)) => false,
+            Err(e) => return Err(e),
+        };
+
+        Ok(self.4.health(responsive))
+    }
+
+    /// Whether the worker thread has stopped, or is unwinding a panic
+    ///
+    /// `is_running` alone is not enough after a failed call - the thread drops its channels
+    /// while unwinding, before it finishes. A worker that is still alive answers a ping, while
+    /// one that is stopping drops it unanswered
+    fn has_stopped(&self) -> bool {
+        if !self.is_running() {
+            return true;
+        }
+
+        let (tx, rx) = channel();
+        match self.0.send(DefaultWorkerQuery::Ping(tx)) {
+            Ok(()) => matches!(
+                rx.recv_timeout(self.1.timeout.max(std::time::Duration::from_secs(1))),
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected)
+            ),
+            Err(Error::WorkerBusy(_)) => false,
+            Err(_) => true,
+        }
+    }
+
+    /// Stop the javascript the worker is running, from any thread
+    /// The interrupted query returns `Error::Cancelled`, and the worker carries on with the next one
+    ///
+    /// Unlike a timeout, this also stops synchronous code, such as an infinite loop
+    /// Returns false if the worker's runtime has been dropped
+    pub fn interrupt(&self) -> bool {
+        self.3.terminate()
+    }
+
+    /// A handle that stops the javascript the worker is running
+    /// Unlike the worker itself, it can be moved to other threads, such as a watchdog
+    pub fn interrupt_handle(&self) -> crate::InterruptHandle {
+        self.3.clone()
+    }
+
+    /// Stamp a query with the next query ID, and send it to the worker
+    fn send_traced(&self, query: DefaultWorkerQuery) -> Result<QueryId, Error> {
+        let id = self.2.fetch_add(1, Ordering::Relaxed);
+        Self::trace(&self.1, Some(id), TraceStage::Sent);
+        let query = DefaultWorkerQuery::Traced(id, Box::new(query));
+
+        #[cfg(feature = "tracing")]
+        let query = DefaultWorkerQuery::Spanned(tracing::Span::current(), Box::new(query));
+
+        // Counted before sending, so the worker cannot start the query before it is counted
+        // The worker moves queries out of the channel into its priority queues as soon as it can,
+        // so the capacity is checked against this count rather than against the channel
+        match self.1.queue_capacity {
+            Some(capacity) => {
+                self.4
+                    .queued
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                        (n < capacity).then_some(n + 1)
+                    })
+                    .map_err(|_| Error::WorkerBusy(capacity))?;
+            }
+            None => {
+                self.4.queued.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        self.0.send(query).map_err(|e| {
+            self.4.dequeue();
+            match e {
+                Error::Runtime(e) => Error::Runtime(format!("Could not send query {id}: {e}")),
+                e => e,
+            }
+        })?;
+        Ok(id)
+    }
+
+    /// Receive the next response, which must belong to the query `id`
+    /// Returns `Error::Timeout` if it has not arrived by `deadline`
+    fn receive_traced(
+        &self,
+        id: QueryId,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<DefaultWorkerResponse, Error> {
+        // Responses are matched to their query by ID here, rather than by counting late responses,
+        // since a single query can be answered by several chunks
+        loop {
+            return match self.0.receive_until(deadline) {
+                Ok(DefaultWorkerResponse::Traced(response_id, response)) if response_id == id => {
+                    Ok(*response)
+                }
+
+                // Late responses to earlier queries that timed out are dropped
+                Ok(DefaultWorkerResponse::Traced(response_id, _)) if response_id < id => continue,
+
+                Ok(DefaultWorkerResponse::Traced(response_id, _)) => Err(Error::Runtime(format!(
+                    "Mismatched response from the worker: expected query {id}, got query {response_id}"
+                ))),
+                Ok(response) => Ok(response),
+                Err(RecvTimeoutError::Timeout) => Err(Error::Timeout(format!(
+                    "query {id}: no response from the worker in time"
+                ))),
+                Err(e) => Err(Error::Runtime(format!("No response to query {id}: {e}"))),
+            };
+        }
+    }
+
+    /// Returns true if values are sent to or from the worker thread as encoded payloads
+    /// That is, if compression or chunking is enabled
+    fn packs_payloads(options: &DefaultWorkerOptions) -> bool {
+        #[cfg(feature = "worker_compression")]
+        let compression = options.compression_threshold.is_some();
+        #[cfg(not(feature = "worker_compression"))]
+        let compression = false;
+
+        compression || options.chunk_size.is_some()
+    }
+
+    /// Serialize a value for transfer to or from the worker thread
+    /// The first byte marks whether the JSON that follows was compressed
+    ///
+    /// Returns None, without serializing the value, if neither compression nor chunking is enabled
+    /// Otherwise the value is serialized once, and the bytes should be sent in its place
+    #[cfg(feature = "worker_compression")]
+    fn encode_value<T>(value: &T, options: &DefaultWorkerOptions) -> Option<Vec<u8>>
+    where
+        T: serde::Serialize,
+    {
+        if !Self::packs_payloads(options) {
+            return None;
+        }
+
+        let mut writer = PayloadWriter::new(Vec::new(), options, PAYLOAD_JSON).ok()?;
+        crate::serde_json::to_writer(&mut writer, value).ok()?;
+        writer.finish().ok()
+    }
+
+    /// Deserialize a value encoded with `encode_value`
+    #[cfg(feature = "worker_compression")]
+    fn decode_value<T>(bytes: &[u8]) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match bytes.split_first() {
+            Some((&marker, payload)) => Self::decode_payload(marker, payload),
+            None => Err(Error::Runtime(
+                "Invalid payload received from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Deserialize the rest of a payload from `reader`, once its first byte, `marker`, has been read
+    /// Compressed payloads are decompressed as they are read
+    fn decode_payload<T>(marker: u8, reader: impl std::io::Read) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match marker {
+            PAYLOAD_JSON => Ok(crate::serde_json::from_reader(reader)?),
+
+            #[cfg(feature = "worker_compression")]
+            PAYLOAD_LZ4 => Ok(crate::serde_json::from_reader(
+                lz4_flex::frame::FrameDecoder::new(reader),
+            )?),
+
+            _ => Err(Error::Runtime(
+                "Invalid payload received from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Pack the arguments of a function call if compression is enabled
+    /// They are serialized once, and compressed only if they are large enough
+    fn pack_query(&self, query: DefaultWorkerQuery) -> DefaultWorkerQuery {
+        #[cfg(feature = "worker_compression")]
+        match query {
+            _ if self.1.compression_threshold.is_none() => query,
+
+            DefaultWorkerQuery::CallEntrypoint(id, args) => {
+                match Self::encode_value(&args, &self.1) {
+                    Some(bytes) => DefaultWorkerQuery::CallEntrypointPacked(id, bytes),
+                    None => DefaultWorkerQuery::CallEntrypoint(id, args),
+                }
+            }
+
+            DefaultWorkerQuery::CallFunction(id, name, args) => {
+                match Self::encode_value(&args, &self.1) {
+                    Some(bytes) => DefaultWorkerQuery::CallFunctionPacked(id, name, bytes),
+                    None => DefaultWorkerQuery::CallFunction(id, name, args),
+                }
+            }
+
+            query => query,
+        }
+
+        #[cfg(not(feature = "worker_compression"))]
+        query
+    }
+
+    /// Unpack the arguments of a function call packed with `pack_query`
+    fn unpack_query(query: DefaultWorkerQuery) -> Result<DefaultWorkerQuery, Error> {
+        match query {
+            #[cfg(feature = "worker_compression")]
+            DefaultWorkerQuery::CallEntrypointPacked(id, args) => Ok(
+                DefaultWorkerQuery::CallEntrypoint(id, Self::decode_value(&args)?),
+            ),
+
+            #[cfg(feature = "worker_compression")]
+            DefaultWorkerQuery::CallFunctionPacked(id, name, args) => Ok(
+                DefaultWorkerQuery::CallFunction(id, name, Self::decode_value(&args)?),
+            ),
+
+            query => Ok(query),
+        }
+    }
+
+    /// Send a response back from the worker thread
+    /// Values are serialized straight into chunks of at most `chunk_size` bytes, compressing them as needed,
+    /// and each chunk is sent as soon as it fills up - the value is never held as a single buffer of JSON
+    ///
+    /// Other responses, and values when neither compression nor chunking is enabled, are sent unchanged
+    /// Structured values are encoded the same way, with `PAYLOAD_STRUCTURED` set in the first byte
+    fn send_response(
+        response: DefaultWorkerResponse,
+        options: &DefaultWorkerOptions,
+        mut send: impl FnMut(DefaultWorkerResponse),
+    ) {
+        if !Self::packs_payloads(options) {
+            return send(response);
+        }
+
+        let result = match &response {
+            DefaultWorkerResponse::Value(value) => {
+                Self::stream_value(value, PAYLOAD_JSON, options, &mut send)
+            }
+
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerResponse::Structured(value) => {
+                Self::stream_value(value, PAYLOAD_STRUCTURED, options, &mut send)
+            }
+
+            _ => return send(response),
+        };
+
+        // Some chunks may already be out, so the error takes the place of the rest of them
+        if let Err(e) = result {
+            send(DefaultWorkerResponse::Error(Error::Runtime(format!(
+                "Could not encode the value: {e}"
+            ))));
+        }
+    }
+
+    /// Serialize a value as a stream of `ValueChunk` responses
+    fn stream_value<T>(
+        value: &T,
+        flags: u8,
+        options: &DefaultWorkerOptions,
+        send: &mut impl FnMut(DefaultWorkerResponse),
+    ) -> std::io::Result<()>
+    where
+        T: serde::Serialize,
+    {
+        let chunks = ChunkWriter {
+            buffer: Vec::new(),
+            chunk_size: options.chunk_size.unwrap_or(usize::MAX).max(1),
+            send: |chunk, last| send(DefaultWorkerResponse::ValueChunk(chunk, last)),
+        };
+
+        let mut writer = PayloadWriter::new(chunks, options, flags)?;
+        crate::serde_json::to_writer(&mut writer, value)?;
+        writer.finish()?.finish();
+        Ok(())
+    }
+
+    /// Decode a value streamed by `send_response`, starting with its first chunk
+    /// The remaining chunks are received and decoded as they arrive, rather than reassembled first
+    fn receive_value(
+        &self,
+        id: QueryId,
+        deadline: Option<std::time::Instant>,
+        chunk: Vec<u8>,
+        last: bool,
+    ) -> Result<DefaultWorkerResponse, Error> {
+        let mut reader = ChunkReader {
+            worker: self,
+            id,
+            deadline,
+            chunk,
+            position: 0,
+            last,
+            error: None,
+        };
+
+        let mut marker = [0];
+        let result = std::io::Read::read_exact(&mut reader, &mut marker)
+            .map_err(|e| Error::Runtime(e.to_string()))
+            .and_then(|()| {
+                #[cfg(feature = "structured_clone")]
+                if marker[0] & PAYLOAD_STRUCTURED != 0 {
+                    return Self::decode_payload(marker[0] & !PAYLOAD_STRUCTURED, &mut reader)
+                        .map(DefaultWorkerResponse::Structured);
+                }
+
+                Self::decode_payload(marker[0], &mut reader).map(DefaultWorkerResponse::Value)
+            });
+
+        // An error from the worker, or a timeout, explains a failure better than the decoder can
+        if let Some(e) = reader.error.take() {
+            return Err(e);
+        }
+        let response = result?;
+
+        // Make sure no chunks are left behind for the next query
+        while !reader.last {
+            reader.next_chunk()?;
+        }
+        Ok(response)
+    }
+
+    /// Send a query to the worker and wait for the response
+    /// Chunked values are reassembled into a single value or structured response
+    ///
+    /// Returns `Error::Timeout` if the response does not arrive within `DefaultWorkerOptions::timeout`
+    /// of sending the query - time spent waiting behind other queries counts towards it
+    fn send_and_await(&self, query: DefaultWorkerQuery) -> Result<DefaultWorkerResponse, Error> {
+        // A zero timeout is only accepted by `DefaultWorker::new`, and means no limit here
+        let timeout = Some(self.1.timeout).filter(|timeout| !timeout.is_zero());
+        self.send_and_await_within(query, timeout)
+    }
+
+    /// Send a query to the worker and wait for the response, for at most `timeout` if set
+    fn send_and_await_within(
+        &self,
+        query: DefaultWorkerQuery,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<DefaultWorkerResponse, Error> {
+        let sent = std::time::Instant::now();
+        let deadline = timeout.and_then(|timeout| sent.checked_add(timeout));
+        let id = self.send_traced(self.pack_query(query))?;
+
+        let response = match self.receive_traced(id, deadline)? {
+            DefaultWorkerResponse::ValueChunk(chunk, last) => {
+                self.receive_value(id, deadline, chunk, last)?
+            }
+            response => response,
+        };
+
+        let elapsed = sent.elapsed();
+        Self::trace(&self.1, Some(id), TraceStage::Received { elapsed });
+        Ok(response)
+    }
+
+    /// Returns false if the worker thread has stopped, or panicked
+    pub fn is_running(&self) -> bool {
+        self.0.is_running()
+    }
+
+    /// Stop the worker and wait for it to finish
+    /// Consumes the worker and returns an error if the worker panicked
+    ///
+    /// Pending async work in the runtime is dropped - use `shutdown` to let it finish
+    pub fn stop(self) -> Result<(), Error> {
+        // A crashed worker reports the crash, rather than the failure to send
+        let sent = self.0.send(DefaultWorkerQuery::Stop);
+        self.0.join()?;
+        sent
+    }
+
+    /// Gracefully stop the worker, and wait for it to finish
+    /// Queries already sent are completed first, then the runtime's event loop is run
+    /// until pending promises and async ops are done, or until `timeout` expires
+    ///
+    /// The timeout starts now, and covers the queued queries as well - each one is limited to
+    /// the time left, and those not started in time are answered with `Error::Timeout`.
+    /// A query that is already running when this is called keeps its usual timeout
+    ///
+    /// Consumes the worker, and returns an error if pending work did not finish in time,
+    /// or if the worker panicked. The thread is joined in either case
+    pub fn shutdown(self, timeout: std::time::Duration) -> Result<(), Error> {
+        // Waits for the queued queries and the event loop, so the usual timeout does not apply
+        let deadline = std::time::Instant::now().checked_add(timeout);
+        let query = DefaultWorkerQuery::Shutdown(deadline);
+        let result = match self.send_and_await_within(query, None) {
+            Ok(DefaultWorkerResponse::Ok(())) => Ok(()),
+            Ok(DefaultWorkerResponse::Error(e)) => Err(e),
+            Ok(_) => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+            Err(e) => Err(e),
+        };
+
+        self.0.join()?;
+        result
+    }
+
+    /// Evaluate a string of javascript code
     /// Returns the result of the evaluation
     pub fn eval<T>(&self, code: String) -> Result<T, Error>
     where
         T: serde::de::DeserializeOwned,
     {
-        match self.0.send_and_await(DefaultWorkerQuery::Eval(code))? {
+        self.eval_with_priority(code, Priority::Normal)
+    }
+
+    /// Evaluate a string of javascript code, ahead of or behind other queued queries
+    /// Returns the result of the evaluation
+    pub fn eval_with_priority<T>(&self, code: String, priority: Priority) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self.send_and_await(self.prioritize(DefaultWorkerQuery::Eval(code), priority))? {
             DefaultWorkerResponse::Value(v) => Ok(crate::serde_json::from_value(v)?),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
@@ -346,13 +1720,63 @@ impl DefaultWorker {
         }
     }
 
+    /// Evaluate a string of non-ecma javascript code, without waiting for it to finish
+    /// The result is discarded, and errors are passed to `DefaultWorkerOptions::error_handler`
+    /// Returns an error only if the query could not be sent
+    pub fn eval_noreply(&self, code: String) -> Result<(), Error> {
+        self.send_noreply(DefaultWorkerQuery::Eval(code), Priority::Normal)
+    }
+
+    /// Call a function in a module, without waiting for it to return
+    /// The result is discarded, and errors are passed to `DefaultWorkerOptions::error_handler`
+    /// Returns an error only if the query could not be sent
+    pub fn call_function_noreply(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<(), Error> {
+        self.call_function_noreply_with_priority(module_context, name, args, Priority::Normal)
+    }
+
+    /// Call a function in a module, without waiting for it to return,
+    /// ahead of or behind other queued queries
+    /// The result is discarded, and errors are passed to `DefaultWorkerOptions::error_handler`
+    /// Returns an error only if the query could not be sent
+    pub fn call_function_noreply_with_priority(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::serde_json::Value>,
+        priority: Priority,
+    ) -> Result<(), Error> {
+        self.send_noreply(
+            DefaultWorkerQuery::CallFunction(module_context, name, args),
+            priority,
+        )
+    }
+
+    /// Send a query to the worker, without waiting for a response
+    fn send_noreply(&self, query: DefaultWorkerQuery, priority: Priority) -> Result<(), Error> {
+        let query = self.pack_query(query);
+        let query = DefaultWorkerQuery::NoReply(Box::new(query));
+        self.send_traced(self.prioritize(query, priority))
+            .map(|_| ())
+    }
+
+    /// Pack a query, and mark it with a priority if it is not `Normal`
+    fn prioritize(&self, query: DefaultWorkerQuery, priority: Priority) -> DefaultWorkerQuery {
+        let query = self.pack_query(query);
+        match priority {
+            Priority::Normal => query,
+            priority => DefaultWorkerQuery::Prioritized(priority, Box::new(query)),
+        }
+    }
+
     /// Load a module into the worker as the main module
     /// Returns the module id of the loaded module
     pub fn load_main_module(&self, module: crate::Module) -> Result<deno_core::ModuleId, Error> {
-        match self
-            .0
-            .send_and_await(DefaultWorkerQuery::LoadMainModule(module))?
-        {
+        match self.send_and_await(DefaultWorkerQuery::LoadMainModule(module))? {
             DefaultWorkerResponse::ModuleId(id) => Ok(id),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
@@ -364,10 +1788,24 @@ impl DefaultWorker {
     /// Load a module into the worker as a side module
     /// Returns the module id of the loaded module
     pub fn load_module(&self, module: crate::Module) -> Result<deno_core::ModuleId, Error> {
-        match self
-            .0
-            .send_and_await(DefaultWorkerQuery::LoadModule(module))?
-        {
+        match self.send_and_await(DefaultWorkerQuery::LoadModule(module))? {
+            DefaultWorkerResponse::ModuleId(id) => Ok(id),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Re-evaluate an updated version of a loaded module
+    /// The module id will refer to the updated module's exports and entrypoint afterwards
+    /// Registered functions and other runtime state are preserved
+    pub fn reload_module(
+        &self,
+        id: deno_core::ModuleId,
+        module: crate::Module,
+    ) -> Result<deno_core::ModuleId, Error> {
+        match self.send_and_await(DefaultWorkerQuery::ReloadModule(id, module))? {
             DefaultWorkerResponse::ModuleId(id) => Ok(id),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
@@ -387,13 +1825,12 @@ impl DefaultWorker {
     where
         T: serde::de::DeserializeOwned,
     {
-        match self
-            .0
-            .send_and_await(DefaultWorkerQuery::CallEntrypoint(id, args))?
-        {
+        match self.send_and_await(DefaultWorkerQuery::CallEntrypoint(id, args))? {
             DefaultWorkerResponse::Value(v) => {
                 crate::serde_json::from_value(v).map_err(Error::from)
             }
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerResponse::Structured(v) => v.deserialize(),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
                 "Unexpected response from the worker".to_string(),
@@ -413,13 +1850,29 @@ impl DefaultWorker {
     where
         T: serde::de::DeserializeOwned,
     {
-        match self
-            .0
-            .send_and_await(DefaultWorkerQuery::CallFunction(module_context, name, args))?
-        {
+        self.call_function_with_priority(module_context, name, args, Priority::Normal)
+    }
+
+    /// Call a function in a module, ahead of or behind other queued queries
+    /// Returns the result of the function call
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    pub fn call_function_with_priority<T>(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::serde_json::Value>,
+        priority: Priority,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let query = DefaultWorkerQuery::CallFunction(module_context, name, args);
+        match self.send_and_await(self.prioritize(query, priority))? {
             DefaultWorkerResponse::Value(v) => {
                 crate::serde_json::from_value(v).map_err(Error::from)
             }
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerResponse::Structured(v) => v.deserialize(),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
                 "Unexpected response from the worker".to_string(),
@@ -427,39 +1880,1775 @@ impl DefaultWorker {
         }
     }
 
-    /// Get a value from a module
+    /// Call a function in a module that returns binary data, such as a `Uint8Array`
+    /// Each of `bytes` is passed after the other arguments as a `Uint8Array`
+    /// Binary data is sent to and from the worker as-is, instead of through JSON
     /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
-    pub fn get_value<T>(
+    pub fn call_function_bytes(
         &self,
         module_context: Option<deno_core::ModuleId>,
         name: String,
-    ) -> Result<T, Error>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        match self
-            .0
-            .send_and_await(DefaultWorkerQuery::GetValue(module_context, name))?
-        {
-            DefaultWorkerResponse::Value(v) => {
-                crate::serde_json::from_value(v).map_err(Error::from)
-            }
+        args: Vec<crate::serde_json::Value>,
+        bytes: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, Error> {
+        match self.send_and_await(DefaultWorkerQuery::CallFunctionBytes(
+            module_context,
+            name,
+            args,
+            bytes,
+        ))? {
+            DefaultWorkerResponse::Bytes(bytes) => Ok(bytes),
             DefaultWorkerResponse::Error(e) => Err(e),
             _ => Err(Error::Runtime(
                 "Unexpected response from the worker".to_string(),
             )),
         }
     }
-}
 
-/// Options for the default worker
-#[derive(Default, Clone)]
-pub struct DefaultWorkerOptions {
-    /// The default entrypoint function to use if none is registered
-    pub default_entrypoint: Option<String>,
+    /// Call a function in a module, passing arguments and returning its result with the
+    /// structured clone algorithm instead of JSON - see [crate::StructuredValue]
+    /// Binary data in the arguments is moved into the runtime without being copied
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    #[cfg(feature = "structured_clone")]
+    pub fn call_function_structured(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::StructuredValue>,
+    ) -> Result<crate::StructuredValue, Error> {
+        match self.send_and_await(DefaultWorkerQuery::CallFunctionStructured(
+            module_context,
+            name,
+            args,
+        ))? {
+            DefaultWorkerResponse::Structured(value) => Ok(value),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Get a value from a module
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    pub fn get_value<T>(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self.send_and_await(DefaultWorkerQuery::GetValue(module_context, name))? {
+            DefaultWorkerResponse::Value(v) => {
+                crate::serde_json::from_value(v).map_err(Error::from)
+            }
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Get resource usage statistics for the worker's runtime
+    pub fn stats(&self) -> Result<crate::RuntimeStats, Error> {
+        match self.send_and_await(DefaultWorkerQuery::GetStats)? {
+            DefaultWorkerResponse::Stats(stats) => Ok(stats),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Export a log of the worker's loaded modules and globals, so that they can be replayed
+    /// into a replacement worker with `replay` - see [WorkerReplayLog] for what is not kept
+    ///
+    /// # Example
+    /// ```rust
+    /// use rustyscript::{ Error, Module };
+    /// use rustyscript::worker::DefaultWorker;
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> Result<(), Error> {
+    /// let worker = DefaultWorker::builder().timeout(Duration::from_secs(1)).build()?;
+    /// let id = worker.load_module(Module::new("m.js", "export const f = () => globalThis.greeting;"))?;
+    /// worker.set_global("greeting", "hello".into())?;
+    /// let log = worker.export_replay_log()?;
+    ///
+    /// // Later, after the worker crashed or was recycled
+    /// let replacement = DefaultWorker::builder().timeout(Duration::from_secs(1)).build()?;
+    /// let ids = replacement.replay(&log)?;
+    /// let greeting: String = replacement.call_function(Some(ids[&id]), "f".to_string(), vec![])?;
+    /// assert_eq!("hello", greeting);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_replay_log(&self) -> Result<WorkerReplayLog, Error> {
+        match self.send_and_await(DefaultWorkerQuery::ExportReplayLog)? {
+            DefaultWorkerResponse::ReplayLog(log) => Ok(log),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Replay an exported log into the worker
+    /// The log's globals are set first, then its modules are loaded again in their original order,
+    /// running their top-level code again
+    ///
+    /// Returns a map from the module ids in the log to the ids of the modules loaded here
+    pub fn replay(
+        &self,
+        log: &WorkerReplayLog,
+    ) -> Result<std::collections::HashMap<deno_core::ModuleId, deno_core::ModuleId>, Error> {
+        for (name, value) in &log.globals {
+            self.set_global(name, value.clone())?;
+        }
+
+        let mut ids = std::collections::HashMap::with_capacity(log.modules.len());
+        for (id, module) in &log.modules {
+            ids.insert(*id, self.load_module(module.clone())?);
+        }
+        Ok(ids)
+    }
+
+    /// Move the virtual clock of the worker's runtime forward, firing timers that become due
+    /// The worker must have been created with `DefaultWorkerOptions::deterministic` set
+    ///
+    /// Returns the number of timer callbacks run
+    pub fn advance_time(&self, duration: std::time::Duration) -> Result<usize, Error> {
+        self.timer_query(DefaultWorkerQuery::AdvanceTime(duration))
+    }
+
+    /// Set the virtual clock of the worker's runtime, firing timers due up to that time
+    /// The worker must have been created with `DefaultWorkerOptions::deterministic` set
+    ///
+    /// Returns the number of timer callbacks run
+    pub fn set_time(&self, time: std::time::SystemTime) -> Result<usize, Error> {
+        self.timer_query(DefaultWorkerQuery::SetTime(time))
+    }
+
+    /// Set a property of `globalThis` in the worker's runtime
+    /// Use it to update configuration set with `DefaultWorkerOptions::globals`
+    pub fn set_global(&self, name: &str, value: crate::serde_json::Value) -> Result<(), Error> {
+        match self.send_and_await(DefaultWorkerQuery::SetGlobal(name.to_string(), value))? {
+            DefaultWorkerResponse::Ok(()) => Ok(()),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Set the request ID passed to functions registered with a call context,
+    /// for the calls made by the queries that follow
+    pub fn set_request_id(&self, request_id: Option<String>) -> Result<(), Error> {
+        match self.send_and_await(DefaultWorkerQuery::SetRequestId(request_id))? {
+            DefaultWorkerResponse::Ok(()) => Ok(()),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Send a query that fires timers, returning the number fired
+    fn timer_query(&self, query: DefaultWorkerQuery) -> Result<usize, Error> {
+        match self.send_and_await(query)? {
+            DefaultWorkerResponse::Value(v) => Ok(crate::serde_json::from_value(v)?),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// List the exports of a module loaded by the worker, sorted by name
+    pub fn list_exports(&self, id: deno_core::ModuleId) -> Result<Vec<crate::ExportInfo>, Error> {
+        match self.send_and_await(DefaultWorkerQuery::ListExports(id))? {
+            DefaultWorkerResponse::Exports(exports) => Ok(exports),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    /// Receive the oldest pending message sent from JS using `rustyscript.postMessage`
+    /// Returns None if there are no pending messages
+    pub fn recv_message(&self) -> Result<Option<crate::serde_json::Value>, Error> {
+        match self.send_and_await(DefaultWorkerQuery::RecvMessage)? {
+            DefaultWorkerResponse::Message(message) => Ok(message),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+}
+
+/// Sent to the host when a [SupervisedWorker] restarts its worker thread
+#[derive(Debug, Clone)]
+pub struct WorkerRestarted {
+    /// The number of times the worker has been restarted, including this one
+    pub restarts: usize,
+
+    /// The crash report of the stopped worker, as `Error::WorkerCrashed`, if its thread panicked
+    /// Otherwise, the error returned by the call that found the worker had stopped
+    pub error: Error,
+
+    /// Modules that could not be loaded again in the new worker, by the id handed out for them
+    /// Calls using these ids fail until the module is loaded again
+    pub replay_errors: Vec<(deno_core::ModuleId, Error)>,
+}
+
+type RestartCallback = Box<dyn Fn(&WorkerRestarted)>;
+
+/// A [DefaultWorker] that is restarted automatically if its thread panics or stops unexpectedly
+///
+/// The call that found the worker stopped is not retried - it returns `Error::WorkerRestarted`,
+/// and the worker is restarted with the original options. If module replay is enabled, every module loaded through this worker
+/// is loaded again, and module ids returned before the restart remain valid
+///
+/// ```rust
+/// use rustyscript::{Error, worker::{SupervisedWorker, DefaultWorkerOptions}};
+///
+/// fn main() -> Result<(), Error> {
+///     let mut worker = SupervisedWorker::new(DefaultWorkerOptions::default(), true)?;
+///     worker.on_restart(|event| eprintln!("Worker restarted: {}", event.error));
+///
+///     let result: i32 = worker.eval("5 + 5".to_string())?;
+///     assert_eq!(result, 10);
+///     Ok(())
+/// }
+/// ```
+pub struct SupervisedWorker {
+    worker: std::cell::RefCell<DefaultWorker>,
+    options: DefaultWorkerOptions,
+    replay_modules: bool,
+    on_restart: Option<RestartCallback>,
+    restarts: std::cell::Cell<usize>,
+
+    // Module ids handed out by this worker, mapped to ids in the current worker thread
+    modules: std::cell::RefCell<Vec<SupervisedModule>>,
+}
+
+struct SupervisedModule {
+    module: crate::Module,
+    is_main: bool,
+    id: Option<deno_core::ModuleId>,
+}
+
+impl SupervisedWorker {
+    /// Create a new supervised worker
+    /// If `replay_modules` is true, loaded modules are loaded again after a restart
+    pub fn new(options: DefaultWorkerOptions, replay_modules: bool) -> Result<Self, Error> {
+        Ok(Self {
+            worker: std::cell::RefCell::new(DefaultWorker::new(options.clone())?),
+            options,
+            replay_modules,
+            on_restart: None,
+            restarts: std::cell::Cell::new(0),
+            modules: std::cell::RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Set a callback to be called each time the worker is restarted
+    pub fn on_restart<F>(&mut self, callback: F)
+    where
+        F: Fn(&WorkerRestarted) + 'static,
+    {
+        self.on_restart = Some(Box::new(callback));
+    }
+
+    /// The number of times the worker has been restarted
+    pub fn restarts(&self) -> usize {
+        self.restarts.get()
+    }
+
+    /// Stop the worker and wait for it to finish
+    /// Consumes the worker and returns an error if the worker panicked
+    pub fn stop(self) -> Result<(), Error> {
+        self.worker.into_inner().stop()
+    }
+
+    /// Evaluate a string of javascript code
+    /// Returns the result of the evaluation
+    pub fn eval<T>(&self, code: String) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.supervise(|worker| worker.eval(code.clone()))
+    }
+
+    /// Load a module into the worker as the main module
+    /// Returns the module id of the loaded module
+    pub fn load_main_module(&self, module: crate::Module) -> Result<deno_core::ModuleId, Error> {
+        let id = self.supervise(|worker| worker.load_main_module(module.clone()))?;
+        Ok(self.add_module(module, true, id))
+    }
+
+    /// Load a module into the worker as a side module
+    /// Returns the module id of the loaded module
+    pub fn load_module(&self, module: crate::Module) -> Result<deno_core::ModuleId, Error> {
+        let id = self.supervise(|worker| worker.load_module(module.clone()))?;
+        Ok(self.add_module(module, false, id))
+    }
+
+    /// Call the entrypoint function in a module
+    /// Returns the result of the function call
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    pub fn call_entrypoint<T>(
+        &self,
+        id: deno_core::ModuleId,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.supervise(|worker| worker.call_entrypoint(self.module_id(id)?, args.clone()))
+    }
+
+    /// Call a function in a module
+    /// Returns the result of the function call
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    pub fn call_function<T>(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.supervise(|worker| {
+            let module_context = module_context.map(|id| self.module_id(id)).transpose()?;
+            worker.call_function(module_context, name.clone(), args.clone())
+        })
+    }
+
+    /// Get a value from a module
+    /// The module id must be the id of a module loaded with `load_main_module` or `load_module`
+    pub fn get_value<T>(
+        &self,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.supervise(|worker| {
+            let module_context = module_context.map(|id| self.module_id(id)).transpose()?;
+            worker.get_value(module_context, name.clone())
+        })
+    }
+
+    /// Run a call against the current worker, restarting it if the call found it stopped
+    fn supervise<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: Fn(&DefaultWorker) -> Result<T, Error>,
+    {
+        let result = f(&self.worker.borrow());
+        match result {
+            Err(error) if self.worker.borrow().has_stopped() => {
+                let (crash, replay_errors) = self.restart()?;
+                let error = crash.unwrap_or(error);
+
+                let restarts = self.restarts.get() + 1;
+                self.restarts.set(restarts);
+                if let Some(callback) = &self.on_restart {
+                    callback(&WorkerRestarted {
+                        restarts,
+                        error: error.clone(),
+                        replay_errors,
+                    });
+                }
+
+                Err(Error::WorkerRestarted(Box::new(error)))
+            }
+            result => result,
+        }
+    }
+
+    /// Replace the worker with a new one, replaying module loads if enabled
+    /// Returns the old worker's crash report, if it panicked, and the modules that failed to load
+    #[allow(clippy::type_complexity)]
+    fn restart(&self) -> Result<(Option<Error>, Vec<(deno_core::ModuleId, Error)>), Error> {
+        let worker = DefaultWorker::new(self.options.clone())?;
+
+        let mut replay_errors = Vec::new();
+        for (id, entry) in self.modules.borrow_mut().iter_mut().enumerate() {
+            entry.id = None;
+            if self.replay_modules {
+                let result = if entry.is_main {
+                    worker.load_main_module(entry.module.clone())
+                } else {
+                    worker.load_module(entry.module.clone())
+                };
+                match result {
+                    Ok(new_id) => entry.id = Some(new_id),
+                    Err(e) => replay_errors.push((id, e)),
+                }
+            }
+        }
+
+        // The old thread is stopping, so joining it waits for it to finish and reports the panic
+        let crash = self.worker.replace(worker).0.join().err();
+        Ok((crash, replay_errors))
+    }
+
+    /// Record a loaded module, returning the id to hand out for it
+    fn add_module(
+        &self,
+        module: crate::Module,
+        is_main: bool,
+        id: deno_core::ModuleId,
+    ) -> deno_core::ModuleId {
+        let mut modules = self.modules.borrow_mut();
+        modules.push(SupervisedModule {
+            module,
+            is_main,
+            id: Some(id),
+        });
+        modules.len() - 1
+    }
+
+    /// Get the id of a module in the current worker thread
+    fn module_id(&self, id: deno_core::ModuleId) -> Result<deno_core::ModuleId, Error> {
+        self.modules
+            .borrow()
+            .get(id)
+            .and_then(|entry| entry.id)
+            .ok_or_else(|| Error::Runtime("Module not found".to_string()))
+    }
+}
+
+/// Identifies one of the runtimes hosted by a [MultiRuntimeWorker]
+pub type RuntimeKey = String;
+
+/// Options for a [MultiRuntimeWorker]
+#[derive(Clone)]
+pub struct MultiRuntimeWorkerOptions {
+    /// The most runtimes hosted at once
+    /// Creating another runtime evicts the least recently used one
+    pub max_runtimes: usize,
+}
+
+impl Default for MultiRuntimeWorkerOptions {
+    fn default() -> Self {
+        Self { max_runtimes: 64 }
+    }
+}
+
+/// Query types for a [MultiRuntimeWorker]
+pub enum MultiRuntimeQuery {
+    /// Stops the worker, dropping all of its runtimes
+    Stop,
+
+    /// Creates a runtime for a key, replacing any runtime already using it
+    Create(RuntimeKey, Box<DefaultWorkerOptions>),
+
+    /// Drops the runtime for a key
+    Remove(RuntimeKey),
+
+    /// Lists the keys of the hosted runtimes, most recently used first
+    Keys,
+
+    /// Handles a query with the runtime for a key
+    /// `Stop` and `Shutdown` queries are not supported - use `Remove` instead
+    Query(RuntimeKey, DefaultWorkerQuery),
+}
+
+/// Response types for a [MultiRuntimeWorker]
+#[derive(Debug, Clone)]
+pub enum MultiRuntimeResponse {
+    /// The runtime was created, and these runtimes were evicted to make room for it
+    Created(Vec<RuntimeKey>),
+
+    /// Whether a runtime was removed
+    Removed(bool),
+
+    /// The keys of the hosted runtimes, most recently used first
+    Keys(Vec<RuntimeKey>),
+
+    /// The response of the runtime to a query
+    Response(DefaultWorkerResponse),
+}
+
+/// The runtimes hosted by a [MultiRuntimeWorker], and when each was last used
+pub struct MultiRuntimes {
+    runtimes: std::collections::HashMap<
+        RuntimeKey,
+        (
+            crate::detached_runtime::DetachedRuntime<<DefaultWorker as InnerWorker>::Runtime>,
+            u64,
+        ),
+    >,
+    clock: u64,
+    max_runtimes: usize,
+}
+
+impl MultiRuntimes {
+    /// Get the runtime for a key, marking it as the most recently used
+    /// Runtimes can be evicted in any order, so each is only entered while it handles a query
+    fn touch(
+        &mut self,
+        key: &str,
+    ) -> Result<
+        &mut crate::detached_runtime::DetachedRuntime<<DefaultWorker as InnerWorker>::Runtime>,
+        Error,
+    > {
+        self.clock += 1;
+        let (runtime, last_used) = self
+            .runtimes
+            .get_mut(key)
+            .ok_or_else(|| Error::Runtime(format!("No runtime for key `{key}`")))?;
+        *last_used = self.clock;
+        Ok(runtime)
+    }
+
+    /// Drop least recently used runtimes until there is room for one more
+    fn make_room(&mut self) -> Vec<RuntimeKey> {
+        let mut evicted = vec![];
+        while !self.runtimes.is_empty() && self.runtimes.len() >= self.max_runtimes {
+            let oldest = self
+                .runtimes
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                self.runtimes.remove(&key);
+                evicted.push(key);
+            }
+        }
+        evicted
+    }
+}
+
+/// A worker hosting several independent runtimes on a single thread
+/// Each runtime has its own options, and is addressed by a key
+///
+/// Useful for hosts with many mostly-idle tenants, which would otherwise need a thread each
+/// Queries for different runtimes are handled one at a time, so a slow query delays every runtime
+///
+/// Once `max_runtimes` are hosted, creating another evicts the least recently used runtime,
+/// which loses its state - queries for an evicted key fail until it is created again
+///
+/// ```rust
+/// use rustyscript::{Error, worker::{DefaultWorkerOptions, MultiRuntimeWorker, MultiRuntimeWorkerOptions}};
+/// use std::time::Duration;
+///
+/// fn main() -> Result<(), Error> {
+///     let worker = MultiRuntimeWorker::new(MultiRuntimeWorkerOptions { max_runtimes: 2 })?;
+///     let options = DefaultWorkerOptions {
+///         timeout: Duration::from_secs(1),
+///         ..Default::default()
+///     };
+///
+///     worker.create("tenant_a", options.clone())?;
+///     worker.create("tenant_b", options.clone())?;
+///     worker.eval::<()>("tenant_a", "globalThis.name = 'a'".to_string())?;
+///
+///     // tenant_b was used least recently, so it makes room for tenant_c
+///     let evicted = worker.create("tenant_c", options)?;
+///     assert_eq!(vec!["tenant_b".to_string()], evicted);
+///
+///     let name: String = worker.eval("tenant_a", "globalThis.name".to_string())?;
+///     assert_eq!("a", name);
+///     worker.stop()
+/// }
+/// ```
+pub struct MultiRuntimeWorker(Worker<MultiRuntimeWorker>);
+impl InnerWorker for MultiRuntimeWorker {
+    type Runtime = MultiRuntimes;
+    type RuntimeOptions = MultiRuntimeWorkerOptions;
+    type Query = MultiRuntimeQuery;
+    type Response = MultiRuntimeResponse;
+
+    fn init_runtime(options: Self::RuntimeOptions) -> Result<Self::Runtime, Error> {
+        if options.max_runtimes == 0 {
+            return Err(Error::Configuration(
+                "max_runtimes must be greater than zero".to_string(),
+            ));
+        }
+
+        Ok(MultiRuntimes {
+            runtimes: std::collections::HashMap::new(),
+            clock: 0,
+            max_runtimes: options.max_runtimes,
+        })
+    }
+
+    fn handle_query(runtimes: &mut Self::Runtime, query: Self::Query) -> Self::Response {
+        let error = |e| Self::Response::Response(DefaultWorkerResponse::Error(e));
+        match query {
+            MultiRuntimeQuery::Stop => Self::Response::Removed(false),
+
+            MultiRuntimeQuery::Create(key, options) => {
+                // The old runtime is dropped first, so it never counts against the limit
+                runtimes.runtimes.remove(&key);
+                let evicted = runtimes.make_room();
+                match DefaultWorker::init_runtime(*options) {
+                    Ok(runtime) => {
+                        let runtime = crate::detached_runtime::DetachedRuntime::new(runtime);
+                        runtimes.clock += 1;
+                        runtimes.runtimes.insert(key, (runtime, runtimes.clock));
+                        Self::Response::Created(evicted)
+                    }
+                    Err(e) => error(e),
+                }
+            }
+
+            MultiRuntimeQuery::Remove(key) => {
+                Self::Response::Removed(runtimes.runtimes.remove(&key).is_some())
+            }
+
+            MultiRuntimeQuery::Keys => {
+                let mut keys: Vec<_> = runtimes.runtimes.iter().collect();
+                keys.sort_by_key(|(_, (_, last_used))| std::cmp::Reverse(*last_used));
+                Self::Response::Keys(keys.into_iter().map(|(key, _)| key.clone()).collect())
+            }
+
+            MultiRuntimeQuery::Query(key, query) => match query {
+                DefaultWorkerQuery::Stop | DefaultWorkerQuery::Shutdown(_) => error(
+                    Error::Runtime("Use MultiRuntimeQuery::Remove to stop a runtime".to_string()),
+                ),
+                query => match runtimes.touch(&key) {
+                    Ok(runtime) => runtime.with(|runtime| {
+                        let response = DefaultWorker::handle_query(runtime, query);
+                        Self::Response::Response(DefaultWorker::transform_response(
+                            response, &runtime.2,
+                        ))
+                    }),
+                    Err(e) => error(e),
+                },
+            },
+        }
+    }
+
+    fn describe_query(query: &Self::Query) -> Option<String> {
+        Some(match query {
+            MultiRuntimeQuery::Stop => "Stop".to_string(),
+            MultiRuntimeQuery::Create(key, _) => format!("Create({key})"),
+            MultiRuntimeQuery::Remove(key) => format!("Remove({key})"),
+            MultiRuntimeQuery::Keys => "Keys".to_string(),
+            MultiRuntimeQuery::Query(key, query) => format!("Query({key}, {})", query.describe()),
+        })
+    }
+
+    // Custom thread impl to handle stop
+    fn thread(mut runtime: Self::Runtime, rx: Receiver<Self::Query>, tx: Sender<Self::Response>) {
+        while let Ok(msg) = rx.recv() {
+            let stop = matches!(msg, MultiRuntimeQuery::Stop);
+            set_current_query(Self::describe_query(&msg));
+            let response = Self::handle_query(&mut runtime, msg);
+            set_current_query(None);
+            tx.send(response).unwrap();
+
+            if stop {
+                break;
+            }
+        }
+    }
+}
+
+impl MultiRuntimeWorker {
+    /// Create a new worker, hosting no runtimes
+    pub fn new(options: MultiRuntimeWorkerOptions) -> Result<Self, Error> {
+        Worker::new(options).map(Self)
+    }
+
+    /// Stop the worker and wait for it to finish, dropping all of its runtimes
+    /// Consumes the worker and returns an error if the worker panicked
+    pub fn stop(self) -> Result<(), Error> {
+        // A crashed worker reports the crash, rather than the failure to send
+        let sent = self.0.send(MultiRuntimeQuery::Stop);
+        self.0.join()?;
+        sent
+    }
+
+    /// Create a runtime for `key`, replacing any runtime already using it
+    /// Returns the keys of the runtimes evicted to make room for it
+    ///
+    /// Bootstrap modules in `options` are loaded before this returns
+    pub fn create(
+        &self,
+        key: &str,
+        options: DefaultWorkerOptions,
+    ) -> Result<Vec<RuntimeKey>, Error> {
+        match self.0.send_and_await(MultiRuntimeQuery::Create(
+            key.to_string(),
+            Box::new(options),
+        ))? {
+            MultiRuntimeResponse::Created(evicted) => Ok(evicted),
+            response => Err(Self::unexpected(response)),
+        }
+    }
+
+    /// Drop the runtime for `key`
+    /// Returns false if there was no such runtime
+    pub fn remove(&self, key: &str) -> Result<bool, Error> {
+        match self
+            .0
+            .send_and_await(MultiRuntimeQuery::Remove(key.to_string()))?
+        {
+            MultiRuntimeResponse::Removed(removed) => Ok(removed),
+            response => Err(Self::unexpected(response)),
+        }
+    }
+
+    /// The keys of the hosted runtimes, most recently used first
+    pub fn keys(&self) -> Result<Vec<RuntimeKey>, Error> {
+        match self.0.send_and_await(MultiRuntimeQuery::Keys)? {
+            MultiRuntimeResponse::Keys(keys) => Ok(keys),
+            response => Err(Self::unexpected(response)),
+        }
+    }
+
+    /// Send a query to the runtime for `key`, and wait for its response
+    /// Errors, including a missing runtime, are returned as `DefaultWorkerResponse::Error`
+    pub fn query(
+        &self,
+        key: &str,
+        query: DefaultWorkerQuery,
+    ) -> Result<DefaultWorkerResponse, Error> {
+        match self
+            .0
+            .send_and_await(MultiRuntimeQuery::Query(key.to_string(), query))?
+        {
+            MultiRuntimeResponse::Response(response) => Ok(response),
+            response => Err(Self::unexpected(response)),
+        }
+    }
+
+    /// Evaluate a string of javascript code in the runtime for `key`
+    /// Returns the result of the evaluation
+    pub fn eval<T>(&self, key: &str, code: String) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Self::value(self.query(key, DefaultWorkerQuery::Eval(code))?)
+    }
+
+    /// Load a module into the runtime for `key` as the main module
+    /// Returns the module id of the loaded module
+    pub fn load_main_module(
+        &self,
+        key: &str,
+        module: crate::Module,
+    ) -> Result<deno_core::ModuleId, Error> {
+        Self::module_id(self.query(key, DefaultWorkerQuery::LoadMainModule(module))?)
+    }
+
+    /// Load a module into the runtime for `key` as a side module
+    /// Returns the module id of the loaded module
+    pub fn load_module(
+        &self,
+        key: &str,
+        module: crate::Module,
+    ) -> Result<deno_core::ModuleId, Error> {
+        Self::module_id(self.query(key, DefaultWorkerQuery::LoadModule(module))?)
+    }
+
+    /// Call the entrypoint function of a module in the runtime for `key`
+    /// The module id must be the id of a module loaded into that runtime
+    pub fn call_entrypoint<T>(
+        &self,
+        key: &str,
+        id: deno_core::ModuleId,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Self::value(self.query(key, DefaultWorkerQuery::CallEntrypoint(id, args))?)
+    }
+
+    /// Call a function in the runtime for `key`
+    /// The module id must be the id of a module loaded into that runtime
+    pub fn call_function<T>(
+        &self,
+        key: &str,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+        args: Vec<crate::serde_json::Value>,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Self::value(self.query(
+            key,
+            DefaultWorkerQuery::CallFunction(module_context, name, args),
+        )?)
+    }
+
+    /// Get a value from the runtime for `key`
+    /// The module id must be the id of a module loaded into that runtime
+    pub fn get_value<T>(
+        &self,
+        key: &str,
+        module_context: Option<deno_core::ModuleId>,
+        name: String,
+    ) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Self::value(self.query(key, DefaultWorkerQuery::GetValue(module_context, name))?)
+    }
+
+    fn value<T>(response: DefaultWorkerResponse) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match response {
+            DefaultWorkerResponse::Value(v) => {
+                crate::serde_json::from_value(v).map_err(Error::from)
+            }
+            #[cfg(feature = "structured_clone")]
+            DefaultWorkerResponse::Structured(v) => v.deserialize(),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    fn module_id(response: DefaultWorkerResponse) -> Result<deno_core::ModuleId, Error> {
+        match response {
+            DefaultWorkerResponse::ModuleId(id) => Ok(id),
+            DefaultWorkerResponse::Error(e) => Err(e),
+            _ => Err(Error::Runtime(
+                "Unexpected response from the worker".to_string(),
+            )),
+        }
+    }
+
+    fn unexpected(response: MultiRuntimeResponse) -> Error {
+        match response {
+            MultiRuntimeResponse::Response(DefaultWorkerResponse::Error(e)) => e,
+            _ => Error::Runtime("Unexpected response from the worker".to_string()),
+        }
+    }
+}
+
+/// What a [FleetManager] does to a worker chosen to free memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EvictionAction {
+    /// Stop the worker and remove it from the fleet
+    #[default]
+    Evict,
+
+    /// Replace the worker with a fresh one, created with the same options
+    /// The worker stays in the fleet, but loses its loaded modules and state
+    Reset,
+}
+
+/// Options for a [FleetManager]
+#[derive(Debug, Clone, Copy)]
+pub struct FleetOptions {
+    /// Total heap, in bytes, the fleet's runtimes may allocate before workers are evicted or reset
+    pub memory_budget: usize,
+
+    /// What to do with the workers chosen to free memory
+    pub action: EvictionAction,
+}
+
+impl Default for FleetOptions {
+    fn default() -> Self {
+        Self {
+            memory_budget: 1024 * 1024 * 1024,
+            action: EvictionAction::default(),
+        }
+    }
+}
+
+/// Memory usage and eviction counts for a [FleetManager]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FleetStats {
+    /// Number of workers in the fleet
+    pub workers: usize,
+
+    /// Bytes of heap in use, across all workers
+    pub used_heap_size: usize,
+
+    /// Bytes of heap allocated, across all workers - this is what is checked against the budget
+    pub total_heap_size: usize,
+
+    /// The fleet's memory budget
+    pub memory_budget: usize,
+
+    /// Number of workers evicted since the fleet was created
+    pub evictions: u64,
+
+    /// Number of workers reset since the fleet was created
+    pub resets: u64,
+
+    /// Statistics for each worker, by key
+    /// Workers that could not report their statistics are omitted
+    pub members: std::collections::HashMap<RuntimeKey, crate::RuntimeStats>,
+}
+
+struct FleetMember {
+    worker: DefaultWorker,
+    options: DefaultWorkerOptions,
+    priority: Priority,
+    last_used: u64,
+}
+
+/// Tracks the heap usage of a set of [DefaultWorker]s, keeping their total under a memory budget
+///
+/// Whenever the budget is exceeded, workers are evicted or reset until the fleet fits again -
+/// `Low` priority workers first, then `Normal`, then `High`, and within a priority the least recently used first
+///
+/// Usage is measured when a worker is registered, and on each call to [FleetManager::enforce],
+/// which should be called periodically, or after work that may have grown a runtime's heap
+///
+/// ```rust
+/// use rustyscript::{Error, worker::{DefaultWorkerOptions, FleetManager, FleetOptions, Priority}};
+///
+/// fn main() -> Result<(), Error> {
+///     let mut fleet = FleetManager::new(FleetOptions::default());
+///     fleet.register("tenant_a", DefaultWorkerOptions::default(), Priority::High)?;
+///     fleet.register("tenant_b", DefaultWorkerOptions::default(), Priority::Low)?;
+///
+///     let value: i64 = fleet.get("tenant_a").unwrap().eval("1 + 1".to_string())?;
+///     assert_eq!(2, value);
+///
+///     let stats = fleet.stats();
+///     println!("{} workers using {} bytes of heap", stats.workers, stats.total_heap_size);
+///     Ok(())
+/// }
+/// ```
+pub struct FleetManager {
+    options: FleetOptions,
+    members: std::collections::HashMap<RuntimeKey, FleetMember>,
+    clock: u64,
+    evictions: u64,
+    resets: u64,
+}
+
+impl FleetManager {
+    /// Create a new fleet, with no workers
+    pub fn new(options: FleetOptions) -> Self {
+        Self {
+            options,
+            members: std::collections::HashMap::new(),
+            clock: 0,
+            evictions: 0,
+            resets: 0,
+        }
+    }
+
+    /// Start a worker and add it to the fleet as `key`, replacing and stopping any worker already using it
+    /// The budget is then enforced - returns the keys of the workers evicted or reset to make room
+    pub fn register(
+        &mut self,
+        key: &str,
+        options: DefaultWorkerOptions,
+        priority: Priority,
+    ) -> Result<Vec<RuntimeKey>, Error> {
+        let worker = DefaultWorker::new(options.clone())?;
+        self.clock += 1;
+        let member = FleetMember {
+            worker,
+            options,
+            priority,
+            last_used: self.clock,
+        };
+
+        if let Some(old) = self.members.insert(key.to_string(), member) {
+            let _ = old.worker.stop();
+        }
+        self.enforce()
+    }
+
+    /// Get the worker for `key`, marking it as the most recently used
+    pub fn get(&mut self, key: &str) -> Option<&DefaultWorker> {
+        self.clock += 1;
+        let member = self.members.get_mut(key)?;
+        member.last_used = self.clock;
+        Some(&member.worker)
+    }
+
+    /// Stop the worker for `key`, and remove it from the fleet
+    /// Returns false if there was no such worker
+    pub fn remove(&mut self, key: &str) -> Result<bool, Error> {
+        match self.members.remove(key) {
+            Some(member) => member.worker.stop().map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// The keys of the workers in the fleet
+    pub fn keys(&self) -> Vec<RuntimeKey> {
+        self.members.keys().cloned().collect()
+    }
+
+    /// Measure the heap of every worker, and evict or reset workers until the fleet fits its budget
+    /// Returns the keys of the workers evicted or reset
+    pub fn enforce(&mut self) -> Result<Vec<RuntimeKey>, Error> {
+        let mut usage: std::collections::HashMap<_, _> = self
+            .members
+            .iter()
+            .map(|(key, member)| {
+                let heap = member.worker.stats().map_or(0, |s| s.total_heap_size);
+                (key.clone(), heap)
+            })
+            .collect();
+
+        // Lowest priority first, then least recently used
+        let mut candidates: Vec<_> = self
+            .members
+            .iter()
+            .map(|(key, member)| {
+                (
+                    std::cmp::Reverse(member.priority),
+                    member.last_used,
+                    key.clone(),
+                )
+            })
+            .collect();
+        candidates.sort();
+
+        let mut affected = vec![];
+        for (_, _, key) in candidates {
+            if usage.values().sum::<usize>() <= self.options.memory_budget {
+                break;
+            }
+
+            match self.options.action {
+                EvictionAction::Evict => {
+                    if let Some(member) = self.members.remove(&key) {
+                        let _ = member.worker.stop();
+                    }
+                    usage.remove(&key);
+                    self.evictions += 1;
+                }
+
+                EvictionAction::Reset => {
+                    if let Some(member) = self.members.get_mut(&key) {
+                        let worker = DefaultWorker::new(member.options.clone())?;
+                        let heap = worker.stats().map_or(0, |s| s.total_heap_size);
+                        let _ = std::mem::replace(&mut member.worker, worker).stop();
+                        usage.insert(key.clone(), heap);
+                    }
+                    self.resets += 1;
+                }
+            }
+            affected.push(key);
+        }
+
+        Ok(affected)
+    }
+
+    /// Memory usage and eviction counts for the fleet
+    pub fn stats(&self) -> FleetStats {
+        let members: std::collections::HashMap<_, _> = self
+            .members
+            .iter()
+            .filter_map(|(key, member)| Some((key.clone(), member.worker.stats().ok()?)))
+            .collect();
+
+        FleetStats {
+            workers: self.members.len(),
+            used_heap_size: members.values().map(|s| s.used_heap_size).sum(),
+            total_heap_size: members.values().map(|s| s.total_heap_size).sum(),
+            memory_budget: self.options.memory_budget,
+            evictions: self.evictions,
+            resets: self.resets,
+            members,
+        }
+    }
+
+    /// Stop every worker in the fleet
+    /// Returns the first error reported by a worker, after all of them have stopped
+    pub fn stop(self) -> Result<(), Error> {
+        self.members
+            .into_values()
+            .map(|member| member.worker.stop())
+            .fold(Ok(()), Result::and)
+    }
+}
+
+/// Options for the default worker
+#[derive(Default, Clone)]
+pub struct DefaultWorkerOptions {
+    /// The default entrypoint function to use if none is registered
+    pub default_entrypoint: Option<String>,
+
+    /// The timeout to use for the runtime
+    /// [DefaultWorker] also stops waiting for a response after this long, returning `Error::Timeout`
+    pub timeout: std::time::Duration,
+
+    /// If set, values are sent back from the worker as `DefaultWorkerResponse::ValueChunk`
+    /// messages of at most this many bytes of JSON, which are reassembled by
+    /// the client methods of [DefaultWorker]
+    pub chunk_size: Option<usize>,
+
+    /// If set, values and function arguments larger than this many bytes of JSON
+    /// are compressed with lz4 before being sent to or from the worker
+    #[cfg(feature = "worker_compression")]
+    pub compression_threshold: Option<usize>,
+
+    /// If set, a DevTools-compatible inspector server is started for the worker's runtime
+    #[cfg(feature = "inspector")]
+    pub inspector: Option<crate::InspectorOptions>,
+
+    /// Called on the worker thread with every response, before it is sent back
+    /// Use it to apply policies such as redacting fields or capping sizes in one place
+    /// Values are transformed before being compressed or split into chunks
+    pub response_transform: Option<ResponseTransform>,
+
+    /// Modules loaded in order as soon as the worker's runtime is created, before any queries are served
+    /// Use these to give every worker the same polyfills and helper globals
+    /// If any of them fail to load, `DefaultWorker::new` returns the error
+    pub bootstrap: Vec<crate::Module>,
+
+    /// Called on the worker thread as soon as the worker's runtime is created, before the bootstrap modules
+    /// Use it to put application state with `Runtime::put_state`, and register the functions using it
+    /// If it returns an error, `DefaultWorker::new` returns the error
+    pub setup: Option<RuntimeSetup>,
+
+    /// Values set on `globalThis` as the worker's runtime is created, before the bootstrap modules
+    /// They can be updated later with `DefaultWorker::set_global`
+    pub globals: std::collections::HashMap<String, crate::serde_json::Value>,
+
+    /// If set, the worker's runtime is deterministic, with a seeded `Math.random` and a virtual clock
+    /// that only moves through `DefaultWorker::advance_time` and `DefaultWorker::set_time`
+    pub deterministic: Option<crate::DeterministicOptions>,
+
+    /// If set, usage is recorded against a tenant's quota, shared with any other runtimes using its store
+    /// The worker refuses to run javascript once any of the quota's limits is reached
+    pub quota: Option<crate::Quota>,
+
+    /// If set, at most this many queries can be waiting for the worker at once
+    /// Further queries fail with `Error::WorkerBusy` until the worker catches up,
+    /// instead of letting the queue grow without limit
+    pub queue_capacity: Option<usize>,
+
+    /// Called on the worker thread with errors from queries sent without waiting for a reply,
+    /// such as `DefaultWorker::eval_noreply` - those errors are dropped if this is not set
+    pub error_handler: Option<ErrorHandler>,
+
+    /// Called as each query is sent, started, finished and answered, with the query's ID
+    /// Use it to correlate logs from the host and worker threads
+    pub trace_handler: Option<TraceHandler>,
+
+    /// Called on the worker thread with promise rejections that nothing handled
+    /// If set, these no longer fail the query they happened during
+    pub on_unhandled_rejection: Option<JsExceptionHandler>,
+
+    /// Called on the worker thread with exceptions thrown from timer callbacks
+    /// If set, these no longer fail the query they happened during
+    pub on_uncaught_exception: Option<JsExceptionHandler>,
+
+    /// If true, the worker keeps running the event loop while it has no queries to handle,
+    /// so that timers like `setInterval` keep firing between queries
+    /// Errors raised while doing so are passed to `error_handler`
+    ///
+    /// Queries are picked up within a few milliseconds while the event loop has pending work
+    pub background_event_loop: bool,
+}
+
+impl DefaultWorkerOptions {
+    /// Options for the worker's runtime
+    fn runtime_options(&self) -> crate::RuntimeOptions {
+        crate::RuntimeOptions {
+            default_entrypoint: self.default_entrypoint.clone(),
+            timeout: self.timeout,
+            deterministic: self.deterministic.clone(),
+            quota: self.quota.clone(),
+            globals: self.globals.clone(),
+            on_unhandled_rejection: self.on_unhandled_rejection.clone().map(|handler| {
+                Box::new(move |e: &crate::JsException| handler(e)) as crate::ExceptionHandler
+            }),
+            on_uncaught_exception: self.on_uncaught_exception.clone().map(|handler| {
+                Box::new(move |e: &crate::JsException| handler(e)) as crate::ExceptionHandler
+            }),
+
+            #[cfg(feature = "inspector")]
+            inspector: self.inspector.clone(),
+
+            ..Default::default()
+        }
+    }
+
+    /// Check the configuration, without starting a worker
+    /// Returns `Error::Configuration` describing the first problem found
+    ///
+    /// Note that the default `timeout` of zero is rejected here, since it fails
+    /// any call that waits on a promise
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |msg: &str| Err(Error::Configuration(msg.to_string()));
+
+        if self.timeout.is_zero() {
+            return invalid("timeout must be greater than zero");
+        }
+
+        if let Some(name) = &self.default_entrypoint {
+            let mut chars = name.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+            if !valid {
+                return invalid(&format!(
+                    "default_entrypoint `{name}` is not a valid function name"
+                ));
+            }
+        }
+
+        if self.queue_capacity == Some(0) {
+            return invalid("queue_capacity must be greater than zero");
+        }
+
+        if self.chunk_size == Some(0) {
+            return invalid("chunk_size must be greater than zero");
+        }
+
+        if self
+            .bootstrap
+            .iter()
+            .any(|module| module.filename().is_empty())
+        {
+            return invalid("bootstrap modules must have a filename");
+        }
+
+        // Checks shared with standalone runtimes, such as feature compatibility
+        crate::RuntimeBuilder::from_options(self.runtime_options()).validate()
+    }
+}
+
+/// A builder for [DefaultWorker], as an alternative to filling in a [DefaultWorkerOptions] struct
+/// The configuration is checked by `build`, before the worker thread is started
+///
+/// Unlike `DefaultWorkerOptions::default`, the timeout starts out unlimited, as it does for [crate::RuntimeOptions]
+pub struct DefaultWorkerBuilder(DefaultWorkerOptions);
+
+impl Default for DefaultWorkerBuilder {
+    fn default() -> Self {
+        Self(DefaultWorkerOptions {
+            timeout: std::time::Duration::MAX,
+            ..Default::default()
+        })
+    }
+}
+
+impl DefaultWorkerBuilder {
+    /// Create a new builder, with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder starting from an existing set of options
+    pub fn from_options(options: DefaultWorkerOptions) -> Self {
+        Self(options)
+    }
+
+    /// Amount of time each query may run for before timing out
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.0.timeout = timeout;
+        self
+    }
+
+    /// Function to use as entrypoint if a module does not register one
+    pub fn default_entrypoint(mut self, name: &str) -> Self {
+        self.0.default_entrypoint = Some(name.to_string());
+        self
+    }
+
+    /// Send values larger than this many bytes of JSON back in several chunks
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.0.chunk_size = Some(size);
+        self
+    }
+
+    /// Compress values and arguments larger than this many bytes of JSON
+    #[cfg(feature = "worker_compression")]
+    pub fn compression_threshold(mut self, threshold: usize) -> Self {
+        self.0.compression_threshold = Some(threshold);
+        self
+    }
+
+    /// Start a DevTools-compatible inspector server for the worker's runtime
+    #[cfg(feature = "inspector")]
+    pub fn inspector(mut self, options: crate::InspectorOptions) -> Self {
+        self.0.inspector = Some(options);
+        self
+    }
+
+    /// Transform every response on the worker thread, before it is sent
+    pub fn response_transform(mut self, transform: ResponseTransform) -> Self {
+        self.0.response_transform = Some(transform);
+        self
+    }
+
+    /// Add a module to load as soon as the worker's runtime is created
+    pub fn with_bootstrap_module(mut self, module: crate::Module) -> Self {
+        self.0.bootstrap.push(module);
+        self
+    }
+
+    /// Make the worker's runtime deterministic, with a seeded `Math.random` and a virtual clock
+    pub fn deterministic(mut self, options: crate::DeterministicOptions) -> Self {
+        self.0.deterministic = Some(options);
+        self
+    }
+
+    /// Record usage against a tenant's quota, and refuse to run javascript once it is exhausted
+    pub fn quota(mut self, quota: crate::Quota) -> Self {
+        self.0.quota = Some(quota);
+        self
+    }
+
+    /// Keep running the event loop while the worker is idle, so timers keep firing between queries
+    pub fn background_event_loop(mut self, enabled: bool) -> Self {
+        self.0.background_event_loop = enabled;
+        self
+    }
+
+    /// Maximum number of queries that can be waiting for the worker at once
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.0.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Handle errors from queries sent without waiting for a reply
+    pub fn error_handler(mut self, handler: ErrorHandler) -> Self {
+        self.0.error_handler = Some(handler);
+        self
+    }
+
+    /// Report each query as it is sent, started, finished and answered
+    pub fn trace_handler(mut self, handler: TraceHandler) -> Self {
+        self.0.trace_handler = Some(handler);
+        self
+    }
+
+    /// Handle promise rejections that nothing handled, instead of failing the query
+    pub fn on_unhandled_rejection(mut self, handler: JsExceptionHandler) -> Self {
+        self.0.on_unhandled_rejection = Some(handler);
+        self
+    }
+
+    /// Handle exceptions thrown from timer callbacks, instead of failing the query
+    pub fn on_uncaught_exception(mut self, handler: JsExceptionHandler) -> Self {
+        self.0.on_uncaught_exception = Some(handler);
+        self
+    }
+
+    /// Check the configuration, without starting the worker
+    /// Returns `Error::Configuration` describing the first problem found
+    pub fn validate(&self) -> Result<(), Error> {
+        self.0.validate()
+    }
+
+    /// Consume the builder, returning the options it holds
+    pub fn into_options(self) -> DefaultWorkerOptions {
+        self.0
+    }
+
+    /// Check the configuration, and start the worker
+    pub fn build(self) -> Result<DefaultWorker, Error> {
+        self.validate()?;
+        DefaultWorker::new(self.0)
+    }
+}
+
+/// Handler for errors from queries sent to a [DefaultWorker] without waiting for a reply
+pub type ErrorHandler = std::sync::Arc<dyn Fn(Error) + Send + Sync>;
+
+/// Handler for unhandled rejections and uncaught exceptions in a [DefaultWorker]'s runtime
+/// See `DefaultWorkerOptions::on_unhandled_rejection` and `DefaultWorkerOptions::on_uncaught_exception`
+pub type JsExceptionHandler = std::sync::Arc<dyn Fn(&crate::JsException) + Send + Sync>;
+
+/// Function preparing the runtime of a [DefaultWorker], on the worker thread
+/// See `DefaultWorkerOptions::setup`
+pub type RuntimeSetup =
+    std::sync::Arc<dyn Fn(&mut crate::Runtime) -> Result<(), Error> + Send + Sync>;
+
+/// Function applied to every response of a [DefaultWorker] on the worker thread, before it is sent
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{serde_json::Value, worker::{DefaultWorker, DefaultWorkerOptions, DefaultWorkerResponse}, Error};
+/// use std::sync::Arc;
+///
+/// # fn main() -> Result<(), Error> {
+/// let worker = DefaultWorker::new(DefaultWorkerOptions {
+///     // Strip a secret field from every object returned
+///     response_transform: Some(Arc::new(|response| match response {
+///         DefaultWorkerResponse::Value(Value::Object(mut object)) => {
+///             object.remove("password");
+///             DefaultWorkerResponse::Value(Value::Object(object))
+///         }
+///         response => response,
+///     })),
+///     ..Default::default()
+/// })?;
+///
+/// let user: Value = worker.eval("({ name: 'a', password: 'b' })".to_string())?;
+/// assert_eq!(None, user.get("password"));
+/// # Ok(())
+/// # }
+/// ```
+pub type ResponseTransform =
+    std::sync::Arc<dyn Fn(DefaultWorkerResponse) -> DefaultWorkerResponse + Send + Sync>;
+
+/// Identifies a query sent through a [DefaultWorker]
+/// IDs start at 1 and increase with each query sent to the same worker
+pub type QueryId = u64;
+
+/// The state of a [DefaultWorker], as reported by `DefaultWorker::ping`
+#[derive(Debug, Clone)]
+pub struct WorkerHealth {
+    /// True if the worker answered the ping within the timeout
+    /// A worker that is not responsive is still handling a query - see `busy_for`
+    pub responsive: bool,
+
+    /// Number of queries sent to the worker that it has not yet started
+    pub queue_depth: usize,
+
+    /// When the worker last started or finished a query, or when it was created
+    pub last_activity: std::time::SystemTime,
+
+    /// How long the worker has been handling its current query, if it is handling one
+    /// A value that keeps growing points to a hung query, rather than a busy worker
+    pub busy_for: Option<std::time::Duration>,
+}
+
+/// Activity of a worker thread, shared with the [DefaultWorker] that started it
+#[derive(Debug)]
+struct WorkerActivity {
+    queued: std::sync::atomic::AtomicUsize,
+    last_activity: std::sync::Mutex<std::time::SystemTime>,
+    busy_since: std::sync::Mutex<Option<std::time::Instant>>,
+}
+
+impl WorkerActivity {
+    fn new() -> Self {
+        Self {
+            queued: Default::default(),
+            last_activity: std::sync::Mutex::new(std::time::SystemTime::now()),
+            busy_since: Default::default(),
+        }
+    }
+
+    /// Remove a query from the queue depth
+    fn dequeue(&self) {
+        let _ = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+    }
+
+    /// The worker started handling a query
+    /// `queued` is false for queries that were not counted when sent
+    fn start(&self, queued: bool) {
+        if queued {
+            self.dequeue();
+        }
+        *self.busy_since.lock().unwrap() = Some(std::time::Instant::now());
+        *self.last_activity.lock().unwrap() = std::time::SystemTime::now();
+    }
+
+    /// The worker finished handling its current query
+    fn finish(&self) {
+        *self.busy_since.lock().unwrap() = None;
+        *self.last_activity.lock().unwrap() = std::time::SystemTime::now();
+    }
+
+    fn health(&self, responsive: bool) -> WorkerHealth {
+        WorkerHealth {
+            responsive,
+            queue_depth: self.queued.load(Ordering::SeqCst),
+            last_activity: *self.last_activity.lock().unwrap(),
+            busy_for: self.busy_since.lock().unwrap().map(|since| since.elapsed()),
+        }
+    }
+}
+
+/// A step in the handling of a query by a [DefaultWorker]
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    /// The query this event belongs to
+    pub query_id: QueryId,
+
+    /// What happened to the query
+    pub stage: TraceStage,
+}
+
+/// The steps reported for each query through `DefaultWorkerOptions::trace_handler`
+#[derive(Debug, Clone)]
+pub enum TraceStage {
+    /// The query was sent to the worker - reported on the calling thread
+    Sent,
+
+    /// The worker started handling the query - reported on the worker thread
+    Started,
+
+    /// The worker finished handling the query - reported on the worker thread
+    /// `error` is set if the query failed
+    Finished {
+        /// Time spent handling the query
+        elapsed: std::time::Duration,
+
+        /// The error returned for the query, if any
+        error: Option<Error>,
+    },
+
+    /// The response to the query was received - reported on the calling thread
+    /// Not reported for queries sent without waiting for a reply
+    Received {
+        /// Time since the query was sent
+        elapsed: std::time::Duration,
+    },
+}
+
+/// Handler for the trace events of a [DefaultWorker]
+/// It is called on both the calling thread and the worker thread, so it must be `Send + Sync`
+///
+/// # Example
+///
+/// ```rust
+/// use rustyscript::{worker::{DefaultWorker, DefaultWorkerOptions, TraceStage}, Error};
+/// use std::sync::{Arc, Mutex};
+///
+/// # fn main() -> Result<(), Error> {
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let worker = DefaultWorker::new(DefaultWorkerOptions {
+///     trace_handler: Some(Arc::new({
+///         let log = log.clone();
+///         move |event| {
+///             let thread = std::thread::current().id();
+///             log.lock().unwrap().push(format!("{thread:?} query {} {:?}", event.query_id, event.stage));
+///         }
+///     })),
+///     ..Default::default()
+/// })?;
+///
+/// let _ = worker.eval::<i64>("throw new Error('oops')".to_string());
+/// let log = log.lock().unwrap();
+/// assert_eq!(4, log.len());
+/// assert!(log[2].contains("query 1 Finished") && log[2].contains("oops"));
+/// # Ok(())
+/// # }
+/// ```
+pub type TraceHandler = std::sync::Arc<dyn Fn(&TraceEvent) + Send + Sync>;
+
+/// Marks an encoded payload as plain JSON
+const PAYLOAD_JSON: u8 = 0;
+
+/// Marks an encoded payload as JSON compressed into an lz4 frame
+#[cfg(feature = "worker_compression")]
+const PAYLOAD_LZ4: u8 = 1;
+
+/// Set in the first byte of an encoded payload that holds a structured value, rather than JSON
+#[cfg(feature = "structured_clone")]
+const PAYLOAD_STRUCTURED: u8 = 0x80;
+
+/// Encodes a payload as it is written, starting with the byte that marks its format
+/// With a compression threshold, JSON is held back until it grows past the threshold,
+/// then compressed as it is written from there on
+enum PayloadWriter<W: std::io::Write> {
+    /// Waiting to see if the payload is large enough to compress
+    /// The inner writer is only missing if switching to compression failed
+    #[cfg(feature = "worker_compression")]
+    Pending {
+        inner: Option<W>,
+        buffer: Vec<u8>,
+        threshold: usize,
+        flags: u8,
+    },
+
+    /// Writing plain JSON
+    Plain(W),
+
+    /// Writing compressed JSON
+    #[cfg(feature = "worker_compression")]
+    Compressed(lz4_flex::frame::FrameEncoder<W>),
+}
+
+impl<W: std::io::Write> PayloadWriter<W> {
+    /// Start a payload, with `flags` set in its first byte
+    fn new(mut inner: W, options: &DefaultWorkerOptions, flags: u8) -> std::io::Result<Self> {
+        #[cfg(feature = "worker_compression")]
+        if let Some(threshold) = options.compression_threshold {
+            return Ok(Self::Pending {
+                inner: Some(inner),
+                buffer: Vec::new(),
+                threshold,
+                flags,
+            });
+        }
+
+        #[cfg(not(feature = "worker_compression"))]
+        let _ = options;
+
+        inner.write_all(&[PAYLOAD_JSON | flags])?;
+        Ok(Self::Plain(inner))
+    }
+
+    /// Write out anything still held back, and return the inner writer
+    fn finish(self) -> std::io::Result<W> {
+        match self {
+            #[cfg(feature = "worker_compression")]
+            Self::Pending {
+                inner: Some(mut inner),
+                buffer,
+                flags,
+                ..
+            } => {
+                inner.write_all(&[PAYLOAD_JSON | flags])?;
+                inner.write_all(&buffer)?;
+                Ok(inner)
+            }
+
+            #[cfg(feature = "worker_compression")]
+            Self::Pending { inner: None, .. } => {
+                Err(std::io::Error::other("the payload could not be compressed"))
+            }
+
+            Self::Plain(inner) => Ok(inner),
+
+            #[cfg(feature = "worker_compression")]
+            Self::Compressed(encoder) => encoder.finish().map_err(std::io::Error::other),
+        }
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for PayloadWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(feature = "worker_compression")]
+            Self::Pending {
+                inner,
+                buffer,
+                threshold,
+                flags,
+            } => {
+                buffer.extend_from_slice(buf);
+                if buffer.len() > *threshold {
+                    let mut inner = inner.take().ok_or_else(|| {
+                        std::io::Error::other("the payload could not be compressed")
+                    })?;
+                    inner.write_all(&[PAYLOAD_LZ4 | *flags])?;
+                    let mut encoder = lz4_flex::frame::FrameEncoder::new(inner);
+                    encoder.write_all(buffer)?;
+                    *self = Self::Compressed(encoder);
+                }
+                Ok(buf.len())
+            }
+
+            Self::Plain(inner) => inner.write(buf),
+
+            #[cfg(feature = "worker_compression")]
+            Self::Compressed(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(feature = "worker_compression")]
+            Self::Pending { .. } => Ok(()),
+
+            Self::Plain(inner) => inner.flush(),
+
+            #[cfg(feature = "worker_compression")]
+            Self::Compressed(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Splits the bytes written to it into chunks of `chunk_size` bytes,
+/// passing each one to `send` as soon as it is full
+struct ChunkWriter<F: FnMut(Vec<u8>, bool)> {
+    buffer: Vec<u8>,
+    chunk_size: usize,
+    send: F,
+}
+
+impl<F: FnMut(Vec<u8>, bool)> ChunkWriter<F> {
+    /// Send the last chunk
+    fn finish(mut self) {
+        (self.send)(self.buffer, true);
+    }
+}
+
+impl<F: FnMut(Vec<u8>, bool)> std::io::Write for ChunkWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        // A full chunk is only sent once more bytes follow it, so the last chunk is never empty
+        while self.buffer.len() > self.chunk_size {
+            let rest = self.buffer.split_off(self.chunk_size);
+            (self.send)(std::mem::replace(&mut self.buffer, rest), false);
+        }
+        Ok(buf.len())
+    }
 
-    /// The timeout to use for the runtime
-    pub timeout: std::time::Duration,
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads a value streamed as `ValueChunk` responses, receiving each chunk as it is needed
+struct ChunkReader<'a> {
+    worker: &'a DefaultWorker,
+    id: QueryId,
+    deadline: Option<std::time::Instant>,
+    chunk: Vec<u8>,
+    position: usize,
+    last: bool,
+
+    /// Set if receiving a chunk failed, so the caller can return it instead of a decoding error
+    error: Option<Error>,
+}
+
+impl ChunkReader<'_> {
+    /// Receive the next chunk of the value
+    fn next_chunk(&mut self) -> Result<(), Error> {
+        match self.worker.receive_traced(self.id, self.deadline)? {
+            DefaultWorkerResponse::ValueChunk(chunk, last) => {
+                self.chunk = chunk;
+                self.position = 0;
+                self.last = last;
+                Ok(())
+            }
+
+            // The worker could not finish encoding the value
+            DefaultWorkerResponse::Error(e) => {
+                self.last = true;
+                Err(e)
+            }
+
+            _ => Err(Error::Runtime(
+                "Invalid payload received from the worker".to_string(),
+            )),
+        }
+    }
+}
+
+impl std::io::Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.last {
+                return Ok(0);
+            }
+            if let Err(e) = self.next_chunk() {
+                let message = e.to_string();
+                self.error = Some(e);
+                return Err(std::io::Error::other(message));
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
 }
 
 /// Query types for the default worker
@@ -467,6 +3656,14 @@ pub enum DefaultWorkerQuery {
     /// Stops the worker
     Stop,
 
+    /// Runs the event loop until pending work is done or the deadline passes, then stops the worker
+    /// Queries queued ahead of it must also finish by the deadline, or are answered with `Error::Timeout`
+    Shutdown(Option<std::time::Instant>),
+
+    /// Answered as soon as the worker receives it, ahead of any queued queries
+    /// Sent by `DefaultWorker::ping`
+    Ping(Sender<()>),
+
     /// Evaluates a string of javascript code
     Eval(String),
 
@@ -476,6 +3673,9 @@ pub enum DefaultWorkerQuery {
     /// Loads a module into the worker as a side module
     LoadModule(crate::Module),
 
+    /// Re-evaluates an updated version of a loaded module, keeping its module id
+    ReloadModule(deno_core::ModuleId, crate::Module),
+
     /// Calls an entrypoint function in a module
     CallEntrypoint(deno_core::ModuleId, Vec<crate::serde_json::Value>),
 
@@ -486,21 +3686,1194 @@ pub enum DefaultWorkerQuery {
         Vec<crate::serde_json::Value>,
     ),
 
+    /// Calls a function in a module with additional binary arguments, returning binary data
+    CallFunctionBytes(
+        Option<deno_core::ModuleId>,
+        String,
+        Vec<crate::serde_json::Value>,
+        Vec<Vec<u8>>,
+    ),
+
+    /// Calls a function in a module, with arguments and result passed by structured clone
+    #[cfg(feature = "structured_clone")]
+    CallFunctionStructured(
+        Option<deno_core::ModuleId>,
+        String,
+        Vec<crate::StructuredValue>,
+    ),
+
+    /// Calls an entrypoint function in a module, with compressed arguments
+    #[cfg(feature = "worker_compression")]
+    CallEntrypointPacked(deno_core::ModuleId, Vec<u8>),
+
+    /// Calls a function in a module, with compressed arguments
+    #[cfg(feature = "worker_compression")]
+    CallFunctionPacked(Option<deno_core::ModuleId>, String, Vec<u8>),
+
     /// Gets a value from a module
     GetValue(Option<deno_core::ModuleId>, String),
+
+    /// Receives the oldest message sent from JS using `rustyscript.postMessage`
+    RecvMessage,
+
+    /// Gets resource usage statistics for the runtime
+    GetStats,
+
+    /// Exports a log of the modules and globals of the worker, for `DefaultWorker::replay`
+    ExportReplayLog,
+
+    /// Moves the runtime's virtual clock forward, firing timers that become due
+    AdvanceTime(std::time::Duration),
+
+    /// Sets the runtime's virtual clock, firing timers due up to that time
+    SetTime(std::time::SystemTime),
+
+    /// Sets a property of `globalThis`
+    SetGlobal(String, crate::serde_json::Value),
+
+    /// Sets the request ID passed to functions registered with a call context
+    SetRequestId(Option<String>),
+
+    /// Handles a query without sending a response
+    /// Errors are passed to `DefaultWorkerOptions::error_handler`, or dropped
+    NoReply(Box<DefaultWorkerQuery>),
+
+    /// Lists the exports of a module
+    ListExports(deno_core::ModuleId),
+
+    /// Handles a query ahead of, or behind, queries of other priorities already waiting
+    /// Queries of the same priority are handled in the order they were sent
+    Prioritized(Priority, Box<DefaultWorkerQuery>),
+
+    /// Handles a query stamped with an ID, which is carried by its responses and trace events
+    /// [DefaultWorker] stamps every query it sends
+    Traced(QueryId, Box<DefaultWorkerQuery>),
+
+    /// Handles a query within a span from the thread that sent it
+    /// [DefaultWorker] sends every query this way with the `tracing` feature
+    #[cfg(feature = "tracing")]
+    Spanned(tracing::Span, Box<DefaultWorkerQuery>),
+}
+
+/// A query waiting to be handled, with its ID and the span it was sent from
+#[cfg(feature = "tracing")]
+type QueuedQuery = (Option<QueryId>, DefaultWorkerQuery, tracing::Span);
+
+/// A query waiting to be handled, with its ID
+#[cfg(not(feature = "tracing"))]
+type QueuedQuery = (Option<QueryId>, DefaultWorkerQuery, ());
+
+impl DefaultWorkerQuery {
+    /// A short description of the query, for crash reports
+    fn describe(&self) -> String {
+        match self {
+            Self::Stop => "Stop".to_string(),
+            Self::Ping(_) => "Ping".to_string(),
+            Self::Shutdown(deadline) => format!("Shutdown({deadline:?})"),
+            Self::Eval(code) => format!("Eval({:?})", code.chars().take(40).collect::<String>()),
+            Self::LoadMainModule(module) => format!("LoadMainModule({})", module.filename()),
+            Self::LoadModule(module) => format!("LoadModule({})", module.filename()),
+            Self::ReloadModule(id, module) => format!("ReloadModule({id}, {})", module.filename()),
+            Self::CallEntrypoint(id, _) => format!("CallEntrypoint({id})"),
+            Self::CallFunction(_, name, _) => format!("CallFunction({name})"),
+            Self::CallFunctionBytes(_, name, _, _) => format!("CallFunctionBytes({name})"),
+
+            #[cfg(feature = "structured_clone")]
+            Self::CallFunctionStructured(_, name, _) => format!("CallFunctionStructured({name})"),
+
+            #[cfg(feature = "worker_compression")]
+            Self::CallEntrypointPacked(id, _) => format!("CallEntrypoint({id})"),
+
+            #[cfg(feature = "worker_compression")]
+            Self::CallFunctionPacked(_, name, _) => format!("CallFunction({name})"),
+
+            Self::GetValue(_, name) => format!("GetValue({name})"),
+            Self::RecvMessage => "RecvMessage".to_string(),
+            Self::GetStats => "GetStats".to_string(),
+            Self::ExportReplayLog => "ExportReplayLog".to_string(),
+            Self::AdvanceTime(duration) => format!("AdvanceTime({duration:?})"),
+            Self::SetTime(time) => format!("SetTime({time:?})"),
+            Self::SetGlobal(name, _) => format!("SetGlobal({name})"),
+            Self::SetRequestId(id) => format!("SetRequestId({id:?})"),
+            Self::NoReply(query) => format!("NoReply({})", query.describe()),
+            Self::ListExports(id) => format!("ListExports({id})"),
+            Self::Prioritized(priority, query) => {
+                format!("Prioritized({priority:?}, {})", query.describe())
+            }
+            Self::Traced(id, query) => format!("Traced({id}, {})", query.describe()),
+
+            #[cfg(feature = "tracing")]
+            Self::Spanned(_, query) => query.describe(),
+        }
+    }
+}
+
+/// The order in which a [DefaultWorker] handles waiting queries
+/// Queries sent without a priority are `Normal`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Handled before any other waiting query, such as health checks
+    High = 0,
+
+    /// The default priority
+    #[default]
+    Normal = 1,
+
+    /// Handled only once no other queries are waiting, such as background reports
+    Low = 2,
 }
 
 /// Response types for the default worker
+/// Responses can be serialized, errors included, to be passed on over another channel
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum DefaultWorkerResponse {
     /// A successful response with a value
     Value(crate::serde_json::Value),
 
+    /// A part of a value too large to send in a single response, as encoded JSON bytes
+    /// Chunks are sent as the value is serialized, and the flag is set on the last one
+    /// With the `structured_clone` feature, structured values are chunked the same way
+    /// Values that were compressed are sent as a single chunk when chunking is disabled
+    ValueChunk(Vec<u8>, bool),
+
+    /// A successful response with binary data
+    Bytes(Vec<u8>),
+
+    /// A successful response with a value passed by structured clone
+    /// With the `structured_clone` feature, function and entrypoint calls are answered with
+    /// this instead of `Value`
+    #[cfg(feature = "structured_clone")]
+    Structured(crate::StructuredValue),
+
     /// A successful response with a module id
     ModuleId(deno_core::ModuleId),
 
     /// A successful response with no value
     Ok(()),
 
+    /// A message sent from JS, if one was pending
+    Message(Option<crate::serde_json::Value>),
+
+    /// Resource usage statistics for the runtime
+    Stats(crate::RuntimeStats),
+
+    /// The exports of a module
+    Exports(Vec<crate::ExportInfo>),
+
+    /// A log of the worker's modules and globals
+    ReplayLog(WorkerReplayLog),
+
     /// An error response
+    /// Use `Error::report` for a flat summary of the error
     Error(Error),
+
+    /// A response to a `DefaultWorkerQuery::Traced` query, with the ID of that query
+    Traced(QueryId, Box<DefaultWorkerResponse>),
+}
+
+/// A log of the modules and globals of a [DefaultWorker], from `DefaultWorker::export_replay_log`
+/// Can be serialized, to warm a replacement worker after a crash or a deliberate recycle
+///
+/// This is not a snapshot of the runtime - replaying it loads the modules again and sets the globals,
+/// so the new worker only matches the old one as far as that reproduces its state:
+/// - Module-level state starts over, since each module's top-level code runs again
+/// - Other state held only in JS, such as pending timers and promises or objects stored on
+///   `globalThis` by scripts, is not kept
+/// - State held in Rust, through `Runtime::put_state`, is not kept
+/// - Modules are loaded with their latest source, so a reloaded module is replayed as its new version
+///
+/// Entrypoints registered from JS, and the default entrypoint, are restored as the modules are loaded again
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WorkerReplayLog {
+    /// Modules loaded into the worker, in the order they were loaded, with the ids they had
+    /// Modules from `DefaultWorkerOptions::bootstrap` are left out, since they are loaded with the worker
+    pub modules: Vec<(deno_core::ModuleId, crate::Module)>,
+
+    /// Globals set with `DefaultWorkerOptions::globals` or `DefaultWorker::set_global`,
+    /// with their values at the time of the export
+    pub globals: Vec<(String, crate::serde_json::Value)>,
+}
+
+#[cfg(test)]
+mod test_worker {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_validate() {
+        let e = DefaultWorkerOptions::default().validate().unwrap_err();
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        let e = DefaultWorker::builder()
+            .default_entrypoint("not a name")
+            .validate()
+            .unwrap_err();
+        assert!(e.to_string().contains("not a valid function name"), "{e}");
+
+        let e = DefaultWorker::builder()
+            .queue_capacity(0)
+            .build()
+            .err()
+            .expect("Invalid configuration was accepted");
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+
+        DefaultWorker::builder()
+            .timeout(Duration::from_secs(1))
+            .default_entrypoint("$main_2")
+            .queue_capacity(4)
+            .validate()
+            .expect("Valid configuration was rejected");
+    }
+
+    #[test]
+    fn test_globals() {
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            globals: [("limit".to_string(), 10.into())].into(),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+
+        let limit: i64 = worker.eval("limit".to_string()).expect("Could not eval");
+        assert_eq!(10, limit);
+
+        worker
+            .set_global("limit", 20.into())
+            .expect("Could not set value");
+        let limit: i64 = worker.eval("limit".to_string()).expect("Could not eval");
+        assert_eq!(20, limit);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_replay_log() {
+        let options = || DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            bootstrap: vec![crate::Module::new(
+                "test_replay_log_bootstrap.js",
+                "globalThis.double = (n) => n * 2;",
+            )],
+            globals: [("limit".to_string(), 10.into())].into(),
+            ..Default::default()
+        };
+
+        let worker = DefaultWorker::new(options()).expect("Could not create the worker");
+        let lib = worker
+            .load_module(crate::Module::new(
+                "test_replay_log_lib.js",
+                "export const scale = (n) => double(n) * limit * 10;",
+            ))
+            .expect("Could not load module");
+        let main = worker
+            .load_module(crate::Module::new(
+                "test_replay_log_main.js",
+                "export const greet = () => `hi ${name}`;",
+            ))
+            .expect("Could not load module");
+        worker
+            .reload_module(
+                lib,
+                crate::Module::new(
+                    "test_replay_log_lib.js",
+                    "export const scale = (n) => double(n) * limit;",
+                ),
+            )
+            .expect("Could not reload module");
+        worker
+            .set_global("name", "bob".into())
+            .expect("Could not set value");
+        worker
+            .eval::<i64>("limit = 3".to_string())
+            .expect("Could not eval");
+
+        let log = worker
+            .export_replay_log()
+            .expect("Could not export the replay log");
+        assert_eq!(
+            vec![lib, main],
+            log.modules.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                ("limit".to_string(), 3.into()),
+                ("name".to_string(), "bob".into())
+            ],
+            log.globals
+        );
+        worker.stop().expect("Could not stop the worker");
+
+        // Replay logs can be serialized
+        let log: WorkerReplayLog = crate::serde_json::from_str(
+            &crate::serde_json::to_string(&log).expect("Could not serialize the log"),
+        )
+        .expect("Could not deserialize the log");
+
+        let replacement = DefaultWorker::new(options()).expect("Could not create the worker");
+        let ids = replacement.replay(&log).expect("Could not replay the log");
+        let scaled: i64 = replacement
+            .call_function(Some(ids[&lib]), "scale".to_string(), vec![2.into()])
+            .expect("Could not call function");
+        assert_eq!(12, scaled);
+        let greeting: String = replacement
+            .call_function(Some(ids[&main]), "greet".to_string(), vec![])
+            .expect("Could not call function");
+        assert_eq!("hi bob", greeting);
+        replacement.stop().expect("Could not stop the worker");
+    }
+
+    #[cfg(feature = "structured_clone")]
+    #[test]
+    fn test_call_function_structured() {
+        use crate::StructuredValue;
+
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+        let module = crate::Module::new(
+            "test_structured.js",
+            "
+            export function summarize(map, buffer) {
+                return { keys: new Set(map.keys()), size: buffer.byteLength, at: new Date(5) };
+            }
+            export function counts() {
+                const counts = new Map([['a', 1]]);
+                return { counts, again: counts };
+            }
+        ",
+        );
+        let id = worker.load_module(module).expect("Could not load module");
+
+        let map = StructuredValue::Map(vec![(
+            StructuredValue::String("a".to_string()),
+            StructuredValue::Number(1.0),
+        )]);
+        let buffer = StructuredValue::ArrayBuffer(vec![1, 2, 3].into());
+        let value = worker
+            .call_function_structured(Some(id), "summarize".to_string(), vec![map, buffer])
+            .expect("Could not call function");
+        assert_eq!(
+            StructuredValue::Object(vec![
+                (
+                    "keys".to_string(),
+                    StructuredValue::Set(vec![StructuredValue::String("a".to_string())])
+                ),
+                ("size".to_string(), StructuredValue::Number(3.0)),
+                ("at".to_string(), StructuredValue::Date(5.0)),
+            ]),
+            value
+        );
+
+        // Plain calls are answered by structured clone too, so maps and shared objects deserialize
+        let counts: std::collections::HashMap<String, std::collections::HashMap<String, i64>> =
+            worker
+                .call_function(Some(id), "counts".to_string(), vec![])
+                .expect("Could not call function");
+        assert_eq!(Some(&1), counts["again"].get("a"));
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_exception_handlers() {
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let (rejections, exceptions) = (errors.clone(), errors.clone());
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            on_unhandled_rejection: Some(std::sync::Arc::new(move |e| {
+                rejections
+                    .lock()
+                    .expect("Could not lock the errors")
+                    .push(format!("rejection: {}", e.exception_message));
+            })),
+            on_uncaught_exception: Some(std::sync::Arc::new(move |e| {
+                exceptions
+                    .lock()
+                    .expect("Could not lock the errors")
+                    .push(format!("exception: {}", e.exception_message));
+            })),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+
+        let module = worker
+            .load_module(crate::Module::new(
+                "test_exception_handlers.js",
+                "
+                export const f = () => new Promise(resolve => {
+                    Promise.reject(new Error('a'));
+                    setTimeout(() => { resolve(1); throw new Error('b'); }, 1);
+                });
+            ",
+            ))
+            .expect("Could not load module");
+        let value: i64 = worker
+            .call_function(Some(module), "f".to_string(), vec![])
+            .expect("Could not call function");
+        assert_eq!(1, value);
+
+        let errors = errors.lock().expect("Could not lock the errors");
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("rejection") && e.contains('a')),
+            "{errors:?}"
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("exception") && e.contains('b')),
+            "{errors:?}"
+        );
+        drop(errors);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let worker = DefaultWorker::new(Default::default()).expect("Could not create the worker");
+        let handle = worker.interrupt_handle();
+        let interrupter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            handle.terminate()
+        });
+
+        // The worker is stuck inside JS until it is interrupted
+        let e = worker
+            .eval::<()>("while (true) {}".to_string())
+            .unwrap_err();
+        assert!(matches!(e, Error::Cancelled(_)), "{e}");
+        assert!(interrupter.join().expect("Could not join the interrupter"));
+
+        let value: i64 = worker.eval("1 + 1".to_string()).expect("Could not eval");
+        assert_eq!(2, value);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_errors = errors.clone();
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            error_handler: Some(std::sync::Arc::new(move |e| {
+                handler_errors
+                    .lock()
+                    .expect("Could not lock the errors")
+                    .push(e);
+            })),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+
+        let busy = "const end = Date.now() + 500; while (Date.now() < end) {}";
+        for _ in 0..3 {
+            worker
+                .eval_noreply(busy.to_string())
+                .expect("Could not send query");
+        }
+
+        // Queries queued behind the shutdown share its deadline, instead of all running to completion
+        let start = std::time::Instant::now();
+        worker
+            .shutdown(Duration::from_millis(100))
+            .expect("Could not shut down the worker");
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let errors = errors.lock().expect("Could not lock the errors");
+        assert!(errors.len() >= 2);
+        assert!(errors.iter().all(|e| matches!(e, Error::Timeout(_))));
+    }
+
+    #[test]
+    fn test_ping() {
+        let worker = DefaultWorker::new(Default::default()).expect("Could not create the worker");
+        let health = worker
+            .ping(Duration::from_secs(5))
+            .expect("Could not ping the worker");
+        assert!(health.responsive);
+        assert_eq!(0, health.queue_depth);
+        assert!(health.busy_for.is_none());
+
+        // A worker stuck on a query does not answer, and reports how long it has been busy
+        worker
+            .eval_noreply("const end = Date.now() + 500; while (Date.now() < end) {}".to_string())
+            .expect("Could not queue the eval");
+        worker
+            .eval_noreply("1".to_string())
+            .expect("Could not queue the eval");
+        std::thread::sleep(Duration::from_millis(100));
+
+        let health = worker
+            .ping(Duration::from_millis(50))
+            .expect("Could not ping the worker");
+        assert!(!health.responsive);
+        assert_eq!(1, health.queue_depth);
+        assert!(health.busy_for.expect("Worker should be busy") >= Duration::from_millis(100));
+
+        // Pings are answered ahead of the queued query
+        let health = worker
+            .ping(Duration::from_secs(5))
+            .expect("Could not ping the worker");
+        assert!(health.responsive);
+
+        let value: i64 = worker.eval("1 + 1".to_string()).expect("Could not eval");
+        assert_eq!(2, value);
+        let health = worker
+            .ping(Duration::from_secs(5))
+            .expect("Could not ping the worker");
+        assert_eq!(0, health.queue_depth);
+        assert!(health.busy_for.is_none());
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    crate::worker_protocol! {
+        struct CounterWorker {
+            query: CounterQuery,
+            response: CounterResponse,
+            runtime: crate::Runtime,
+
+            fn init(start: i64) {
+                let mut runtime = crate::Runtime::new(Default::default())?;
+                runtime.eval::<()>(&format!("globalThis.count = {start}"))?;
+                Ok(runtime)
+            }
+
+            Add => fn add(runtime, n: i64) -> i64 {
+                runtime.eval(&format!("count += {n}"))
+            }
+
+            Get => fn get(runtime) -> i64 {
+                runtime.eval("count")
+            }
+
+            Fail => fn fail(_runtime, message: String) -> () {
+                Err(Error::Runtime(message))
+            }
+        }
+    }
+
+    #[test]
+    fn test_worker_protocol() {
+        let worker = CounterWorker::new(10).expect("Could not create the worker");
+        assert_eq!(12, worker.add(2).expect("Could not add to the counter"));
+        assert_eq!(15, worker.add(3).expect("Could not add to the counter"));
+        assert_eq!(15, worker.get().expect("Could not get the counter"));
+
+        let e = worker.fail("oops".to_string()).unwrap_err();
+        assert!(
+            matches!(e, Error::Runtime(ref message) if message == "oops"),
+            "{e}"
+        );
+
+        // Queries can also be sent through the underlying worker
+        match worker
+            .worker()
+            .send_and_await(CounterQuery::Get())
+            .expect("Could not send query")
+        {
+            CounterResponse::Get(value) => {
+                assert_eq!(15, value.expect("Could not get the counter"))
+            }
+            _ => panic!("Unexpected response"),
+        }
+
+        let e = CounterWorker::new_bounded(0, 0)
+            .err()
+            .expect("Worker should not be created");
+        assert!(matches!(e, Error::Configuration(_)), "{e}");
+    }
+
+    crate::worker_client! {
+        struct GreeterClient {
+            fn greet(name: String, times: usize) -> String;
+            fn count_calls() -> i64 = "countCalls";
+            fn reset() -> ();
+        }
+    }
+
+    #[test]
+    fn test_worker_client() {
+        let worker = DefaultWorker::builder()
+            .timeout(Duration::from_secs(1))
+            .build()
+            .expect("Could not create the worker");
+        let module = worker
+            .load_module(crate::Module::new(
+                "test_worker_client.js",
+                "
+                let calls = 0;
+                export const greet = (name, times) => { calls++; return `hi ${name}`.repeat(times); };
+                export const countCalls = () => calls;
+                export const reset = () => { calls = 0; };
+            ",
+            ))
+            .expect("Could not load module");
+
+        let client = GreeterClient::with_module(worker, module);
+        assert_eq!(
+            "hi bobhi bob",
+            client.greet("bob".to_string(), 2).expect("Could not greet")
+        );
+        assert_eq!(1, client.count_calls().expect("Could not count calls"));
+        client.reset().expect("Could not reset the calls");
+        assert_eq!(0, client.count_calls().expect("Could not count calls"));
+
+        // Other queries go through the underlying worker
+        let sum: i64 = client
+            .worker()
+            .eval("1 + 1".to_string())
+            .expect("Could not eval");
+        assert_eq!(2, sum);
+
+        // Functions outside the global context are not found without a module
+        let client = GreeterClient::new(client.into_inner());
+        client.count_calls().unwrap_err();
+        client
+            .into_inner()
+            .stop()
+            .expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_payload_encoding() {
+        // Nothing is serialized up front unless compression or chunking is enabled
+        let options = DefaultWorkerOptions::default();
+        assert!(!DefaultWorker::packs_payloads(&options));
+        #[cfg(feature = "worker_compression")]
+        assert_eq!(None, DefaultWorker::encode_value(&"value", &options));
+
+        // Chunks are full until the last one, which is the only one flagged
+        let options = DefaultWorkerOptions {
+            chunk_size: Some(16),
+            ..Default::default()
+        };
+        let mut chunks = vec![];
+        DefaultWorker::send_response(
+            DefaultWorkerResponse::Value("abc".repeat(20).into()),
+            &options,
+            |response| chunks.push(response),
+        );
+        let Some((DefaultWorkerResponse::ValueChunk(tail, true), full)) = chunks.split_last()
+        else {
+            panic!("Expected the value to end with the last chunk");
+        };
+        assert!(!tail.is_empty() && tail.len() <= 16);
+        assert!(full.iter().all(
+            |chunk| matches!(chunk, DefaultWorkerResponse::ValueChunk(chunk, false) if chunk.len() == 16)
+        ));
+
+        let builder = DefaultWorker::builder()
+            .timeout(Duration::from_secs(1))
+            .chunk_size(16);
+        #[cfg(feature = "worker_compression")]
+        let builder = builder.compression_threshold(64);
+        let worker = builder.build().expect("Could not create the worker");
+        let module = worker
+            .load_module(crate::Module::new(
+                "test_payload_encoding.js",
+                "export const echo = (value) => value;",
+            ))
+            .expect("Could not load module");
+
+        for value in ["a".to_string(), "abc".repeat(100)] {
+            let echoed: String = worker
+                .call_function(Some(module), "echo".to_string(), vec![value.clone().into()])
+                .expect("Could not call function");
+            assert_eq!(value, echoed);
+        }
+
+        // Structured values are chunked too, keeping what JSON cannot represent
+        #[cfg(feature = "structured_clone")]
+        {
+            use crate::StructuredValue;
+            let value = StructuredValue::Map(vec![
+                (
+                    StructuredValue::String("abc".repeat(100)),
+                    StructuredValue::Number(f64::INFINITY),
+                ),
+                (
+                    StructuredValue::Undefined,
+                    StructuredValue::Number(f64::NEG_INFINITY),
+                ),
+            ]);
+            let echoed = worker
+                .call_function_structured(Some(module), "echo".to_string(), vec![value.clone()])
+                .expect("Could not call function");
+            assert_eq!(value, echoed);
+        }
+
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_background_event_loop() {
+        let script = "globalThis.ticks = 0; setInterval(() => ticks++, 10)".to_string();
+        let background = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            background_event_loop: true,
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+        background
+            .eval::<()>(script.clone())
+            .expect("Could not eval");
+
+        let foreground = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+        foreground.eval::<()>(script).expect("Could not eval");
+
+        // Only the worker driving its event loop runs the interval between queries
+        std::thread::sleep(Duration::from_millis(200));
+        let background_ticks: i64 = background
+            .eval("ticks".to_string())
+            .expect("Could not eval");
+        let foreground_ticks: i64 = foreground
+            .eval("ticks".to_string())
+            .expect("Could not eval");
+        assert!(background_ticks >= 5, "{background_ticks}");
+        assert!(foreground_ticks < background_ticks, "{foreground_ticks}");
+
+        background.stop().expect("Could not stop the worker");
+        foreground.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_receive_timeout() {
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+
+        // Synchronous code is not stopped by the runtime's timeout, but the caller stops waiting
+        let e = worker
+            .eval::<()>("const end = Date.now() + 1000; while (Date.now() < end) {}".to_string())
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        // The late response is not mistaken for the response to the next query
+        std::thread::sleep(Duration::from_millis(1000));
+        let value: i64 = worker.eval("1 + 1".to_string()).expect("Could not eval");
+        assert_eq!(2, value);
+        worker.stop().expect("Could not stop the worker");
+
+        // Without query IDs, the plain worker discards the response it still owes instead
+        let worker = Worker::<DefaultWorker>::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+        let e = worker
+            .send_and_await_timeout(
+                DefaultWorkerQuery::Eval(
+                    "const end = Date.now() + 500; while (Date.now() < end) {}; 1".to_string(),
+                ),
+                Duration::from_millis(100),
+            )
+            .unwrap_err();
+        assert!(matches!(e, Error::Timeout(_)), "{e}");
+
+        let response = worker
+            .send_and_await(DefaultWorkerQuery::Eval("2".to_string()))
+            .expect("Could not receive the response");
+        assert!(
+            matches!(&response, DefaultWorkerResponse::Value(v) if v == 2),
+            "{response:?}"
+        );
+        worker
+            .send(DefaultWorkerQuery::Stop)
+            .expect("Could not stop the worker");
+        worker.join().expect("Could not join the worker");
+    }
+
+    #[test]
+    fn test_setup() {
+        struct Counter(i64);
+        let worker = DefaultWorker::new(DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            setup: Some(std::sync::Arc::new(|runtime| {
+                runtime.put_state(Counter(0))?;
+                runtime.register_function_with_context("requestId", |_, context| {
+                    Ok(context.request_id.clone().into())
+                })?;
+                runtime.register_stateful_function("count", |_, state| {
+                    let mut counter = state.borrow_mut::<Counter>()?;
+                    counter.0 += 1;
+                    Ok(counter.0.into())
+                })
+            })),
+            ..Default::default()
+        })
+        .expect("Could not create the worker");
+
+        let value: i64 = worker
+            .eval("rustyscript.functions.count() + rustyscript.functions.count()".to_string())
+            .expect("Could not eval");
+        assert_eq!(3, value);
+
+        worker
+            .set_request_id(Some("req-1".to_string()))
+            .expect("Could not set the request id");
+        let id: String = worker
+            .eval("rustyscript.functions.requestId()".to_string())
+            .expect("Could not eval");
+        assert_eq!("req-1", id);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    struct PanickingWorker;
+    impl InnerWorker for PanickingWorker {
+        type Runtime = ();
+        type RuntimeOptions = ();
+        type Query = &'static str;
+        type Response = ();
+
+        fn init_runtime(_options: ()) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn handle_query(_runtime: &mut (), query: &'static str) {
+            if query == "panic" {
+                panic!("boom");
+            }
+        }
+
+        fn describe_query(query: &&'static str) -> Option<String> {
+            Some(query.to_string())
+        }
+    }
+
+    #[test]
+    fn test_crash_report() {
+        let worker = Worker::<PanickingWorker>::new(()).expect("Could not create the worker");
+        worker.send_and_await("ok").expect("Could not send query");
+        worker.send("panic").expect("Could not send query");
+
+        match worker.join() {
+            Err(Error::WorkerCrashed(report)) => {
+                assert_eq!("boom", report.message);
+                assert_eq!(Some("panic"), report.query.as_deref());
+                assert!(report.location.is_some_and(|l| l.contains("worker.rs")));
+                assert!(!report.backtrace.is_empty());
+            }
+            result => panic!("Expected a crash report, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_supervised_restart() {
+        let dir = std::env::temp_dir().join(format!(
+            "rustyscript_test_supervised_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).expect("Could not create the directory");
+        std::fs::write(dir.join("dep.js"), "export const x = 5;")
+            .expect("Could not write the file");
+
+        let mut worker = SupervisedWorker::new(DefaultWorkerOptions::default(), true)
+            .expect("Could not create the worker");
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = events.clone();
+        worker.on_restart(move |event| sink.borrow_mut().push(event.clone()));
+
+        let imports = worker
+            .load_module(crate::Module::new(
+                dir.join("main.js")
+                    .to_str()
+                    .expect("Could not convert the path"),
+                "import { x } from './dep.js'; export const value = () => x;",
+            ))
+            .expect("Could not load module");
+        let standalone = worker
+            .load_module(crate::Module::new(
+                "standalone.js",
+                "export const two = () => 2;",
+            ))
+            .expect("Could not load module");
+        let value: i64 = worker
+            .call_function(Some(imports), "value".to_string(), vec![])
+            .expect("Could not call function");
+        assert_eq!(5, value);
+
+        // The import can no longer be loaded when the module is replayed
+        std::fs::remove_file(dir.join("dep.js")).expect("Could not remove the file");
+
+        // Stop the thread behind the supervisor's back
+        worker
+            .worker
+            .borrow()
+            .0
+            .send_and_await(DefaultWorkerQuery::Stop)
+            .expect("Could not send query");
+
+        // The call that finds the worker stopped is flagged, not retried
+        let e = worker
+            .call_function::<i64>(Some(standalone), "two".to_string(), vec![])
+            .unwrap_err();
+        assert!(matches!(e, Error::WorkerRestarted(_)), "{e}");
+        assert_eq!(1, worker.restarts());
+
+        let events = events.borrow();
+        assert_eq!(1, events.len());
+        assert_eq!(1, events[0].restarts);
+        assert_eq!(1, events[0].replay_errors.len());
+        assert_eq!(imports, events[0].replay_errors[0].0);
+
+        let value: i64 = worker
+            .call_function(Some(standalone), "two".to_string(), vec![])
+            .expect("Could not call function");
+        assert_eq!(2, value);
+        assert!(worker
+            .call_function::<i64>(Some(imports), "value".to_string(), vec![])
+            .is_err());
+
+        worker.stop().expect("Could not stop the worker");
+        std::fs::remove_dir_all(&dir).expect("Could not remove the directory");
+    }
+
+    #[test]
+    fn test_multi_runtime_worker() {
+        let worker = MultiRuntimeWorker::new(MultiRuntimeWorkerOptions { max_runtimes: 2 })
+            .expect("Could not create the worker");
+        let options = DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        assert!(worker
+            .create("a", options.clone())
+            .expect("Could not create the runtime")
+            .is_empty());
+        assert!(worker
+            .create("b", options.clone())
+            .expect("Could not create the runtime")
+            .is_empty());
+
+        // Runtimes do not share globals
+        worker
+            .eval::<()>("a", "globalThis.x = 1".to_string())
+            .expect("Could not eval");
+        let x: Option<i64> = worker
+            .eval("b", "globalThis.x".to_string())
+            .expect("Could not eval");
+        assert_eq!(None, x);
+
+        let id = worker
+            .load_module(
+                "a",
+                crate::Module::new("test_multi.js", "export const double = (n) => n * 2;"),
+            )
+            .expect("Could not load module");
+        let value: i64 = worker
+            .call_function("a", Some(id), "double".to_string(), vec![21.into()])
+            .expect("Could not call function");
+        assert_eq!(42, value);
+        assert_eq!(
+            vec!["a", "b"],
+            worker.keys().expect("Could not list the runtimes")
+        );
+
+        // `b` was used least recently
+        assert_eq!(
+            vec!["b"],
+            worker
+                .create("c", options)
+                .expect("Could not create the runtime")
+        );
+        let e = worker.eval::<i64>("b", "1".to_string()).unwrap_err();
+        assert!(e.to_string().contains("No runtime for key `b`"), "{e}");
+
+        assert!(worker.remove("a").expect("Could not remove the runtime"));
+        assert!(!worker.remove("a").expect("Could not remove the runtime"));
+        assert_eq!(
+            vec!["c"],
+            worker.keys().expect("Could not list the runtimes")
+        );
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_shared_quota() {
+        let quota = crate::Quota {
+            tenant: "tenant".to_string(),
+            limits: crate::QuotaLimits {
+                invocations: Some(2),
+                ..Default::default()
+            },
+            store: std::sync::Arc::new(crate::MemoryQuotaStore::default()),
+        };
+        let worker = || {
+            DefaultWorker::builder()
+                .timeout(Duration::from_secs(1))
+                .quota(quota.clone())
+                .build()
+                .expect("Could not create the worker")
+        };
+
+        // Both workers count against the same quota
+        let (a, b) = (worker(), worker());
+        a.eval::<i64>("1".to_string()).expect("Could not eval");
+        b.eval::<i64>("2".to_string()).expect("Could not eval");
+        let e = a.eval::<i64>("3".to_string()).unwrap_err();
+        assert!(matches!(e, Error::QuotaExceeded(_)), "{e}");
+
+        a.stop().expect("Could not stop the worker");
+        b.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_bounded_priority_queue() {
+        let worker = DefaultWorker::builder()
+            .timeout(Duration::from_secs(2))
+            .queue_capacity(2)
+            .build()
+            .expect("Could not create the worker");
+        let module = worker
+            .load_module(crate::Module::new(
+                "test_bounded_priority_queue.js",
+                "
+                globalThis.order = [];
+                export const record = (n) => order.push(n);
+                export const busy = (ms) => { const end = Date.now() + ms; while (Date.now() < end) {} };
+            ",
+            ))
+            .expect("Could not load module");
+        let send = |name: &str, arg: i64, priority| {
+            worker.call_function_noreply_with_priority(
+                Some(module),
+                name.to_string(),
+                vec![arg.into()],
+                priority,
+            )
+        };
+
+        // Keep the worker busy, so the next queries wait in its priority queues
+        send("busy", 300, Priority::Normal).expect("Could not send query");
+        std::thread::sleep(Duration::from_millis(100));
+        send("record", 1, Priority::Low).expect("Could not send query");
+        send("record", 2, Priority::High).expect("Could not send query");
+
+        // Queries waiting in the priority queues count against the capacity
+        let e = send("record", 3, Priority::High).unwrap_err();
+        assert!(matches!(e, Error::WorkerBusy(2)), "{e}");
+
+        std::thread::sleep(Duration::from_millis(400));
+        let order: Vec<i64> = worker.eval("order".to_string()).expect("Could not eval");
+        assert_eq!(vec![2, 1], order);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_multi_runtime_drop_order() {
+        let worker = MultiRuntimeWorker::new(MultiRuntimeWorkerOptions { max_runtimes: 3 })
+            .expect("Could not create the worker");
+        let options = DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        for key in ["a", "b", "c"] {
+            worker
+                .create(key, options.clone())
+                .expect("Could not create the runtime");
+            worker
+                .eval::<()>(key, format!("globalThis.key = '{key}'"))
+                .expect("Could not eval");
+        }
+
+        // Runtimes older than the newest can be removed, replaced, and evicted
+        assert!(worker.remove("b").expect("Could not remove the runtime"));
+        worker
+            .create("a", options.clone())
+            .expect("Could not create the runtime");
+        worker
+            .eval::<i64>("c", "1".to_string())
+            .expect("Could not eval");
+        worker
+            .create("d", options.clone())
+            .expect("Could not create the runtime");
+        assert_eq!(
+            vec!["a".to_string()],
+            worker
+                .create("e", options)
+                .expect("Could not create the runtime")
+        );
+
+        // The runtimes that are left are still usable
+        let key: String = worker
+            .eval("c", "globalThis.key".to_string())
+            .expect("Could not eval");
+        assert_eq!("c", key);
+        let key: Option<String> = worker
+            .eval("d", "globalThis.key".to_string())
+            .expect("Could not eval");
+        assert_eq!(None, key);
+        worker.stop().expect("Could not stop the worker");
+    }
+
+    #[test]
+    fn test_fleet_manager() {
+        let options = DefaultWorkerOptions {
+            timeout: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        let mut fleet = FleetManager::new(FleetOptions::default());
+        fleet
+            .register("a", options.clone(), Priority::High)
+            .expect("Could not register the worker");
+        fleet
+            .register("b", options.clone(), Priority::Low)
+            .expect("Could not register the worker");
+        fleet
+            .register("c", options.clone(), Priority::Low)
+            .expect("Could not register the worker");
+        fleet
+            .get("c")
+            .expect("Could not find the worker")
+            .eval::<()>("globalThis.x = 1".to_string())
+            .expect("Could not eval");
+
+        let stats = fleet.stats();
+        assert_eq!(3, stats.workers);
+        assert_eq!(3, stats.members.len());
+        assert!(stats.total_heap_size > 0);
+
+        // Leave room for about two runtimes - the least recently used low priority worker goes first
+        let per_worker = stats.total_heap_size / 3;
+        fleet.options.memory_budget = per_worker * 2 + per_worker / 2;
+        assert_eq!(
+            vec!["b"],
+            fleet.enforce().expect("Could not enforce the budget")
+        );
+        assert_eq!(1, fleet.stats().evictions);
+
+        // Resetting keeps the worker, but drops its state
+        fleet
+            .get("c")
+            .expect("Could not find the worker")
+            .eval::<()>("globalThis.x = new Array(4_000_000).fill(1.5)".to_string())
+            .expect("Could not eval");
+        let fresh = fleet.stats().members["a"].total_heap_size;
+        fleet.options = FleetOptions {
+            memory_budget: fresh * 2 + fresh / 2,
+            action: EvictionAction::Reset,
+        };
+        assert_eq!(
+            vec!["c"],
+            fleet.enforce().expect("Could not enforce the budget")
+        );
+        let x: Option<i64> = fleet
+            .get("c")
+            .expect("Could not find the worker")
+            .eval("globalThis.x".to_string())
+            .expect("Could not eval");
+        assert_eq!(None, x);
+        assert_eq!(1, fleet.stats().resets);
+
+        fleet.stop().expect("Could not stop the fleet");
+    }
 }